@@ -0,0 +1,441 @@
+use rodio::cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
+use rodio::cpal::{self, FromSample, SizedSample};
+use rodio::source::UniformSourceIterator;
+use rodio::{Decoder, Source};
+use serde::Serialize;
+use std::fs::File;
+use std::io::BufReader;
+use std::sync::{mpsc, Arc, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Manager};
+
+use crate::show_engine::ShowEngine;
+use crate::timecode::{SyncMode, TimecodeChase};
+
+/// How often the thread holding an output stream checks it is still wanted.
+const STREAM_POLL: Duration = Duration::from_millis(50);
+
+/// How often playback is matched to the engine.
+const FOLLOW_POLL: Duration = Duration::from_millis(10);
+
+/// How often the audio position is reported to the engine clock.
+const REPORT_INTERVAL: Duration = Duration::from_millis(100);
+
+/// The engine and the audio further apart than this mean the show was
+/// started, sought or resumed; the audio jumps instead of being followed.
+const RESYNC_SECS: f64 = 0.25;
+
+/// The soundtrack the show plays to.
+#[derive(Debug, Clone, Serialize)]
+pub struct AudioTrack {
+    pub path: String,
+    pub sample_rate: u32,
+    pub channels: u16,
+    pub duration: f64,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct AudioStatus {
+    pub track: Option<AudioTrack>,
+    /// Output device name; None is the default device.
+    pub device: Option<String>,
+    pub playing: bool,
+    /// Soundtrack position being heard, in seconds.
+    pub position: Option<f64>,
+    /// Delay from the output stream taking a frame to it being heard.
+    pub latency_ms: Option<f64>,
+    pub error: Option<String>,
+}
+
+// The soundtrack decoded and converted to the output's rate and channels.
+type Feed = UniformSourceIterator<Decoder<BufReader<File>>, f32>;
+
+// Output frame `first_frame` of the track was taken when `at_written`
+// frames had been.
+#[derive(Clone, Copy)]
+struct Cue {
+    first_frame: u64,
+    at_written: u64,
+}
+
+#[derive(Default)]
+struct State {
+    track: Option<AudioTrack>,
+    device: Option<String>,
+    // Bumped whenever the stream is replaced so the old one exits.
+    generation: u64,
+    // Sample rate and channel count of the open output stream.
+    output: Option<(u32, u16)>,
+    feed: Option<Feed>,
+    cue: Option<Cue>,
+    // Frames the output stream has taken, at its rate.
+    written: u64,
+    latency_frames: u64,
+    error: Option<String>,
+}
+
+impl State {
+    // Output frame of the soundtrack being heard, while playing.
+    fn heard_frame(&self) -> Option<i64> {
+        let cue = self.cue?;
+        Some(cue.first_frame as i64 + self.written as i64 - self.latency_frames as i64 - cue.at_written as i64)
+    }
+}
+
+/// Plays the show's soundtrack and keeps the engine clock on it: the audio
+/// starts, stops and jumps with the show, and the engine follows the
+/// output stream's sample position from there. Between shows the stream
+/// plays silence, so playback starts without opening the device. What the
+/// stream takes is heard `latency_ms` later, so a start or jump skips that
+/// much of the track to line up with the show. Cheap to clone.
+#[derive(Clone, Default)]
+pub struct AudioPlayer {
+    state: Arc<Mutex<State>>,
+}
+
+/// Names of the output devices, as `set_device` takes them.
+pub fn devices() -> Result<Vec<String>, String> {
+    let devices = cpal::default_host()
+        .output_devices()
+        .map_err(|e| format!("Failed to list audio devices: {}", e))?;
+    Ok(devices.filter_map(|device| device.name().ok()).collect())
+}
+
+fn output_device(name: Option<&str>) -> Result<cpal::Device, String> {
+    let host = cpal::default_host();
+    match name {
+        None => host
+            .default_output_device()
+            .ok_or_else(|| "No audio output device".to_string()),
+        Some(name) => host
+            .output_devices()
+            .map_err(|e| format!("Failed to list audio devices: {}", e))?
+            .find(|device| device.name().is_ok_and(|n| n == name))
+            .ok_or_else(|| format!("Audio device {} not found", name)),
+    }
+}
+
+fn decoder(path: &str) -> Result<Decoder<BufReader<File>>, String> {
+    let file = File::open(path).map_err(|e| format!("Failed to open {}: {}", path, e))?;
+    Decoder::new(BufReader::new(file)).map_err(|e| format!("{}: {}", path, e))
+}
+
+// The soundtrack from `from` on, for an output of `channels` at `rate`.
+fn feed(path: &str, from: Duration, channels: u16, rate: u32) -> Result<Feed, String> {
+    let mut decoder = decoder(path)?;
+    if !from.is_zero() {
+        decoder
+            .try_seek(from)
+            .map_err(|e| format!("Failed to seek {}: {}", path, e))?;
+    }
+    Ok(UniformSourceIterator::new(decoder, channels, rate))
+}
+
+impl AudioPlayer {
+    fn lock(&self) -> Result<MutexGuard<'_, State>, String> {
+        self.state.lock().map_err(|_| "Audio player is unavailable".to_string())
+    }
+
+    /// Loads the soundtrack at `path` (WAV, FLAC, MP3, OGG Vorbis or AAC)
+    /// and opens the output device.
+    pub fn load(&self, app: &AppHandle, path: &str) -> Result<AudioTrack, String> {
+        let decoder = decoder(path)?;
+        let (sample_rate, channels) = (decoder.sample_rate(), decoder.channels());
+        // Some formats do not say how long they are; those are decoded through.
+        let duration = match decoder.total_duration() {
+            Some(duration) => duration.as_secs_f64(),
+            None => decoder.count() as f64 / channels as f64 / sample_rate as f64,
+        };
+        if duration <= 0.0 {
+            return Err(format!("{} contains no audio", path));
+        }
+        let info = AudioTrack {
+            path: path.to_string(),
+            sample_rate,
+            channels,
+            duration,
+        };
+        let device = self.lock()?.device.clone();
+        self.open(app, info.clone(), device)?;
+        log::info!("Loaded soundtrack {} ({:.1}s)", path, info.duration);
+        Ok(info)
+    }
+
+    /// Plays through output device `id` from now on; None is the default device.
+    pub fn set_device(&self, app: &AppHandle, id: Option<String>) -> Result<AudioStatus, String> {
+        let track = self.lock()?.track.clone();
+        match track {
+            Some(track) => self.open(app, track, id.clone())?,
+            None => self.lock()?.device = id.clone(),
+        }
+        log::info!("Audio device set to {}", id.as_deref().unwrap_or("default"));
+        self.status()
+    }
+
+    pub fn status(&self) -> Result<AudioStatus, String> {
+        let state = self.lock()?;
+        let rate = state.output.map(|(rate, _)| rate as f64);
+        Ok(AudioStatus {
+            track: state.track.clone(),
+            device: state.device.clone(),
+            playing: state.cue.is_some(),
+            position: state.heard_frame().zip(rate).map(|(frame, rate)| frame.max(0) as f64 / rate),
+            latency_ms: rate.map(|rate| state.latency_frames as f64 / rate * 1000.0),
+            error: state.error.clone(),
+        })
+    }
+
+    // Replaces the output stream with one for `track` on `device`.
+    fn open(&self, app: &AppHandle, track: AudioTrack, device: Option<String>) -> Result<(), String> {
+        let generation = {
+            let mut state = self.lock()?;
+            state.generation += 1;
+            state.track = Some(track);
+            state.device = device.clone();
+            state.output = None;
+            state.feed = None;
+            state.cue = None;
+            state.written = 0;
+            state.latency_frames = 0;
+            state.error = None;
+            state.generation
+        };
+        let (ready, opened) = mpsc::channel();
+        let player = self.clone();
+        std::thread::spawn(move || player.stream(generation, device.as_deref(), ready));
+        opened
+            .recv()
+            .map_err(|_| "Audio output stopped while opening".to_string())??;
+        tauri::async_runtime::spawn(follow(app.clone(), self.clone(), generation));
+        Ok(())
+    }
+
+    fn is_current(&self, generation: u64) -> bool {
+        self.lock().map(|s| s.generation == generation).unwrap_or(false)
+    }
+
+    // Holds the output stream, which cannot leave the thread that opened
+    // it, until superseded. Blocks the thread.
+    fn stream(&self, generation: u64, device: Option<&str>, ready: mpsc::Sender<Result<(), String>>) {
+        let stream = match self.open_stream(generation, device) {
+            Ok(stream) => stream,
+            Err(e) => {
+                self.fail(generation, e.clone());
+                let _ = ready.send(Err(e));
+                return;
+            }
+        };
+        let _ = ready.send(Ok(()));
+        while self.is_current(generation) {
+            std::thread::sleep(STREAM_POLL);
+        }
+        drop(stream);
+    }
+
+    fn open_stream(&self, generation: u64, device: Option<&str>) -> Result<cpal::Stream, String> {
+        let device = output_device(device)?;
+        let config = device
+            .default_output_config()
+            .map_err(|e| format!("Audio device has no output format: {}", e))?;
+        self.lock()?.output = Some((config.sample_rate().0, config.channels()));
+        let stream = match config.sample_format() {
+            cpal::SampleFormat::F32 => self.build_stream::<f32>(&device, &config.config(), generation),
+            cpal::SampleFormat::I32 => self.build_stream::<i32>(&device, &config.config(), generation),
+            cpal::SampleFormat::I16 => self.build_stream::<i16>(&device, &config.config(), generation),
+            cpal::SampleFormat::U16 => self.build_stream::<u16>(&device, &config.config(), generation),
+            other => Err(format!("Audio device uses unsupported sample format {}", other)),
+        }?;
+        stream
+            .play()
+            .map_err(|e| format!("Failed to start audio output: {}", e))?;
+        Ok(stream)
+    }
+
+    fn build_stream<T>(&self, device: &cpal::Device, config: &cpal::StreamConfig, generation: u64) -> Result<cpal::Stream, String>
+    where
+        T: SizedSample + FromSample<f32>,
+    {
+        let player = self.clone();
+        let failed = self.clone();
+        let channels = config.channels as usize;
+        let rate = config.sample_rate.0 as f64;
+        device
+            .build_output_stream(
+                config,
+                move |data: &mut [T], info: &cpal::OutputCallbackInfo| {
+                    let timestamp = info.timestamp();
+                    let latency = timestamp.playback.duration_since(&timestamp.callback);
+                    player.fill(generation, data, channels, latency.map(|l| (l.as_secs_f64() * rate) as u64));
+                },
+                move |e| failed.fail(generation, format!("Audio output failed: {}", e)),
+                None,
+            )
+            .map_err(|e| format!("Failed to open audio output: {}", e))
+    }
+
+    // Fills the output stream's buffer with the soundtrack while cued and
+    // silence otherwise, counting the frames it takes.
+    fn fill<T>(&self, generation: u64, data: &mut [T], channels: usize, latency_frames: Option<u64>)
+    where
+        T: SizedSample + FromSample<f32>,
+    {
+        let Ok(mut state) = self.state.lock() else {
+            data.fill(T::EQUILIBRIUM);
+            return;
+        };
+        if state.generation != generation {
+            data.fill(T::EQUILIBRIUM);
+            return;
+        }
+        let cued = state.cue.is_some();
+        match state.feed.as_mut() {
+            Some(feed) if cued => {
+                for sample in data.iter_mut() {
+                    *sample = T::from_sample(feed.next().unwrap_or(0.0));
+                }
+            }
+            _ => data.fill(T::EQUILIBRIUM),
+        }
+        state.written += (data.len() / channels) as u64;
+        if let Some(latency_frames) = latency_frames {
+            state.latency_frames = latency_frames;
+        }
+    }
+
+    fn fail(&self, generation: u64, error: String) {
+        log::error!("{}", error);
+        if let Ok(mut state) = self.lock() {
+            if state.generation == generation {
+                state.cue = None;
+                state.error = Some(error);
+            }
+        }
+    }
+
+    // Starts the soundtrack so that `time` is heard now.
+    fn cue_at(&self, time: f64) -> Result<(), String> {
+        let (track, (rate, channels), first_frame) = {
+            let state = self.lock()?;
+            let (Some(track), Some(output)) = (state.track.clone(), state.output) else {
+                return Ok(());
+            };
+            let first_frame = (time.max(0.0) * output.0 as f64) as u64 + state.latency_frames;
+            (track, output, first_frame)
+        };
+        let from = first_frame as f64 / rate as f64;
+        // Past the end there is nothing to decode; the cue plays silence.
+        let feed = if from < track.duration {
+            Some(feed(&track.path, Duration::from_secs_f64(from), channels, rate)?)
+        } else {
+            None
+        };
+        let mut state = self.lock()?;
+        state.feed = feed;
+        state.cue = Some(Cue {
+            first_frame,
+            at_written: state.written,
+        });
+        log::info!("Soundtrack playing from {:.3}s", time);
+        Ok(())
+    }
+
+    fn silence(&self) {
+        if let Ok(mut state) = self.lock() {
+            state.feed = None;
+            if state.cue.take().is_some() {
+                log::info!("Soundtrack stopped");
+            }
+        }
+    }
+
+    // Soundtrack time being heard and the track's duration, while playing.
+    fn heard_time(&self) -> Option<(f64, f64)> {
+        let state = self.lock().ok()?;
+        let track = state.track.as_ref()?;
+        let (rate, _) = state.output?;
+        let frame = state.heard_frame()?;
+        Some((frame as f64 / rate as f64, track.duration))
+    }
+}
+
+// Starts, stops and jumps the soundtrack with the show, and reports its
+// position to the engine so the show runs on the audio clock.
+async fn follow(app: AppHandle, player: AudioPlayer, generation: u64) {
+    let mut interval = tokio::time::interval(FOLLOW_POLL);
+    let mut last_report = Instant::now();
+    while player.is_current(generation) {
+        interval.tick().await;
+        let Ok(status) = app.state::<ShowEngine>().status() else {
+            continue;
+        };
+        let playing = player.lock().map(|s| s.cue.is_some()).unwrap_or(false);
+        if !status.is_running || status.is_held {
+            if playing {
+                player.silence();
+            }
+            continue;
+        }
+        if !playing {
+            if let Err(e) = player.cue_at(status.current_time) {
+                player.fail(generation, e);
+                return;
+            }
+            continue;
+        }
+        let Some((heard, duration)) = player.heard_time() else {
+            continue;
+        };
+        // Past the end of the track the show plays on in silence.
+        if heard >= duration && status.current_time >= duration {
+            continue;
+        }
+        if (heard - status.current_time).abs() > RESYNC_SECS {
+            if let Err(e) = player.cue_at(status.current_time) {
+                player.fail(generation, e);
+                return;
+            }
+        } else if heard < duration
+            && last_report.elapsed() >= REPORT_INTERVAL
+            && app.state::<TimecodeChase>().mode() == SyncMode::Internal
+        {
+            // Chasing timecode, the audio follows the show rather than leading it.
+            last_report = Instant::now();
+            if let Err(e) = app.state::<ShowEngine>().follow_audio(&app, heard) {
+                log::debug!("Audio position not applied: {}", e);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cue_lines_up_with_the_latency() {
+        let mut state = State {
+            written: 96_000,
+            latency_frames: 4_800,
+            ..State::default()
+        };
+        // Cued at 10 s on a 48 kHz output: the first frame the stream takes
+        // is heard 100 ms later, when the show is at 10.1 s.
+        state.cue = Some(Cue {
+            first_frame: 480_000 + 4_800,
+            at_written: 96_000,
+        });
+        assert_eq!(state.heard_frame(), Some(480_000));
+        state.written += 48_000;
+        assert_eq!(state.heard_frame(), Some(528_000));
+    }
+
+    #[test]
+    fn fill_counts_frames_and_stays_silent_until_cued() {
+        let player = AudioPlayer::default();
+        let mut data = [1.0f32; 8];
+        player.fill(0, &mut data, 2, Some(480));
+        assert_eq!(data, [0.0; 8]);
+        let state = player.lock().unwrap();
+        assert_eq!((state.written, state.latency_frames), (4, 480));
+    }
+}
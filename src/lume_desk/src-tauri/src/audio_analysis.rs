@@ -0,0 +1,229 @@
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+use tauri::AppHandle;
+
+use crate::registry;
+use crate::waveform::{self, WaveformEnvelope};
+
+/// Bump when the analysis changes so old cache entries are not used.
+const ANALYSIS_VERSION: u32 = 1;
+
+/// Waveform resolution returned to the editor; it zooms by downsampling further.
+const WAVEFORM_BUCKETS: usize = 4000;
+
+/// Energy is measured in slices this long; onsets and beats land on them.
+const HOP_SECONDS: f64 = 0.01;
+
+/// Slices quieter than this (dBFS) never hold an onset.
+const SILENCE_DB: f32 = -60.0;
+
+/// Onsets closer together than this are one onset.
+const MIN_ONSET_GAP: usize = 5;
+
+/// An onset must rise this many standard deviations above the local mean,
+/// over `THRESHOLD_WINDOW` slices either side.
+const THRESHOLD_DEVIATIONS: f32 = 1.5;
+const THRESHOLD_WINDOW: usize = 50;
+
+const MIN_BPM: f64 = 60.0;
+const MAX_BPM: f64 = 200.0;
+
+/// Tempos near this are preferred, so half and double time are not picked
+/// over the usual beat.
+const PREFERRED_BPM: f64 = 120.0;
+
+/// Too few onsets to tell a tempo from.
+const MIN_ONSETS: usize = 8;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AudioAnalysis {
+    pub waveform: WaveformEnvelope,
+    /// Times in seconds where a note or hit starts.
+    pub onsets: Vec<f64>,
+    /// Beat grid in seconds at `bpm`; empty when no tempo was found.
+    pub beats: Vec<f64>,
+    pub bpm: Option<f64>,
+    /// Whether this came from the cache rather than a fresh analysis.
+    #[serde(skip_deserializing)]
+    pub cached: bool,
+}
+
+// Names the cache entry after the file's path, size and mtime, so an edited
+// track is analyzed again.
+fn cache_file(cache_dir: &Path, path: &Path) -> Result<PathBuf, String> {
+    let metadata = fs::metadata(path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    let modified = metadata
+        .modified()
+        .ok()
+        .and_then(|m| m.duration_since(UNIX_EPOCH).ok())
+        .map_or(0, |d| d.as_nanos());
+    let canonical = fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+    let key = format!("{}\n{}\n{}\n{}", ANALYSIS_VERSION, canonical.display(), metadata.len(), modified);
+    let digest = Sha256::digest(key.as_bytes());
+    let name: String = digest[..12].iter().map(|b| format!("{:02x}", b)).collect();
+    Ok(cache_dir.join(format!("{}.json", name)))
+}
+
+/// Waveform, onsets, beats and tempo of the track at `path`. Results
+/// are kept in `cache_dir`, so a track is only analyzed again once it
+/// changes. Progress is reported with waveform-progress events.
+pub fn analyze(app: &AppHandle, path: &Path, cache_dir: &Path) -> Result<AudioAnalysis, String> {
+    let cache = cache_file(cache_dir, path)?;
+    if let Some(mut analysis) = fs::read(&cache)
+        .ok()
+        .and_then(|data| serde_json::from_slice::<AudioAnalysis>(&data).ok())
+    {
+        analysis.cached = true;
+        return Ok(analysis);
+    }
+
+    let (waveform, energy) = waveform::analyze_with_energy(app, path, WAVEFORM_BUCKETS, HOP_SECONDS)?;
+    let (onsets, bpm, beats) = detect(&energy);
+    let to_seconds = |slices: Vec<usize>| slices.into_iter().map(|i| i as f64 * HOP_SECONDS).collect();
+    let analysis = AudioAnalysis {
+        waveform,
+        onsets: to_seconds(onsets),
+        beats: to_seconds(beats),
+        bpm,
+        cached: false,
+    };
+    match analysis.bpm {
+        Some(bpm) => log::info!("{}: {} onsets, {:.1} BPM", path.display(), analysis.onsets.len(), bpm),
+        None => log::info!("{}: {} onsets, no clear tempo", path.display(), analysis.onsets.len()),
+    }
+    // The analysis is still good without a cache entry.
+    if let Err(e) = registry::save_json(Some(cache), &analysis) {
+        log::warn!("Failed to cache audio analysis: {}", e);
+    }
+    Ok(analysis)
+}
+
+// Rise in loudness from each slice to the next, in dB; falls count as none.
+fn onset_strength(energy: &[f32]) -> Vec<f32> {
+    let db: Vec<f32> = energy.iter().map(|&e| 10.0 * (e + 1e-10).log10()).collect();
+    let mut strength = vec![0.0; db.len()];
+    for i in 1..db.len() {
+        if db[i] > SILENCE_DB {
+            strength[i] = (db[i] - db[i - 1]).max(0.0);
+        }
+    }
+    strength
+}
+
+// Slices where the strength peaks well above its surroundings.
+fn pick_onsets(strength: &[f32]) -> Vec<usize> {
+    let mut onsets: Vec<usize> = Vec::new();
+    for i in 0..strength.len() {
+        let window = &strength[i.saturating_sub(THRESHOLD_WINDOW)..(i + THRESHOLD_WINDOW + 1).min(strength.len())];
+        let mean = window.iter().sum::<f32>() / window.len() as f32;
+        let variance = window.iter().map(|s| (s - mean) * (s - mean)).sum::<f32>() / window.len() as f32;
+        let local = &strength[i.saturating_sub(3)..(i + 4).min(strength.len())];
+        let is_peak = local.iter().all(|&s| s <= strength[i]);
+        if strength[i] > 0.0 && is_peak && strength[i] > mean + THRESHOLD_DEVIATIONS * variance.sqrt() {
+            if onsets.last().is_some_and(|&last| i - last < MIN_ONSET_GAP) {
+                continue;
+            }
+            onsets.push(i);
+        }
+    }
+    onsets
+}
+
+// Tempo in BPM from the autocorrelation of the onset strength, weighted
+// toward `PREFERRED_BPM`, with the beat period in slices.
+fn estimate_tempo(strength: &[f32]) -> Option<(f64, f64)> {
+    let min_lag = (60.0 / MAX_BPM / HOP_SECONDS).floor() as usize;
+    let max_lag = (60.0 / MIN_BPM / HOP_SECONDS).ceil() as usize;
+    if strength.len() <= max_lag * 4 {
+        return None;
+    }
+    let correlation: Vec<f64> = (0..=max_lag + 1)
+        .map(|lag| {
+            strength[lag..]
+                .iter()
+                .zip(strength)
+                .map(|(a, b)| (*a as f64) * (*b as f64))
+                .sum()
+        })
+        .collect();
+    let weight = |lag: f64| {
+        let octaves = (60.0 / (lag * HOP_SECONDS) / PREFERRED_BPM).log2();
+        (-0.5 * octaves * octaves).exp()
+    };
+    let best = (min_lag..=max_lag).max_by(|&a, &b| {
+        (correlation[a] * weight(a as f64)).total_cmp(&(correlation[b] * weight(b as f64)))
+    })?;
+    if correlation[best] <= 0.0 {
+        return None;
+    }
+    // A parabola through the peak and its neighbours places it between slices.
+    let (left, centre, right) = (correlation[best - 1], correlation[best], correlation[best + 1]);
+    let curvature = left - 2.0 * centre + right;
+    let offset = if curvature < 0.0 { 0.5 * (left - right) / curvature } else { 0.0 };
+    let period = best as f64 + offset.clamp(-0.5, 0.5);
+    Some((60.0 / (period * HOP_SECONDS), period))
+}
+
+// Onsets, tempo and beat slices. The beat grid is placed where it lines up
+// with the most onset strength.
+fn detect(energy: &[f32]) -> (Vec<usize>, Option<f64>, Vec<usize>) {
+    let strength = onset_strength(energy);
+    let onsets = pick_onsets(&strength);
+    if onsets.len() < MIN_ONSETS {
+        return (onsets, None, Vec::new());
+    }
+    let Some((bpm, period)) = estimate_tempo(&strength) else {
+        return (onsets, None, Vec::new());
+    };
+    let grid = |phase: f64| {
+        (0..)
+            .map(move |k| (phase + k as f64 * period).round() as usize)
+            .take_while(|&i| i < strength.len())
+    };
+    let phase = (0..period.ceil() as usize)
+        .map(|p| p as f64)
+        .max_by(|&a, &b| {
+            let score = |phase| grid(phase).map(|i| strength[i]).sum::<f32>();
+            score(a).total_cmp(&score(b))
+        })
+        .unwrap_or(0.0);
+    (onsets, Some((bpm * 10.0).round() / 10.0), grid(phase).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Ten seconds of quiet with a click every `period` slices.
+    fn clicks(period: usize, first: usize) -> Vec<f32> {
+        (0..1000)
+            .map(|i| if i >= first && (i - first) % period == 0 { 0.5 } else { 1e-5 })
+            .collect()
+    }
+
+    #[test]
+    fn finds_the_tempo_and_grid_of_a_click_track() {
+        let (onsets, bpm, beats) = detect(&clicks(50, 7));
+        assert_eq!(onsets.len(), 20);
+        assert_eq!(onsets[..3], [7, 57, 107]);
+        assert_eq!(bpm, Some(120.0));
+        assert_eq!(beats[..3], [7, 57, 107]);
+    }
+
+    #[test]
+    fn prefers_the_usual_beat_over_half_time() {
+        let (_, bpm, _) = detect(&clicks(40, 0));
+        assert_eq!(bpm, Some(150.0));
+    }
+
+    #[test]
+    fn silence_has_no_tempo() {
+        let (onsets, bpm, beats) = detect(&[1e-6; 1000]);
+        assert!(onsets.is_empty());
+        assert_eq!(bpm, None);
+        assert!(beats.is_empty());
+    }
+}
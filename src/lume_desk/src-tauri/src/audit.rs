@@ -0,0 +1,128 @@
+use crate::registry::ControllerInfo;
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Oldest entries are dropped once the session log reaches this size.
+const MAX_ENTRIES: usize = 10_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AuditKind {
+    ManualOverride,
+    Flash,
+    FlashRelease,
+    ShowCue,
+    ExternalTrigger,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct AuditEntry {
+    /// Milliseconds since the Unix epoch.
+    pub timestamp: u64,
+    pub kind: AuditKind,
+    pub controller: Option<String>,
+    /// Friendly name of the controller at the time of the entry.
+    pub controller_name: Option<String>,
+    pub channel: Option<u32>,
+    /// Show effect id for show cues.
+    pub effect_id: Option<String>,
+    pub success: bool,
+    pub detail: String,
+}
+
+/// Session audit trail of operator actions and fire events.
+#[derive(Debug, Default)]
+pub struct AuditLog {
+    entries: Mutex<VecDeque<AuditEntry>>,
+}
+
+pub fn now_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+impl AuditLog {
+    pub fn record(
+        &self,
+        kind: AuditKind,
+        controller: &ControllerInfo,
+        channel: u32,
+        result: &Result<(), String>,
+        detail: String,
+    ) {
+        let entry = AuditEntry {
+            timestamp: now_millis(),
+            kind,
+            controller: Some(controller.address.clone()),
+            controller_name: controller.display_name(),
+            channel: Some(channel),
+            effect_id: None,
+            success: result.is_ok(),
+            detail: match result {
+                Ok(()) => detail,
+                Err(error) => format!("{} ({})", detail, error),
+            },
+        };
+        log::info!("Audit: {:?} {}:{} {}", entry.kind, controller.label(), channel, entry.detail);
+        self.push(entry);
+    }
+
+    /// Records a show cue. `controller` is None if the show referenced an
+    /// unregistered controller; `reference` is then kept as written.
+    pub fn record_cue(
+        &self,
+        effect_id: &str,
+        reference: &str,
+        controller: Option<&ControllerInfo>,
+        channel: u32,
+        result: &Result<(), String>,
+    ) {
+        self.push(AuditEntry {
+            timestamp: now_millis(),
+            kind: AuditKind::ShowCue,
+            controller: Some(controller.map_or_else(|| reference.to_string(), |c| c.address.clone())),
+            controller_name: controller.and_then(ControllerInfo::display_name),
+            channel: Some(channel),
+            effect_id: Some(effect_id.to_string()),
+            success: result.is_ok(),
+            detail: result.as_ref().err().cloned().unwrap_or_default(),
+        });
+    }
+
+    /// Records a signal from the external trigger and whether it started the show.
+    pub fn record_trigger(&self, source: &str, result: &Result<(), String>) {
+        self.push(AuditEntry {
+            timestamp: now_millis(),
+            kind: AuditKind::ExternalTrigger,
+            controller: None,
+            controller_name: None,
+            channel: None,
+            effect_id: None,
+            success: result.is_ok(),
+            detail: match result {
+                Ok(()) => format!("Show started by {}", source),
+                Err(error) => format!("Signal from {} ignored ({})", source, error),
+            },
+        });
+    }
+
+    fn push(&self, entry: AuditEntry) {
+        let Ok(mut entries) = self.entries.lock() else {
+            log::error!("Audit log is unavailable; entry dropped");
+            return;
+        };
+        if entries.len() >= MAX_ENTRIES {
+            entries.pop_front();
+        }
+        entries.push_back(entry);
+    }
+
+    pub fn entries(&self) -> Result<Vec<AuditEntry>, String> {
+        let entries = self.entries.lock().map_err(|_| "Audit log is unavailable".to_string())?;
+        Ok(entries.iter().cloned().collect())
+    }
+}
@@ -0,0 +1,364 @@
+use sha2::{Digest, Sha256};
+use std::path::{Path, PathBuf};
+
+use crate::audit::{now_millis, AuditEntry, AuditKind};
+use crate::performance::{PerformanceHistory, PerformanceSample};
+use crate::show_store;
+
+// US Letter in points, Courier 8pt so the columns line up.
+const PAGE_WIDTH: u32 = 612;
+const PAGE_HEIGHT: u32 = 792;
+const MARGIN: u32 = 50;
+const FONT_SIZE: u32 = 8;
+const LINE_HEIGHT: u32 = 11;
+// Courier glyphs are 0.6 em wide.
+const LINE_CHARS: usize = ((PAGE_WIDTH - 2 * MARGIN) * 10 / (FONT_SIZE * 6)) as usize;
+// Two lines at the bottom are kept for the footer.
+const LINES_PER_PAGE: usize = ((PAGE_HEIGHT - 2 * MARGIN) / LINE_HEIGHT) as usize - 2;
+// Worst-jitter samples listed in the performance section.
+const PERFORMANCE_SPIKES: usize = 20;
+
+pub struct ReportHeader {
+    pub show_name: Option<String>,
+    pub operator: Option<String>,
+}
+
+/// Renders the fire events in `entries` to a PDF at `path`, on a blocking
+/// thread. Every page footer carries the SHA-256 of the report text as
+/// printed, i.e. the body lines joined with '\n', so a copy can be checked
+/// later. `performance`, if given, is summarised after the events. Returns
+/// the written path.
+pub async fn export_pdf(
+    path: PathBuf,
+    header: ReportHeader,
+    entries: Vec<AuditEntry>,
+    performance: Option<PerformanceHistory>,
+) -> Result<PathBuf, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let pdf = report_pdf(&header, &entries, performance.as_ref());
+        write(&path, &pdf)?;
+        log::info!("Audit report with {} entries written to {}", entries.len(), path.display());
+        Ok(path)
+    })
+    .await
+    .map_err(|e| format!("Audit report failed: {}", e))?
+}
+
+fn report_pdf(header: &ReportHeader, entries: &[AuditEntry], performance: Option<&PerformanceHistory>) -> Vec<u8> {
+    let mut lines = report_lines(header, entries);
+    if let Some(history) = performance {
+        lines.extend(performance_lines(history));
+    }
+    // Hashed as rendered, so the footer can be checked against the page text.
+    let lines: Vec<String> = lines.iter().map(|line| printable(line)).collect();
+    let hash = content_hash(&lines);
+    render_pdf(&lines, &hash)
+}
+
+fn write(path: &Path, pdf: &[u8]) -> Result<(), String> {
+    show_store::write_atomic(path, pdf).map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+}
+
+fn content_hash(lines: &[String]) -> String {
+    let digest = Sha256::digest(lines.join("\n").as_bytes());
+    digest.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn report_lines(header: &ReportHeader, entries: &[AuditEntry]) -> Vec<String> {
+    let cues = entries.iter().filter(|e| e.kind == AuditKind::ShowCue).count();
+    let triggers = entries.iter().filter(|e| e.kind == AuditKind::ExternalTrigger).count();
+    let failed = entries.iter().filter(|e| !e.success).count();
+    let mut lines = vec![
+        "LUME Fire Report".to_string(),
+        String::new(),
+        format!("Show:      {}", header.show_name.as_deref().unwrap_or("-")),
+        format!("Operator:  {}", header.operator.as_deref().unwrap_or("-")),
+        format!("Generated: {} UTC", format_utc(now_millis())),
+    ];
+    if let (Some(first), Some(last)) = (entries.first(), entries.last()) {
+        lines.push(format!(
+            "Session:   {} to {} UTC",
+            format_utc(first.timestamp),
+            format_utc(last.timestamp)
+        ));
+    }
+    lines.extend([
+        String::new(),
+        format!(
+            "Events: {}  Succeeded: {}  Failed: {}  Show cues: {}  Manual: {}  Triggers: {}",
+            entries.len(),
+            entries.len() - failed,
+            failed,
+            cues,
+            entries.len() - cues - triggers,
+            triggers
+        ),
+        String::new(),
+        format!("{:<23} {:<15} {:<24} {:>3} {:<6} Detail", "Time (UTC)", "Kind", "Controller", "Ch", "Result"),
+        "-".repeat(LINE_CHARS),
+    ]);
+    for entry in entries {
+        let controller = entry
+            .controller_name
+            .clone()
+            .or_else(|| entry.controller.clone())
+            .unwrap_or_default();
+        let detail = match (&entry.effect_id, entry.detail.is_empty()) {
+            (Some(id), true) => id.clone(),
+            (Some(id), false) => format!("{}: {}", id, entry.detail),
+            (None, _) => entry.detail.clone(),
+        };
+        let line = format!(
+            "{:<23} {:<15} {:<24} {:>3} {:<6} {}",
+            format_utc(entry.timestamp),
+            kind_label(entry.kind),
+            truncate(&controller, 24),
+            entry.channel.map(|c| c.to_string()).unwrap_or_default(),
+            if entry.success { "ok" } else { "FAILED" },
+            detail
+        );
+        lines.push(truncate(&line, LINE_CHARS));
+    }
+    if entries.is_empty() {
+        lines.push("No fire events were recorded this session.".to_string());
+    }
+    lines
+}
+
+// Peaks over the session and the samples with the worst engine jitter, in
+// time order, to line glitches up with the events above.
+fn performance_lines(history: &PerformanceHistory) -> Vec<String> {
+    let samples = &history.samples;
+    let mut lines = vec![
+        String::new(),
+        "Performance".to_string(),
+        "-".repeat(LINE_CHARS),
+    ];
+    let (Some(first), Some(last)) = (samples.first(), samples.last()) else {
+        lines.push("No performance samples were recorded this session.".to_string());
+        return lines;
+    };
+    let peak = |value: fn(&PerformanceSample) -> f64| samples.iter().map(value).fold(0.0, f64::max);
+    let point_ms = history.interval_ms * history.samples_per_point as u64;
+    let degraded = samples.iter().filter(|s| s.degraded).count() as u64 * point_ms;
+    lines.extend([
+        format!(
+            "Samples:   {} from {} to {} UTC, one per {} ms",
+            samples.len(),
+            format_utc(first.timestamp),
+            format_utc(last.timestamp),
+            point_ms
+        ),
+        format!(
+            "Peaks:     memory {:.0} MB  cpu {:.0}%  jitter {:.1} ms  degraded about {} s",
+            peak(|s| s.memory_mb),
+            peak(|s| s.cpu_usage),
+            peak(|s| s.jitter_ms),
+            degraded / 1000
+        ),
+        String::new(),
+        format!("{:<23} {:>9} {:>6} {:>10} Notes", "Time (UTC)", "Memory MB", "CPU %", "Jitter ms"),
+    ]);
+    let mut spikes: Vec<&PerformanceSample> = samples.iter().filter(|s| s.jitter_ms > 0.0).collect();
+    spikes.sort_by(|a, b| b.jitter_ms.total_cmp(&a.jitter_ms));
+    spikes.truncate(PERFORMANCE_SPIKES);
+    spikes.sort_by_key(|s| s.timestamp);
+    for sample in &spikes {
+        let notes = match (sample.show_running, sample.degraded) {
+            (true, true) => "show, degraded",
+            (true, false) => "show",
+            (false, true) => "degraded",
+            (false, false) => "",
+        };
+        lines.push(format!(
+            "{:<23} {:>9.0} {:>6.0} {:>10.1} {}",
+            format_utc(sample.timestamp),
+            sample.memory_mb,
+            sample.cpu_usage,
+            sample.jitter_ms,
+            notes
+        ));
+    }
+    if spikes.is_empty() {
+        lines.push("The engine did not run this session.".to_string());
+    }
+    lines
+}
+
+fn kind_label(kind: AuditKind) -> &'static str {
+    match kind {
+        AuditKind::ManualOverride => "manual",
+        AuditKind::Flash => "flash",
+        AuditKind::FlashRelease => "flash release",
+        AuditKind::ShowCue => "show cue",
+        AuditKind::ExternalTrigger => "trigger",
+    }
+}
+
+fn truncate(text: &str, max: usize) -> String {
+    if text.chars().count() <= max {
+        return text.to_string();
+    }
+    let mut cut: String = text.chars().take(max.saturating_sub(1)).collect();
+    cut.push('~');
+    cut
+}
+
+/// "2025-08-11 20:15:03.250" for a Unix timestamp in milliseconds.
+fn format_utc(millis: u64) -> String {
+    let secs = millis / 1000;
+    let (year, month, day) = civil_from_days((secs / 86_400) as i64);
+    let rem = secs % 86_400;
+    format!(
+        "{:04}-{:02}-{:02} {:02}:{:02}:{:02}.{:03}",
+        year,
+        month,
+        day,
+        rem / 3600,
+        rem % 3600 / 60,
+        rem % 60,
+        millis % 1000
+    )
+}
+
+// Howard Hinnant's days-to-civil algorithm for the proleptic Gregorian calendar.
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+// Printable ASCII only; the standard fonts have no Unicode mapping.
+fn printable(text: &str) -> String {
+    text.chars().map(|c| if matches!(c, ' '..='~') { c } else { '?' }).collect()
+}
+
+fn pdf_string(text: &str) -> String {
+    text.chars()
+        .map(|c| match c {
+            '(' | ')' | '\\' => format!("\\{}", c),
+            _ => c.to_string(),
+        })
+        .collect()
+}
+
+// A minimal PDF 1.4: catalog, page tree, one font and a content stream per page.
+fn render_pdf(lines: &[String], hash: &str) -> Vec<u8> {
+    let pages: Vec<&[String]> = lines.chunks(LINES_PER_PAGE).collect();
+    let page_count = pages.len();
+    // Objects 1-3 are fixed; each page adds a page object and its stream.
+    let page_id = |i: usize| 4 + 2 * i;
+
+    let mut objects: Vec<String> = vec![
+        "<< /Type /Catalog /Pages 2 0 R >>".to_string(),
+        format!(
+            "<< /Type /Pages /Kids [{}] /Count {} >>",
+            (0..page_count).map(|i| format!("{} 0 R", page_id(i))).collect::<Vec<_>>().join(" "),
+            page_count
+        ),
+        "<< /Type /Font /Subtype /Type1 /BaseFont /Courier >>".to_string(),
+    ];
+    for (i, page) in pages.iter().enumerate() {
+        let mut content = format!(
+            "BT /F1 {} Tf {} TL {} {} Td\n",
+            FONT_SIZE,
+            LINE_HEIGHT,
+            MARGIN,
+            PAGE_HEIGHT - MARGIN
+        );
+        for line in page.iter() {
+            content.push_str(&format!("({}) '\n", pdf_string(line)));
+        }
+        content.push_str(&format!(
+            "ET\nBT /F1 {} Tf {} {} Td (SHA-256 {}   Page {} of {}) Tj ET\n",
+            FONT_SIZE - 1,
+            MARGIN,
+            MARGIN / 2,
+            hash,
+            i + 1,
+            page_count
+        ));
+        objects.push(format!(
+            "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 {} {}] /Resources << /Font << /F1 3 0 R >> >> /Contents {} 0 R >>",
+            PAGE_WIDTH,
+            PAGE_HEIGHT,
+            page_id(i) + 1
+        ));
+        objects.push(format!("<< /Length {} >>\nstream\n{}endstream", content.len(), content));
+    }
+
+    let mut pdf = b"%PDF-1.4\n".to_vec();
+    let mut offsets = Vec::with_capacity(objects.len());
+    for (i, object) in objects.iter().enumerate() {
+        offsets.push(pdf.len());
+        pdf.extend_from_slice(format!("{} 0 obj\n{}\nendobj\n", i + 1, object).as_bytes());
+    }
+    let xref = pdf.len();
+    let mut trailer = format!("xref\n0 {}\n0000000000 65535 f \n", objects.len() + 1);
+    for offset in offsets {
+        trailer.push_str(&format!("{:010} 00000 n \n", offset));
+    }
+    trailer.push_str(&format!(
+        "trailer\n<< /Size {} /Root 1 0 R >>\nstartxref\n{}\n%%EOF\n",
+        objects.len() + 1,
+        xref
+    ));
+    pdf.extend_from_slice(trailer.as_bytes());
+    pdf
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // The body lines as a reader would copy them off the pages.
+    fn page_text(pdf: &str) -> String {
+        let mut lines = Vec::new();
+        for line in pdf.lines() {
+            let Some(text) = line.strip_prefix('(').and_then(|l| l.strip_suffix(") '")) else {
+                continue;
+            };
+            let mut unescaped = String::new();
+            let mut chars = text.chars();
+            while let Some(c) = chars.next() {
+                unescaped.push(if c == '\\' { chars.next().unwrap() } else { c });
+            }
+            lines.push(unescaped);
+        }
+        lines.join("\n")
+    }
+
+    #[test]
+    fn footer_hash_matches_the_printed_text() {
+        let header = ReportHeader {
+            show_name: Some("Fête (finale)".to_string()),
+            operator: Some("Jürgen".to_string()),
+        };
+        let entries = vec![AuditEntry {
+            timestamp: 1_755_000_000_000,
+            kind: AuditKind::ShowCue,
+            controller: Some("10.0.0.1".to_string()),
+            controller_name: Some("Bühne links".to_string()),
+            channel: Some(3),
+            effect_id: Some("shell".to_string()),
+            success: true,
+            detail: String::new(),
+        }];
+        let pdf = String::from_utf8(report_pdf(&header, &entries, None)).unwrap();
+        let text = page_text(&pdf);
+        assert!(text.contains("Show:      F?te (finale)"));
+        assert!(text.contains("B?hne links"));
+
+        let footer = pdf.split("(SHA-256 ").nth(1).unwrap();
+        let hash = &footer[..64];
+        let digest = Sha256::digest(text.as_bytes());
+        assert_eq!(hash, digest.iter().map(|b| format!("{:02x}", b)).collect::<String>());
+    }
+}
@@ -0,0 +1,162 @@
+use serde::Serialize;
+use std::cmp::Reverse;
+use std::fs;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use crate::audit::now_millis;
+use crate::models::Show;
+use crate::show_store::{self, ShowStore};
+
+/// Hidden folder next to the show that holds its backups.
+const BACKUP_DIR: &str = ".backups";
+
+const BACKUP_EXTENSION: &str = "bak";
+
+pub const DEFAULT_RETENTION: usize = 5;
+pub const MAX_RETENTION: usize = 100;
+
+static RETENTION: AtomicUsize = AtomicUsize::new(DEFAULT_RETENTION);
+
+#[derive(Debug, Serialize)]
+pub struct BackupInfo {
+    pub path: PathBuf,
+    /// Unix timestamp in milliseconds.
+    pub created_at: u64,
+    pub size_bytes: u64,
+}
+
+pub fn retention() -> usize {
+    RETENTION.load(Ordering::Relaxed)
+}
+
+/// Backups kept per show; 0 turns backups off. Existing backups beyond the
+/// new count are pruned on the next save.
+pub fn set_retention(count: usize) -> Result<(), String> {
+    if count > MAX_RETENTION {
+        return Err(format!("Backup retention must be at most {}, got {}", MAX_RETENTION, count));
+    }
+    RETENTION.store(count, Ordering::Relaxed);
+    log::info!("Keeping the last {} backups per show", count);
+    Ok(())
+}
+
+fn backup_dir(show_path: &Path) -> PathBuf {
+    show_path.parent().unwrap_or(Path::new(".")).join(BACKUP_DIR)
+}
+
+fn file_name(path: &Path) -> Result<String, String> {
+    path.file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .ok_or_else(|| format!("{} is not a file path", path.display()))
+}
+
+// "<show file name>.<millis>.bak", zero-padded so names sort by age.
+fn backup_name(show_name: &str, created_at: u64) -> String {
+    format!("{}.{:013}.{}", show_name, created_at, BACKUP_EXTENSION)
+}
+
+// The creation time if `name` is a backup of `show_name`, None for anything else.
+fn parse_backup_name(show_name: &str, name: &str) -> Option<u64> {
+    let stamp = name
+        .strip_prefix(show_name)?
+        .strip_prefix('.')?
+        .strip_suffix(BACKUP_EXTENSION)?
+        .strip_suffix('.')?;
+    (stamp.len() == 13 && stamp.bytes().all(|b| b.is_ascii_digit()))
+        .then(|| stamp.parse().ok())
+        .flatten()
+}
+
+/// Backups of the show at `show_path`, newest first.
+pub fn list(show_path: &Path) -> Result<Vec<BackupInfo>, String> {
+    let show_name = file_name(show_path)?;
+    let dir = backup_dir(show_path);
+    let entries = match fs::read_dir(&dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(format!("Failed to read {}: {}", dir.display(), e)),
+    };
+    let mut backups: Vec<BackupInfo> = entries
+        .filter_map(Result::ok)
+        .filter_map(|entry| {
+            let created_at = parse_backup_name(&show_name, &entry.file_name().to_string_lossy())?;
+            Some(BackupInfo {
+                path: entry.path(),
+                created_at,
+                size_bytes: entry.metadata().map(|m| m.len()).unwrap_or(0),
+            })
+        })
+        .collect();
+    backups.sort_by_key(|b| Reverse(b.created_at));
+    Ok(backups)
+}
+
+/// Writes a standalone copy of `show` to the backup folder of `show_path`,
+/// then deletes the oldest backups beyond the retention count. Does nothing
+/// when backups are off.
+pub fn create(show_path: &Path, show: &Show) -> Result<Option<PathBuf>, String> {
+    let keep = retention();
+    if keep == 0 {
+        return Ok(None);
+    }
+    let show_name = file_name(show_path)?;
+    let dir = backup_dir(show_path);
+    fs::create_dir_all(&dir).map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+    let mut created_at = now_millis();
+    // Two saves within the same millisecond must not overwrite each other.
+    while dir.join(backup_name(&show_name, created_at)).exists() {
+        created_at += 1;
+    }
+    let path = dir.join(backup_name(&show_name, created_at));
+    let data = show_store::encode_show_file(show)?;
+    show_store::write_atomic(&path, &data).map_err(|e| format!("Failed to write backup {}: {}", path.display(), e))?;
+    log::info!("Backed up show to {}", path.display());
+
+    for stale in list(show_path)?.into_iter().skip(keep) {
+        match fs::remove_file(&stale.path) {
+            Ok(()) => log::info!("Removed old backup {}", stale.path.display()),
+            Err(e) => log::warn!("Failed to remove old backup {}: {}", stale.path.display(), e),
+        }
+    }
+    Ok(Some(path))
+}
+
+/// The show file a backup belongs to, after checking `backup_path` really is one.
+pub fn show_path_for(backup_path: &Path) -> Result<PathBuf, String> {
+    let not_backup = || format!("{} is not a show backup", backup_path.display());
+    let dir = backup_path.parent().filter(|d| d.ends_with(BACKUP_DIR)).ok_or_else(not_backup)?;
+    let name = file_name(backup_path)?;
+    let show_name = name
+        .strip_suffix(BACKUP_EXTENSION)
+        .and_then(|n| n.strip_suffix('.'))
+        .and_then(|n| n.rsplit_once('.'))
+        .map(|(show_name, _)| show_name)
+        .filter(|show_name| parse_backup_name(show_name, &name).is_some())
+        .ok_or_else(not_backup)?;
+    Ok(dir.parent().unwrap_or(Path::new(".")).join(show_name))
+}
+
+/// Puts a backup back in place of its show and loads it. The show file as it
+/// is now is backed up first, so a restore can itself be undone.
+pub fn restore(store: &ShowStore, backup_path: &Path) -> Result<Show, String> {
+    let show_path = show_path_for(backup_path)?;
+    // Read first: backing up the current file may rotate this backup away.
+    let restored = show_store::read_show_file(backup_path)?;
+    if show_path.exists() {
+        let current = show_store::read_show_file(&show_path)?;
+        create(&show_path, &current)?;
+    }
+    let data = show_store::encode_show_file(&restored)?;
+    show_store::write_atomic(&show_path, &data)
+        .map_err(|e| format!("Failed to restore {}: {}", show_path.display(), e))?;
+    // The journal belongs to the replaced snapshot.
+    match fs::remove_file(show_store::journal_path(&show_path)) {
+        Ok(()) => {}
+        Err(e) if e.kind() == ErrorKind::NotFound => {}
+        Err(e) => log::warn!("Failed to remove stale show journal: {}", e),
+    }
+    log::info!("Restored {} from {}", show_path.display(), backup_path.display());
+    store.import(show_path)
+}
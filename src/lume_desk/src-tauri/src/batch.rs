@@ -0,0 +1,60 @@
+use serde::{Deserialize, Serialize};
+
+/// What a batch operation over several controllers does when one fails.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BatchPolicy {
+    /// Carry on with the rest and report every failure.
+    #[default]
+    Continue,
+    /// Stop at the first failure; controllers not yet reached are skipped.
+    AbortOnFirstError,
+}
+
+/// Outcome of a batch operation, the same for every operation. `details`
+/// holds the operation's own result for each controller attempted.
+#[derive(Debug, Serialize)]
+pub struct BatchResult<T> {
+    pub policy: BatchPolicy,
+    pub succeeded: Vec<String>,
+    /// Address and error of each controller that failed.
+    pub failed: Vec<(String, String)>,
+    /// Not attempted because the batch was aborted.
+    pub skipped: Vec<String>,
+    /// Set once a failure stopped the batch, even if nothing was left to skip.
+    pub aborted: bool,
+    pub details: Vec<T>,
+}
+
+impl<T> BatchResult<T> {
+    pub fn new(policy: BatchPolicy) -> Self {
+        Self {
+            policy,
+            succeeded: Vec::new(),
+            failed: Vec::new(),
+            skipped: Vec::new(),
+            aborted: false,
+            details: Vec::new(),
+        }
+    }
+
+    /// Adds one controller's outcome; returns true if the batch must stop.
+    pub fn record(&mut self, address: &str, outcome: Result<(), String>, detail: T) -> bool {
+        self.details.push(detail);
+        match outcome {
+            Ok(()) => {
+                self.succeeded.push(address.to_string());
+                false
+            }
+            Err(error) => {
+                self.failed.push((address.to_string(), error));
+                self.aborted |= self.policy == BatchPolicy::AbortOnFirstError;
+                self.aborted
+            }
+        }
+    }
+
+    pub fn aborted(&self) -> bool {
+        self.aborted
+    }
+}
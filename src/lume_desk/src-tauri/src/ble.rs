@@ -0,0 +1,201 @@
+use btleplug::api::{Central, CentralState, Characteristic, Manager as _, Peripheral as _, ScanFilter, WriteType};
+use btleplug::platform::{Adapter, Manager, Peripheral};
+use std::collections::HashMap;
+use std::sync::OnceLock;
+use std::time::Duration;
+use tokio::sync::Mutex;
+use uuid::{uuid, Uuid};
+
+use crate::audit;
+use crate::controller_client::RequestError;
+use crate::registry::ControllerInfo;
+use crate::transport::{self, Transport};
+
+/// GATT service every LUME BLE controller advertises.
+pub const SERVICE_UUID: Uuid = uuid!("4c554d45-0001-4000-8000-00805f9b34fb");
+
+/// The desk writes requests here.
+const REQUEST_UUID: Uuid = uuid!("4c554d45-0002-4000-8000-00805f9b34fb");
+
+/// The controller leaves the reply here for the desk to read.
+const REPLY_UUID: Uuid = uuid!("4c554d45-0003-4000-8000-00805f9b34fb");
+
+/// Connecting and discovering services takes seconds over BLE, not milliseconds.
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Advertised local names, with the controller type each one runs.
+const LUME_NAMES: [(&str, &str); 3] = [
+    ("lume-controller", "firework"),
+    ("lume-lighting", "lights"),
+    ("lume-haze", "haze"),
+];
+
+const PERMISSION_HINT: &str =
+    "Bluetooth access was denied; allow LUME to use Bluetooth in the system settings and scan again";
+
+struct Link {
+    adapter: Adapter,
+    // Connected peripherals by id, with their request and reply characteristics.
+    connected: HashMap<String, (Peripheral, Characteristic, Characteristic)>,
+}
+
+// One link for the whole app: the OS only offers one central per adapter.
+// Requests are serialized, which BLE peripherals expect anyway.
+fn link() -> &'static Mutex<Option<Link>> {
+    static LINK: OnceLock<Mutex<Option<Link>>> = OnceLock::new();
+    LINK.get_or_init(|| Mutex::new(None))
+}
+
+fn describe(e: btleplug::Error) -> String {
+    match e {
+        btleplug::Error::PermissionDenied => PERMISSION_HINT.to_string(),
+        btleplug::Error::NoAdapterAvailable => "No Bluetooth adapter is available".to_string(),
+        e => format!("Bluetooth error: {}", e),
+    }
+}
+
+async fn open_adapter() -> Result<Adapter, String> {
+    let manager = Manager::new().await.map_err(describe)?;
+    let adapter = manager
+        .adapters()
+        .await
+        .map_err(describe)?
+        .into_iter()
+        .next()
+        .ok_or_else(|| "No Bluetooth adapter is available".to_string())?;
+    if adapter.adapter_state().await.map_err(describe)? == CentralState::PoweredOff {
+        return Err("Bluetooth is turned off".to_string());
+    }
+    Ok(adapter)
+}
+
+async fn adapter(link: &mut Option<Link>) -> Result<&mut Link, String> {
+    if link.is_none() {
+        *link = Some(Link {
+            adapter: open_adapter().await?,
+            connected: HashMap::new(),
+        });
+    }
+    Ok(link.as_mut().expect("link was just opened"))
+}
+
+/// Scans for `duration` and returns the LUME controllers that advertised.
+/// The first scan may trigger the OS Bluetooth permission prompt.
+pub async fn scan(duration: Duration) -> Result<Vec<ControllerInfo>, String> {
+    let mut guard = link().lock().await;
+    let link = adapter(&mut guard).await?;
+    link.adapter
+        .start_scan(ScanFilter {
+            services: vec![SERVICE_UUID],
+        })
+        .await
+        .map_err(describe)?;
+    tokio::time::sleep(duration).await;
+    if let Err(e) = link.adapter.stop_scan().await {
+        log::warn!("Failed to stop Bluetooth scan: {}", e);
+    }
+
+    let mut found = Vec::new();
+    for peripheral in link.adapter.peripherals().await.map_err(describe)? {
+        let Ok(Some(properties)) = peripheral.properties().await else {
+            continue;
+        };
+        if !properties.services.contains(&SERVICE_UUID) {
+            continue;
+        }
+        let name = properties.local_name.unwrap_or_default();
+        let controller_type = LUME_NAMES
+            .iter()
+            .find(|(prefix, _)| name.starts_with(prefix))
+            .map_or("lights", |(_, controller_type)| controller_type);
+        found.push(ControllerInfo {
+            address: format!("{}{}", transport::BLE_PREFIX, peripheral.id()),
+            name,
+            controller_type: controller_type.to_string(),
+            firmware_version: None,
+            channel_count: None,
+            power_budget_watts: None,
+            min_command_spacing_ms: None,
+            soft_start_ms: None,
+            safe_defaults: Default::default(),
+            favorite: false,
+            enabled: true,
+            mac: None,
+            transport: Transport::Ble,
+            last_seen: Some(audit::now_millis()),
+        });
+    }
+    log::info!("Bluetooth scan found {} LUME controllers", found.len());
+    Ok(found)
+}
+
+async fn connect(link: &mut Link, id: &str) -> Result<(Peripheral, Characteristic, Characteristic), String> {
+    if let Some((peripheral, request, reply)) = link.connected.get(id) {
+        if peripheral.is_connected().await.unwrap_or(false) {
+            return Ok((peripheral.clone(), request.clone(), reply.clone()));
+        }
+        link.connected.remove(id);
+    }
+    let peripheral = link
+        .adapter
+        .peripherals()
+        .await
+        .map_err(describe)?
+        .into_iter()
+        .find(|p| p.id().to_string() == id)
+        .ok_or_else(|| format!("Bluetooth device {} is not in range; scan again", id))?;
+    peripheral.connect_with_timeout(CONNECT_TIMEOUT).await.map_err(describe)?;
+    peripheral
+        .discover_services_with_timeout(CONNECT_TIMEOUT)
+        .await
+        .map_err(describe)?;
+    let characteristics = peripheral.characteristics();
+    let find = |uuid: Uuid| characteristics.iter().find(|c| c.uuid == uuid).cloned();
+    let (Some(request), Some(reply)) = (find(REQUEST_UUID), find(REPLY_UUID)) else {
+        let _ = peripheral.disconnect().await;
+        return Err(format!("Bluetooth device {} does not offer the LUME control service", id));
+    };
+    log::info!("Connected to Bluetooth controller {}", id);
+    link.connected
+        .insert(id.to_string(), (peripheral.clone(), request.clone(), reply.clone()));
+    Ok((peripheral, request, reply))
+}
+
+/// Sends `"<method> <path>"` and returns the controller's JSON reply. The
+/// reply carries the HTTP status the same request would get over Wi-Fi in
+/// `status`; anything but 2xx is a rejection.
+pub async fn request(address: &str, method: &str, path: &str, timeout: Duration) -> Result<serde_json::Value, RequestError> {
+    let id = transport::ble_id(address).unwrap_or(address);
+    let mut guard = link().lock().await;
+    let link = adapter(&mut guard).await.map_err(RequestError::Unreachable)?;
+    let (peripheral, request, reply) = connect(link, id).await.map_err(RequestError::Unreachable)?;
+
+    let exchange = async {
+        peripheral
+            .write(&request, format!("{} {}", method, path).as_bytes(), WriteType::WithResponse)
+            .await?;
+        peripheral.read(&reply).await
+    };
+    let body = match tokio::time::timeout(timeout, exchange).await {
+        Ok(Ok(body)) => body,
+        Ok(Err(e)) => {
+            // Drop the link so the next request reconnects from scratch.
+            link.connected.remove(id);
+            return Err(RequestError::Unreachable(format!("{} did not respond: {}", address, describe(e))));
+        }
+        Err(_) => {
+            link.connected.remove(id);
+            return Err(RequestError::Unreachable(format!(
+                "{} did not respond within {} ms",
+                address,
+                timeout.as_millis()
+            )));
+        }
+    };
+    let value: serde_json::Value = serde_json::from_slice(&body).unwrap_or(serde_json::Value::Null);
+    let status = value.get("status").and_then(|s| s.as_u64()).unwrap_or(200);
+    if !(200..300).contains(&status) {
+        return Err(RequestError::Rejected(format!("{} rejected {} with status {}", address, path, status)));
+    }
+    Ok(value)
+}
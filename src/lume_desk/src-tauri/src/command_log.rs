@@ -0,0 +1,110 @@
+use serde::Serialize;
+use std::collections::{HashMap, VecDeque};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use crate::audit::now_millis;
+use crate::controller_client::RequestError;
+
+pub const DEFAULT_CAPACITY: usize = 50;
+pub const MAX_CAPACITY: usize = 1000;
+
+/// What came back for a command.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AckStatus {
+    Acked,
+    /// The controller answered but refused the command.
+    Rejected,
+    /// No answer at all.
+    NoReply,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CommandRecord {
+    /// Milliseconds since the Unix epoch, when the command was sent.
+    pub timestamp: u64,
+    pub channel: u32,
+    /// The request as sent, e.g. "/dimmer?id=2&level=40".
+    pub request: String,
+    pub status: AckStatus,
+    pub latency_ms: u64,
+    pub error: Option<String>,
+}
+
+/// The last few commands sent to each controller, oldest dropped first.
+#[derive(Debug)]
+pub struct CommandLog {
+    capacity: AtomicUsize,
+    entries: Mutex<HashMap<String, VecDeque<CommandRecord>>>,
+}
+
+impl Default for CommandLog {
+    fn default() -> Self {
+        Self {
+            capacity: AtomicUsize::new(DEFAULT_CAPACITY),
+            entries: Mutex::default(),
+        }
+    }
+}
+
+impl CommandLog {
+    pub fn record(&self, controller: &str, channel: u32, request: String, elapsed: Duration, result: &Result<(), RequestError>) {
+        let capacity = self.capacity.load(Ordering::Relaxed);
+        if capacity == 0 {
+            return;
+        }
+        let record = CommandRecord {
+            timestamp: now_millis().saturating_sub(elapsed.as_millis() as u64),
+            channel,
+            request,
+            status: match result {
+                Ok(()) => AckStatus::Acked,
+                Err(e) if e.reached_controller() => AckStatus::Rejected,
+                Err(_) => AckStatus::NoReply,
+            },
+            latency_ms: elapsed.as_millis() as u64,
+            error: result.as_ref().err().map(|e| e.to_string()),
+        };
+        let Ok(mut entries) = self.entries.lock() else {
+            return;
+        };
+        let log = entries.entry(controller.to_string()).or_default();
+        log.push_back(record);
+        while log.len() > capacity {
+            log.pop_front();
+        }
+    }
+
+    /// Up to `n` of the latest commands sent to `controller`, oldest first.
+    pub fn last(&self, controller: &str, n: usize) -> Result<Vec<CommandRecord>, String> {
+        let entries = self.entries.lock().map_err(|_| "Command log is unavailable".to_string())?;
+        Ok(entries
+            .get(controller)
+            .map(|log| log.iter().skip(log.len().saturating_sub(n)).cloned().collect())
+            .unwrap_or_default())
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity.load(Ordering::Relaxed)
+    }
+
+    /// Commands kept per controller; 0 turns the log off. Shrinking drops
+    /// the oldest entries straight away.
+    pub fn set_capacity(&self, capacity: usize) -> Result<(), String> {
+        if capacity > MAX_CAPACITY {
+            return Err(format!("Command log size must be at most {}, got {}", MAX_CAPACITY, capacity));
+        }
+        self.capacity.store(capacity, Ordering::Relaxed);
+        let mut entries = self.entries.lock().map_err(|_| "Command log is unavailable".to_string())?;
+        for log in entries.values_mut() {
+            while log.len() > capacity {
+                log.pop_front();
+            }
+        }
+        entries.retain(|_, log| !log.is_empty());
+        log::info!("Keeping the last {} commands per controller", capacity);
+        Ok(())
+    }
+}
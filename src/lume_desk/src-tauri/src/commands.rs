@@ -1,13 +1,81 @@
-use tauri::command;
+use tauri::{command, AppHandle, Manager, State};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
+use std::net::IpAddr;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+use sysinfo::{MemoryRefreshKind, ProcessRefreshKind, ProcessesToUpdate, RefreshKind, System};
 
-#[derive(Debug, Serialize, Deserialize)]
-pub struct ShowStatus {
-    pub is_running: bool,
-    pub current_time: f64,
-    pub total_duration: f64,
-    pub active_effects: Vec<String>,
+use crate::audio::{self, AudioPlayer, AudioStatus, AudioTrack};
+use crate::audio_analysis::{self, AudioAnalysis};
+use crate::audit::{now_millis, AuditEntry, AuditKind, AuditLog};
+use crate::audit_report::{self, ReportHeader};
+use crate::backup::{self, BackupInfo};
+use crate::batch::{BatchPolicy, BatchResult};
+use crate::command_log::CommandRecord;
+use crate::control_lock::{ControlLock, ControlOwner};
+use crate::controller_client;
+use crate::controller_upload::{ControllerUploads, UploadReport, UploadVerification};
+use crate::crossfade::{self, CrossfadeReport};
+use crate::cue_sheet;
+use crate::diagnostics::{self, DiagnosticReport, ShowSummary};
+use crate::discovery::{self, BroadcastProbe, DiscoveredController, Discovery};
+use crate::dispatcher::{self, Dispatcher};
+use crate::dmx::{DmxConfig, DmxOutput, DmxPatch, DmxUniverse};
+use crate::edit_ops::{self, EffectTemplate, QuantizeReport, RandomizeParams, RandomizeReport};
+use crate::events::{self, EventSchema, FireSource, ShowOutputWarning};
+use crate::manual_control::{self, EffectParams};
+use crate::export::{self, CsvOptions, ExportOutcome};
+use crate::fleet::{self, ConnectionHealth, FleetEntry};
+use crate::folder_import::{self, ImportManifest};
+use crate::haze;
+use crate::laser::{LaserZones, Point};
+use crate::live_edit::{self, LiveEdit};
+use crate::models::{self, Effect, Layer, Rgb, Show, ShowParseError};
+use crate::monitor_window::{self, DisplayInfo};
+use crate::network::{self, NetworkInfo, StaticIpConfig, StaticIpOutcome};
+use crate::mqtt::{Mqtt, MqttCredentials, MqttStatus};
+use crate::network_map::{self, NetworkMap};
+use crate::output_refresh::{OutputRefresh, RefreshRate};
+use crate::palette;
+use crate::performance::{self, PerformanceHistory, PerformanceRecorder};
+use crate::power::{ControllerPower, PowerMonitor, PowerSettings};
+use crate::preflight::{self, PreflightReport, SelfTestControl};
+use crate::protocols::osc::{Osc, OscCommand, OscConfig};
+use crate::readback::{self, ControllerState};
+use crate::relay;
+use crate::registry_check::{self, ConsistencyReport};
+use crate::rig_test::{self, ControllerTestResult};
+use crate::registry::{self, ControllerConfig, ControllerInfo, ControllerRegistry, ControllerTelemetry, MergedDuplicate, Zone};
+use crate::response_cache;
+use crate::safe_mode;
+use crate::safety::{self, ArmState, Safety};
+use crate::schedule::{RecurrenceRule, ScheduleInfo, ShowScheduler};
+use crate::show_output::{self, ShowOutputConfig};
+use crate::show_schema;
+use crate::show_stats::{self, ShowStats};
+use crate::show_engine::{self, ComparisonSide, ErrorPolicy, ShowEngine, ShowStatus};
+use crate::show_store::{self, ImportedShow, SaveReport, ShowStore};
+use crate::shutdown;
+use crate::thumbnail::ThumbnailCache;
+use crate::timecode::{self, SyncMode, SyncStatus, TimecodeChase};
+use crate::time_format::{self, TimeDisplay, TimeFormat};
+use crate::transport::{self, Transport};
+use crate::trigger::{ExternalTrigger, TriggerSource, TriggerStatus};
+use crate::validation::{self, ValidationMode, ValidationReport};
+use crate::waveform::{self, WaveformEnvelope};
+use crate::zones::{self, ZoneReport};
+
+/// Per-controller settings and readings, for the controller stats view.
+#[derive(Debug, Serialize)]
+pub struct ControllerStats {
+    pub address: String,
+    pub name: Option<String>,
+    /// Set while the controller's outputs soft-start.
+    pub soft_start_ms: Option<u64>,
+    /// The last power reading, if power monitoring polled it.
+    pub power: Option<ControllerPower>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -16,109 +84,1680 @@ pub struct SystemInfo {
     pub platform: String,
     pub architecture: String,
     pub memory_usage: u64,
+    #[serde(default)]
+    pub hostname: String,
+    #[serde(default)]
+    pub cpu_core_count: usize,
+    /// Installed RAM in bytes.
+    #[serde(default)]
+    pub total_system_memory: u64,
+    /// Seconds since the app process started.
+    #[serde(default)]
+    pub uptime_secs: u64,
 }
 
 // Show control commands
 #[command]
-pub async fn start_show(show_data: String) -> Result<String, String> {
-    log::info!("Starting show with data length: {}", show_data.len());
-    
-    // TODO: Implement actual show start logic
-    // This would interface with your lighting/firework controllers
-    
-    Ok(format!("Show started with {} bytes of data", show_data.len()))
+pub async fn start_show(
+    app: AppHandle,
+    engine: State<'_, ShowEngine>,
+    store: State<'_, ShowStore>,
+    control: State<'_, ControlLock>,
+    show: Option<Show>,
+    operator_id: Option<String>,
+    resume: Option<bool>,
+) -> Result<String, String> {
+    control.check(operator_id.as_deref())?;
+
+    // Without a show, plays the active comparison version, or else the
+    // show already loaded in the backend. An explicit show ends comparison mode.
+    // Everything that can fail on bad data runs before the engine is touched.
+    let explicit = show.is_some();
+    let show: Show = match show {
+        Some(show) => show,
+        None => match engine.comparison_show()? {
+            Some(show) => show,
+            None => store.current()?.ok_or_else(|| "No show loaded".to_string())?,
+        },
+    };
+    log::info!("Starting show '{}' with {} effects", show.name, show.effects.len());
+    let show = palette::resolve(&show)?;
+    // Resuming picks up where the show was last stopped; otherwise it starts fresh.
+    let from = if resume.unwrap_or(false) { show.last_position.unwrap_or(0.0) } else { 0.0 };
+    let message = if from > 0.0 {
+        format!("Show '{}' resumed at {:.1}s", show.name, from)
+    } else {
+        format!("Show '{}' started with {} effects", show.name, show.effects.len())
+    };
+    engine.start_at(&app, show, from)?;
+    if explicit {
+        engine.clear_comparison()?;
+    }
+    Ok(message)
+}
+
+/// Crossfades from the running show into the show file at `path` over
+/// `duration` seconds; the new show becomes the loaded one.
+#[command]
+pub async fn crossfade_to_show(
+    app: AppHandle,
+    engine: State<'_, ShowEngine>,
+    store: State<'_, ShowStore>,
+    control: State<'_, ControlLock>,
+    path: String,
+    duration: f64,
+    operator_id: Option<String>,
+) -> Result<CrossfadeReport, String> {
+    control.check(operator_id.as_deref())?;
+    crossfade::check_duration(duration)?;
+    if !engine.status()?.is_running {
+        return Err("No show is running to crossfade from".to_string());
+    }
+    let show = palette::resolve(&store.import(PathBuf::from(&path))?)?;
+    log::info!("Crossfading into '{}' over {:.1}s", show.name, duration);
+    engine.crossfade(&app, show, duration).await
+}
+
+/// Starts the show file at `show_path` whenever `rule` comes due, across
+/// restarts. A run missed while the desk was off is skipped, not made up.
+#[command]
+pub async fn schedule_recurring(
+    scheduler: State<'_, ShowScheduler>,
+    show_path: PathBuf,
+    rule: RecurrenceRule,
+) -> Result<ScheduleInfo, String> {
+    scheduler.add(show_path, rule)
+}
+
+#[command]
+pub async fn list_schedules(scheduler: State<'_, ShowScheduler>) -> Result<Vec<ScheduleInfo>, String> {
+    scheduler.list()
 }
 
 #[command]
-pub async fn stop_show() -> Result<(), String> {
+pub async fn remove_schedule(scheduler: State<'_, ShowScheduler>, id: String) -> Result<bool, String> {
+    scheduler.remove(&id)
+}
+
+/// Parses show JSON text, e.g. a file being edited by hand, reporting where
+/// the first error is.
+#[command]
+pub async fn try_parse_show(show_data: String) -> Result<Show, ShowParseError> {
+    models::parse_show(&show_data)
+}
+
+/// Stops the show, then drives configured channels to their safe defaults.
+/// Reports the controllers that took them and those that did not answer.
+#[command]
+pub async fn stop_show(
+    app: AppHandle,
+    engine: State<'_, ShowEngine>,
+    store: State<'_, ShowStore>,
+    control: State<'_, ControlLock>,
+    registry: State<'_, ControllerRegistry>,
+    dispatcher: State<'_, Dispatcher>,
+    operator_id: Option<String>,
+) -> Result<ZoneReport, String> {
+    control.check(operator_id.as_deref())?;
     log::info!("Stopping show");
-    
-    // TODO: Implement actual show stop logic
-    
-    Ok(())
+    if let Some((show_id, time)) = engine.stop(&app).await? {
+        // Failing to note the position must not make the stop look failed.
+        if let Err(e) = store.remember_position(&show_id, time) {
+            log::warn!("Failed to remember playback position: {}", e);
+        }
+    }
+    zones::apply_safe_defaults(&dispatcher, &registry).await
 }
 
+/// Holds the running show at its current position until `resume_show`.
 #[command]
-pub async fn get_show_status() -> Result<ShowStatus, String> {
-    // TODO: Get real status from your show controller
-    Ok(ShowStatus {
-        is_running: false,
-        current_time: 0.0,
-        total_duration: 0.0,
-        active_effects: vec![],
-    })
+pub async fn pause_show(
+    app: AppHandle,
+    engine: State<'_, ShowEngine>,
+    control: State<'_, ControlLock>,
+    operator_id: Option<String>,
+) -> Result<f64, String> {
+    control.check(operator_id.as_deref())?;
+    engine.pause(&app)
+}
+
+/// Continues a paused show, or one held after a cue failure.
+#[command]
+pub async fn resume_show(
+    app: AppHandle,
+    engine: State<'_, ShowEngine>,
+    control: State<'_, ControlLock>,
+    operator_id: Option<String>,
+) -> Result<f64, String> {
+    control.check(operator_id.as_deref())?;
+    engine.resume(&app)
+}
+
+#[command]
+pub async fn set_show_error_policy(engine: State<'_, ShowEngine>, policy: ErrorPolicy) -> Result<(), String> {
+    engine.set_error_policy(policy)
+}
+
+/// Back to the start of the running show, which keeps playing.
+#[command]
+pub async fn rewind_show(
+    app: AppHandle,
+    engine: State<'_, ShowEngine>,
+    control: State<'_, ControlLock>,
+    operator_id: Option<String>,
+) -> Result<f64, String> {
+    control.check(operator_id.as_deref())?;
+    engine.seek(&app, 0.0).await
+}
+
+/// Moves the playhead of the running or paused show to `position_ms`; a
+/// paused show stays paused. Cues before it are not fired.
+#[command]
+pub async fn seek_show(
+    app: AppHandle,
+    engine: State<'_, ShowEngine>,
+    control: State<'_, ControlLock>,
+    position_ms: u64,
+    operator_id: Option<String>,
+) -> Result<f64, String> {
+    control.check(operator_id.as_deref())?;
+    engine.seek(&app, position_ms as f64 / 1000.0).await
+}
+
+/// Jumps to the end of the running show; skipped cues are not fired.
+#[command]
+pub async fn skip_to_end(
+    app: AppHandle,
+    engine: State<'_, ShowEngine>,
+    control: State<'_, ControlLock>,
+    operator_id: Option<String>,
+) -> Result<f64, String> {
+    control.check(operator_id.as_deref())?;
+    engine.skip_to_end(&app).await
+}
+
+/// Changes a cue the running show has not reached yet, without stopping
+/// it. The loaded show gets the same edit, undoably, so it is kept once
+/// the show ends. Returns the show time the edit was made at.
+#[command]
+pub async fn apply_live_edit(
+    engine: State<'_, ShowEngine>,
+    store: State<'_, ShowStore>,
+    control: State<'_, ControlLock>,
+    edit: LiveEdit,
+    operator_id: Option<String>,
+) -> Result<f64, String> {
+    control.check(operator_id.as_deref())?;
+    let running = engine.running_show()?.ok_or("No show is playing")?;
+    let playhead = engine.apply_live_edit(&edit)?;
+    if store.current()?.is_some_and(|show| show.id == running.id) {
+        if let Err(e) = store.edit("Live edit", |show| live_edit::apply(show, &edit, &playhead)) {
+            log::warn!("Live edit was not applied to the loaded show: {}", e);
+        }
+    }
+    Ok(playhead.time)
+}
+
+/// Takes exclusive transport control, or renews it for the holder.
+/// Lapses after `lease_secs` (default 300) unless renewed.
+#[command]
+pub async fn acquire_control(
+    control: State<'_, ControlLock>,
+    operator_id: String,
+    lease_secs: Option<u64>,
+) -> Result<ControlOwner, String> {
+    control.acquire(&operator_id, lease_secs.map(Duration::from_secs))
+}
+
+#[command]
+pub async fn release_control(control: State<'_, ControlLock>, operator_id: String) -> Result<bool, String> {
+    control.release(&operator_id)
+}
+
+#[command]
+pub async fn get_control_owner(control: State<'_, ControlLock>) -> Result<Option<ControlOwner>, String> {
+    control.owner()
+}
+
+#[command]
+pub async fn get_show_status(engine: State<'_, ShowEngine>, formatted: Option<bool>) -> Result<ShowStatus, String> {
+    let mut status = engine.status()?;
+    if formatted.unwrap_or(false) {
+        status.current_time_display = Some(time_format::format_current(status.current_time));
+    }
+    Ok(status)
+}
+
+/// How show times are formatted for display: seconds, minutes:seconds or
+/// SMPTE timecode at `fps`.
+#[command]
+pub async fn set_time_display(format: TimeFormat, fps: Option<u32>) -> Result<TimeDisplay, String> {
+    time_format::set(format, fps)
+}
+
+#[command]
+pub async fn get_time_display() -> Result<TimeDisplay, String> {
+    Ok(time_format::current())
+}
+
+/// Called by the audio player or timecode reader with its current position.
+/// Returns the measured drift in milliseconds.
+#[command]
+pub async fn report_sync_position(app: AppHandle, engine: State<'_, ShowEngine>, position: f64) -> Result<f64, String> {
+    engine.report_sync(&app, position)
+}
+
+/// Sets the playback engine tick rate; see show_engine for the trade-offs.
+#[command]
+pub async fn set_engine_tick_rate(hz: u32) -> Result<(), String> {
+    show_engine::set_tick_rate(hz)
+}
+
+/// Loads two show files for A/B review; start_show with empty data plays the active one.
+#[command]
+pub async fn load_comparison(engine: State<'_, ShowEngine>, path_a: PathBuf, path_b: PathBuf) -> Result<(), String> {
+    log::info!("Loading comparison {} vs {}", path_a.display(), path_b.display());
+    let a = palette::resolve(&show_store::read_show_file(&path_a)?)?;
+    let b = palette::resolve(&show_store::read_show_file(&path_b)?)?;
+    engine.load_comparison(a, b)
+}
+
+/// Switches the playing version, keeping the current timestamp. Returns that timestamp.
+#[command]
+pub async fn switch_comparison(
+    app: AppHandle,
+    engine: State<'_, ShowEngine>,
+    control: State<'_, ControlLock>,
+    which: ComparisonSide,
+    operator_id: Option<String>,
+) -> Result<f64, String> {
+    control.check(operator_id.as_deref())?;
+    engine.switch_comparison(&app, which).await
+}
+
+#[command]
+pub async fn get_event_schema() -> Result<Vec<EventSchema>, String> {
+    Ok(events::schema())
 }
 
 // System information commands
 #[command]
 pub async fn get_system_info() -> Result<SystemInfo, String> {
+    system_info()
+}
+
+fn system_info() -> Result<SystemInfo, String> {
     let version = env!("CARGO_PKG_VERSION").to_string();
     
+    let mut system = System::new_with_specifics(
+        RefreshKind::nothing().with_memory(MemoryRefreshKind::nothing().with_ram()),
+    );
+    let pid = sysinfo::get_current_pid()?;
+    system.refresh_processes_specifics(ProcessesToUpdate::Some(&[pid]), false, ProcessRefreshKind::nothing());
+    let cpu_core_count = System::physical_core_count()
+        .or_else(|| std::thread::available_parallelism().ok().map(|n| n.get()))
+        .unwrap_or(1);
+
     Ok(SystemInfo {
         app_version: version,
         platform: std::env::consts::OS.to_string(),
         architecture: std::env::consts::ARCH.to_string(),
-        memory_usage: get_memory_usage(),
+        memory_usage: performance::memory_usage(),
+        hostname: tauri_plugin_os::hostname(),
+        cpu_core_count,
+        total_system_memory: system.total_memory(),
+        uptime_secs: system.process(pid).map_or(0, |p| p.run_time()),
     })
 }
 
 // Hardware discovery commands
 #[command]
-pub async fn scan_controllers() -> Result<Vec<String>, String> {
+pub async fn scan_controllers(app: AppHandle, discovery: State<'_, Discovery>) -> Result<Vec<DiscoveredController>, String> {
     log::info!("Scanning for controllers...");
-    
-    // TODO: Implement actual controller discovery
-    // This would scan for your LUME devices on the network
-    
-    Ok(vec![
-        "lume-base.local".to_string(),
-        "lume-controller-01.local".to_string(),
-    ])
+    discovery.run_pass(&app, true).await
+}
+
+/// Scans Bluetooth LE for LUME controllers; defaults to a 5 second scan.
+#[command]
+pub async fn scan_ble_controllers(app: AppHandle, duration_secs: Option<u64>) -> Result<Vec<String>, String> {
+    let duration = Duration::from_secs(duration_secs.unwrap_or(5).clamp(1, 60));
+    log::info!("Scanning Bluetooth for controllers for {}s...", duration.as_secs());
+    discovery::scan_ble(&app, duration).await
+}
+
+/// Sets how often discovery re-runs in the background; 0 disables it.
+#[command]
+pub async fn set_discovery_interval(
+    app: AppHandle,
+    discovery: State<'_, Discovery>,
+    secs: u64,
+) -> Result<(), String> {
+    let interval = (secs > 0).then(|| Duration::from_secs(secs));
+    discovery.set_interval(&app, interval)
+}
+
+/// Sets the port and payload of the broadcast discovery probe; either left
+/// out goes back to the default.
+#[command]
+pub async fn set_broadcast_discovery(
+    discovery: State<'_, Discovery>,
+    port: Option<u16>,
+    magic: Option<String>,
+) -> Result<BroadcastProbe, String> {
+    let probe = BroadcastProbe {
+        port: port.unwrap_or(discovery::DEFAULT_BROADCAST_PORT),
+        magic: magic.unwrap_or_else(|| discovery::DEFAULT_BROADCAST_MAGIC.to_string()),
+    };
+    discovery.set_broadcast(probe)?;
+    discovery.broadcast()
 }
 
+/// Checks that a controller answers and reads its firmware and channel
+/// count; caches both for a registered controller.
 #[command]
-pub async fn test_controller_connection(address: String) -> Result<bool, String> {
+pub async fn test_controller_connection(
+    registry: State<'_, ControllerRegistry>,
+    dispatcher: State<'_, Dispatcher>,
+    address: String,
+) -> Result<ConnectionHealth, String> {
     log::info!("Testing connection to: {}", address);
-    
-    // TODO: Implement actual connection test
-    // This would ping/test your controller
-    
-    Ok(true)
+    let controller = registry.get(&address).ok();
+    let address = controller.as_ref().map_or(address, |c| c.address.clone());
+
+    let health = fleet::check_connection(&address, controller.clone()).await;
+    match health.latency_ms {
+        Some(ms) => {
+            log::info!("{} answered in {} ms", address, ms);
+            dispatcher.note_reachability(&address, true, None);
+        }
+        None => {
+            log::info!("{}", health.error.as_deref().unwrap_or("Unreachable"));
+            dispatcher.note_reachability(&address, false, health.error.clone());
+        }
+    }
+    if controller.is_some() && health.reachable && health.error.is_none() {
+        registry.update(&address, |info| {
+            info.firmware_version = health.firmware_version.clone();
+            info.channel_count = health.channel_count;
+        })?;
+    }
+    Ok(health)
+}
+
+/// Sets how long to wait for a controller on `transport` before giving up.
+#[command]
+pub async fn set_transport_timeout(transport: Transport, ms: u64) -> Result<(), String> {
+    log::info!("Setting {:?} timeout to {} ms", transport, ms);
+    transport::set_timeout(transport, ms)
+}
+
+/// Re-sends a controller's steady outputs `hz` times a second, independent
+/// of the engine tick; 0 turns refreshing off.
+#[command]
+pub async fn set_output_refresh_rate(
+    app: AppHandle,
+    registry: State<'_, ControllerRegistry>,
+    refresh: State<'_, OutputRefresh>,
+    target: String,
+    hz: u32,
+) -> Result<(), String> {
+    let controller = registry.get(&target)?;
+    refresh.set_rate(&app, &controller, hz)
+}
+
+#[command]
+pub async fn get_output_refresh_rates(refresh: State<'_, OutputRefresh>) -> Result<Vec<RefreshRate>, String> {
+    refresh.rates()
+}
+
+// Controller registry commands
+/// Returns the existing entry the controller was merged into, if it was
+/// already registered under another address spelling or MAC.
+#[command]
+pub async fn add_controller(
+    registry: State<'_, ControllerRegistry>,
+    controller: ControllerInfo,
+) -> Result<Option<MergedDuplicate>, String> {
+    log::info!("Registering controller: {}", controller.address);
+    registry.add(controller)
+}
+
+#[command]
+pub async fn remove_controller(registry: State<'_, ControllerRegistry>, address: String) -> Result<bool, String> {
+    log::info!("Removing controller: {}", address);
+    let address = registry.get(&address).map(|c| c.address).unwrap_or(address);
+    registry.remove(&address)
+}
+
+/// Renames a controller, pins it to a new address or changes its limits.
+/// Refused during a show when it moves the controller, as the running show
+/// still addresses it by the old one.
+#[command]
+pub async fn update_controller_config(
+    registry: State<'_, ControllerRegistry>,
+    engine: State<'_, ShowEngine>,
+    address: String,
+    config: ControllerConfig,
+) -> Result<ControllerInfo, String> {
+    if config.address.is_some() && engine.status()?.is_running {
+        return Err("Stop the show before changing a controller's address".to_string());
+    }
+    let controller = registry.configure(&address, config)?;
+    log::info!("Updated config of {}", controller.label());
+    Ok(controller)
+}
+
+/// Takes a controller out of playback, polling and reconnects, or brings
+/// it back, without forgetting its config. Outputs it holds are cleared first.
+#[command]
+pub async fn set_controller_enabled(
+    registry: State<'_, ControllerRegistry>,
+    dispatcher: State<'_, Dispatcher>,
+    address: String,
+    enabled: bool,
+) -> Result<ControllerInfo, String> {
+    let address = registry.get(&address)?.address;
+    if !enabled {
+        dispatcher.release_controller(&address).await;
+    }
+    registry.update(&address, |info| info.enabled = enabled)?;
+    log::info!("Controller {} {}", address, if enabled { "enabled" } else { "disabled" });
+    registry.get(&address)
+}
+
+/// Duplicate entries for one device, favorites that do not answer, and
+/// names that resolve to more than one controller.
+#[command]
+pub async fn check_registry_consistency(
+    registry: State<'_, ControllerRegistry>,
+    dispatcher: State<'_, Dispatcher>,
+) -> Result<ConsistencyReport, String> {
+    registry_check::check(&registry, &dispatcher).await
+}
+
+#[command]
+pub async fn list_controllers(
+    registry: State<'_, ControllerRegistry>,
+    favorites_only: Option<bool>,
+    favorites_first: Option<bool>,
+) -> Result<Vec<ControllerInfo>, String> {
+    let mut controllers = registry.list()?;
+    if favorites_only.unwrap_or(false) {
+        controllers.retain(|c| c.favorite);
+    }
+    if favorites_first.unwrap_or(false) {
+        // Stable, so each group keeps its address order.
+        controllers.sort_by_key(|c| !c.favorite);
+    }
+    Ok(controllers)
+}
+
+#[command]
+pub async fn favorite_controller(
+    registry: State<'_, ControllerRegistry>,
+    address: String,
+    favorite: bool,
+) -> Result<ControllerInfo, String> {
+    let address = registry.get(&address)?.address;
+    registry.update(&address, |info| info.favorite = favorite)?;
+    registry.get(&address)
+}
+
+#[command]
+pub async fn get_controller_network_info(
+    registry: State<'_, ControllerRegistry>,
+    address: String,
+    force: Option<bool>,
+) -> Result<NetworkInfo, String> {
+    let controller = registry.get(&address)?;
+    let info = network::info(&controller, force.unwrap_or(false)).await?;
+    // Remembered so a second entry for the same device can be recognised.
+    if let Some(mac) = info.mac.as_deref().map(registry::normalize_mac) {
+        if controller.mac.as_deref() != Some(mac.as_str()) {
+            registry.update(&controller.address, |c| c.mac = Some(mac))?;
+        }
+    }
+    Ok(info)
+}
+
+/// Firmware and capabilities of one controller, from the cache when recent.
+#[command]
+pub async fn get_controller_capabilities(
+    registry: State<'_, ControllerRegistry>,
+    address: String,
+    force: Option<bool>,
+) -> Result<FleetEntry, String> {
+    let entry = fleet::query_one(registry.get(&address)?, force.unwrap_or(false)).await;
+    match &entry.error {
+        Some(error) => Err(error.clone()),
+        None => Ok(entry),
+    }
+}
+
+/// Forgets cached replies from a controller so the next query asks it again.
+#[command]
+pub async fn invalidate_controller_cache(registry: State<'_, ControllerRegistry>, address: String) -> Result<usize, String> {
+    let address = registry.get(&address).map(|c| c.address).unwrap_or(address);
+    Ok(response_cache::invalidate(&address))
+}
+
+/// How long controller info is cached; 0 turns caching off.
+#[command]
+pub async fn set_controller_cache_ttl(secs: u64) -> Result<(), String> {
+    response_cache::set_ttl(secs)
+}
+
+/// The last `n` commands sent to a controller, oldest first; all that are
+/// kept if `n` is not given.
+#[command]
+pub async fn get_controller_command_log(
+    registry: State<'_, ControllerRegistry>,
+    dispatcher: State<'_, Dispatcher>,
+    address: String,
+    n: Option<usize>,
+) -> Result<Vec<CommandRecord>, String> {
+    let address = registry.get(&address).map(|c| c.address).unwrap_or(address);
+    dispatcher.command_log(&address, n.unwrap_or_else(|| dispatcher.command_log_size()))
+}
+
+/// Commands kept per controller for the command log; 0 turns it off.
+#[command]
+pub async fn set_command_log_size(dispatcher: State<'_, Dispatcher>, size: usize) -> Result<(), String> {
+    dispatcher.set_command_log_size(size)
+}
+
+/// Ramps a controller's lighting outputs up from off over `ms` rather than
+/// switching them straight on; 0 turns it off.
+#[command]
+pub async fn set_soft_start(registry: State<'_, ControllerRegistry>, address: String, ms: u64) -> Result<ControllerInfo, String> {
+    let controller = registry.get(&address)?;
+    if controller.is_pyro() && ms > 0 {
+        return Err("Pyro channels always fire instantly".to_string());
+    }
+    if ms > dispatcher::MAX_SOFT_START.as_millis() as u64 {
+        return Err(format!("Soft-start can be at most {} ms", dispatcher::MAX_SOFT_START.as_millis()));
+    }
+    registry.update(&controller.address, |info| info.soft_start_ms = (ms > 0).then_some(ms))?;
+    registry.get(&controller.address)
+}
+
+/// The level (0-100) a channel goes to on stop and emergency stop instead of
+/// off; None goes back to off.
+#[command]
+pub async fn set_safe_default(
+    registry: State<'_, ControllerRegistry>,
+    controller: String,
+    channel: u32,
+    value: Option<u8>,
+) -> Result<ControllerInfo, String> {
+    let controller = registry.get(&controller)?;
+    if let Some(value) = value {
+        controller.check_safe_default(channel, value)?;
+    }
+    registry.update(&controller.address, |info| match value {
+        Some(value) => {
+            info.safe_defaults.insert(channel, value);
+        }
+        None => {
+            info.safe_defaults.remove(&channel);
+        }
+    })?;
+    log::info!("Safe default of {} channel {} set to {:?}", controller.label(), channel, value);
+    registry.get(&controller.address)
+}
+
+/// Pushes a static IP configuration and waits for the controller to come
+/// back. Refused during a show, as the controller drops off the network.
+#[command]
+pub async fn set_controller_static_ip(
+    registry: State<'_, ControllerRegistry>,
+    engine: State<'_, ShowEngine>,
+    address: String,
+    config: StaticIpConfig,
+) -> Result<StaticIpOutcome, String> {
+    if engine.status()?.is_running {
+        return Err("Stop the show before changing controller network settings".to_string());
+    }
+    let controller = registry.get(&address)?;
+    network::set_static_ip(&registry, &controller, &config).await
+}
+
+/// Uploads the part of `show_data` (or of the loaded show, when empty) that
+/// the controller plays, for it to run without the desk. Progress is
+/// reported with show-upload-progress events and the stored copy is
+/// verified by checksum.
+#[command]
+pub async fn upload_show_to_controller(
+    app: AppHandle,
+    registry: State<'_, ControllerRegistry>,
+    store: State<'_, ShowStore>,
+    engine: State<'_, ShowEngine>,
+    uploads: State<'_, ControllerUploads>,
+    address: String,
+    show_data: String,
+) -> Result<UploadReport, String> {
+    if engine.status()?.is_running {
+        return Err("Stop the show before uploading to a controller".to_string());
+    }
+    let controller = registry.get(&address)?;
+    if !controller.enabled {
+        return Err(format!("{} is disabled", controller.label()));
+    }
+    let show = if show_data.trim().is_empty() {
+        store.current()?.ok_or_else(|| "No show loaded".to_string())?
+    } else {
+        models::parse_show(&show_data).map_err(|e| e.to_string())?
+    };
+    let show = palette::resolve(&show)?;
+    uploads.upload(&app, &registry, &controller, &show).await
+}
+
+#[command]
+pub async fn verify_uploaded_show(
+    registry: State<'_, ControllerRegistry>,
+    uploads: State<'_, ControllerUploads>,
+    address: String,
+) -> Result<UploadVerification, String> {
+    uploads.verify(&registry.get(&address)?).await
+}
+
+/// Starts a controller's uploaded sequence once it verifies. Pyro
+/// controllers need the system armed, as they fire without the desk.
+#[command]
+pub async fn trigger_controller_playback(
+    registry: State<'_, ControllerRegistry>,
+    safety: State<'_, Safety>,
+    control: State<'_, ControlLock>,
+    uploads: State<'_, ControllerUploads>,
+    address: String,
+    operator_id: Option<String>,
+) -> Result<UploadVerification, String> {
+    control.check(operator_id.as_deref())?;
+    let controller = registry.get(&address)?;
+    if !controller.enabled {
+        return Err(format!("{} is disabled", controller.label()));
+    }
+    if controller.is_pyro() {
+        safety.require_armed()?;
+    }
+    uploads.trigger(&controller).await
+}
+
+/// Asks a controller to show itself, e.g. by blinking, to find it in the rig.
+#[command]
+pub async fn identify_controller(
+    registry: State<'_, ControllerRegistry>,
+    dispatcher: State<'_, Dispatcher>,
+    address: String,
+) -> Result<(), String> {
+    let controller = registry.get(&address)?;
+    let driver = dispatcher.driver(&controller.address);
+    let path = driver
+        .identify()
+        .ok_or_else(|| format!("{} controllers cannot identify themselves", driver.name()))?;
+    controller_client::post(&controller.address, &path, transport::timeout(controller.transport))
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[command]
+pub async fn query_fleet(
+    registry: State<'_, ControllerRegistry>,
+    force: Option<bool>,
+    policy: Option<BatchPolicy>,
+) -> Result<BatchResult<FleetEntry>, String> {
+    log::info!("Querying controller fleet");
+    fleet::query(&registry, force.unwrap_or(false), policy.unwrap_or_default()).await
+}
+
+/// What a controller is outputting now, compared with what was last
+/// commanded. Controllers that cannot report their outputs say so.
+#[command]
+pub async fn read_controller_state(
+    registry: State<'_, ControllerRegistry>,
+    dispatcher: State<'_, Dispatcher>,
+    address: String,
+) -> Result<ControllerState, String> {
+    readback::read(&dispatcher, &registry.get(&address)?).await
+}
+
+/// Test pulse on a relay device channel, e.g. to check a smoke curtain is wired.
+#[command]
+pub async fn pulse_relay(
+    registry: State<'_, ControllerRegistry>,
+    dispatcher: State<'_, Dispatcher>,
+    audit: State<'_, AuditLog>,
+    address: String,
+    channel: u32,
+    ms: u64,
+) -> Result<(), String> {
+    let controller = registry.get(&address)?;
+    log::info!("Pulsing {} channel {} for {} ms", controller.label(), channel, ms);
+    let result = relay::pulse(&dispatcher, &controller, channel, ms).await;
+    audit.record(AuditKind::ManualOverride, &controller, channel, &result, format!("Relay test pulse {} ms", ms));
+    result
+}
+
+/// Pulses each controller's first channel in turn, `stagger_ms` apart, so a
+/// tech can walk the rig and confirm every unit responds.
+#[command]
+#[allow(clippy::too_many_arguments)]
+pub async fn test_controllers(
+    app: AppHandle,
+    registry: State<'_, ControllerRegistry>,
+    dispatcher: State<'_, Dispatcher>,
+    safety: State<'_, Safety>,
+    audit: State<'_, AuditLog>,
+    addresses: Vec<String>,
+    stagger_ms: u64,
+    policy: Option<BatchPolicy>,
+) -> Result<BatchResult<ControllerTestResult>, String> {
+    let controllers = addresses
+        .iter()
+        .map(|address| registry.get(address))
+        .collect::<Result<Vec<_>, _>>()?;
+    rig_test::run(&app, &dispatcher, &safety, &audit, controllers, stagger_ms, policy.unwrap_or_default()).await
+}
+
+/// Disarms, stops the show and puts every controller into its safe state.
+#[command]
+pub async fn emergency_stop(app: AppHandle) -> Result<ZoneReport, String> {
+    safety::emergency_stop(&app).await
+}
+
+/// Subnets, shared gateways and latency groups of the registered
+/// controllers as a graph, for spotting controllers behind a slow access point.
+#[command]
+pub async fn build_network_map(
+    registry: State<'_, ControllerRegistry>,
+    dispatcher: State<'_, Dispatcher>,
+) -> Result<NetworkMap, String> {
+    log::info!("Building network map");
+    network_map::build(&registry, &dispatcher).await
+}
+
+// Zone commands
+#[command]
+pub async fn create_zone(registry: State<'_, ControllerRegistry>, name: String, addresses: Vec<String>) -> Result<Zone, String> {
+    log::info!("Saving zone '{}' with {} controllers", name, addresses.len());
+    registry.create_zone(&name, addresses)
+}
+
+#[command]
+pub async fn delete_zone(registry: State<'_, ControllerRegistry>, name: String) -> Result<bool, String> {
+    log::info!("Deleting zone '{}'", name);
+    registry.delete_zone(&name)
+}
+
+#[command]
+pub async fn list_zones(registry: State<'_, ControllerRegistry>) -> Result<Vec<Zone>, String> {
+    registry.zones()
+}
+
+#[command]
+pub async fn blackout_zone(
+    registry: State<'_, ControllerRegistry>,
+    dispatcher: State<'_, Dispatcher>,
+    name: String,
+) -> Result<ZoneReport, String> {
+    log::info!("Blackout of zone '{}'", name);
+    zones::blackout(&dispatcher, &registry, &name).await
+}
+
+#[command]
+pub async fn clear_blackout(
+    registry: State<'_, ControllerRegistry>,
+    dispatcher: State<'_, Dispatcher>,
+    name: String,
+) -> Result<ZoneReport, String> {
+    log::info!("Clearing blackout of zone '{}'", name);
+    zones::clear_blackout(&dispatcher, &registry, &name)
+}
+
+#[command]
+pub async fn isolate_zone(
+    registry: State<'_, ControllerRegistry>,
+    dispatcher: State<'_, Dispatcher>,
+    name: String,
+) -> Result<ZoneReport, String> {
+    zones::isolate(&dispatcher, &registry, &name)
+}
+
+#[command]
+pub async fn clear_isolation(dispatcher: State<'_, Dispatcher>) -> Result<(), String> {
+    log::info!("Clearing zone isolation");
+    zones::clear_isolation(&dispatcher)
+}
+
+#[command]
+pub async fn test_zone(registry: State<'_, ControllerRegistry>, name: String) -> Result<ZoneReport, String> {
+    log::info!("Testing zone '{}'", name);
+    zones::test(&registry, &name).await
+}
+
+#[command]
+pub async fn set_zone_muted(
+    registry: State<'_, ControllerRegistry>,
+    dispatcher: State<'_, Dispatcher>,
+    name: String,
+    muted: bool,
+) -> Result<ZoneReport, String> {
+    log::info!("{} zone '{}'", if muted { "Muting" } else { "Unmuting" }, name);
+    zones::set_muted(&dispatcher, &registry, &name, muted)
+}
+
+// DMX output commands
+#[command]
+pub async fn get_dmx_config(dmx: State<'_, DmxOutput>) -> Result<DmxConfig, String> {
+    dmx.config()
+}
+
+/// Sends Art-Net `universe` to `target`, a node's IPv4 address or a broadcast address.
+#[command]
+pub async fn set_artnet_universe(dmx: State<'_, DmxOutput>, universe: u16, target: String) -> Result<DmxUniverse, String> {
+    dmx.set_artnet_universe(universe, &target)
+}
+
+/// Sends sACN `universe` by unicast to `target`, or by multicast without one.
+/// `priority` is 0-200 and defaults to 100.
+#[command]
+pub async fn set_sacn_universe(
+    dmx: State<'_, DmxOutput>,
+    universe: u16,
+    target: Option<String>,
+    priority: Option<u8>,
+) -> Result<DmxUniverse, String> {
+    dmx.set_sacn_universe(universe, target.as_deref(), priority)
+}
+
+/// Synchronizes sACN output on `universe`; None turns synchronization off.
+#[command]
+pub async fn set_sacn_sync(dmx: State<'_, DmxOutput>, universe: Option<u16>) -> Result<(), String> {
+    dmx.set_sync_universe(universe)
+}
+
+#[command]
+pub async fn remove_dmx_universe(dmx: State<'_, DmxOutput>, universe: u16) -> Result<bool, String> {
+    dmx.remove_universe(universe)
+}
+
+/// Plays the show's cues on `patch.fixture`'s channel as DMX levels or colors
+/// instead of sending them to a controller.
+#[command]
+pub async fn patch_dmx_channel(dmx: State<'_, DmxOutput>, patch: DmxPatch) -> Result<DmxPatch, String> {
+    log::info!(
+        "Patching {} channel {} to universe {} at {}",
+        patch.fixture, patch.channel, patch.universe, patch.address
+    );
+    dmx.patch(patch)
+}
+
+#[command]
+pub async fn unpatch_dmx_channel(dmx: State<'_, DmxOutput>, fixture: String, channel: u32) -> Result<bool, String> {
+    dmx.unpatch(&fixture, channel)
+}
+
+// Safety commands
+#[command]
+pub async fn arm_system(safety: State<'_, Safety>, registry: State<'_, ControllerRegistry>) -> Result<ArmState, String> {
+    let state = safety.arm()?;
+    safety::arm_controllers(&registry, true).await?;
+    Ok(state)
+}
+
+#[command]
+pub async fn disarm_system(safety: State<'_, Safety>, registry: State<'_, ControllerRegistry>) -> Result<ArmState, String> {
+    let state = safety.disarm()?;
+    safety::arm_controllers(&registry, false).await?;
+    Ok(state)
+}
+
+#[command]
+pub async fn get_arm_state(safety: State<'_, Safety>) -> Result<ArmState, String> {
+    safety.state()
+}
+
+/// Answers a close-blocked event: stops the show, disarms and exits.
+#[command]
+pub async fn confirm_close(app: AppHandle) -> Result<(), String> {
+    shutdown::confirm_close(&app).await
+}
+
+// External trigger commands
+/// Sets where the start signal comes from; None removes the trigger. Always disarms.
+#[command]
+pub async fn configure_trigger(
+    app: AppHandle,
+    trigger: State<'_, ExternalTrigger>,
+    source: Option<TriggerSource>,
+) -> Result<(), String> {
+    trigger.configure(&app, source)
+}
+
+/// The next trigger signal starts the loaded show.
+#[command]
+pub async fn arm_trigger(trigger: State<'_, ExternalTrigger>) -> Result<TriggerStatus, String> {
+    trigger.set_armed(true)
+}
+
+#[command]
+pub async fn disarm_trigger(trigger: State<'_, ExternalTrigger>) -> Result<TriggerStatus, String> {
+    trigger.set_armed(false)
+}
+
+#[command]
+pub async fn get_trigger_status(trigger: State<'_, ExternalTrigger>) -> Result<TriggerStatus, String> {
+    trigger.status()
+}
+
+/// Reports a key press from a keyboard-emulating trigger button.
+#[command]
+pub async fn fire_trigger(app: AppHandle, trigger: State<'_, ExternalTrigger>, key: String) -> Result<(), String> {
+    trigger.key_pressed(&app, &key)
+}
+
+// OSC commands
+/// Listens for OSC show commands on `listen_port` of `listen_address`
+/// (loopback by default), or not at all without a port, and sends a marker
+/// cue to each of `targets` ("host:port") as markers pass. When
+/// `allowed_senders` is not empty, only their commands run.
+#[command]
+pub async fn configure_osc(
+    app: AppHandle,
+    osc: State<'_, Osc>,
+    listen_port: Option<u16>,
+    listen_address: Option<IpAddr>,
+    allowed_senders: Option<Vec<IpAddr>>,
+    targets: Vec<String>,
+) -> Result<OscConfig, String> {
+    osc.configure(&app, listen_port, listen_address, allowed_senders.unwrap_or_default(), targets).await
+}
+
+/// Makes OSC messages to `address` run `command`; None removes the mapping.
+#[command]
+pub async fn map_osc_address(osc: State<'_, Osc>, address: String, command: Option<OscCommand>) -> Result<OscConfig, String> {
+    osc.map(&address, command)
+}
+
+#[command]
+pub async fn get_osc_config(osc: State<'_, Osc>) -> Result<OscConfig, String> {
+    osc.config()
+}
+
+// Audio commands
+/// Loads the soundtrack the show plays to. Playback then starts,
+/// stops and seeks with the show, whose clock follows the audio.
+#[command]
+pub async fn load_audio(app: AppHandle, audio: State<'_, AudioPlayer>, path: String) -> Result<AudioTrack, String> {
+    audio.load(&app, &path)
+}
+
+/// Plays through output device `id`, as listed by `list_audio_devices`; None
+/// is the default device.
+#[command]
+pub async fn set_audio_device(app: AppHandle, audio: State<'_, AudioPlayer>, id: Option<String>) -> Result<AudioStatus, String> {
+    audio.set_device(&app, id)
+}
+
+#[command]
+pub async fn list_audio_devices() -> Result<Vec<String>, String> {
+    audio::devices()
+}
+
+#[command]
+pub async fn get_audio_status(audio: State<'_, AudioPlayer>) -> Result<AudioStatus, String> {
+    audio.status()
+}
+
+// Timecode commands
+/// Sets where the show clock comes from: "internal", or chasing "mtc" from
+/// MIDI port `input` or "ltc" from audio device `input`. `offset` is the
+/// timecode in seconds that show time zero falls on.
+#[command]
+pub async fn set_sync_mode(
+    app: AppHandle,
+    chase: State<'_, TimecodeChase>,
+    mode: SyncMode,
+    input: Option<String>,
+    offset: Option<f64>,
+) -> Result<SyncStatus, String> {
+    chase.set_mode(&app, mode, input, offset.unwrap_or(0.0))
+}
+
+#[command]
+pub async fn get_sync_status(chase: State<'_, TimecodeChase>) -> Result<SyncStatus, String> {
+    chase.status()
+}
+
+#[command]
+pub async fn list_midi_ports() -> Result<Vec<String>, String> {
+    timecode::midi_ports()
+}
+
+// MQTT commands
+/// Connects to the broker at `broker_url` ("mqtt://host:port", or
+/// "mqtts://" for TLS), replacing any earlier one, and keeps reconnecting,
+/// across restarts too, until `disconnect_mqtt`.
+#[command]
+pub async fn connect_mqtt(
+    app: AppHandle,
+    mqtt: State<'_, Mqtt>,
+    broker_url: String,
+    credentials: Option<MqttCredentials>,
+) -> Result<MqttStatus, String> {
+    mqtt.connect(&app, &broker_url, credentials).await
+}
+
+#[command]
+pub async fn disconnect_mqtt(app: AppHandle, mqtt: State<'_, Mqtt>) -> Result<MqttStatus, String> {
+    mqtt.disconnect(&app)
+}
+
+#[command]
+pub async fn get_mqtt_status(mqtt: State<'_, Mqtt>) -> Result<MqttStatus, String> {
+    mqtt.status()
+}
+
+/// Heartbeats and telemetry controllers sent over MQTT, by address.
+#[command]
+pub async fn get_controller_telemetry(
+    registry: State<'_, ControllerRegistry>,
+) -> Result<BTreeMap<String, ControllerTelemetry>, String> {
+    registry.telemetry()
+}
+
+// Live output commands
+#[command]
+#[allow(clippy::too_many_arguments)]
+pub async fn trigger_effect_now(
+    app: AppHandle,
+    registry: State<'_, ControllerRegistry>,
+    dispatcher: State<'_, Dispatcher>,
+    safety: State<'_, Safety>,
+    audit: State<'_, AuditLog>,
+    controller: String,
+    channel: u32,
+    effect_params: Option<EffectParams>,
+    duration: f64,
+) -> Result<(), String> {
+    let controller = registry.get(&controller)?;
+    let params = effect_params.unwrap_or_default();
+    let result = manual_control::trigger_now(&dispatcher, &safety, &audit, &controller, channel, &params, duration).await;
+    show_engine::report_fire(&app, None, &controller.address, channel, FireSource::Manual, &result);
+    result
+}
+
+#[command]
+pub async fn flash_effect(
+    app: AppHandle,
+    registry: State<'_, ControllerRegistry>,
+    dispatcher: State<'_, Dispatcher>,
+    audit: State<'_, AuditLog>,
+    controller: String,
+    channel: u32,
+    effect_params: Option<EffectParams>,
+) -> Result<(), String> {
+    let controller = registry.get(&controller)?;
+    let result = manual_control::flash(&dispatcher, &audit, &controller, channel, &effect_params.unwrap_or_default()).await;
+    show_engine::report_fire(&app, None, &controller.address, channel, FireSource::Manual, &result);
+    result
+}
+
+#[command]
+pub async fn release_flash(
+    registry: State<'_, ControllerRegistry>,
+    dispatcher: State<'_, Dispatcher>,
+    audit: State<'_, AuditLog>,
+    controller: String,
+    channel: u32,
+) -> Result<(), String> {
+    let controller = registry.get(&controller)?;
+    manual_control::release(&dispatcher, &audit, &controller, channel).await
+}
+
+#[command]
+pub async fn set_haze_output(
+    registry: State<'_, ControllerRegistry>,
+    dispatcher: State<'_, Dispatcher>,
+    audit: State<'_, AuditLog>,
+    address: String,
+    level: u8,
+    timeout_secs: f64,
+) -> Result<(), String> {
+    let controller = registry.get(&address)?;
+    log::info!("Setting haze output on {} to {}% for {}s", controller.label(), level, timeout_secs);
+    let result = haze::set_output(&dispatcher, &controller, level, timeout_secs).await;
+    audit.record(
+        AuditKind::ManualOverride,
+        &controller,
+        haze::HAZE_CHANNEL,
+        &result,
+        format!("Haze output {}% for {:.0}s", level, timeout_secs),
+    );
+    result
+}
+
+#[command]
+pub async fn set_channel_muted(
+    registry: State<'_, ControllerRegistry>,
+    dispatcher: State<'_, Dispatcher>,
+    controller: String,
+    channel: u32,
+    muted: bool,
+) -> Result<(), String> {
+    let controller = registry.get(&controller)?;
+    log::info!("{} {} channel {}", if muted { "Muting" } else { "Unmuting" }, controller.label(), channel);
+    dispatcher.set_muted(&controller.address, channel, muted)
+}
+
+/// Resolves a friendly name or address to the registered controller.
+#[command]
+pub async fn resolve_controller(
+    registry: State<'_, ControllerRegistry>,
+    name_or_address: String,
+) -> Result<ControllerInfo, String> {
+    registry.get(&name_or_address)
+}
+
+#[command]
+pub async fn get_audit_log(audit: State<'_, AuditLog>) -> Result<Vec<AuditEntry>, String> {
+    audit.entries()
+}
+
+/// Writes this session's fire events as a PDF report and returns its path.
+/// With `include_performance`, a summary of the performance history is appended.
+#[command]
+pub async fn export_audit_log_pdf(
+    store: State<'_, ShowStore>,
+    audit: State<'_, AuditLog>,
+    recorder: State<'_, PerformanceRecorder>,
+    path: PathBuf,
+    operator: Option<String>,
+    include_performance: Option<bool>,
+) -> Result<PathBuf, String> {
+    let header = ReportHeader {
+        show_name: store.current()?.map(|show| show.name),
+        operator: operator.filter(|o| !o.trim().is_empty()),
+    };
+    let performance = match include_performance {
+        Some(true) => Some(recorder.history()?),
+        _ => None,
+    };
+    audit_report::export_pdf(path, header, audit.entries()?, performance).await
 }
 
 // File operations enhanced
+/// Encodes `show` in `format` (lume, csv or json) and returns the text.
+/// `csv` picks the delimiter, time format and columns of a CSV cue list.
 #[command]
-pub async fn export_show(_show_data: String, format: String) -> Result<String, String> {
-    log::info!("Exporting show in format: {}", format);
-    
-    let export_id = uuid::Uuid::new_v4().to_string();
-    
-    // TODO: Implement actual export logic based on format
-    match format.as_str() {
-        "lume" => {
-            // Export in native LUME format
-            Ok(format!("Exported show as LUME format with ID: {}", export_id))
+pub async fn export_show(show: Show, format: String, csv: Option<CsvOptions>) -> Result<String, String> {
+    log::info!("Exporting show '{}' in format: {}", show.name, format);
+    let data = export::encode(&show, &format, &csv.unwrap_or_default())?;
+    String::from_utf8(data).map_err(|e| format!("Export is not text: {}", e))
+}
+
+/// Saves `show` as a versioned .lume file at `path`, asking for one with a
+/// save dialog when it is None. Returns the path written, or None if the
+/// operator cancelled; `import_show` opens the file again.
+#[command]
+pub async fn export_show_file(app: AppHandle, show: Show, path: Option<String>) -> Result<Option<String>, String> {
+    let written = export::export_lume(&app, &show, path.map(PathBuf::from)).await?;
+    Ok(written.map(|path| path.display().to_string()))
+}
+
+/// JSON Schema of the show format, for validating files before import.
+#[command]
+pub async fn export_show_schema() -> Result<serde_json::Value, String> {
+    show_schema::export()
+}
+
+/// Writes a printable cue sheet of the loaded show for the crew.
+#[command]
+pub async fn export_cue_sheet(
+    store: State<'_, ShowStore>,
+    registry: State<'_, ControllerRegistry>,
+    path: PathBuf,
+    format: String,
+) -> Result<PathBuf, String> {
+    let show = store.current()?.ok_or_else(|| "No show loaded".to_string())?;
+    cue_sheet::export(&show, &registry.list()?, &path, &format)?;
+    Ok(path)
+}
+
+#[command]
+pub async fn export_show_multi(
+    show: Show,
+    formats: Vec<String>,
+    dir: String,
+    csv: Option<CsvOptions>,
+) -> Result<HashMap<String, ExportOutcome>, String> {
+    log::info!("Exporting show '{}' to {:?} in {}", show.name, formats, dir);
+
+    let dir = PathBuf::from(dir);
+    if !dir.is_dir() {
+        return Err(format!("Export directory does not exist: {}", dir.display()));
+    }
+    Ok(export::export_multi(show, formats, dir, csv.unwrap_or_default()).await)
+}
+
+// Show file commands
+#[command]
+pub async fn load_show(app: AppHandle, store: State<'_, ShowStore>, show: Show) -> Result<(), String> {
+    log::info!("Loading show '{}' with {} effects", show.name, show.effects.len());
+    apply_show_output(&app, &show);
+    store.load(show)
+}
+
+fn apply_show_output(app: &AppHandle, show: &Show) {
+    if let Some(message) = show_output::apply(show) {
+        events::emit(
+            app,
+            events::SHOW_OUTPUT_WARNING,
+            ShowOutputWarning {
+                show_id: show.id.clone(),
+                message,
+            },
+        );
+    }
+}
+
+#[command]
+pub async fn upsert_effect(store: State<'_, ShowStore>, effect: Effect) -> Result<(), String> {
+    store.upsert_effect(effect)
+}
+
+/// Turns a cue off without deleting it; disabled cues are skipped at playback.
+#[command]
+pub async fn set_effect_enabled(store: State<'_, ShowStore>, effect_id: String, enabled: bool) -> Result<(), String> {
+    let label = if enabled { "Enable effect" } else { "Disable effect" };
+    store.edit(label, |show| {
+        let effect = show
+            .effects
+            .iter_mut()
+            .find(|e| e.id == effect_id)
+            .ok_or_else(|| format!("Effect {} not found", effect_id))?;
+        effect.enabled = enabled;
+        Ok(())
+    })
+}
+
+#[command]
+pub async fn create_layer(store: State<'_, ShowStore>, name: String) -> Result<(), String> {
+    let name = name.trim().to_string();
+    if name.is_empty() {
+        return Err("Layer name must not be empty".to_string());
+    }
+    store.edit("Create layer", |show| {
+        if show.layer(&name).is_some() {
+            return Err(format!("Layer {} already exists", name));
         }
-        "csv" => {
-            // Export timing data as CSV
-            Ok(format!("Exported timing data as CSV with ID: {}", export_id))
+        show.layers.push(Layer {
+            name,
+            visible: true,
+            muted: false,
+        });
+        Ok(())
+    })
+}
+
+/// Moves an effect onto `layer`, or off any layer if None.
+#[command]
+pub async fn assign_effect_to_layer(
+    store: State<'_, ShowStore>,
+    effect_id: String,
+    layer: Option<String>,
+) -> Result<(), String> {
+    store.edit("Assign effect to layer", |show| {
+        if let Some(layer) = &layer {
+            show.layer_mut(layer)?;
         }
-        _ => Err(format!("Unsupported export format: {}", format))
+        let effect = show
+            .effects
+            .iter_mut()
+            .find(|e| e.id == effect_id)
+            .ok_or_else(|| format!("Effect {} not found", effect_id))?;
+        effect.layer = layer;
+        Ok(())
+    })
+}
+
+#[command]
+pub async fn set_layer_visible(store: State<'_, ShowStore>, layer: String, visible: bool) -> Result<(), String> {
+    let label = if visible { "Show layer" } else { "Hide layer" };
+    store.edit(label, |show| {
+        show.layer_mut(&layer)?.visible = visible;
+        Ok(())
+    })
+}
+
+/// Skips every effect on a layer at playback. Takes effect straight away
+/// if the show is playing: effects of the layer still running are released.
+#[command]
+pub async fn set_layer_muted(
+    store: State<'_, ShowStore>,
+    engine: State<'_, ShowEngine>,
+    registry: State<'_, ControllerRegistry>,
+    dispatcher: State<'_, Dispatcher>,
+    layer: String,
+    muted: bool,
+) -> Result<(), String> {
+    let label = if muted { "Mute layer" } else { "Unmute layer" };
+    store.edit(label, |show| {
+        show.layer_mut(&layer)?.muted = muted;
+        Ok(())
+    })?;
+    for (controller, channel) in engine.set_layer_muted(&layer, muted)? {
+        let address = registry.get(&controller).map(|c| c.address).unwrap_or(controller);
+        // Nothing to release if the effect was not holding its output.
+        let _ = dispatcher.release(&address, channel).await;
     }
+    Ok(())
 }
 
 #[command]
-pub async fn validate_show_data(_show_data: String) -> Result<HashMap<String, bool>, String> {
-    log::info!("Validating show data...");
-    
-    let mut validation_results = HashMap::new();
-    
-    // TODO: Implement actual validation logic
-    validation_results.insert("timing_valid".to_string(), true);
-    validation_results.insert("effects_valid".to_string(), true);
-    validation_results.insert("controllers_available".to_string(), false);
-    
-    Ok(validation_results)
+pub async fn remove_effect(store: State<'_, ShowStore>, effect_id: String) -> Result<bool, String> {
+    store.remove_effect(&effect_id)
+}
+
+#[command]
+pub async fn save_show(store: State<'_, ShowStore>, path: Option<String>) -> Result<SaveReport, String> {
+    let report = store.save_full(path.map(PathBuf::from))?;
+    back_up_saved_show(&store, &report);
+    Ok(report)
+}
+
+#[command]
+pub async fn save_show_delta(store: State<'_, ShowStore>) -> Result<SaveReport, String> {
+    let report = store.save_delta()?;
+    back_up_saved_show(&store, &report);
+    Ok(report)
+}
+
+// The save itself succeeded, so a failed backup is only logged.
+fn back_up_saved_show(store: &ShowStore, report: &SaveReport) {
+    let result = store
+        .current()
+        .and_then(|show| show.ok_or_else(|| "No show loaded".to_string()))
+        .and_then(|show| backup::create(Path::new(&report.path), &show));
+    if let Err(e) = result {
+        log::warn!("Show saved, but the backup failed: {}", e);
+    }
+}
+
+/// Backups kept per show on each save; 0 turns them off.
+#[command]
+pub async fn set_backup_retention(count: usize) -> Result<(), String> {
+    backup::set_retention(count)
+}
+
+#[command]
+pub async fn list_backups(show_path: PathBuf) -> Result<Vec<BackupInfo>, String> {
+    backup::list(&show_path)
+}
+
+/// Restores a backup over its show file and loads it.
+#[command]
+pub async fn restore_backup(store: State<'_, ShowStore>, backup_path: PathBuf) -> Result<Show, String> {
+    backup::restore(&store, &backup_path)
+}
+
+/// With `recover`, a truncated file opens with as many complete effects as
+/// it still holds, and the result says how many were recovered.
+#[command]
+pub async fn import_show(
+    app: AppHandle,
+    store: State<'_, ShowStore>,
+    path: String,
+    recover: Option<bool>,
+) -> Result<ImportedShow, String> {
+    log::info!("Importing show from: {}", path);
+    let imported = store.import_with(PathBuf::from(path), recover.unwrap_or(false))?;
+    apply_show_output(&app, &imported.show);
+    Ok(imported)
+}
+
+/// Reads every show file in a folder, e.g. when migrating a library, and
+/// returns a manifest of what opened, what failed and which titles repeat.
+/// Progress is reported with show-import-progress events.
+#[command]
+pub async fn import_show_folder(app: AppHandle, dir: String) -> Result<ImportManifest, String> {
+    tauri::async_runtime::spawn_blocking(move || folder_import::import_folder(&app, Path::new(&dir)))
+        .await
+        .map_err(|e| format!("Folder import failed: {}", e))?
+}
+
+#[command]
+pub async fn get_show_output_config(store: State<'_, ShowStore>) -> Result<Option<ShowOutputConfig>, String> {
+    let show = store.current()?.ok_or_else(|| "No show loaded".to_string())?;
+    Ok(show.output)
+}
+
+/// Stores the output settings in the loaded show and applies them; None clears them.
+#[command]
+pub async fn set_show_output_config(
+    app: AppHandle,
+    store: State<'_, ShowStore>,
+    config: Option<ShowOutputConfig>,
+) -> Result<(), String> {
+    let show = store.edit("Edit output settings", |show| {
+        show_output::set(show, config)?;
+        Ok(show.clone())
+    })?;
+    apply_show_output(&app, &show);
+    Ok(())
+}
+
+#[command]
+pub async fn render_show_thumbnail(
+    cache: State<'_, ThumbnailCache>,
+    path: String,
+    width: u32,
+    height: u32,
+) -> Result<String, String> {
+    cache.get(PathBuf::from(path), width, height).await
+}
+
+/// Peak and RMS envelope of an audio track in `samples` buckets, for the
+/// timeline background. Progress is reported with waveform-progress events.
+#[command]
+pub async fn analyze_audio_waveform(app: AppHandle, path: String, samples: usize) -> Result<WaveformEnvelope, String> {
+    tauri::async_runtime::spawn_blocking(move || waveform::analyze(&app, Path::new(&path), samples))
+        .await
+        .map_err(|e| format!("Waveform analysis failed: {}", e))?
+}
+
+/// Waveform, onsets, beat grid and tempo of an audio track for the
+/// timeline editor. Cached per file, so reopening a show is instant.
+#[command]
+pub async fn analyze_audio(app: AppHandle, path: String) -> Result<AudioAnalysis, String> {
+    let cache_dir = app
+        .path()
+        .app_cache_dir()
+        .map_err(|e| format!("No cache directory: {}", e))?
+        .join("audio-analysis");
+    tauri::async_runtime::spawn_blocking(move || audio_analysis::analyze(&app, Path::new(&path), &cache_dir))
+        .await
+        .map_err(|e| format!("Audio analysis failed: {}", e))?
+}
+
+// Show editing commands
+#[command]
+pub async fn undo_show_edit(store: State<'_, ShowStore>) -> Result<Option<String>, String> {
+    store.undo()
+}
+
+#[command]
+pub async fn redo_show_edit(store: State<'_, ShowStore>) -> Result<Option<String>, String> {
+    store.redo()
+}
+
+#[command]
+pub async fn quantize_show(
+    store: State<'_, ShowStore>,
+    bpm: f64,
+    division: u32,
+    strength: Option<f64>,
+) -> Result<QuantizeReport, String> {
+    let report = store.edit("Quantize to beat grid", |show| {
+        edit_ops::quantize(show, bpm, division, strength.unwrap_or(1.0))
+    })?;
+    log::info!(
+        "Quantized show at {} BPM / {}: {} effects moved, max shift {:.3}s",
+        bpm, division, report.moved, report.max_shift
+    );
+    Ok(report)
+}
+
+/// Adds a row of cues fired `interval_ms` apart, as one undo step.
+#[command]
+pub async fn generate_ripple(
+    store: State<'_, ShowStore>,
+    registry: State<'_, ControllerRegistry>,
+    channels: Vec<(String, u32)>,
+    start_time: f64,
+    interval_ms: u64,
+    effect: EffectTemplate,
+) -> Result<Vec<String>, String> {
+    let channels = channels
+        .into_iter()
+        .map(|(address, channel)| Ok((registry.get(&address)?, channel)))
+        .collect::<Result<Vec<_>, String>>()?;
+    let ids = store.edit("Generate ripple", |show| {
+        edit_ops::ripple(show, &channels, start_time, interval_ms, &effect)
+    })?;
+    log::info!("Generated ripple of {} cues at {:.2}s, {} ms apart", ids.len(), start_time, interval_ms);
+    Ok(ids)
+}
+
+/// Varies the timing and levels of the given effects, as one undo step. The
+/// report carries the seed, which reproduces the same variation.
+#[command]
+pub async fn randomize_effects(
+    store: State<'_, ShowStore>,
+    registry: State<'_, ControllerRegistry>,
+    effect_ids: Vec<String>,
+    params: RandomizeParams,
+) -> Result<RandomizeReport, String> {
+    let controllers = registry.list()?;
+    let report = store.edit("Randomize effects", |show| {
+        edit_ops::randomize(show, &controllers, &effect_ids, &params)
+    })?;
+    log::info!(
+        "Randomized {} effects with seed {}, max shift {:.0} ms",
+        report.changed, report.seed, report.max_shift_ms
+    );
+    Ok(report)
+}
+
+/// Adds or recolors a named palette entry; effects referencing it follow.
+#[command]
+pub async fn add_palette_color(store: State<'_, ShowStore>, name: String, rgb: Rgb) -> Result<(), String> {
+    log::info!("Setting palette color '{}' to {:?}", name, rgb);
+    store.edit("Edit palette", |show| palette::add_color(show, &name, rgb))
+}
+
+/// Returns the loaded show with palette references replaced by literal colors.
+#[command]
+pub async fn resolve_palette(store: State<'_, ShowStore>) -> Result<Show, String> {
+    let show = store.current()?.ok_or_else(|| "No show loaded".to_string())?;
+    palette::resolve(&show)
+}
+
+/// Aggregate figures for the loaded show, e.g. to sanity-check an import.
+#[command]
+pub async fn get_show_stats(store: State<'_, ShowStore>, registry: State<'_, ControllerRegistry>) -> Result<ShowStats, String> {
+    let show = store.current()?.ok_or_else(|| "No show loaded".to_string())?;
+    Ok(show_stats::compute(&show, &registry.list()?))
+}
+
+// Validation commands
+/// Pings the controllers the show uses first, so availability is current.
+/// Every issue carries its severity; `mode` (lenient by default) decides
+/// whether warnings make the show invalid.
+#[command]
+pub async fn validate_show_data(
+    registry: State<'_, ControllerRegistry>,
+    zones: State<'_, LaserZones>,
+    dispatcher: State<'_, Dispatcher>,
+    dmx: State<'_, DmxOutput>,
+    show: Show,
+    mode: Option<ValidationMode>,
+) -> Result<ValidationReport, String> {
+    let mode = mode.unwrap_or_default();
+    log::info!("Validating show '{}' ({:?} mode)...", show.name, mode);
+
+    let controllers = registry.list()?;
+    validation::refresh_availability(&show, &controllers, &dispatcher).await;
+    Ok(validation::validate(&show, &controllers, &zones, &dispatcher, &dmx.config()?, mode))
+}
+
+/// Sets the safe-projection zone of a laser; beams outside it are refused.
+#[command]
+pub async fn set_laser_zone(
+    registry: State<'_, ControllerRegistry>,
+    zones: State<'_, LaserZones>,
+    address: String,
+    polygon: Vec<Point>,
+) -> Result<(), String> {
+    let controller = registry.get(&address)?;
+    if !controller.is_laser() {
+        return Err(format!("{} is not a laser", controller.label()));
+    }
+    log::info!("Setting laser zone for {} with {} points", controller.label(), polygon.len());
+    zones.set(&controller.address, polygon)
+}
+
+/// Pre-show self-test. Reports each check with a self-test-progress event
+/// as it completes; cancel_self_test returns a partial report.
+#[command]
+pub async fn preflight_show(
+    app: AppHandle,
+    store: State<'_, ShowStore>,
+    registry: State<'_, ControllerRegistry>,
+    dispatcher: State<'_, Dispatcher>,
+    dmx: State<'_, DmxOutput>,
+    self_test: State<'_, SelfTestControl>,
+) -> Result<PreflightReport, String> {
+    let mut show = store.current()?.ok_or_else(|| "No show loaded".to_string())?;
+    // DMX fixtures have no controller to check.
+    let patched = dmx.config()?;
+    show.effects.retain(|e| !patched.is_patched(&e.controller, e.channel));
+    log::info!("Running preflight for show '{}'", show.name);
+
+    let controllers = registry.list()?;
+    let cancel = self_test.begin()?;
+    let progress: preflight::Progress = Arc::new(move |update| events::emit(&app, events::SELF_TEST_PROGRESS, update));
+    let report = preflight::run(&show, &controllers, &dispatcher, cancel, progress).await;
+    self_test.finish();
+    log::info!(
+        "Preflight finished: ready={}, {} issues, cancelled={}",
+        report.ready,
+        report.issues.len(),
+        report.cancelled
+    );
+    Ok(report)
+}
+
+/// Stops the running self-test after the checks in flight. Returns false if none was running.
+#[command]
+pub async fn cancel_self_test(self_test: State<'_, SelfTestControl>) -> Result<bool, String> {
+    self_test.cancel()
+}
+
+// Monitor window commands
+#[command]
+pub async fn list_displays(app: AppHandle) -> Result<Vec<DisplayInfo>, String> {
+    monitor_window::list_displays(&app)
+}
+
+#[command]
+pub async fn open_monitor_window(app: AppHandle, display_index: usize) -> Result<(), String> {
+    monitor_window::open(&app, display_index)
+}
+
+#[command]
+pub async fn close_monitor_window(app: AppHandle) -> Result<bool, String> {
+    monitor_window::close(&app)
 }
 
 // Notification helpers
@@ -129,20 +1768,107 @@ pub async fn send_system_notification(title: String, message: String) -> Result<
     Ok(())
 }
 
-// Performance monitoring
-fn get_memory_usage() -> u64 {
-    // TODO: Implement actual memory monitoring
-    // For now return a placeholder
-    1024 * 1024 * 64 // 64MB placeholder
+// Safe mode commands
+/// Lets the UI show a banner while hardware access is disabled.
+#[command]
+pub async fn is_safe_mode() -> Result<bool, String> {
+    Ok(safe_mode::is_active())
 }
 
+/// Enters safe mode without restarting, e.g. when the operator holds the
+/// safe mode key while the window loads. Stops the show, background
+/// discovery and the external trigger; stays on until the app is restarted.
 #[command]
-pub async fn get_performance_stats() -> Result<HashMap<String, f64>, String> {
-    let mut stats = HashMap::new();
-    
-    stats.insert("memory_mb".to_string(), (get_memory_usage() / 1024 / 1024) as f64);
-    stats.insert("cpu_usage".to_string(), 0.0); // TODO: Get real CPU usage
-    stats.insert("fps".to_string(), 60.0); // TODO: Get real render FPS
-    
-    Ok(stats)
+pub async fn enter_safe_mode(
+    app: AppHandle,
+    engine: State<'_, ShowEngine>,
+    discovery: State<'_, Discovery>,
+    trigger: State<'_, ExternalTrigger>,
+) -> Result<(), String> {
+    engine.stop(&app).await?;
+    safe_mode::enter("requested by the operator");
+    discovery.set_interval(&app, None)?;
+    trigger.configure(&app, None)
+}
+
+/// Soft-start and the last polled power draw of each registered controller.
+#[command]
+pub async fn get_controller_stats(
+    registry: State<'_, ControllerRegistry>,
+    power: State<'_, PowerMonitor>,
+) -> Result<Vec<ControllerStats>, String> {
+    let mut readings: HashMap<String, ControllerPower> =
+        power.readings()?.into_iter().map(|r| (r.address.clone(), r)).collect();
+    Ok(registry
+        .list()?
+        .into_iter()
+        .map(|c| ControllerStats {
+            soft_start_ms: c.soft_start().map(|d| d.as_millis() as u64),
+            name: c.display_name(),
+            power: readings.remove(&c.address),
+            address: c.address,
+        })
+        .collect())
+}
+
+/// Starts or stops polling controllers for their live power draw.
+#[command]
+pub async fn set_power_monitoring(app: AppHandle, power: State<'_, PowerMonitor>, settings: PowerSettings) -> Result<(), String> {
+    power.configure(&app, settings)
+}
+
+/// Lets the engine cut back preview updates and ramp frame rates on its own
+/// when it falls behind, to keep cue timing accurate.
+#[command]
+pub async fn set_adaptive_performance(app: AppHandle, enabled: bool) -> Result<(), String> {
+    performance::set_adaptive(&app, enabled);
+    Ok(())
+}
+
+#[command]
+pub async fn get_performance_stats(
+    dispatcher: State<'_, Dispatcher>,
+    power: State<'_, PowerMonitor>,
+) -> Result<HashMap<String, f64>, String> {
+    Ok(performance::stats(&dispatcher, &power))
+}
+
+/// Samples of the performance stats taken over this session, for graphing
+/// after a show. Long sessions are kept at a coarser resolution.
+#[command]
+pub async fn get_performance_history(recorder: State<'_, PerformanceRecorder>) -> Result<PerformanceHistory, String> {
+    recorder.history()
+}
+
+/// How often the performance history is sampled.
+#[command]
+pub async fn set_performance_sampling(
+    app: AppHandle,
+    recorder: State<'_, PerformanceRecorder>,
+    interval_ms: u64,
+) -> Result<(), String> {
+    recorder.set_interval(&app, interval_ms)
+}
+
+/// Writes a redacted JSON bundle for support to `path` and returns it.
+#[command]
+pub async fn generate_diagnostic_report(
+    app: AppHandle,
+    registry: State<'_, ControllerRegistry>,
+    store: State<'_, ShowStore>,
+    engine: State<'_, ShowEngine>,
+    dispatcher: State<'_, Dispatcher>,
+    power: State<'_, PowerMonitor>,
+    path: PathBuf,
+) -> Result<PathBuf, String> {
+    let report = DiagnosticReport {
+        generated_at: now_millis(),
+        system: system_info()?,
+        performance: performance::stats(&dispatcher, &power),
+        controllers: registry.list()?,
+        show: store.current()?.as_ref().map(ShowSummary::from),
+        engine: engine.status()?,
+        log_tail: diagnostics::log_tail(&app),
+    };
+    diagnostics::write(&path, &report)
 }
\ No newline at end of file
@@ -0,0 +1,171 @@
+use serde::Serialize;
+use std::sync::{Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+
+use crate::audit::now_millis;
+
+/// How long control is held without being renewed.
+pub const DEFAULT_LEASE: Duration = Duration::from_secs(300);
+
+const MAX_LEASE: Duration = Duration::from_secs(4 * 3600);
+
+struct Lease {
+    operator_id: String,
+    acquired_at: u64,
+    expires: Instant,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ControlOwner {
+    pub operator_id: String,
+    /// Unix timestamp in milliseconds.
+    pub acquired_at: u64,
+    pub expires_in_ms: u64,
+}
+
+/// Exclusive control of show transport (start, stop, seek, resume). While
+/// an operator holds it, transport commands from anyone else are refused;
+/// status queries stay open to all. The lease lapses unless renewed by
+/// acquiring again.
+#[derive(Default)]
+pub struct ControlLock {
+    lease: Mutex<Option<Lease>>,
+}
+
+impl ControlLock {
+    // Drops an expired lease on the way.
+    fn lock(&self) -> Result<MutexGuard<'_, Option<Lease>>, String> {
+        let mut lease = self.lease.lock().map_err(|_| "Control lock is unavailable".to_string())?;
+        if lease.as_ref().is_some_and(|l| l.expires <= Instant::now()) {
+            if let Some(expired) = lease.take() {
+                log::warn!("Transport control of {} expired", expired.operator_id);
+            }
+        }
+        Ok(lease)
+    }
+
+    /// Takes or renews control for `operator_id`.
+    pub fn acquire(&self, operator_id: &str, lease: Option<Duration>) -> Result<ControlOwner, String> {
+        let operator_id = operator_id.trim();
+        if operator_id.is_empty() {
+            return Err("Operator id must not be empty".to_string());
+        }
+        let duration = lease.unwrap_or(DEFAULT_LEASE);
+        if duration.is_zero() || duration > MAX_LEASE {
+            return Err(format!("Control lease must be between 1 and {} seconds", MAX_LEASE.as_secs()));
+        }
+        let mut current = self.lock()?;
+        let acquired_at = match current.as_ref() {
+            Some(held) if held.operator_id != operator_id => {
+                return Err(format!("Show transport is controlled by {}", held.operator_id));
+            }
+            Some(held) => held.acquired_at,
+            None => {
+                log::warn!("Transport control acquired by {}", operator_id);
+                now_millis()
+            }
+        };
+        *current = Some(Lease {
+            operator_id: operator_id.to_string(),
+            acquired_at,
+            expires: Instant::now() + duration,
+        });
+        Ok(owner(current.as_ref().expect("lease was just set")))
+    }
+
+    /// Gives up control. Only the holder can release it; returns false if
+    /// nobody held it.
+    pub fn release(&self, operator_id: &str) -> Result<bool, String> {
+        let mut current = self.lock()?;
+        match current.as_ref() {
+            None => Ok(false),
+            Some(held) if held.operator_id != operator_id.trim() => {
+                Err(format!("Show transport is controlled by {}", held.operator_id))
+            }
+            Some(_) => {
+                *current = None;
+                log::info!("Transport control released by {}", operator_id.trim());
+                Ok(true)
+            }
+        }
+    }
+
+    pub fn owner(&self) -> Result<Option<ControlOwner>, String> {
+        Ok(self.lock()?.as_ref().map(owner))
+    }
+
+    /// Fails unless nobody holds control or `operator_id` does. Sources
+    /// without an id, like the external trigger, pass only when unlocked.
+    pub fn check(&self, operator_id: Option<&str>) -> Result<(), String> {
+        match self.lock()?.as_ref() {
+            Some(held) if operator_id.map(str::trim) != Some(held.operator_id.as_str()) => {
+                Err(format!("Show transport is controlled by {}", held.operator_id))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+fn owner(lease: &Lease) -> ControlOwner {
+    ControlOwner {
+        operator_id: lease.operator_id.clone(),
+        acquired_at: lease.acquired_at,
+        expires_in_ms: lease.expires.saturating_duration_since(Instant::now()).as_millis() as u64,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn other_operators_are_refused_while_held() {
+        let control = ControlLock::default();
+        control.acquire("alice", None).unwrap();
+        assert!(control.acquire("bob", None).unwrap_err().contains("alice"));
+        assert!(control.check(Some("bob")).is_err());
+        assert!(control.check(Some(" alice ")).is_ok());
+        assert!(control.acquire(" ", None).is_err());
+    }
+
+    #[test]
+    fn holder_renews_the_lease() {
+        let control = ControlLock::default();
+        let first = control.acquire("alice", Some(Duration::from_secs(1))).unwrap();
+        let renewed = control.acquire("alice", Some(Duration::from_secs(60))).unwrap();
+        assert_eq!(renewed.acquired_at, first.acquired_at);
+        assert!(renewed.expires_in_ms > 1000);
+        assert!(control.acquire("alice", Some(MAX_LEASE + Duration::from_secs(1))).is_err());
+    }
+
+    #[test]
+    fn lease_expires_unless_renewed() {
+        let control = ControlLock::default();
+        control.acquire("alice", Some(Duration::from_millis(20))).unwrap();
+        std::thread::sleep(Duration::from_millis(40));
+        assert!(control.owner().unwrap().is_none());
+        control.acquire("bob", None).unwrap();
+        assert_eq!(control.owner().unwrap().unwrap().operator_id, "bob");
+    }
+
+    #[test]
+    fn only_the_holder_releases() {
+        let control = ControlLock::default();
+        assert!(!control.release("alice").unwrap());
+        control.acquire("alice", None).unwrap();
+        assert!(control.release("bob").is_err());
+        assert!(control.owner().unwrap().is_some());
+        assert!(control.release("alice").unwrap());
+        assert!(control.owner().unwrap().is_none());
+    }
+
+    #[test]
+    fn sources_without_an_id_pass_only_while_unlocked() {
+        let control = ControlLock::default();
+        assert!(control.check(None).is_ok());
+        control.acquire("alice", None).unwrap();
+        assert!(control.check(None).is_err());
+        control.release("alice").unwrap();
+        assert!(control.check(None).is_ok());
+    }
+}
@@ -0,0 +1,255 @@
+use std::sync::{OnceLock, RwLock};
+use std::time::{Duration, Instant};
+use tauri_plugin_http::reqwest;
+
+use crate::safe_mode;
+use crate::transport;
+
+// Replaced by `reset_connections`, so connections pooled on a network the
+// desk has left are dropped instead of reused.
+fn pool() -> &'static RwLock<reqwest::Client> {
+    static CLIENT: OnceLock<RwLock<reqwest::Client>> = OnceLock::new();
+    CLIENT.get_or_init(|| RwLock::new(reqwest::Client::new()))
+}
+
+fn client() -> reqwest::Client {
+    pool().read().map(|c| c.clone()).unwrap_or_default()
+}
+
+/// Drops every pooled HTTP connection; the next request connects afresh.
+pub fn reset_connections() {
+    if let Ok(mut client) = pool().write() {
+        *client = reqwest::Client::new();
+        log::info!("Controller connection pool reset");
+    }
+}
+
+/// Accepts bare hostnames/IPs as well as full URLs.
+pub fn base_url(address: &str) -> String {
+    if address.starts_with("http://") || address.starts_with("https://") {
+        address.trim_end_matches('/').to_string()
+    } else {
+        format!("http://{}", address)
+    }
+}
+
+// Bluetooth controllers take the same requests over their GATT service.
+#[cfg(feature = "ble")]
+async fn over_ble(address: &str, method: &str, path: &str, timeout: Duration) -> Result<serde_json::Value, RequestError> {
+    crate::ble::request(address, method, path, timeout).await
+}
+
+#[cfg(not(feature = "ble"))]
+async fn over_ble(address: &str, _method: &str, _path: &str, _timeout: Duration) -> Result<serde_json::Value, RequestError> {
+    Err(RequestError::Unreachable(format!(
+        "{} is a Bluetooth controller, but this build has no Bluetooth support",
+        address
+    )))
+}
+
+// Wired firing modules take console commands, which their driver sends in
+// place of request paths.
+async fn over_serial(address: &str, command: &str, timeout: Duration) -> Result<serde_json::Value, RequestError> {
+    crate::serial::request(address, command, timeout).await
+}
+
+/// Requests `/status` and returns the round-trip time.
+pub async fn probe(address: &str, timeout: Duration) -> Result<Duration, String> {
+    safe_mode::check()?;
+    let started = Instant::now();
+    if transport::ble_id(address).is_some() {
+        over_ble(address, "GET", "/status", timeout).await.map_err(|e| e.to_string())?;
+        return Ok(started.elapsed());
+    }
+    if transport::serial_port(address).is_some() {
+        over_serial(address, crate::serial::STATUS_COMMAND, timeout).await.map_err(|e| e.to_string())?;
+        return Ok(started.elapsed());
+    }
+    let response = client()
+        .get(format!("{}/status", base_url(address)))
+        .timeout(timeout)
+        .send()
+        .await
+        .map_err(|e| format!("{} is unreachable: {}", address, e))?;
+    if !response.status().is_success() {
+        return Err(format!("{} responded with HTTP {}", address, response.status()));
+    }
+    Ok(started.elapsed())
+}
+
+/// GETs `path` and parses the JSON body.
+pub async fn get_json(address: &str, path: &str, timeout: Duration) -> Result<serde_json::Value, String> {
+    safe_mode::check()?;
+    if transport::ble_id(address).is_some() {
+        return over_ble(address, "GET", path, timeout).await.map_err(|e| e.to_string());
+    }
+    if transport::serial_port(address).is_some() {
+        return over_serial(address, path, timeout).await.map_err(|e| e.to_string());
+    }
+    let response = client()
+        .get(format!("{}{}", base_url(address), path))
+        .timeout(timeout)
+        .send()
+        .await
+        .map_err(|e| format!("{} is unreachable: {}", address, e))?;
+    if !response.status().is_success() {
+        return Err(format!("{} responded to {} with HTTP {}", address, path, response.status()));
+    }
+    let body = response
+        .bytes()
+        .await
+        .map_err(|e| format!("Failed to read {} from {}: {}", path, address, e))?;
+    serde_json::from_slice(&body).map_err(|e| format!("{} sent invalid JSON for {}: {}", address, path, e))
+}
+
+/// Why a control request failed.
+#[derive(Debug)]
+pub enum RequestError {
+    /// No response at all: the controller is offline or the network is down.
+    Unreachable(String),
+    /// The controller answered but refused the command.
+    Rejected(String),
+}
+
+impl RequestError {
+    pub fn reached_controller(&self) -> bool {
+        matches!(self, RequestError::Rejected(_))
+    }
+}
+
+impl std::fmt::Display for RequestError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RequestError::Unreachable(message) | RequestError::Rejected(message) => f.write_str(message),
+        }
+    }
+}
+
+/// Sends a control request (e.g. `/channel?id=3`) and checks the reply.
+pub async fn post(address: &str, path: &str, timeout: Duration) -> Result<(), RequestError> {
+    safe_mode::check().map_err(RequestError::Unreachable)?;
+    post_in_safe_mode(address, path, timeout).await
+}
+
+/// Like `post`, but sent in safe mode too. Only for requests that switch
+/// outputs off or stop them, so an emergency stop can still reach hardware.
+pub async fn post_in_safe_mode(address: &str, path: &str, timeout: Duration) -> Result<(), RequestError> {
+    if transport::ble_id(address).is_some() {
+        return over_ble(address, "POST", path, timeout).await.map(|_| ());
+    }
+    if transport::serial_port(address).is_some() {
+        return over_serial(address, path, timeout).await.map(|_| ());
+    }
+    send_post(address, path, timeout).await.map(|_| ())
+}
+
+/// Like `post`, but returns the JSON reply; Null if the body is not JSON.
+pub async fn post_json(address: &str, path: &str, timeout: Duration) -> Result<serde_json::Value, RequestError> {
+    safe_mode::check().map_err(RequestError::Unreachable)?;
+    if transport::ble_id(address).is_some() {
+        return over_ble(address, "POST", path, timeout).await;
+    }
+    if transport::serial_port(address).is_some() {
+        return over_serial(address, path, timeout).await;
+    }
+    let response = send_post(address, path, timeout).await?;
+    let body = response
+        .bytes()
+        .await
+        .map_err(|e| RequestError::Unreachable(format!("{} did not finish replying: {}", address, e)))?;
+    Ok(serde_json::from_slice(&body).unwrap_or(serde_json::Value::Null))
+}
+
+/// POSTs `body` as application/octet-stream. HTTP only: the Bluetooth and
+/// serial links carry short requests, not bulk data.
+pub async fn post_body(address: &str, path: &str, body: Vec<u8>, timeout: Duration) -> Result<(), RequestError> {
+    safe_mode::check().map_err(RequestError::Unreachable)?;
+    if transport::ble_id(address).is_some() {
+        return Err(RequestError::Rejected(format!("{} cannot receive {} over Bluetooth", address, path)));
+    }
+    if transport::serial_port(address).is_some() {
+        return Err(RequestError::Rejected(format!("{} cannot receive {} over its serial console", address, path)));
+    }
+    let response = client()
+        .post(format!("{}{}", base_url(address), path))
+        .header("Content-Type", "application/octet-stream")
+        .body(body)
+        .timeout(timeout)
+        .send()
+        .await
+        .map_err(|e| RequestError::Unreachable(format!("{} did not respond: {}", address, e)))?;
+    if !response.status().is_success() {
+        return Err(RequestError::Rejected(format!(
+            "{} rejected {} with HTTP {}",
+            address,
+            path,
+            response.status()
+        )));
+    }
+    Ok(())
+}
+
+async fn send_post(address: &str, path: &str, timeout: Duration) -> Result<reqwest::Response, RequestError> {
+    let response = client()
+        .post(format!("{}{}", base_url(address), path))
+        .timeout(timeout)
+        .send()
+        .await
+        .map_err(|e| RequestError::Unreachable(format!("{} did not respond: {}", address, e)))?;
+    if !response.status().is_success() {
+        return Err(RequestError::Rejected(format!(
+            "{} rejected {} with HTTP {}",
+            address,
+            path,
+            response.status()
+        )));
+    }
+    Ok(response)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock_controller::{MockController, Reply};
+
+    const TIMEOUT: Duration = Duration::from_millis(500);
+
+    #[tokio::test]
+    async fn refusal_counts_as_reaching_the_controller() {
+        let mock = MockController::start().await;
+        mock.always("/channel", Reply::Status(409));
+        let error = post(&mock.address, "/channel?id=3", TIMEOUT).await.unwrap_err();
+        assert!(error.reached_controller());
+        mock.assert_paths(&["/channel?id=3"]);
+    }
+
+    #[tokio::test]
+    async fn dropped_and_stalled_requests_are_unreachable() {
+        let mock = MockController::start().await;
+        mock.script("/channel", [Reply::Drop, Reply::delayed(TIMEOUT * 4, Reply::ok())]);
+        assert!(!post(&mock.address, "/channel?id=1", TIMEOUT).await.unwrap_err().reached_controller());
+        assert!(!post(&mock.address, "/channel?id=2", TIMEOUT).await.unwrap_err().reached_controller());
+        post(&mock.address, "/channel?id=3", TIMEOUT).await.unwrap();
+        mock.assert_paths(&["/channel?id=1", "/channel?id=2", "/channel?id=3"]);
+    }
+
+    #[tokio::test]
+    async fn only_stops_go_out_in_safe_mode() {
+        let mock = MockController::start().await;
+        safe_mode::enter_on_this_thread();
+        assert!(post(&mock.address, "/channel?id=3", TIMEOUT).await.is_err());
+        post_in_safe_mode(&mock.address, "/emergency/stop", TIMEOUT).await.unwrap();
+        mock.assert_paths(&["/emergency/stop"]);
+    }
+
+    #[tokio::test]
+    async fn post_body_delivers_the_bytes() {
+        let mock = MockController::start().await;
+        post_body(&mock.address, "/sequence/chunk?offset=0", b"cues".to_vec(), TIMEOUT)
+            .await
+            .unwrap();
+        let received = mock.received();
+        assert_eq!(received[0].method, "POST");
+        assert_eq!(received[0].body, b"cues");
+    }
+}
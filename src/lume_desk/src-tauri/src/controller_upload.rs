@@ -0,0 +1,242 @@
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Duration;
+use tauri::AppHandle;
+
+use crate::controller_client::{self, RequestError};
+use crate::dispatcher::{self, OutputAction};
+use crate::driver;
+use crate::events::{self, ShowUploadProgress};
+use crate::models::Show;
+use crate::registry::{ControllerInfo, ControllerRegistry};
+use crate::transport::{self, Transport};
+
+/// Bytes per upload request; controller firmware buffers one chunk at a time.
+const CHUNK_SIZE: usize = 4096;
+
+/// A controller writes each chunk to flash before replying.
+const CHUNK_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Version of the compiled sequence format.
+const SEQUENCE_FORMAT: u32 = 1;
+
+/// One request the controller sends to itself at `at_ms` into the sequence.
+#[derive(Debug, Clone, Serialize)]
+struct Cue {
+    at_ms: u64,
+    path: String,
+}
+
+#[derive(Debug, Serialize)]
+struct Sequence<'a> {
+    format: u32,
+    show_id: &'a str,
+    cues: Vec<Cue>,
+}
+
+/// What the desk last uploaded to a controller.
+#[derive(Debug, Clone, Serialize)]
+pub struct UploadedSequence {
+    pub address: String,
+    pub show_id: String,
+    pub cues: usize,
+    pub bytes: usize,
+    pub crc32: u32,
+}
+
+#[derive(Debug, Serialize)]
+pub struct UploadVerification {
+    pub address: String,
+    pub show_id: String,
+    pub expected_crc32: u32,
+    pub reported_crc32: Option<u32>,
+    pub matches: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct UploadReport {
+    pub upload: UploadedSequence,
+    pub verification: UploadVerification,
+}
+
+/// Sequences uploaded this session, by controller address, so verification
+/// has a checksum to compare against.
+#[derive(Clone, Default)]
+pub struct ControllerUploads {
+    uploads: Arc<Mutex<HashMap<String, UploadedSequence>>>,
+}
+
+impl ControllerUploads {
+    fn lock(&self) -> Result<MutexGuard<'_, HashMap<String, UploadedSequence>>, String> {
+        self.uploads.lock().map_err(|_| "Upload records are unavailable".to_string())
+    }
+
+    fn get(&self, address: &str) -> Result<UploadedSequence, String> {
+        self.lock()?
+            .get(address)
+            .cloned()
+            .ok_or_else(|| format!("No show has been uploaded to {} this session", address))
+    }
+
+    /// Compiles the part of `show` that `controller` plays and uploads it in
+    /// chunks, then checks the controller stored it intact. The controller
+    /// expects `POST /sequence/begin?size=&crc=`, then
+    /// `POST /sequence/chunk?offset=` with each chunk as the body and
+    /// `POST /sequence/commit`. Firmware without standalone playback answers
+    /// 404 to the first request and nothing is stored.
+    pub async fn upload(
+        &self,
+        app: &AppHandle,
+        registry: &ControllerRegistry,
+        controller: &ControllerInfo,
+        show: &Show,
+    ) -> Result<UploadReport, String> {
+        if controller.transport != Transport::Http {
+            return Err(format!("{} is not on the network; sequences upload over HTTP only", controller.label()));
+        }
+        let cues = compile(registry, controller, show)?;
+        if cues.is_empty() {
+            return Err(format!("Show '{}' has no effects on {}", show.name, controller.label()));
+        }
+        let cue_count = cues.len();
+        let data = serde_json::to_vec(&Sequence {
+            format: SEQUENCE_FORMAT,
+            show_id: &show.id,
+            cues,
+        })
+        .map_err(|e| e.to_string())?;
+        let crc32 = crc32fast::hash(&data);
+        let address = controller.address.clone();
+        log::info!("Uploading {} cues ({} bytes) of '{}' to {}", cue_count, data.len(), show.name, controller.label());
+
+        let timeout = transport::timeout(Transport::Http);
+        controller_client::post(&address, &format!("/sequence/begin?size={}&crc={}", data.len(), crc32), timeout)
+            .await
+            .map_err(|e| match e {
+                RequestError::Rejected(_) => format!("{} does not support standalone playback: {}", controller.label(), e),
+                RequestError::Unreachable(_) => e.to_string(),
+            })?;
+        let mut sent = 0;
+        for chunk in data.chunks(CHUNK_SIZE) {
+            controller_client::post_body(&address, &format!("/sequence/chunk?offset={}", sent), chunk.to_vec(), CHUNK_TIMEOUT)
+                .await
+                .map_err(|e| format!("Upload to {} failed after {} of {} bytes: {}", controller.label(), sent, data.len(), e))?;
+            sent += chunk.len();
+            events::emit(
+                app,
+                events::SHOW_UPLOAD_PROGRESS,
+                ShowUploadProgress {
+                    address: address.clone(),
+                    show_id: show.id.clone(),
+                    bytes_sent: sent,
+                    total_bytes: data.len(),
+                },
+            );
+        }
+        controller_client::post(&address, "/sequence/commit", timeout)
+            .await
+            .map_err(|e| format!("{} did not accept the uploaded sequence: {}", controller.label(), e))?;
+
+        let upload = UploadedSequence {
+            address: address.clone(),
+            show_id: show.id.clone(),
+            cues: cue_count,
+            bytes: data.len(),
+            crc32,
+        };
+        self.lock()?.insert(address, upload.clone());
+        let verification = self.verify(controller).await?;
+        if !verification.matches {
+            log::warn!("{} stored a different sequence than was uploaded", controller.label());
+        }
+        Ok(UploadReport { upload, verification })
+    }
+
+    /// Compares the checksum `GET /sequence/info` reports with the one of the
+    /// sequence last uploaded from this desk.
+    pub async fn verify(&self, controller: &ControllerInfo) -> Result<UploadVerification, String> {
+        let expected = self.get(&controller.address)?;
+        let info = controller_client::get_json(&controller.address, "/sequence/info", transport::timeout(Transport::Http))
+            .await
+            .map_err(|e| format!("{} does not report its stored sequence: {}", controller.label(), e))?;
+        let reported_crc32 = info.get("crc").and_then(Value::as_u64).and_then(|crc| u32::try_from(crc).ok());
+        let matches = reported_crc32 == Some(expected.crc32)
+            && info.get("size").and_then(Value::as_u64) == Some(expected.bytes as u64);
+        Ok(UploadVerification {
+            address: controller.address.clone(),
+            show_id: expected.show_id,
+            expected_crc32: expected.crc32,
+            reported_crc32,
+            matches,
+        })
+    }
+
+    /// Starts the stored sequence with `POST /sequence/play`, but only once
+    /// it has verified as the one uploaded from this desk.
+    pub async fn trigger(&self, controller: &ControllerInfo) -> Result<UploadVerification, String> {
+        let verification = self.verify(controller).await?;
+        if !verification.matches {
+            return Err(format!(
+                "{} does not hold the sequence uploaded for '{}'; upload it again",
+                controller.label(),
+                verification.show_id
+            ));
+        }
+        controller_client::post(&controller.address, "/sequence/play", transport::timeout(Transport::Http))
+            .await
+            .map_err(|e| e.to_string())?;
+        log::info!("Standalone playback of '{}' started on {}", verification.show_id, controller.label());
+        Ok(verification)
+    }
+}
+
+// The effects `controller` plays, as the requests its driver would send, in
+// time order. Effects that need the desk while they run (ramps, haze
+// timing, laser paths, relay devices) cannot be compiled.
+fn compile(registry: &ControllerRegistry, controller: &ControllerInfo, show: &Show) -> Result<Vec<Cue>, String> {
+    let driver = driver::for_controller(&controller.address, &controller.controller_type);
+    let mut cues = Vec::new();
+    for effect in &show.effects {
+        let targets = registry.get(&effect.controller).is_ok_and(|c| c.address == controller.address);
+        if !targets || !show.plays(effect) {
+            continue;
+        }
+        let unsupported = if effect.ramp.is_some() {
+            Some("ramps")
+        } else if controller.is_haze() {
+            Some("haze effects")
+        } else if controller.is_laser() {
+            Some("laser effects")
+        } else if controller.is_relay() {
+            Some("relay device effects")
+        } else {
+            None
+        };
+        if let Some(kind) = unsupported {
+            return Err(format!("Effect {} cannot play standalone: {} need the desk", effect.id, kind));
+        }
+        let at_ms = (effect.start_time.max(0.0) * 1000.0).round() as u64;
+        if controller.is_pyro() {
+            cues.push(Cue {
+                at_ms,
+                path: driver.request(&OutputAction::Fire, effect.channel)?,
+            });
+            continue;
+        }
+        let (on, off) = dispatcher::lighting_actions(&effect.params);
+        cues.push(Cue {
+            at_ms,
+            path: driver.request(&on, effect.channel)?,
+        });
+        if effect.duration > 0.0 {
+            cues.push(Cue {
+                at_ms: at_ms + (effect.duration * 1000.0).round() as u64,
+                path: driver.request(&off, effect.channel)?,
+            });
+        }
+    }
+    cues.sort_by_key(|cue| cue.at_ms);
+    Ok(cues)
+}
@@ -0,0 +1,120 @@
+use serde::Serialize;
+use std::collections::HashSet;
+use std::time::Duration;
+
+use crate::dispatcher::{Dispatcher, OutputAction};
+use crate::models::Effect;
+use crate::output_refresh::OutputRefresh;
+use crate::ramp::{self, Ramp, RampValue};
+use crate::registry::{ControllerInfo, ControllerRegistry};
+
+pub const MAX_CROSSFADE_SECS: f64 = 600.0;
+
+#[derive(Debug, Serialize)]
+pub struct CrossfadeReport {
+    pub show_id: String,
+    pub duration: f64,
+    /// Outgoing level channels fading down.
+    pub faded_channels: usize,
+    /// Outgoing held outputs that were switched off at once.
+    pub cut_channels: usize,
+}
+
+pub fn check_duration(duration: f64) -> Result<Duration, String> {
+    if !duration.is_finite() || duration <= 0.0 || duration > MAX_CROSSFADE_SECS {
+        return Err(format!(
+            "Crossfade must last more than 0 and at most {:.0}s, got {}",
+            MAX_CROSSFADE_SECS, duration
+        ));
+    }
+    Ok(Duration::from_secs_f64(duration))
+}
+
+/// Fades every dimmer, haze and color output the outgoing show left on
+/// down to zero over `duration`. Everything else it still holds (relays,
+/// laser frames, lighting effects) is switched off immediately. A fading
+/// channel the incoming show commands is simply taken over by it.
+pub async fn fade_out(
+    dispatcher: &Dispatcher,
+    registry: &ControllerRegistry,
+    refresh: &OutputRefresh,
+    show_id: String,
+    duration: Duration,
+) -> Result<CrossfadeReport, String> {
+    let mut fading: Vec<(ControllerInfo, u32, OutputAction)> = Vec::new();
+    for controller in registry.list()? {
+        for (channel, action) in dispatcher.refreshable_states(&controller.address) {
+            if action.scaled(1.0).is_some() {
+                fading.push((controller.clone(), channel, action));
+            }
+        }
+    }
+    let keep: HashSet<(String, u32)> = fading.iter().map(|(c, channel, _)| (c.address.clone(), *channel)).collect();
+    let cut_channels = dispatcher.release_all_except(&keep).await;
+
+    for (controller, channel, action) in &fading {
+        let Some(off) = action.clear_action() else {
+            continue;
+        };
+        let level = action.clone();
+        let result = dispatcher
+            .stream(
+                &controller.address,
+                *channel,
+                action.clone(),
+                move |progress| level.scaled(1.0 - progress).ok_or_else(|| "Output cannot fade".to_string()),
+                duration,
+                duration,
+                off,
+                ramp::frame_rate(controller, refresh),
+            )
+            .await;
+        if let Err(e) = result {
+            log::warn!("Failed to fade out {} channel {}: {}", controller.label(), channel, e);
+        }
+    }
+    log::info!(
+        "Crossfading over {:.1}s: {} channels fading, {} cut",
+        duration.as_secs_f64(),
+        fading.len(),
+        cut_channels
+    );
+    Ok(CrossfadeReport {
+        show_id,
+        duration: duration.as_secs_f64(),
+        faded_channels: fading.len(),
+        cut_channels,
+    })
+}
+
+fn zero_like(value: &RampValue) -> RampValue {
+    match value {
+        RampValue::Level(_) => RampValue::Level(0.0),
+        RampValue::Color(_) => RampValue::Color([0, 0, 0]),
+    }
+}
+
+/// An incoming effect that starts `remaining` seconds before the crossfade
+/// ends, adjusted to fade up from zero: ramps start from zero and last at
+/// least until the crossfade ends, and plain haze effects become a ramp up
+/// to their level. Discrete effects (pyro, relays, lasers, lighting
+/// effects) and haze with its own timeout are returned unchanged and cut in.
+pub fn fade_in(effect: &Effect, controller: &ControllerInfo, remaining: f64) -> Effect {
+    let mut faded = effect.clone();
+    if remaining <= 0.0 || effect.duration <= 0.0 {
+        return faded;
+    }
+    if let Some(ramp) = &mut faded.ramp {
+        ramp.from = zero_like(&ramp.to);
+        ramp.duration = Some(ramp.duration(effect).max(remaining).min(effect.duration));
+    } else if controller.is_haze() && !effect.params.contains_key("timeout_secs") {
+        let level = effect.params.get("level").and_then(|v| v.as_f64()).unwrap_or(100.0);
+        faded.ramp = Some(Ramp {
+            from: RampValue::Level(0.0),
+            to: RampValue::Level(level.clamp(0.0, 100.0)),
+            curve: ramp::Curve::Linear,
+            duration: Some(remaining.min(effect.duration)),
+        });
+    }
+    faded
+}
@@ -0,0 +1,198 @@
+use std::collections::HashMap;
+use std::path::Path;
+
+use crate::models::{Effect, Show};
+use crate::registry::ControllerInfo;
+use crate::show_store;
+use crate::time_format;
+
+/// Effects starting closer together than this are one cue.
+const SAME_CUE_SECS: f64 = 0.001;
+
+struct Cue<'a> {
+    number: usize,
+    time: f64,
+    effects: Vec<&'a Effect>,
+}
+
+struct Section<'a> {
+    /// None for cues before the first marker, or when there are no markers.
+    title: Option<(String, f64)>,
+    cues: Vec<Cue<'a>>,
+}
+
+/// The first of `keys` set to a non-blank string in the effect's params.
+pub fn param<'a>(effect: &'a Effect, keys: &[&str]) -> Option<&'a str> {
+    keys.iter()
+        .find_map(|key| effect.params.get(*key).and_then(|v| v.as_str()))
+        .filter(|s| !s.trim().is_empty())
+}
+
+impl Cue<'_> {
+    fn name(&self) -> String {
+        let names: Vec<&str> = self
+            .effects
+            .iter()
+            .map(|e| param(e, &["name", "label"]).unwrap_or(if e.effect_type.is_empty() { &e.id } else { &e.effect_type }))
+            .collect();
+        names.join(" + ")
+    }
+
+    fn description(&self) -> String {
+        let notes: Vec<&str> = self.effects.iter().filter_map(|e| param(e, &["description", "notes"])).collect();
+        notes.join("; ")
+    }
+
+    fn fires(&self, controllers: &HashMap<&str, &ControllerInfo>) -> Vec<String> {
+        self.effects
+            .iter()
+            .map(|e| {
+                let controller = controllers.get(e.controller.as_str()).map_or(e.controller.clone(), |c| c.label());
+                format!("{} ch {}", controller, e.channel)
+            })
+            .collect()
+    }
+}
+
+// Effects that play grouped into numbered cues, split into sections at the markers.
+fn sections(show: &Show) -> Vec<Section<'_>> {
+    let mut effects: Vec<&Effect> = show.effects.iter().filter(|e| show.plays(e)).collect();
+    effects.sort_by(|a, b| a.start_time.total_cmp(&b.start_time));
+    let mut markers: Vec<(String, f64)> = show.markers.iter().map(|m| (m.name.clone(), m.time)).collect();
+    markers.sort_by(|a, b| a.1.total_cmp(&b.1));
+
+    let mut sections = vec![Section { title: None, cues: Vec::new() }];
+    let mut markers = markers.into_iter().peekable();
+    let mut number = 0;
+    for effect in effects {
+        while let Some(marker) = markers.next_if(|(_, time)| *time <= effect.start_time) {
+            sections.push(Section {
+                title: Some(marker),
+                cues: Vec::new(),
+            });
+        }
+        let section = sections.last_mut().expect("there is always a section");
+        match section.cues.last_mut() {
+            Some(cue) if effect.start_time - cue.time < SAME_CUE_SECS => cue.effects.push(effect),
+            _ => {
+                number += 1;
+                section.cues.push(Cue {
+                    number,
+                    time: effect.start_time,
+                    effects: vec![effect],
+                });
+            }
+        }
+    }
+    // Markers after the last cue still head (empty) sections.
+    sections.extend(markers.map(|marker| Section {
+        title: Some(marker),
+        cues: Vec::new(),
+    }));
+    sections.retain(|s| s.title.is_some() || !s.cues.is_empty());
+    sections
+}
+
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+fn encode_csv(show: &Show, controllers: &HashMap<&str, &ControllerInfo>) -> String {
+    let mut csv = String::from("section,cue,time,name,description,fires\n");
+    for section in sections(show) {
+        let title = section.title.as_ref().map_or("", |(name, _)| name.as_str());
+        for cue in &section.cues {
+            let row = [
+                csv_field(title),
+                cue.number.to_string(),
+                csv_field(&time_format::format_current(cue.time)),
+                csv_field(&cue.name()),
+                csv_field(&cue.description()),
+                csv_field(&cue.fires(controllers).join("; ")),
+            ];
+            csv.push_str(&row.join(","));
+            csv.push('\n');
+        }
+    }
+    csv
+}
+
+fn escape(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+const STYLE: &str = "body { font-family: sans-serif; font-size: 11pt; }
+table { width: 100%; border-collapse: collapse; }
+thead { display: table-header-group; }
+th, td { padding: 4px 6px; text-align: left; vertical-align: top; border-bottom: 1px solid #ccc; }
+.meta th { border-bottom: 2px solid #000; font-weight: normal; }
+.meta strong { font-size: 14pt; }
+.section td { padding-top: 12px; font-weight: bold; border-bottom: 2px solid #888; }
+tr { page-break-inside: avoid; }
+@page { margin: 15mm; }";
+
+// One table whose head repeats at the top of every printed page.
+fn encode_html(show: &Show, controllers: &HashMap<&str, &ControllerInfo>) -> String {
+    let sections = sections(show);
+    let cue_count: usize = sections.iter().map(|s| s.cues.len()).sum();
+    let generated = chrono::Local::now().format("%Y-%m-%d %H:%M");
+    let mut html = format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{name} — cue sheet</title>\n<style>\n{STYLE}\n</style>\n</head>\n<body>\n<table>\n<thead>\n\
+         <tr class=\"meta\"><th colspan=\"5\"><strong>{name}</strong><br>{cues} cues, {duration} long. Printed {generated}.{description}</th></tr>\n\
+         <tr><th>Cue</th><th>Time</th><th>Name</th><th>Description</th><th>Fires</th></tr>\n</thead>\n<tbody>\n",
+        name = escape(&show.name),
+        cues = cue_count,
+        duration = escape(&time_format::format_current(show.total_duration)),
+        description = if show.description.trim().is_empty() {
+            String::new()
+        } else {
+            format!("<br>{}", escape(&show.description))
+        },
+    );
+    for section in &sections {
+        if let Some((title, time)) = &section.title {
+            html.push_str(&format!(
+                "<tr class=\"section\"><td colspan=\"5\">{} — {}</td></tr>\n",
+                escape(title),
+                escape(&time_format::format_current(*time))
+            ));
+        }
+        for cue in &section.cues {
+            html.push_str(&format!(
+                "<tr><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr>\n",
+                cue.number,
+                escape(&time_format::format_current(cue.time)),
+                escape(&cue.name()),
+                escape(&cue.description()),
+                cue.fires(controllers).iter().map(|f| escape(f)).collect::<Vec<_>>().join("<br>"),
+            ));
+        }
+    }
+    html.push_str("</tbody>\n</table>\n</body>\n</html>\n");
+    html
+}
+
+/// Writes a cue sheet for the crew: enabled effects grouped into numbered
+/// cues by start time, in sections at the show's markers, with what each cue
+/// fires. Times use the current display format. "csv" or "html"; the HTML
+/// repeats the show header on every printed page. Meant for reading, not
+/// re-import.
+pub fn export(show: &Show, controllers: &[ControllerInfo], path: &Path, format: &str) -> Result<(), String> {
+    let by_address: HashMap<&str, &ControllerInfo> = controllers.iter().map(|c| (c.address.as_str(), c)).collect();
+    let data = match format {
+        "csv" => encode_csv(show, &by_address),
+        "html" => encode_html(show, &by_address),
+        _ => return Err(format!("Unsupported cue sheet format: {}, expected csv or html", format)),
+    };
+    show_store::write_atomic(path, data.as_bytes()).map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+    log::info!("Wrote {} cue sheet for '{}' to {}", format, show.name, path.display());
+    Ok(())
+}
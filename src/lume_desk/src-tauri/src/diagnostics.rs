@@ -0,0 +1,108 @@
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::{BTreeSet, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+use tauri::{AppHandle, Manager};
+
+use crate::commands::SystemInfo;
+use crate::models::Show;
+use crate::registry::ControllerInfo;
+use crate::show_engine::ShowStatus;
+use crate::show_store;
+
+/// Lines of the newest log file included in a report.
+const LOG_TAIL_LINES: usize = 500;
+
+/// Values under keys containing any of these are replaced before writing.
+const SENSITIVE_KEYS: [&str; 6] = ["token", "passphrase", "password", "secret", "api_key", "credential"];
+
+const REDACTED: &str = "[redacted]";
+
+/// Sent to support as one JSON file.
+#[derive(Debug, Serialize)]
+pub struct DiagnosticReport {
+    pub generated_at: u64,
+    pub system: SystemInfo,
+    pub performance: HashMap<String, f64>,
+    pub controllers: Vec<ControllerInfo>,
+    pub show: Option<ShowSummary>,
+    pub engine: ShowStatus,
+    pub log_tail: Vec<String>,
+}
+
+/// Show metadata only; the effect list itself stays with the operator.
+#[derive(Debug, Serialize)]
+pub struct ShowSummary {
+    pub id: String,
+    pub name: String,
+    pub total_duration: f64,
+    pub effect_count: usize,
+    pub controllers: BTreeSet<String>,
+}
+
+impl From<&Show> for ShowSummary {
+    fn from(show: &Show) -> Self {
+        Self {
+            id: show.id.clone(),
+            name: show.name.clone(),
+            total_duration: show.total_duration,
+            effect_count: show.effects.len(),
+            controllers: show.effects.iter().map(|e| e.controller.clone()).collect(),
+        }
+    }
+}
+
+/// Last lines of the most recently written log file, empty if file logging is off.
+/// Lines mentioning a sensitive key are redacted whole.
+pub fn log_tail(app: &AppHandle) -> Vec<String> {
+    let Ok(dir) = app.path().app_log_dir() else {
+        return Vec::new();
+    };
+    let newest = fs::read_dir(dir).ok().and_then(|entries| {
+        entries
+            .filter_map(Result::ok)
+            .filter(|e| e.path().extension().is_some_and(|ext| ext == "log"))
+            .max_by_key(|e| e.metadata().and_then(|m| m.modified()).ok())
+    });
+    let Some(contents) = newest.and_then(|e| fs::read_to_string(e.path()).ok()) else {
+        return Vec::new();
+    };
+    let lines: Vec<&str> = contents.lines().collect();
+    let start = lines.len().saturating_sub(LOG_TAIL_LINES);
+    lines[start..]
+        .iter()
+        .map(|line| if is_sensitive(line) { REDACTED.to_string() } else { line.to_string() })
+        .collect()
+}
+
+fn is_sensitive(text: &str) -> bool {
+    let text = text.to_lowercase();
+    SENSITIVE_KEYS.iter().any(|s| text.contains(s))
+}
+
+/// Redacts the report and writes it to `path`. Returns the written path.
+pub fn write(path: &Path, report: &DiagnosticReport) -> Result<PathBuf, String> {
+    let mut value = serde_json::to_value(report).map_err(|e| format!("Failed to encode report: {}", e))?;
+    redact(&mut value);
+    let data = serde_json::to_vec_pretty(&value).map_err(|e| format!("Failed to encode report: {}", e))?;
+    show_store::write_atomic(path, &data).map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+    log::info!("Diagnostic report written to {}", path.display());
+    Ok(path.to_path_buf())
+}
+
+fn redact(value: &mut Value) {
+    match value {
+        Value::Object(map) => {
+            for (key, value) in map.iter_mut() {
+                if is_sensitive(key) {
+                    *value = Value::String(REDACTED.to_string());
+                } else {
+                    redact(value);
+                }
+            }
+        }
+        Value::Array(items) => items.iter_mut().for_each(redact),
+        _ => {}
+    }
+}
@@ -0,0 +1,554 @@
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Manager};
+use tokio::task::JoinSet;
+
+use crate::audit;
+use crate::controller_client;
+use crate::driver::{ControllerLink, SerialLink};
+use crate::events::{self, ControllerDiscovered};
+use crate::mdns;
+use crate::registry::{self, ControllerInfo, ControllerRegistry};
+use crate::safe_mode;
+use crate::serial;
+use crate::transport::{self, Transport};
+
+/// mDNS hostnames the controller firmware announces, with the controller type each one runs.
+const LUME_HOSTNAMES: [(&str, &str); 3] = [
+    ("lume-controller.local", "firework"),
+    ("lume-lighting.local", "lights"),
+    ("lume-haze.local", "haze"),
+];
+
+/// Matches the frontend's scan interval.
+pub const DEFAULT_INTERVAL: Duration = Duration::from_secs(30);
+
+const MIN_INTERVAL: Duration = Duration::from_secs(5);
+
+/// A pass started this soon after the previous one is skipped.
+const DEBOUNCE: Duration = Duration::from_secs(2);
+
+/// Hosts that answered within this window are not probed again by the background loop.
+const RECENTLY_SEEN: Duration = Duration::from_secs(120);
+
+/// How long a pass listens for mDNS and broadcast answers.
+const REPLY_WINDOW: Duration = Duration::from_millis(1500);
+
+/// Port controllers listen on for discovery broadcasts by default.
+pub const DEFAULT_BROADCAST_PORT: u16 = 4210;
+
+/// Payload of a discovery broadcast unless configured otherwise.
+pub const DEFAULT_BROADCAST_MAGIC: &str = "LUME_DISCOVER";
+
+const MAX_MAGIC_LEN: usize = 64;
+
+/// The UDP broadcast probe, for networks that filter the multicast mDNS
+/// needs. Each controller listening on `port` answers `magic` with a JSON
+/// object: `name`, `type`, `fw`, `mac`, `id` and the HTTP `port`, all optional.
+#[derive(Debug, Clone, Serialize)]
+pub struct BroadcastProbe {
+    pub port: u16,
+    pub magic: String,
+}
+
+impl Default for BroadcastProbe {
+    fn default() -> Self {
+        Self {
+            port: DEFAULT_BROADCAST_PORT,
+            magic: DEFAULT_BROADCAST_MAGIC.to_string(),
+        }
+    }
+}
+
+/// A controller that answered a discovery pass.
+#[derive(Debug, Clone, Serialize)]
+pub struct DiscoveredController {
+    /// What it is registered under: its hostname, with the port unless 80.
+    pub address: String,
+    pub name: String,
+    pub ip: Option<String>,
+    pub port: u16,
+    pub firmware_version: Option<String>,
+    /// Empty if the controller does not say.
+    pub controller_type: String,
+    pub mac: Option<String>,
+    /// The id the firmware reports, stable across address changes.
+    pub device_id: Option<String>,
+}
+
+impl DiscoveredController {
+    // MAC or device id identify a controller across its hostname and IP;
+    // without either, only the same address does.
+    fn same_device(&self, other: &DiscoveredController) -> bool {
+        let same = |a: &Option<String>, b: &Option<String>| a.is_some() && a == b;
+        same(&self.mac, &other.mac)
+            || same(&self.device_id, &other.device_id)
+            || registry::normalize_address(&self.address) == registry::normalize_address(&other.address)
+    }
+
+    // The same controller found twice; what `other` reports wins, gaps are
+    // filled from `self`.
+    fn merge(self, other: DiscoveredController) -> DiscoveredController {
+        DiscoveredController {
+            address: self.address,
+            name: if other.name.is_empty() { self.name } else { other.name },
+            ip: other.ip.or(self.ip),
+            port: other.port,
+            firmware_version: other.firmware_version.or(self.firmware_version),
+            controller_type: if other.controller_type.is_empty() { self.controller_type } else { other.controller_type },
+            mac: other.mac.or(self.mac),
+            device_id: other.device_id.or(self.device_id),
+        }
+    }
+}
+
+struct DiscoveryState {
+    interval: Option<Duration>,
+    // Bumped whenever the interval changes so the old loop exits.
+    generation: u64,
+    last_pass: Option<Instant>,
+    last_seen: HashMap<String, Instant>,
+    broadcast: BroadcastProbe,
+}
+
+/// Periodically re-runs discovery and merges new controllers into the registry.
+#[derive(Clone)]
+pub struct Discovery {
+    state: Arc<Mutex<DiscoveryState>>,
+}
+
+impl Default for Discovery {
+    fn default() -> Self {
+        Self {
+            state: Arc::new(Mutex::new(DiscoveryState {
+                interval: Some(DEFAULT_INTERVAL),
+                generation: 0,
+                last_pass: None,
+                last_seen: HashMap::new(),
+                broadcast: BroadcastProbe::default(),
+            })),
+        }
+    }
+}
+
+impl Discovery {
+    fn lock(&self) -> Result<MutexGuard<'_, DiscoveryState>, String> {
+        self.state.lock().map_err(|_| "Discovery is unavailable".to_string())
+    }
+
+    /// Starts the background loop with the current interval. Called once at startup.
+    pub fn start(&self, app: &AppHandle) -> Result<(), String> {
+        if safe_mode::is_active() {
+            log::info!("Background discovery not started in safe mode");
+            return Ok(());
+        }
+        let (interval, generation) = {
+            let state = self.lock()?;
+            (state.interval, state.generation)
+        };
+        if let Some(interval) = interval {
+            tauri::async_runtime::spawn(run_loop(app.clone(), self.clone(), interval, generation));
+        }
+        Ok(())
+    }
+
+    /// Changes the refresh interval; `None` disables background discovery.
+    pub fn set_interval(&self, app: &AppHandle, interval: Option<Duration>) -> Result<(), String> {
+        if let Some(interval) = interval {
+            if interval < MIN_INTERVAL {
+                return Err(format!(
+                    "Discovery interval must be at least {} seconds",
+                    MIN_INTERVAL.as_secs()
+                ));
+            }
+        }
+        {
+            let mut state = self.lock()?;
+            state.interval = interval;
+            state.generation += 1;
+        }
+        match interval {
+            Some(interval) => log::info!("Discovery interval set to {}s", interval.as_secs()),
+            None => log::info!("Background discovery disabled"),
+        }
+        self.start(app)
+    }
+
+    /// Changes the port and payload of the broadcast probe.
+    pub fn set_broadcast(&self, probe: BroadcastProbe) -> Result<(), String> {
+        if probe.port == 0 {
+            return Err("Broadcast discovery needs a port".to_string());
+        }
+        if probe.magic.is_empty() || probe.magic.len() > MAX_MAGIC_LEN {
+            return Err(format!("The discovery payload must be 1 to {} bytes", MAX_MAGIC_LEN));
+        }
+        log::info!("Broadcast discovery set to port {} with payload {:?}", probe.port, probe.magic);
+        self.lock()?.broadcast = probe;
+        Ok(())
+    }
+
+    pub fn broadcast(&self) -> Result<BroadcastProbe, String> {
+        Ok(self.lock()?.broadcast.clone())
+    }
+
+    fn is_current(&self, generation: u64) -> bool {
+        self.lock().map(|s| s.generation == generation).unwrap_or(false)
+    }
+
+    /// Probes the LUME hostnames, browses mDNS for `_lume._tcp` and sends
+    /// the broadcast probe at the same time, and registers any new
+    /// responders. Answers from one controller are merged by MAC or device
+    /// id, falling back to its address. A `forced` pass (manual
+    /// scan) ignores the debounce and recently-seen filters. Returns what
+    /// answered, one entry per controller; nothing is probed in safe mode.
+    pub async fn run_pass(&self, app: &AppHandle, forced: bool) -> Result<Vec<DiscoveredController>, String> {
+        let found = self.scan(forced).await?;
+        let registry = app.state::<ControllerRegistry>();
+        for controller in &found {
+            self.register(app, &registry, controller).await?;
+        }
+        Ok(found)
+    }
+
+    // The probes of a pass, without registering what answered.
+    async fn scan(&self, forced: bool) -> Result<Vec<DiscoveredController>, String> {
+        if safe_mode::is_active() {
+            log::info!("Discovery pass skipped in safe mode");
+            return Ok(vec![]);
+        }
+        let (candidates, broadcast): (Vec<(&'static str, &'static str)>, BroadcastProbe) = {
+            let mut state = self.lock()?;
+            if !forced && state.last_pass.is_some_and(|t| t.elapsed() < DEBOUNCE) {
+                return Ok(vec![]);
+            }
+            state.last_pass = Some(Instant::now());
+            let candidates = LUME_HOSTNAMES
+                .iter()
+                .copied()
+                .filter(|(host, _)| {
+                    forced || !state.last_seen.get(*host).is_some_and(|t| t.elapsed() < RECENTLY_SEEN)
+                })
+                .collect();
+            (candidates, state.broadcast.clone())
+        };
+
+        // Probing writes to every USB serial port, so only a requested scan does it.
+        let wired = async {
+            if forced {
+                probe_serial().await
+            } else {
+                Vec::new()
+            }
+        };
+        let (probed, advertised, answered, wired) =
+            tokio::join!(probe_hostnames(candidates), browse_mdns(), probe_broadcast(&broadcast), wired);
+        for controller in &probed {
+            self.lock()?.last_seen.insert(controller.address.clone(), Instant::now());
+        }
+        // Hostnames first, so a controller found several ways keeps the
+        // address that survives DHCP changes.
+        let mut found: Vec<DiscoveredController> = Vec::new();
+        for controller in probed.into_iter().chain(advertised).chain(answered).chain(wired) {
+            match found.iter().position(|c| c.same_device(&controller)) {
+                Some(index) => {
+                    let existing = found.remove(index);
+                    found.insert(index, existing.merge(controller));
+                }
+                None => found.push(controller),
+            }
+        }
+        found.sort_by(|a, b| a.address.cmp(&b.address));
+        Ok(found)
+    }
+
+    async fn register(&self, app: &AppHandle, registry: &ControllerRegistry, found: &DiscoveredController) -> Result<(), String> {
+        let address = &found.address;
+        if registry.get(address).is_ok() {
+            return registry.mark_seen(address);
+        }
+        if let Some(existing) = registered_ip(registry, address).await {
+            log::warn!("{} is already registered as {}; not adding it again", address, existing);
+            return Ok(());
+        }
+
+        log::info!("Discovered {} controller at {}", found.controller_type, address);
+        let transport = match transport::serial_port(address) {
+            Some(_) => Transport::Serial,
+            None => Transport::Http,
+        };
+        let merged = registry.add(ControllerInfo {
+            address: address.clone(),
+            name: found.name.clone(),
+            controller_type: found.controller_type.clone(),
+            firmware_version: found.firmware_version.clone(),
+            channel_count: None,
+            power_budget_watts: None,
+            min_command_spacing_ms: None,
+            soft_start_ms: None,
+            safe_defaults: Default::default(),
+            favorite: false,
+            enabled: true,
+            mac: found.mac.clone(),
+            transport,
+            last_seen: Some(audit::now_millis()),
+        })?;
+        if merged.is_none() {
+            events::emit(
+                app,
+                events::CONTROLLER_DISCOVERED,
+                ControllerDiscovered {
+                    address: address.clone(),
+                    name: found.name.clone(),
+                    controller_type: found.controller_type.clone(),
+                    transport,
+                },
+            );
+        }
+        Ok(())
+    }
+}
+
+async fn probe_hostnames(candidates: Vec<(&'static str, &'static str)>) -> Vec<DiscoveredController> {
+    let mut probes = JoinSet::new();
+    for (host, controller_type) in candidates {
+        probes.spawn(async move {
+            controller_client::probe(host, transport::timeout(Transport::Http)).await.ok()?;
+            let ip = tokio::net::lookup_host((host, 80)).await.ok()?.next().map(|a| a.ip().to_string());
+            Some(DiscoveredController {
+                address: host.to_string(),
+                name: host.trim_end_matches(".local").to_string(),
+                ip,
+                port: 80,
+                firmware_version: None,
+                controller_type: controller_type.to_string(),
+                mac: None,
+                device_id: None,
+            })
+        });
+    }
+    let mut found = Vec::new();
+    while let Some(joined) = probes.join_next().await {
+        if let Ok(Some(controller)) = joined {
+            found.push(controller);
+        }
+    }
+    found
+}
+
+// Firing modules wired over USB, found by asking every serial port for the
+// firmware's status. Ports that answer as something else, or not at all,
+// are closed again.
+async fn probe_serial() -> Vec<DiscoveredController> {
+    let mut probes = JoinSet::new();
+    for address in serial::ports() {
+        probes.spawn(async move {
+            if let Err(e) = SerialLink::new(&address).heartbeat().await {
+                log::debug!("No firing module on {}: {}", address, e);
+                serial::close(&address);
+                return None;
+            }
+            let device = address.rsplit('/').next().unwrap_or(&address).to_string();
+            Some(DiscoveredController {
+                name: format!("Firing module ({})", device),
+                address,
+                ip: None,
+                port: 0,
+                firmware_version: None,
+                controller_type: "firework".to_string(),
+                mac: None,
+                device_id: None,
+            })
+        });
+    }
+    let mut found = Vec::new();
+    while let Some(joined) = probes.join_next().await {
+        if let Ok(Some(controller)) = joined {
+            found.push(controller);
+        }
+    }
+    found
+}
+
+// Controllers advertising `_lume._tcp`. Their TXT record carries `type`
+// (firework, lights or haze), `fw`, the firmware version, and may carry
+// `mac` and `id`.
+async fn browse_mdns() -> Vec<DiscoveredController> {
+    let instances = match mdns::browse(mdns::LUME_SERVICE, REPLY_WINDOW).await {
+        Ok(instances) => instances,
+        Err(e) => {
+            log::warn!("mDNS discovery failed: {}", e);
+            return Vec::new();
+        }
+    };
+    instances
+        .into_iter()
+        .filter_map(|instance| {
+            let ip = instance.ip.map(|ip| ip.to_string());
+            let host = instance.host.clone().or_else(|| ip.clone())?;
+            Some(DiscoveredController {
+                address: http_address(host.trim_end_matches('.'), instance.port),
+                name: instance.name,
+                ip,
+                port: instance.port,
+                firmware_version: instance.txt.get("fw").cloned(),
+                controller_type: instance.txt.get("type").cloned().unwrap_or_default(),
+                mac: instance.txt.get("mac").map(|mac| registry::normalize_mac(mac)),
+                device_id: instance.txt.get("id").cloned(),
+            })
+        })
+        .collect()
+}
+
+// Sends the probe to the limited broadcast address and collects answers
+// until the reply window closes.
+async fn probe_broadcast(probe: &BroadcastProbe) -> Vec<DiscoveredController> {
+    match broadcast_replies(probe).await {
+        Ok(found) => found,
+        Err(e) => {
+            log::warn!("Broadcast discovery failed: {}", e);
+            Vec::new()
+        }
+    }
+}
+
+async fn broadcast_replies(probe: &BroadcastProbe) -> Result<Vec<DiscoveredController>, String> {
+    let socket = tokio::net::UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0))
+        .await
+        .map_err(|e| format!("Failed to open broadcast socket: {}", e))?;
+    socket
+        .set_broadcast(true)
+        .map_err(|e| format!("Failed to enable broadcast: {}", e))?;
+    socket
+        .send_to(probe.magic.as_bytes(), SocketAddr::new(IpAddr::V4(Ipv4Addr::BROADCAST), probe.port))
+        .await
+        .map_err(|e| format!("Failed to send discovery broadcast: {}", e))?;
+
+    let mut found = Vec::new();
+    let mut buffer = [0_u8; 2048];
+    let deadline = tokio::time::Instant::now() + REPLY_WINDOW;
+    while let Ok(received) = tokio::time::timeout_at(deadline, socket.recv_from(&mut buffer)).await {
+        let Ok((len, from)) = received else {
+            continue;
+        };
+        match parse_broadcast_reply(&buffer[..len], from.ip()) {
+            Some(controller) => found.push(controller),
+            None => log::debug!("Ignoring unrecognised discovery reply from {}", from),
+        }
+    }
+    Ok(found)
+}
+
+fn parse_broadcast_reply(reply: &[u8], from: IpAddr) -> Option<DiscoveredController> {
+    let reply: Value = serde_json::from_slice(reply).ok()?;
+    let text = |key: &str| reply.get(key).and_then(Value::as_str).filter(|s| !s.is_empty()).map(str::to_string);
+    let port = match reply.get("port") {
+        Some(port) => u16::try_from(port.as_u64()?).ok().filter(|p| *p != 0)?,
+        None => 80,
+    };
+    let ip = from.to_string();
+    Some(DiscoveredController {
+        address: http_address(&ip, port),
+        name: text("name").unwrap_or_else(|| ip.clone()),
+        ip: Some(ip),
+        port,
+        firmware_version: text("fw"),
+        controller_type: text("type").unwrap_or_default(),
+        mac: text("mac").map(|mac| registry::normalize_mac(&mac)),
+        device_id: text("id"),
+    })
+}
+
+fn http_address(host: &str, port: u16) -> String {
+    if port == 80 {
+        host.to_string()
+    } else {
+        format!("{}:{}", host, port)
+    }
+}
+
+// A controller registered by IP that `host` resolves to, so one added by
+// hand is not registered a second time under its hostname.
+async fn registered_ip(registry: &ControllerRegistry, host: &str) -> Option<String> {
+    let resolved = tokio::net::lookup_host((host, 80)).await.ok()?;
+    let controllers = registry.list().ok()?;
+    for ip in resolved.map(|a| a.ip().to_string()) {
+        if let Some(existing) = controllers.iter().find(|c| registry::normalize_address(&c.address) == ip) {
+            return Some(existing.address.clone());
+        }
+    }
+    None
+}
+
+/// Scans Bluetooth LE for `duration` and registers any new LUME controllers.
+/// BLE scans are slow and may prompt for permission, so they only run on
+/// request, never in the background loop. Returns the addresses found.
+#[cfg(feature = "ble")]
+pub async fn scan_ble(app: &AppHandle, duration: Duration) -> Result<Vec<String>, String> {
+    let registry = app.state::<ControllerRegistry>();
+    let mut found = Vec::new();
+    for controller in crate::ble::scan(duration).await? {
+        found.push(controller.address.clone());
+        if registry.get(&controller.address).is_ok() {
+            continue;
+        }
+        log::info!("Discovered {} controller over Bluetooth at {}", controller.controller_type, controller.address);
+        let event = ControllerDiscovered {
+            address: controller.address.clone(),
+            name: controller.name.clone(),
+            controller_type: controller.controller_type.clone(),
+            transport: Transport::Ble,
+        };
+        if registry.add(controller)?.is_none() {
+            events::emit(app, events::CONTROLLER_DISCOVERED, event);
+        }
+    }
+    found.sort();
+    Ok(found)
+}
+
+#[cfg(not(feature = "ble"))]
+pub async fn scan_ble(_app: &AppHandle, _duration: Duration) -> Result<Vec<String>, String> {
+    Err("This build has no Bluetooth support".to_string())
+}
+
+async fn run_loop(app: AppHandle, discovery: Discovery, interval: Duration, generation: u64) {
+    loop {
+        tokio::time::sleep(interval).await;
+        if !discovery.is_current(generation) {
+            break;
+        }
+        if let Err(e) = discovery.run_pass(&app, false).await {
+            log::warn!("Background discovery failed: {}", e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn safe_mode_sends_no_broadcast_probe() {
+        let listener = tokio::net::UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0)).await.unwrap();
+        let discovery = Discovery::default();
+        discovery
+            .set_broadcast(BroadcastProbe {
+                port: listener.local_addr().unwrap().port(),
+                magic: DEFAULT_BROADCAST_MAGIC.to_string(),
+            })
+            .unwrap();
+        safe_mode::enter_on_this_thread();
+
+        let started = Instant::now();
+        assert!(discovery.scan(true).await.unwrap().is_empty());
+        // A pass that probed would have waited out the reply window.
+        assert!(started.elapsed() < REPLY_WINDOW);
+        assert!(discovery.lock().unwrap().last_pass.is_none());
+        let mut buffer = [0_u8; 64];
+        assert!(tokio::time::timeout(Duration::from_millis(200), listener.recv_from(&mut buffer)).await.is_err());
+    }
+}
@@ -0,0 +1,894 @@
+use crate::command_log::{CommandLog, CommandRecord};
+use crate::controller_client;
+use crate::driver::{self, ControllerDriver};
+use crate::events::{self, ControllerStatus};
+use crate::laser::{LaserZones, Point};
+use crate::models::Rgb;
+use crate::response_cache;
+use crate::safe_mode;
+use crate::registry::{ControllerInfo, ControllerRegistry};
+use crate::transport;
+use std::collections::{HashMap, HashSet};
+use serde::Serialize;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Manager};
+
+/// A single output operation understood by LUME controllers.
+#[derive(Debug, Clone, PartialEq)]
+pub enum OutputAction {
+    /// Fire a pyro channel on a firework controller.
+    Fire,
+    /// Switch a relay on a lighting controller.
+    Relay(bool),
+    /// Set a dimmable lighting output in percent; 0 is off.
+    Dimmer(u8),
+    /// Set a color lighting output.
+    Color(Rgb),
+    /// Run a lighting effect on one relay.
+    StartEffect { effect: String, interval_ms: Option<u64> },
+    StopEffect,
+    /// Set a haze machine's output level in percent; 0 is off.
+    Haze(u8),
+    /// Project a beam along the given path.
+    Laser(Vec<Point>),
+    LaserOff,
+    /// Switch every relay of a lighting controller.
+    AllRelays(bool),
+    /// Put every output of a controller into its safe state.
+    EmergencyStop,
+}
+
+impl OutputAction {
+    pub fn request_path(&self, channel: u32) -> String {
+        match self {
+            OutputAction::Fire => format!("/channel?id={}", channel),
+            OutputAction::Relay(on) => {
+                format!("/relay?id={}&state={}", channel, if *on { "ON" } else { "OFF" })
+            }
+            OutputAction::Dimmer(level) => format!("/dimmer?id={}&level={}", channel, level),
+            OutputAction::Color([r, g, b]) => format!("/color?id={}&r={}&g={}&b={}", channel, r, g, b),
+            OutputAction::StartEffect { effect, interval_ms } => {
+                let mut path = format!("/effect/selective?type={}&relays={}", effect, channel);
+                if let Some(interval) = interval_ms {
+                    path.push_str(&format!("&interval={}", interval));
+                }
+                path
+            }
+            OutputAction::StopEffect => "/effect/stop".to_string(),
+            OutputAction::Haze(level) => format!("/haze?level={}", level),
+            OutputAction::Laser(points) => {
+                let path: Vec<String> = points.iter().map(|p| format!("{:.4},{:.4}", p.x, p.y)).collect();
+                format!("/laser?output={}&points={}", channel, path.join(";"))
+            }
+            OutputAction::LaserOff => format!("/laser/off?output={}", channel),
+            OutputAction::AllRelays(on) => format!("/all?state={}", if *on { "ON" } else { "OFF" }),
+            OutputAction::EmergencyStop => "/emergency/stop".to_string(),
+        }
+    }
+
+    /// Steady output states that can be re-sent without side effects. Pyro
+    /// fires and lighting effects are one-shot: sending them again would
+    /// fire again or restart the effect.
+    fn is_refreshable(&self) -> bool {
+        match self {
+            OutputAction::Color(rgb) => *rgb != [0, 0, 0],
+            _ => matches!(
+                self,
+                OutputAction::Relay(true)
+                    | OutputAction::Dimmer(1..=u8::MAX)
+                    | OutputAction::Haze(1..=u8::MAX)
+                    | OutputAction::Laser(_)
+            ),
+        }
+    }
+
+    /// The action that switches this steady state off again.
+    pub fn clear_action(&self) -> Option<OutputAction> {
+        match self {
+            OutputAction::Relay(true) => Some(OutputAction::Relay(false)),
+            OutputAction::Dimmer(_) => Some(OutputAction::Dimmer(0)),
+            OutputAction::Color(_) => Some(OutputAction::Color([0, 0, 0])),
+            OutputAction::Haze(_) => Some(OutputAction::Haze(0)),
+            OutputAction::Laser(_) => Some(OutputAction::LaserOff),
+            _ => None,
+        }
+    }
+
+    /// This action at `gain` (0..=1) of its level, or None for outputs that
+    /// have no level and so cannot fade.
+    pub fn scaled(&self, gain: f64) -> Option<OutputAction> {
+        let scale = |level: u8| (level as f64 * gain.clamp(0.0, 1.0)).round() as u8;
+        match self {
+            OutputAction::Dimmer(level) => Some(OutputAction::Dimmer(scale(*level))),
+            OutputAction::Haze(level) => Some(OutputAction::Haze(scale(*level))),
+            OutputAction::Color(rgb) => Some(OutputAction::Color(rgb.map(scale))),
+            _ => None,
+        }
+    }
+
+    /// False for actions that only clear an output.
+    pub fn is_activating(&self) -> bool {
+        !matches!(
+            self,
+            OutputAction::Relay(false)
+                | OutputAction::Dimmer(0)
+                | OutputAction::Color([0, 0, 0])
+                | OutputAction::StopEffect
+                | OutputAction::Haze(0)
+                | OutputAction::LaserOff
+                | OutputAction::AllRelays(false)
+                | OutputAction::EmergencyStop
+        )
+    }
+}
+
+/// Lighting outputs run a named effect if one is given, otherwise the relay
+/// is simply switched on. Returns the activating and clearing actions.
+pub fn lighting_actions(params: &HashMap<String, serde_json::Value>) -> (OutputAction, OutputAction) {
+    match params.get("effect").and_then(|v| v.as_str()) {
+        Some(effect) => (
+            OutputAction::StartEffect {
+                effect: effect.to_uppercase(),
+                interval_ms: params.get("interval_ms").and_then(|v| v.as_u64()),
+            },
+            OutputAction::StopEffect,
+        ),
+        None => (OutputAction::Relay(true), OutputAction::Relay(false)),
+    }
+}
+
+type ChannelKey = (String, u32);
+
+/// Longest soft-start a controller can be given.
+pub const MAX_SOFT_START: Duration = Duration::from_secs(5);
+
+/// Steps per second of a soft-start ramp.
+const SOFT_START_HZ: u32 = 25;
+
+/// Attempts after the first for a fire command that got no reply.
+const MAX_FIRE_RETRIES: u32 = 2;
+
+const FIRE_RETRY_BACKOFF: Duration = Duration::from_millis(150);
+
+/// Counters for the sequenced fire protocol.
+#[derive(Debug, Default)]
+struct FireCounters {
+    sent: AtomicU64,
+    acked: AtomicU64,
+    retried: AtomicU64,
+    duplicates_suppressed: AtomicU64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FireStats {
+    pub sent: u64,
+    pub acked: u64,
+    pub retried: u64,
+    /// Retries the controller recognised as already fired and ignored.
+    pub duplicates_suppressed: u64,
+}
+
+// Sends fires with ids and retries them; apart from the dispatcher so the
+// protocol can be exercised against mock controllers.
+struct FireSequencer {
+    // Fire sequence ids are unique per (session, seq), so a restarted app
+    // never collides with ids a controller remembers from an earlier run.
+    session: String,
+    next_seq: AtomicU64,
+    // Controllers that have acked a fire by id and so suppress duplicates.
+    sequenced: Mutex<HashSet<String>>,
+    counters: FireCounters,
+}
+
+impl FireSequencer {
+    fn new() -> Self {
+        Self {
+            session: uuid::Uuid::new_v4().simple().to_string()[..8].to_string(),
+            next_seq: AtomicU64::new(1),
+            sequenced: Mutex::default(),
+            counters: FireCounters::default(),
+        }
+    }
+
+    // Sequenced fire protocol. Every fire carries `session` and `seq`; a
+    // controller that supports it must:
+    //  - fire at most once per (session, seq), remembering at least the last
+    //    32 pairs, and answer a repeat with `{"ack": seq, "duplicate": true}`
+    //    without firing;
+    //  - answer a new fire with `{"ack": seq}` once the output has fired.
+    // Only commands that got no reply at all are retried, and only on
+    // controllers that have already acked by id. Older firmware ignores the
+    // extra parameters, so it is sent each fire exactly once.
+    async fn fire(&self, controller: &str, channel: u32, request: &str) -> Result<(), controller_client::RequestError> {
+        let counters = &self.counters;
+        let seq = self.next_seq.fetch_add(1, Ordering::Relaxed);
+        let path = format!("{}&session={}&seq={}", request, self.session, seq);
+        counters.sent.fetch_add(1, Ordering::Relaxed);
+
+        let mut attempt = 0;
+        loop {
+            match controller_client::post_json(controller, &path, transport::timeout_for(controller)).await {
+                Ok(reply) => {
+                    match reply.get("ack").and_then(|v| v.as_u64()) {
+                        Some(ack) if ack != seq => {
+                            return Err(controller_client::RequestError::Rejected(format!(
+                                "{} acknowledged fire {} instead of {}",
+                                controller, ack, seq
+                            )));
+                        }
+                        Some(_) => {
+                            if let Ok(mut sequenced) = self.sequenced.lock() {
+                                sequenced.insert(controller.to_string());
+                            }
+                        }
+                        None => {}
+                    }
+                    if reply.get("duplicate").and_then(|v| v.as_bool()) == Some(true) {
+                        counters.duplicates_suppressed.fetch_add(1, Ordering::Relaxed);
+                    }
+                    counters.acked.fetch_add(1, Ordering::Relaxed);
+                    return Ok(());
+                }
+                Err(e) if !e.reached_controller() && attempt < MAX_FIRE_RETRIES && contains(&self.sequenced, controller) => {
+                    attempt += 1;
+                    counters.retried.fetch_add(1, Ordering::Relaxed);
+                    log::warn!("No reply to fire {} on {} channel {}; retrying ({})", seq, controller, channel, e);
+                    tokio::time::sleep(FIRE_RETRY_BACKOFF * attempt).await;
+                }
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    fn stats(&self) -> FireStats {
+        let counters = &self.counters;
+        FireStats {
+            sent: counters.sent.load(Ordering::Relaxed),
+            acked: counters.acked.load(Ordering::Relaxed),
+            retried: counters.retried.load(Ordering::Relaxed),
+            duplicates_suppressed: counters.duplicates_suppressed.load(Ordering::Relaxed),
+        }
+    }
+}
+
+struct DispatcherInner {
+    app: AppHandle,
+    muted: Mutex<HashSet<ChannelKey>>,
+    // Whole controllers muted, e.g. through their zone.
+    muted_controllers: Mutex<HashSet<String>>,
+    // Controllers muted because another zone is isolated. Kept apart from
+    // explicit mutes so ending the isolation does not unmute those.
+    isolated_out: Mutex<HashSet<String>>,
+    // Controllers held dark until their blackout is cleared.
+    blacked_out: Mutex<HashSet<String>>,
+    // Bumped every time a channel is re-triggered or released, so a stale
+    // hold timer never clears a newer activation.
+    holds: Mutex<HashMap<ChannelKey, (u64, OutputAction)>>,
+    // Generation of the soft-start ramp running on a channel; any other
+    // command to the channel ends it.
+    soft_starts: Mutex<HashMap<ChannelKey, u64>>,
+    next_generation: Mutex<u64>,
+    // Last known reachability per controller, from command outcomes.
+    online: Mutex<HashMap<String, bool>>,
+    // Steady state each channel was last set to, for output refresh.
+    states: Mutex<HashMap<ChannelKey, OutputAction>>,
+    fires: FireSequencer,
+    commands: CommandLog,
+}
+
+/// Sends output commands to controllers. Cheap to clone.
+#[derive(Clone)]
+pub struct Dispatcher {
+    inner: Arc<DispatcherInner>,
+}
+
+// Step `i` of `steps` of a soft-start ramp up to `target`.
+fn soft_start_step(target: &OutputAction, i: u32, steps: u32) -> OutputAction {
+    target.scaled(i as f64 / steps as f64).unwrap_or_else(|| target.clone())
+}
+
+fn key(controller: &str, channel: u32) -> ChannelKey {
+    (controller.to_string(), channel)
+}
+
+fn contains(set: &Mutex<HashSet<String>>, controller: &str) -> bool {
+    set.lock().map(|set| set.contains(controller)).unwrap_or(false)
+}
+
+impl Dispatcher {
+    pub fn new(app: AppHandle) -> Self {
+        Self {
+            inner: Arc::new(DispatcherInner {
+                app,
+                muted: Mutex::default(),
+                muted_controllers: Mutex::default(),
+                isolated_out: Mutex::default(),
+                blacked_out: Mutex::default(),
+                holds: Mutex::default(),
+                soft_starts: Mutex::default(),
+                next_generation: Mutex::default(),
+                online: Mutex::default(),
+                states: Mutex::default(),
+                fires: FireSequencer::new(),
+                commands: CommandLog::default(),
+            }),
+        }
+    }
+
+    pub fn set_muted(&self, controller: &str, channel: u32, muted: bool) -> Result<(), String> {
+        let mut set = self.inner.muted.lock().map_err(|_| "Mute state is unavailable".to_string())?;
+        if muted {
+            set.insert(key(controller, channel));
+        } else {
+            set.remove(&key(controller, channel));
+        }
+        Ok(())
+    }
+
+    pub fn set_controller_muted(&self, controller: &str, muted: bool) -> Result<(), String> {
+        let mut set = self
+            .inner
+            .muted_controllers
+            .lock()
+            .map_err(|_| "Mute state is unavailable".to_string())?;
+        if muted {
+            set.insert(controller.to_string());
+        } else {
+            set.remove(controller);
+        }
+        Ok(())
+    }
+
+    /// Replaces the set of controllers muted by an isolate; empty ends it.
+    pub fn set_isolated_out(&self, controllers: HashSet<String>) -> Result<(), String> {
+        *self
+            .inner
+            .isolated_out
+            .lock()
+            .map_err(|_| "Mute state is unavailable".to_string())? = controllers;
+        Ok(())
+    }
+
+    pub fn set_blacked_out(&self, controller: &str, blacked_out: bool) -> Result<(), String> {
+        let mut set = self
+            .inner
+            .blacked_out
+            .lock()
+            .map_err(|_| "Blackout state is unavailable".to_string())?;
+        if blacked_out {
+            set.insert(controller.to_string());
+        } else {
+            set.remove(controller);
+        }
+        Ok(())
+    }
+
+    pub fn is_blacked_out(&self, controller: &str) -> bool {
+        contains(&self.inner.blacked_out, controller)
+    }
+
+    pub fn is_muted(&self, controller: &str, channel: u32) -> bool {
+        let controller_muted =
+            contains(&self.inner.muted_controllers, controller) || contains(&self.inner.isolated_out, controller);
+        controller_muted
+            || self
+                .inner
+                .muted
+                .lock()
+                .map(|set| set.contains(&key(controller, channel)))
+                .unwrap_or(false)
+    }
+
+    /// Sends one action. Activating actions on muted channels are refused;
+    /// clearing actions always go through. Beams outside the laser's safe
+    /// zone never leave this function. On controllers with a soft-start, a
+    /// dimmer, haze or color output switched on from off ramps up to its
+    /// level instead; everything else, fires included, is sent as is.
+    pub async fn send(&self, controller: &str, channel: u32, action: &OutputAction) -> Result<(), String> {
+        self.end_soft_start(controller, channel);
+        let info = self.inner.app.state::<ControllerRegistry>().get(controller).ok();
+        // Clearing still goes through, so disabling never leaves outputs on.
+        if action.is_activating() && info.as_ref().is_some_and(|c| !c.enabled) {
+            return Err(format!("{} is disabled", controller));
+        }
+        match self.soft_start_for(info.as_ref(), controller, channel, action) {
+            Some(ramp) => self.soft_start(controller, channel, action.clone(), ramp).await,
+            None => self.transmit(controller, channel, action, true).await,
+        }
+    }
+
+    // Sends `action` without a soft-start, e.g. a frame of a ramp that is
+    // already gradual.
+    async fn send_instant(&self, controller: &str, channel: u32, action: &OutputAction) -> Result<(), String> {
+        self.end_soft_start(controller, channel);
+        self.transmit(controller, channel, action, true).await
+    }
+
+    fn soft_start_for(
+        &self,
+        info: Option<&ControllerInfo>,
+        controller: &str,
+        channel: u32,
+        action: &OutputAction,
+    ) -> Option<Duration> {
+        if !action.is_activating() || action.scaled(0.0).is_none() || self.current_state(controller, channel).is_some() {
+            return None;
+        }
+        info?.soft_start()
+    }
+
+    // Sends the first step of the ramp to `target` now, so refusals reach
+    // the caller, and the rest in the background. Steps are not recorded as
+    // the channel's state; only reaching the target is.
+    async fn soft_start(&self, controller: &str, channel: u32, target: OutputAction, ramp: Duration) -> Result<(), String> {
+        let steps = ((ramp.as_secs_f64() * SOFT_START_HZ as f64).ceil() as u32).max(1);
+        if steps == 1 {
+            return self.transmit(controller, channel, &target, true).await;
+        }
+        let first = soft_start_step(&target, 1, steps);
+        let generation = self.next_generation();
+        if let Ok(mut ramps) = self.inner.soft_starts.lock() {
+            ramps.insert(key(controller, channel), generation);
+        }
+        if let Err(e) = self.transmit(controller, channel, &first, false).await {
+            self.end_soft_start(controller, channel);
+            return Err(e);
+        }
+
+        let dispatcher = self.clone();
+        let controller = controller.to_string();
+        tauri::async_runtime::spawn(async move {
+            let mut interval = tokio::time::interval(ramp / steps);
+            interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+            interval.tick().await;
+            let mut last = first;
+            for i in 2..=steps {
+                interval.tick().await;
+                if !dispatcher.soft_start_current(&controller, channel, generation) {
+                    return;
+                }
+                let action = soft_start_step(&target, i, steps);
+                if action == last {
+                    continue;
+                }
+                let done = i == steps;
+                let result = dispatcher.transmit(&controller, channel, &action, done).await;
+                // Superseded while the step was in flight: the newer command
+                // may have landed first, so put the channel back to it.
+                if !dispatcher.soft_start_current(&controller, channel, generation) {
+                    let current = dispatcher.current_state(&controller, channel).or_else(|| target.clear_action());
+                    if let Some(current) = current {
+                        let _ = dispatcher.transmit(&controller, channel, &current, false).await;
+                    }
+                    return;
+                }
+                match result {
+                    Err(e) if done => log::warn!("Soft-start on {} channel {} did not reach its level: {}", controller, channel, e),
+                    Err(e) => log::debug!("Soft-start step for {} channel {} dropped: {}", controller, channel, e),
+                    Ok(()) => {}
+                }
+                last = action;
+            }
+            dispatcher.end_soft_start(&controller, channel);
+        });
+        Ok(())
+    }
+
+    fn soft_start_current(&self, controller: &str, channel: u32, generation: u64) -> bool {
+        self.inner
+            .soft_starts
+            .lock()
+            .map(|ramps| ramps.get(&key(controller, channel)) == Some(&generation))
+            .unwrap_or(false)
+    }
+
+    fn end_soft_start(&self, controller: &str, channel: u32) {
+        if let Ok(mut ramps) = self.inner.soft_starts.lock() {
+            ramps.remove(&key(controller, channel));
+        }
+    }
+
+    async fn transmit(&self, controller: &str, channel: u32, action: &OutputAction, record: bool) -> Result<(), String> {
+        // Checked here too so safe mode never marks controllers offline.
+        // Switching outputs off only makes things safe and still goes out.
+        if action.is_activating() {
+            safe_mode::check()?;
+        }
+        if action.is_activating() && self.is_blacked_out(controller) {
+            return Err(format!("{} is blacked out", controller));
+        }
+        if action.is_activating() && self.is_muted(controller, channel) {
+            return Err(format!("{} channel {} is muted", controller, channel));
+        }
+        self.inner.app.state::<LaserZones>().admit(controller, action)?;
+        let driver = self.driver(controller);
+        let request = driver.request(action, channel)?;
+        let timeout = transport::timeout_for(controller);
+        let started = Instant::now();
+        let result = match action {
+            OutputAction::Fire if driver.sequenced_fire() => self.inner.fires.fire(controller, channel, &request).await,
+            _ if action.is_activating() => controller_client::post(controller, &request, timeout).await,
+            _ => controller_client::post_in_safe_mode(controller, &request, timeout).await,
+        };
+        self.inner.commands.record(controller, channel, request, started.elapsed(), &result);
+        self.record_outcome(controller, &result);
+        if result.is_ok() && record {
+            self.record_state(controller, channel, action);
+        }
+        result.map_err(|e| e.to_string())
+    }
+
+    /// The driver for the controller's type and transport; unregistered
+    /// network addresses get the LUME driver.
+    pub fn driver(&self, controller: &str) -> &'static dyn ControllerDriver {
+        let info = self.inner.app.state::<ControllerRegistry>().get(controller).ok();
+        match &info {
+            Some(info) => driver::for_controller(&info.address, &info.controller_type),
+            None => driver::for_controller(controller, ""),
+        }
+    }
+
+    fn record_state(&self, controller: &str, channel: u32, action: &OutputAction) {
+        let Ok(mut states) = self.inner.states.lock() else {
+            return;
+        };
+        match action {
+            OutputAction::EmergencyStop | OutputAction::AllRelays(false) => states.retain(|(c, _), _| c != controller),
+            action if action.is_refreshable() => {
+                states.insert(key(controller, channel), action.clone());
+            }
+            action if !action.is_activating() => {
+                states.remove(&key(controller, channel));
+            }
+            _ => {}
+        }
+    }
+
+    fn current_state(&self, controller: &str, channel: u32) -> Option<OutputAction> {
+        self.inner.states.lock().ok()?.get(&key(controller, channel)).cloned()
+    }
+
+    /// Re-sends the steady state of one channel. If the channel changed while
+    /// the refresh was in flight, its current state is sent again so a stale
+    /// refresh can never be the last word, e.g. relighting a relay just switched off.
+    pub async fn refresh(&self, controller: &str, channel: u32, action: &OutputAction) -> Result<(), String> {
+        if self.current_state(controller, channel).as_ref() != Some(action) {
+            return Ok(());
+        }
+        self.send(controller, channel, action).await?;
+        match self.current_state(controller, channel) {
+            Some(current) if current == *action => Ok(()),
+            Some(current) => self.send(controller, channel, &current).await,
+            None => match action.clear_action() {
+                Some(off) => self.send(controller, channel, &off).await,
+                None => Ok(()),
+            },
+        }
+    }
+
+    /// Steady outputs currently set on `controller`, by channel.
+    pub fn refreshable_states(&self, controller: &str) -> Vec<(u32, OutputAction)> {
+        let Ok(states) = self.inner.states.lock() else {
+            return Vec::new();
+        };
+        states
+            .iter()
+            .filter(|((c, _), _)| c == controller)
+            .map(|((_, channel), action)| (*channel, action.clone()))
+            .collect()
+    }
+
+    /// Up to `n` of the latest commands sent to `controller`, oldest first.
+    pub fn command_log(&self, controller: &str, n: usize) -> Result<Vec<CommandRecord>, String> {
+        self.inner.commands.last(controller, n)
+    }
+
+    pub fn set_command_log_size(&self, size: usize) -> Result<(), String> {
+        self.inner.commands.set_capacity(size)
+    }
+
+    pub fn command_log_size(&self) -> usize {
+        self.inner.commands.capacity()
+    }
+
+    pub fn fire_stats(&self) -> FireStats {
+        self.inner.fires.stats()
+    }
+
+    // Emits controller-status whenever a controller starts or stops responding.
+    fn record_outcome(&self, controller: &str, result: &Result<(), controller_client::RequestError>) {
+        let reached = result.as_ref().map_or_else(|e| e.reached_controller(), |_| true);
+        self.note_reachability(controller, reached, result.as_ref().err().map(|e| e.to_string()));
+    }
+
+    /// Records whether a controller answered, from a command or a probe.
+    pub fn note_reachability(&self, controller: &str, reached: bool, error: Option<String>) {
+        let previous = match self.inner.online.lock() {
+            Ok(mut online) => online.insert(controller.to_string(), reached),
+            Err(_) => return,
+        };
+        // Coming back from offline usually means a reboot or a reflash.
+        if reached && previous == Some(false) {
+            response_cache::invalidate(controller);
+        }
+        if reached {
+            if let Err(e) = self.inner.app.state::<ControllerRegistry>().mark_seen(controller) {
+                log::warn!("Failed to record {} as seen: {}", controller, e);
+            }
+        }
+        if previous != Some(reached) {
+            events::emit(
+                &self.inner.app,
+                events::CONTROLLER_STATUS,
+                ControllerStatus {
+                    address: controller.to_string(),
+                    name: self
+                        .inner
+                        .app
+                        .state::<ControllerRegistry>()
+                        .get(controller)
+                        .ok()
+                        .and_then(|c| c.display_name()),
+                    online: reached,
+                    error,
+                },
+            );
+        }
+    }
+
+    /// Last known reachability; None if the controller was never contacted.
+    pub fn last_known_online(&self, controller: &str) -> Option<bool> {
+        self.inner.online.lock().ok()?.get(controller).copied()
+    }
+
+    /// Clears every held output, e.g. when playback stops.
+    pub async fn release_all(&self) {
+        self.release_all_except(&HashSet::new()).await;
+    }
+
+    /// Clears every held output except the (controller, channel) pairs in
+    /// `keep`. Returns how many were cleared.
+    pub async fn release_all_except(&self, keep: &HashSet<ChannelKey>) -> usize {
+        let held: Vec<(ChannelKey, OutputAction)> = match self.inner.holds.lock() {
+            Ok(mut holds) => {
+                let released: Vec<ChannelKey> = holds.keys().filter(|k| !keep.contains(*k)).cloned().collect();
+                released
+                    .into_iter()
+                    .filter_map(|k| holds.remove(&k).map(|(_, off)| (k, off)))
+                    .collect()
+            }
+            Err(_) => return 0,
+        };
+        let count = held.len();
+        for ((controller, channel), off) in held {
+            if let Err(e) = self.send(&controller, channel, &off).await {
+                log::warn!("Failed to clear {} channel {}: {}", controller, channel, e);
+            }
+        }
+        count
+    }
+
+    /// Clears the held outputs of one controller.
+    pub async fn release_controller(&self, controller: &str) {
+        let held: Vec<(u32, OutputAction)> = match self.inner.holds.lock() {
+            Ok(mut holds) => {
+                let channels: Vec<ChannelKey> = holds.keys().filter(|(c, _)| c == controller).cloned().collect();
+                channels
+                    .into_iter()
+                    .filter_map(|k| holds.remove(&k).map(|(_, off)| (k.1, off)))
+                    .collect()
+            }
+            Err(_) => return,
+        };
+        for (channel, off) in held {
+            if let Err(e) = self.send(controller, channel, &off).await {
+                log::warn!("Failed to clear {} channel {}: {}", controller, channel, e);
+            }
+        }
+    }
+
+    /// Sends `on`, then `off` once `hold` elapses unless the channel was
+    /// re-triggered or released in the meantime.
+    pub async fn hold(
+        &self,
+        controller: &str,
+        channel: u32,
+        on: OutputAction,
+        off: OutputAction,
+        hold: Duration,
+    ) -> Result<(), String> {
+        self.send(controller, channel, &on).await?;
+        let generation = self.track_hold(controller, channel, off.clone());
+
+        let dispatcher = self.clone();
+        let controller = controller.to_string();
+        tauri::async_runtime::spawn(async move {
+            tokio::time::sleep(hold).await;
+            if dispatcher.take_hold(&controller, channel, Some(generation)).is_some() {
+                if let Err(e) = dispatcher.send(&controller, channel, &off).await {
+                    log::warn!("Failed to clear {} channel {}: {}", controller, channel, e);
+                }
+            }
+        });
+        Ok(())
+    }
+
+    /// Sends `first`, then `frame(progress)` at `hz` until progress reaches
+    /// 1 after `ramp`. The last frame is held until `hold` has passed since
+    /// the start, then `off` is sent. Frames equal to the previous one are
+    /// skipped, and a slow controller drops frames rather than falling
+    /// behind. Re-triggering or releasing the channel ends the stream.
+    #[allow(clippy::too_many_arguments)]
+    pub async fn stream(
+        &self,
+        controller: &str,
+        channel: u32,
+        first: OutputAction,
+        frame: impl Fn(f64) -> Result<OutputAction, String> + Send + 'static,
+        ramp: Duration,
+        hold: Duration,
+        off: OutputAction,
+        hz: u32,
+    ) -> Result<(), String> {
+        self.send(controller, channel, &first).await?;
+        let started = tokio::time::Instant::now();
+        let generation = self.track_hold(controller, channel, off.clone());
+
+        let dispatcher = self.clone();
+        let controller = controller.to_string();
+        tauri::async_runtime::spawn(async move {
+            let mut interval = tokio::time::interval(Duration::from_secs_f64(1.0 / hz.max(1) as f64));
+            interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+            let mut last = first;
+            loop {
+                interval.tick().await;
+                if !dispatcher.holds_generation(&controller, channel, generation) {
+                    return;
+                }
+                let progress = if ramp.is_zero() { 1.0 } else { started.elapsed().as_secs_f64() / ramp.as_secs_f64() };
+                match frame(progress.min(1.0)) {
+                    Ok(action) if action != last => {
+                        if let Err(e) = dispatcher.send_instant(&controller, channel, &action).await {
+                            log::debug!("Ramp frame for {} channel {} dropped: {}", controller, channel, e);
+                        }
+                        // Released while the frame was in flight: clear again.
+                        if !dispatcher.holds_generation(&controller, channel, generation) {
+                            let _ = dispatcher.send(&controller, channel, &off).await;
+                            return;
+                        }
+                        last = action;
+                    }
+                    Ok(_) => {}
+                    Err(e) => {
+                        log::warn!("Ramp on {} channel {} stopped: {}", controller, channel, e);
+                        break;
+                    }
+                }
+                if progress >= 1.0 {
+                    break;
+                }
+            }
+            tokio::time::sleep_until(started + hold).await;
+            if dispatcher.take_hold(&controller, channel, Some(generation)).is_some() {
+                if let Err(e) = dispatcher.send(&controller, channel, &off).await {
+                    log::warn!("Failed to clear {} channel {}: {}", controller, channel, e);
+                }
+            }
+        });
+        Ok(())
+    }
+
+    /// Clears a held channel immediately.
+    pub async fn release(&self, controller: &str, channel: u32) -> Result<(), String> {
+        let off = self
+            .take_hold(controller, channel, None)
+            .ok_or_else(|| format!("Nothing is held on {} channel {}", controller, channel))?;
+        self.send(controller, channel, &off).await
+    }
+
+    fn next_generation(&self) -> u64 {
+        match self.inner.next_generation.lock() {
+            Ok(mut next) => {
+                *next += 1;
+                *next
+            }
+            Err(_) => 0,
+        }
+    }
+
+    fn track_hold(&self, controller: &str, channel: u32, off: OutputAction) -> u64 {
+        let generation = self.next_generation();
+        if let Ok(mut holds) = self.inner.holds.lock() {
+            holds.insert(key(controller, channel), (generation, off));
+        }
+        generation
+    }
+
+    fn holds_generation(&self, controller: &str, channel: u32, generation: u64) -> bool {
+        self.inner
+            .holds
+            .lock()
+            .map(|holds| holds.get(&key(controller, channel)).is_some_and(|(g, _)| *g == generation))
+            .unwrap_or(false)
+    }
+
+    // Removes the hold if it is still the given generation (or any, if None).
+    fn take_hold(&self, controller: &str, channel: u32, generation: Option<u64>) -> Option<OutputAction> {
+        let mut holds = self.inner.holds.lock().ok()?;
+        let k = key(controller, channel);
+        match holds.get(&k) {
+            Some((current, _)) if generation.map_or(true, |g| g == *current) => holds.remove(&k).map(|(_, off)| off),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock_controller::{MockController, Reply};
+    use serde_json::json;
+
+    #[tokio::test]
+    async fn unanswered_fire_is_retried_with_the_same_id_once_sequenced() {
+        let mock = MockController::start().await;
+        mock.script(
+            "/channel",
+            [
+                Reply::Json(json!({ "ack": 1 })),
+                Reply::Drop,
+                Reply::Json(json!({ "ack": 2, "duplicate": true })),
+            ],
+        );
+        let fires = FireSequencer::new();
+        fires.fire(&mock.address, 3, "/channel?id=3").await.unwrap();
+        fires.fire(&mock.address, 3, "/channel?id=3").await.unwrap();
+
+        let paths = mock.paths();
+        mock.assert_path_prefixes(&["/channel?id=3&session=", "/channel?id=3&session=", "/channel?id=3&session="]);
+        assert!(paths[1].ends_with("&seq=2"));
+        assert_eq!(paths[1], paths[2]);
+        let stats = fires.stats();
+        assert_eq!((stats.sent, stats.acked, stats.retried, stats.duplicates_suppressed), (2, 2, 1, 1));
+    }
+
+    #[tokio::test]
+    async fn controller_without_ids_is_sent_each_fire_once() {
+        let mock = MockController::start().await;
+        mock.script("/channel", [Reply::ok(), Reply::Drop]);
+        let fires = FireSequencer::new();
+        fires.fire(&mock.address, 1, "/channel?id=1").await.unwrap();
+        assert!(fires.fire(&mock.address, 1, "/channel?id=1").await.is_err());
+        assert_eq!(mock.count("/channel"), 2);
+        assert_eq!(fires.stats().retried, 0);
+    }
+
+    #[tokio::test]
+    async fn retries_back_off_and_give_up() {
+        let mock = MockController::start().await;
+        mock.script("/channel", [Reply::Json(json!({ "ack": 1 }))]);
+        mock.always("/channel", Reply::Drop);
+        let fires = FireSequencer::new();
+        fires.fire(&mock.address, 1, "/channel?id=1").await.unwrap();
+        let error = fires.fire(&mock.address, 1, "/channel?id=1").await.unwrap_err();
+        assert!(!error.reached_controller());
+
+        let received = mock.received();
+        assert_eq!(received.len(), 2 + MAX_FIRE_RETRIES as usize);
+        for attempt in 1..=MAX_FIRE_RETRIES as usize {
+            let gap = received[attempt + 1].at - received[attempt].at;
+            assert!(gap >= FIRE_RETRY_BACKOFF * attempt as u32, "retry {} after {:?}", attempt, gap);
+        }
+    }
+
+    #[tokio::test]
+    async fn acknowledging_another_fire_is_a_refusal() {
+        let mock = MockController::start().await;
+        mock.always("/channel", Reply::Json(json!({ "ack": 7 })));
+        let error = FireSequencer::new().fire(&mock.address, 1, "/channel?id=1").await.unwrap_err();
+        assert!(error.reached_controller());
+        assert_eq!(mock.count("/channel"), 1);
+    }
+}
@@ -0,0 +1,494 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::fs;
+use std::io::ErrorKind;
+use std::net::{IpAddr, Ipv4Addr};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+use tokio::net::UdpSocket;
+use tokio::time::MissedTickBehavior;
+
+use crate::models::{Effect, Rgb};
+use crate::palette::COLOR_PARAM;
+use crate::protocols::{artnet, sacn};
+use crate::ramp::RampValue;
+use crate::registry;
+use crate::safe_mode;
+
+/// Frames per second sent to every universe while patched cues play.
+pub const STREAM_HZ: u32 = 40;
+
+/// Slots in one DMX universe.
+pub const UNIVERSE_SIZE: usize = 512;
+
+/// How many slots a patched channel drives, starting at its address.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Footprint {
+    #[default]
+    Dimmer,
+    Rgb,
+}
+
+impl Footprint {
+    fn slots(self) -> u16 {
+        match self {
+            Footprint::Dimmer => 1,
+            Footprint::Rgb => 3,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DmxProtocol {
+    #[default]
+    ArtNet,
+    /// E1.31 streaming ACN.
+    Sacn,
+}
+
+/// A universe, the protocol it is sent with and where its frames go.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DmxUniverse {
+    pub universe: u16,
+    #[serde(default)]
+    pub protocol: DmxProtocol,
+    /// Art-Net: a node's address, or a broadcast address to reach every
+    /// node on the subnet. sACN: a receiver's address for unicast, or None
+    /// for the universe's multicast group.
+    #[serde(default)]
+    pub target: Option<IpAddr>,
+    /// sACN source priority, 0-200; receivers follow the highest source.
+    #[serde(default = "default_priority")]
+    pub priority: u8,
+}
+
+fn default_priority() -> u8 {
+    sacn::DEFAULT_PRIORITY
+}
+
+/// Sends the cues of one channel of an effect's controller reference to DMX
+/// slots instead of a LUME controller.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DmxPatch {
+    /// The `controller` the show's effects name; not a registered controller.
+    pub fixture: String,
+    pub channel: u32,
+    pub universe: u16,
+    /// First slot, 1-512.
+    pub address: u16,
+    #[serde(default)]
+    pub footprint: Footprint,
+}
+
+impl DmxPatch {
+    fn slots(&self) -> std::ops::RangeInclusive<u16> {
+        self.address..=self.address + self.footprint.slots() - 1
+    }
+
+    fn overlaps(&self, other: &DmxPatch) -> bool {
+        self.universe == other.universe && self.slots().start() <= other.slots().end() && other.slots().start() <= self.slots().end()
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct DmxConfig {
+    #[serde(default)]
+    pub universes: Vec<DmxUniverse>,
+    #[serde(default)]
+    pub patch: Vec<DmxPatch>,
+    /// sACN universes carry this sync address and a sync packet follows
+    /// every frame, so receivers output all universes at once.
+    #[serde(default)]
+    pub sync_universe: Option<u16>,
+}
+
+impl DmxConfig {
+    pub fn patch_for(&self, fixture: &str, channel: u32) -> Option<&DmxPatch> {
+        self.patch.iter().find(|p| p.fixture == fixture && p.channel == channel)
+    }
+
+    pub fn is_patched(&self, fixture: &str, channel: u32) -> bool {
+        self.patch_for(fixture, channel).is_some()
+    }
+}
+
+struct Cue {
+    patch: DmxPatch,
+    effect: Effect,
+    started: Instant,
+}
+
+impl Cue {
+    fn expired(&self) -> bool {
+        self.effect.duration > 0.0 && self.started.elapsed().as_secs_f64() >= self.effect.duration
+    }
+
+    fn value(&self) -> RampValue {
+        let effect = &self.effect;
+        if let Some(ramp) = &effect.ramp {
+            let duration = ramp.duration(effect);
+            let progress = if duration > 0.0 { self.started.elapsed().as_secs_f64() / duration } else { 1.0 };
+            return ramp.value_at(progress.min(1.0));
+        }
+        let color = effect.params.get(COLOR_PARAM).and_then(|v| serde_json::from_value::<Rgb>(v.clone()).ok());
+        match color {
+            Some(rgb) => RampValue::Color(rgb),
+            None => RampValue::Level(effect.params.get("level").and_then(|v| v.as_f64()).unwrap_or(100.0)),
+        }
+    }
+}
+
+// Slot values for a level in percent or a color. A dimmer given a color
+// follows its brightest component.
+fn slot_values(footprint: Footprint, value: &RampValue) -> Vec<u8> {
+    let level = |percent: f64| (percent.clamp(0.0, 100.0) * 2.55).round() as u8;
+    match (footprint, value) {
+        (Footprint::Dimmer, RampValue::Level(percent)) => vec![level(*percent)],
+        (Footprint::Dimmer, RampValue::Color(rgb)) => vec![rgb.iter().copied().max().unwrap_or(0)],
+        (Footprint::Rgb, RampValue::Level(percent)) => vec![level(*percent); 3],
+        (Footprint::Rgb, RampValue::Color(rgb)) => rgb.to_vec(),
+    }
+}
+
+/// One universe's slots, ready to send.
+type Frame = (DmxUniverse, Vec<u8>);
+
+struct Frames {
+    frames: Vec<Frame>,
+    sync_universe: Option<u16>,
+    /// Streaming stops after this frame.
+    last: bool,
+}
+
+#[derive(Default)]
+struct State {
+    config: DmxConfig,
+    path: Option<PathBuf>,
+    cues: Vec<Cue>,
+    streaming: bool,
+}
+
+/// Plays patched cues as DMX over Art-Net or sACN. While any cue is playing every
+/// configured universe is streamed at `STREAM_HZ`; once the last one ends a
+/// final dark frame is sent and streaming stops. Later cues on a slot win.
+/// Cheap to clone.
+#[derive(Clone, Default)]
+pub struct DmxOutput {
+    state: Arc<Mutex<State>>,
+}
+
+impl DmxOutput {
+    fn lock(&self) -> Result<MutexGuard<'_, State>, String> {
+        self.state.lock().map_err(|_| "DMX output is unavailable".to_string())
+    }
+
+    /// Loads the saved universes and patch and remembers `path` for later
+    /// saves. A missing file means nothing is patched yet.
+    pub fn load(&self, path: PathBuf) -> Result<(), String> {
+        let config: DmxConfig = match fs::read(&path) {
+            Ok(data) => serde_json::from_slice(&data).map_err(|e| format!("Invalid DMX config file: {}", e))?,
+            Err(e) if e.kind() == ErrorKind::NotFound => DmxConfig::default(),
+            Err(e) => return Err(format!("Failed to read {}: {}", path.display(), e)),
+        };
+        let mut state = self.lock()?;
+        state.config = config;
+        state.path = Some(path);
+        Ok(())
+    }
+
+    fn save(state: &State) -> Result<(), String> {
+        registry::save_json(state.path.clone(), &state.config).map_err(|e| format!("Failed to save DMX config: {}", e))
+    }
+
+    pub fn config(&self) -> Result<DmxConfig, String> {
+        Ok(self.lock()?.config.clone())
+    }
+
+    /// Sends `universe` over Art-Net, replacing any earlier setup of it.
+    pub fn set_artnet_universe(&self, universe: u16, target: &str) -> Result<DmxUniverse, String> {
+        if universe > artnet::MAX_UNIVERSE {
+            return Err(format!("Art-Net universes go up to {}, got {}", artnet::MAX_UNIVERSE, universe));
+        }
+        let target = parse_target(target)?;
+        log::info!("Art-Net universe {} sent to {}", universe, target);
+        self.put_universe(DmxUniverse {
+            universe,
+            protocol: DmxProtocol::ArtNet,
+            target: Some(target),
+            priority: sacn::DEFAULT_PRIORITY,
+        })
+    }
+
+    /// Sends `universe` over sACN, by unicast to `target` or by multicast
+    /// without one, replacing any earlier setup of it.
+    pub fn set_sacn_universe(&self, universe: u16, target: Option<&str>, priority: Option<u8>) -> Result<DmxUniverse, String> {
+        if !(sacn::MIN_UNIVERSE..=sacn::MAX_UNIVERSE).contains(&universe) {
+            return Err(format!(
+                "sACN universe must be between {} and {}, got {}",
+                sacn::MIN_UNIVERSE,
+                sacn::MAX_UNIVERSE,
+                universe
+            ));
+        }
+        let priority = priority.unwrap_or(sacn::DEFAULT_PRIORITY);
+        if priority > sacn::MAX_PRIORITY {
+            return Err(format!("sACN priority must be at most {}, got {}", sacn::MAX_PRIORITY, priority));
+        }
+        let target = target.filter(|t| !t.trim().is_empty()).map(parse_target).transpose()?;
+        let destination = target.unwrap_or_else(|| sacn::multicast_address(universe));
+        log::info!("sACN universe {} sent to {} at priority {}", universe, destination, priority);
+        self.put_universe(DmxUniverse {
+            universe,
+            protocol: DmxProtocol::Sacn,
+            target,
+            priority,
+        })
+    }
+
+    fn put_universe(&self, entry: DmxUniverse) -> Result<DmxUniverse, String> {
+        let mut state = self.lock()?;
+        state.config.universes.retain(|u| u.universe != entry.universe);
+        state.config.universes.push(entry.clone());
+        state.config.universes.sort_by_key(|u| u.universe);
+        Self::save(&state)?;
+        Ok(entry)
+    }
+
+    /// Sets the sACN sync address; None sends every universe unsynchronized.
+    pub fn set_sync_universe(&self, sync_universe: Option<u16>) -> Result<(), String> {
+        if let Some(universe) = sync_universe {
+            if !(sacn::MIN_UNIVERSE..=sacn::MAX_UNIVERSE).contains(&universe) {
+                return Err(format!(
+                    "sACN sync universe must be between {} and {}, got {}",
+                    sacn::MIN_UNIVERSE,
+                    sacn::MAX_UNIVERSE,
+                    universe
+                ));
+            }
+        }
+        let mut state = self.lock()?;
+        state.config.sync_universe = sync_universe;
+        Self::save(&state)
+    }
+
+    /// Removes a universe that nothing is patched to any more.
+    pub fn remove_universe(&self, universe: u16) -> Result<bool, String> {
+        let mut state = self.lock()?;
+        let patched = state.config.patch.iter().filter(|p| p.universe == universe).count();
+        if patched > 0 {
+            return Err(format!("Universe {} still has {} patched channels", universe, patched));
+        }
+        let before = state.config.universes.len();
+        state.config.universes.retain(|u| u.universe != universe);
+        let removed = state.config.universes.len() != before;
+        if removed {
+            Self::save(&state)?;
+        }
+        Ok(removed)
+    }
+
+    /// Patches a channel to slots of a configured universe, replacing any
+    /// earlier patch of that channel. Slots may not be shared.
+    pub fn patch(&self, patch: DmxPatch) -> Result<DmxPatch, String> {
+        let patch = DmxPatch {
+            fixture: patch.fixture.trim().to_string(),
+            ..patch
+        };
+        if patch.fixture.is_empty() {
+            return Err("Fixture name must not be empty".to_string());
+        }
+        let last = patch.address as usize + patch.footprint.slots() as usize - 1;
+        if patch.address == 0 || last > UNIVERSE_SIZE {
+            return Err(format!(
+                "A {:?} patch at {} does not fit slots 1-{}",
+                patch.footprint, patch.address, UNIVERSE_SIZE
+            ));
+        }
+        let mut state = self.lock()?;
+        if !state.config.universes.iter().any(|u| u.universe == patch.universe) {
+            return Err(format!("Universe {} is not configured", patch.universe));
+        }
+        let same = |p: &DmxPatch| p.fixture == patch.fixture && p.channel == patch.channel;
+        if let Some(other) = state.config.patch.iter().find(|p| !same(p) && p.overlaps(&patch)) {
+            return Err(format!(
+                "Slots {}-{} of universe {} are already patched to {} channel {}",
+                patch.address, last, patch.universe, other.fixture, other.channel
+            ));
+        }
+        state.config.patch.retain(|p| !same(p));
+        state.config.patch.push(patch.clone());
+        state.config.patch.sort_by_key(|p| (p.universe, p.address));
+        Self::save(&state)?;
+        Ok(patch)
+    }
+
+    pub fn unpatch(&self, fixture: &str, channel: u32) -> Result<bool, String> {
+        let mut state = self.lock()?;
+        let before = state.config.patch.len();
+        state.config.patch.retain(|p| !(p.fixture == fixture && p.channel == channel));
+        let removed = state.config.patch.len() != before;
+        if removed {
+            Self::save(&state)?;
+        }
+        Ok(removed)
+    }
+
+    pub fn is_patched(&self, fixture: &str, channel: u32) -> bool {
+        self.lock().is_ok_and(|state| state.config.is_patched(fixture, channel))
+    }
+
+    /// Starts playing `effect` on its patched slots. Effects without a
+    /// duration hold their value until `release_all`.
+    pub fn play(&self, effect: &Effect) -> Result<(), String> {
+        safe_mode::check()?;
+        let mut state = self.lock()?;
+        let patch = state
+            .config
+            .patch_for(&effect.controller, effect.channel)
+            .cloned()
+            .ok_or_else(|| format!("{} channel {} is not patched to DMX", effect.controller, effect.channel))?;
+        state.cues.retain(|c| c.patch != patch);
+        state.cues.push(Cue {
+            patch,
+            effect: effect.clone(),
+            started: Instant::now(),
+        });
+        if !state.streaming {
+            state.streaming = true;
+            tauri::async_runtime::spawn(stream(self.clone()));
+        }
+        Ok(())
+    }
+
+    /// Ends every playing cue; the stream sends a dark frame and stops.
+    pub fn release_all(&self) {
+        if let Ok(mut state) = self.lock() {
+            state.cues.clear();
+        }
+    }
+
+    // The next frame of every universe, and whether it is the last one
+    // before streaming stops. None means stop without sending.
+    fn next_frames(&self) -> Option<Frames> {
+        let mut state = self.lock().ok()?;
+        if safe_mode::is_active() {
+            state.cues.clear();
+            state.streaming = false;
+            return None;
+        }
+        state.cues.retain(|c| !c.expired());
+        let mut frames: Vec<Frame> =
+            state.config.universes.iter().map(|u| (u.clone(), vec![0; UNIVERSE_SIZE])).collect();
+        // Oldest first, so later cues overwrite shared slots.
+        for cue in &state.cues {
+            let Some((_, data)) = frames.iter_mut().find(|(u, _)| u.universe == cue.patch.universe) else {
+                continue;
+            };
+            let start = cue.patch.address as usize - 1;
+            for (slot, value) in slot_values(cue.patch.footprint, &cue.value()).into_iter().enumerate() {
+                if let Some(target) = data.get_mut(start + slot) {
+                    *target = value;
+                }
+            }
+        }
+        let last = state.cues.is_empty();
+        if last {
+            state.streaming = false;
+        }
+        Some(Frames {
+            frames,
+            sync_universe: state.config.sync_universe,
+            last,
+        })
+    }
+}
+
+fn parse_target(target: &str) -> Result<IpAddr, String> {
+    target
+        .trim()
+        .parse::<Ipv4Addr>()
+        .map(IpAddr::V4)
+        .map_err(|_| format!("DMX target must be an IPv4 address, got '{}'", target.trim()))
+}
+
+async fn send_frame(socket: &UdpSocket, universe: &DmxUniverse, sync_universe: Option<u16>, sequence: u8, data: &[u8]) -> Result<(), String> {
+    match (universe.protocol, universe.target) {
+        (DmxProtocol::ArtNet, Some(target)) => artnet::send(socket, target, universe.universe, sequence, data).await,
+        (DmxProtocol::ArtNet, None) => Err(format!("Art-Net universe {} has no target", universe.universe)),
+        (DmxProtocol::Sacn, target) => {
+            sacn::send(socket, target, universe.universe, universe.priority, sync_universe, sequence, data).await
+        }
+    }
+}
+
+async fn stream(output: DmxOutput) {
+    let socket = match open_socket().await {
+        Ok(socket) => socket,
+        Err(e) => {
+            log::error!("DMX output not started: {}", e);
+            if let Ok(mut state) = output.lock() {
+                state.cues.clear();
+                state.streaming = false;
+            }
+            return;
+        }
+    };
+    let mut interval = tokio::time::interval(Duration::from_secs(1) / STREAM_HZ);
+    interval.set_missed_tick_behavior(MissedTickBehavior::Skip);
+    // 0 would switch off reordering at the node, so the sequence runs 1-255.
+    let mut sequence: u8 = 0;
+    // Universes whose last send failed, so a dead node is logged once.
+    let mut failing = BTreeSet::new();
+    let mut sync_failing = false;
+    loop {
+        interval.tick().await;
+        let Some(Frames {
+            frames,
+            sync_universe,
+            last,
+        }) = output.next_frames()
+        else {
+            return;
+        };
+        sequence = sequence.checked_add(1).unwrap_or(1);
+        let sync_universe = sync_universe.filter(|_| frames.iter().any(|(u, _)| u.protocol == DmxProtocol::Sacn));
+        for (universe, data) in &frames {
+            match send_frame(&socket, universe, sync_universe, sequence, data).await {
+                Ok(()) => {
+                    failing.remove(&universe.universe);
+                }
+                Err(e) => {
+                    if failing.insert(universe.universe) {
+                        log::warn!("{}", e);
+                    }
+                }
+            }
+        }
+        if let Some(sync) = sync_universe {
+            match sacn::send_sync(&socket, sync, sequence).await {
+                Ok(()) => sync_failing = false,
+                Err(e) if !sync_failing => {
+                    sync_failing = true;
+                    log::warn!("{}", e);
+                }
+                Err(_) => {}
+            }
+        }
+        if last {
+            return;
+        }
+    }
+}
+
+async fn open_socket() -> Result<UdpSocket, String> {
+    let socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0))
+        .await
+        .map_err(|e| format!("Failed to open DMX socket: {}", e))?;
+    socket
+        .set_broadcast(true)
+        .map_err(|e| format!("Failed to allow Art-Net broadcast: {}", e))?;
+    Ok(socket)
+}
@@ -0,0 +1,330 @@
+use serde_json::Value;
+use std::future::Future;
+use std::pin::Pin;
+use std::time::{Duration, Instant};
+
+use crate::controller_client;
+use crate::dispatcher::OutputAction;
+use crate::haze;
+use crate::registry::ControllerInfo;
+use crate::safe_mode;
+use crate::serial;
+use crate::transport;
+
+/// Both LUME firmwares drive 12 outputs per area.
+const CHANNELS_PER_AREA: u32 = 12;
+
+/// What a controller reports about itself.
+#[derive(Debug, Default)]
+pub struct DriverCaps {
+    pub firmware_version: Option<String>,
+    pub system_name: Option<String>,
+    pub channel_count: Option<u32>,
+    pub max_areas: Option<u32>,
+}
+
+/// Translates desk commands into one controller protocol. The dispatcher
+/// still does the sending, so muting, logging, safe mode and the laser zone
+/// check apply whatever the driver. Methods return request paths, or
+/// console commands for serial controllers. Connections and arming are
+/// the business of the controller's `ControllerLink`.
+pub trait ControllerDriver: Send + Sync {
+    fn name(&self) -> &'static str;
+
+    fn fire(&self, channel: u32) -> String;
+
+    /// `level` is 0-100. None if the hardware has no dimmable outputs.
+    fn set_level(&self, channel: u32, level: u8) -> Option<String>;
+
+    /// Puts every output of the controller into its safe state.
+    fn blackout(&self) -> String;
+
+    /// Makes the controller show itself, e.g. by blinking; None if it cannot.
+    fn identify(&self) -> Option<String> {
+        None
+    }
+
+    /// GET paths whose replies `parse_caps` reads, in the same order.
+    fn query_caps(&self) -> &'static [&'static str];
+
+    fn parse_caps(&self, controller: &ControllerInfo, replies: &[Value]) -> DriverCaps;
+
+    /// Whether fires may carry the session/seq of the dispatcher's
+    /// sequenced fire protocol.
+    fn sequenced_fire(&self) -> bool {
+        false
+    }
+
+    /// The request for any dispatcher action. By default only fires, levels,
+    /// relay switching and blackout are understood.
+    fn request(&self, action: &OutputAction, channel: u32) -> Result<String, String> {
+        let level = match action {
+            OutputAction::Fire => return Ok(self.fire(channel)),
+            OutputAction::EmergencyStop | OutputAction::AllRelays(false) => return Ok(self.blackout()),
+            OutputAction::Dimmer(level) | OutputAction::Haze(level) => self.set_level(channel, *level),
+            OutputAction::Relay(on) => self.set_level(channel, if *on { 100 } else { 0 }),
+            _ => None,
+        };
+        level.ok_or_else(|| format!("The {} driver cannot send {:?}", self.name(), action))
+    }
+}
+
+/// The LUME firework, lighting and haze firmwares.
+pub struct LumeDriver;
+
+impl ControllerDriver for LumeDriver {
+    fn name(&self) -> &'static str {
+        "lume"
+    }
+
+    fn fire(&self, channel: u32) -> String {
+        OutputAction::Fire.request_path(channel)
+    }
+
+    fn set_level(&self, channel: u32, level: u8) -> Option<String> {
+        Some(OutputAction::Dimmer(level).request_path(channel))
+    }
+
+    fn blackout(&self) -> String {
+        OutputAction::EmergencyStop.request_path(0)
+    }
+
+    fn query_caps(&self) -> &'static [&'static str] {
+        &["/version", "/status"]
+    }
+
+    // The lighting firmware reports its relays; the firework firmware has a fixed bank.
+    fn parse_caps(&self, controller: &ControllerInfo, replies: &[Value]) -> DriverCaps {
+        let (version, status) = (&replies[0], &replies[1]);
+        let channel_count = if controller.is_haze() {
+            haze::HAZE_CHANNEL
+        } else {
+            status
+                .get("relayStates")
+                .and_then(Value::as_array)
+                .map(|relays| relays.len() as u32)
+                .unwrap_or(CHANNELS_PER_AREA)
+        };
+        DriverCaps {
+            firmware_version: version.get("version").and_then(Value::as_str).map(str::to_string),
+            system_name: version.get("systemName").and_then(Value::as_str).map(str::to_string),
+            channel_count: Some(channel_count),
+            max_areas: status.get("maxAreas").and_then(Value::as_u64).map(|n| n as u32),
+        }
+    }
+
+    fn sequenced_fire(&self) -> bool {
+        true
+    }
+
+    fn request(&self, action: &OutputAction, channel: u32) -> Result<String, String> {
+        Ok(action.request_path(channel))
+    }
+}
+
+/// Third-party devices with a minimal REST interface:
+/// `POST /fire?channel=`, `POST /level?channel=&value=` (0-100),
+/// `POST /blackout`, `POST /identify` and `GET /info`, which answers
+/// `{"firmware": .., "name": .., "channels": ..}`.
+pub struct GenericHttpDriver;
+
+impl ControllerDriver for GenericHttpDriver {
+    fn name(&self) -> &'static str {
+        "generic-http"
+    }
+
+    fn fire(&self, channel: u32) -> String {
+        format!("/fire?channel={}", channel)
+    }
+
+    fn set_level(&self, channel: u32, level: u8) -> Option<String> {
+        Some(format!("/level?channel={}&value={}", channel, level.min(100)))
+    }
+
+    fn blackout(&self) -> String {
+        "/blackout".to_string()
+    }
+
+    fn identify(&self) -> Option<String> {
+        Some("/identify".to_string())
+    }
+
+    fn query_caps(&self) -> &'static [&'static str] {
+        &["/info"]
+    }
+
+    fn parse_caps(&self, _controller: &ControllerInfo, replies: &[Value]) -> DriverCaps {
+        let info = &replies[0];
+        DriverCaps {
+            firmware_version: info.get("firmware").and_then(Value::as_str).map(str::to_string),
+            system_name: info.get("name").and_then(Value::as_str).map(str::to_string),
+            channel_count: info.get("channels").and_then(Value::as_u64).map(|n| n as u32),
+            max_areas: None,
+        }
+    }
+}
+
+/// The firing firmware's USB serial console: `CHANNEL <n>` fires one
+/// output of the current area, `STOP` makes every output safe and `STATUS`
+/// answers in plain-text lines. There are no dimmable outputs.
+pub struct SerialFiringDriver;
+
+impl ControllerDriver for SerialFiringDriver {
+    fn name(&self) -> &'static str {
+        "serial"
+    }
+
+    fn fire(&self, channel: u32) -> String {
+        format!("CHANNEL {}", channel)
+    }
+
+    fn set_level(&self, _channel: u32, _level: u8) -> Option<String> {
+        None
+    }
+
+    fn blackout(&self) -> String {
+        "STOP".to_string()
+    }
+
+    fn query_caps(&self) -> &'static [&'static str] {
+        &[serial::STATUS_COMMAND]
+    }
+
+    // STATUS carries no version, only the area the module is on.
+    fn parse_caps(&self, _controller: &ControllerInfo, _replies: &[Value]) -> DriverCaps {
+        DriverCaps {
+            channel_count: Some(CHANNELS_PER_AREA),
+            ..DriverCaps::default()
+        }
+    }
+}
+
+/// Controller types with their own driver; every other type is LUME
+/// hardware. Supporting new hardware means adding a driver here.
+const DRIVERS: [(&str, &dyn ControllerDriver); 1] = [("generic-http", &GenericHttpDriver)];
+
+pub fn for_type(controller_type: &str) -> &'static dyn ControllerDriver {
+    DRIVERS
+        .iter()
+        .find(|(t, _)| *t == controller_type)
+        .map_or(&LumeDriver, |(_, driver)| *driver)
+}
+
+/// The driver for the controller at `address`: serial controllers speak
+/// the console protocol whatever their type.
+pub fn for_controller(address: &str, controller_type: &str) -> &'static dyn ControllerDriver {
+    if transport::serial_port(address).is_some() {
+        return &SerialFiringDriver;
+    }
+    for_type(controller_type)
+}
+
+/// What a `ControllerLink` operation resolves to.
+pub type LinkFuture<'a, T> = Pin<Box<dyn Future<Output = Result<T, String>> + Send + 'a>>;
+
+/// The connection to one controller, for what surrounds its commands:
+/// opening it, arming it and checking it is alive. The firmwares keep no
+/// arm state of their own, so arming checks the controller answers and
+/// disarming sends its driver's blackout. The desk's `Safety` still gates
+/// every pyro fire.
+pub trait ControllerLink: Send + Sync {
+    /// Opens the connection if the transport keeps one.
+    fn connect(&self) -> LinkFuture<'_, ()>;
+
+    fn arm(&self, armed: bool) -> LinkFuture<'_, ()>;
+
+    /// Round trip of a status request.
+    fn heartbeat(&self) -> LinkFuture<'_, Duration>;
+}
+
+/// HTTP and Bluetooth controllers, which connect per request.
+pub struct NetworkLink {
+    address: String,
+    driver: &'static dyn ControllerDriver,
+}
+
+impl NetworkLink {
+    pub fn new(address: &str, controller_type: &str) -> Self {
+        Self {
+            address: address.to_string(),
+            driver: for_type(controller_type),
+        }
+    }
+}
+
+impl ControllerLink for NetworkLink {
+    fn connect(&self) -> LinkFuture<'_, ()> {
+        Box::pin(async { Ok(()) })
+    }
+
+    fn arm(&self, armed: bool) -> LinkFuture<'_, ()> {
+        Box::pin(async move {
+            if armed {
+                return self.heartbeat().await.map(drop);
+            }
+            controller_client::post(&self.address, &self.driver.blackout(), transport::timeout_for(&self.address))
+                .await
+                .map_err(|e| e.to_string())
+        })
+    }
+
+    fn heartbeat(&self) -> LinkFuture<'_, Duration> {
+        Box::pin(controller_client::probe(&self.address, transport::timeout_for(&self.address)))
+    }
+}
+
+/// A firing module on a USB serial port. The port stays open between
+/// requests, as opening it can reset the board.
+pub struct SerialLink {
+    address: String,
+}
+
+impl SerialLink {
+    pub fn new(address: &str) -> Self {
+        Self {
+            address: address.to_string(),
+        }
+    }
+}
+
+impl ControllerLink for SerialLink {
+    fn connect(&self) -> LinkFuture<'_, ()> {
+        Box::pin(async move {
+            safe_mode::check()?;
+            serial::open(&self.address).await
+        })
+    }
+
+    fn arm(&self, armed: bool) -> LinkFuture<'_, ()> {
+        Box::pin(async move {
+            if armed {
+                return self.heartbeat().await.map(drop);
+            }
+            controller_client::post(&self.address, &SerialFiringDriver.blackout(), transport::timeout_for(&self.address))
+                .await
+                .map_err(|e| e.to_string())
+        })
+    }
+
+    // Only the firing firmware answers STATUS with its area, so anything
+    // else on the port fails the heartbeat.
+    fn heartbeat(&self) -> LinkFuture<'_, Duration> {
+        Box::pin(async move {
+            self.connect().await?;
+            let started = Instant::now();
+            let reply = controller_client::get_json(&self.address, serial::STATUS_COMMAND, transport::timeout_for(&self.address)).await?;
+            if !serial::is_firing_module(&reply) {
+                return Err(format!("{} is not a firing module", self.address));
+            }
+            Ok(started.elapsed())
+        })
+    }
+}
+
+/// The link to the controller at `address`.
+pub fn link_for(address: &str, controller_type: &str) -> Box<dyn ControllerLink> {
+    if transport::serial_port(address).is_some() {
+        return Box::new(SerialLink::new(address));
+    }
+    Box::new(NetworkLink::new(address, controller_type))
+}
@@ -0,0 +1,327 @@
+use crate::models::{Effect, Show};
+use crate::ramp::{Ramp, RampValue};
+use crate::registry::{self, ControllerInfo};
+use crate::relay;
+use crate::validation;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Shifts smaller than this are treated as already on the grid.
+const ON_GRID_EPSILON: f64 = 1e-6;
+
+#[derive(Debug, Default, Serialize)]
+pub struct QuantizeReport {
+    pub grid_seconds: f64,
+    pub moved: usize,
+    pub unchanged: usize,
+    pub max_shift: f64,
+    pub mean_shift: f64,
+}
+
+/// Snaps effect start times toward the nearest `1 / division` of a beat.
+/// `strength` of 1.0 snaps fully, 0.5 moves halfway, 0.0 leaves times alone.
+pub fn quantize(show: &mut Show, bpm: f64, division: u32, strength: f64) -> Result<QuantizeReport, String> {
+    if !bpm.is_finite() || bpm <= 0.0 {
+        return Err(format!("BPM must be positive, got {}", bpm));
+    }
+    if division == 0 {
+        return Err("Division must be at least 1".to_string());
+    }
+    if !(0.0..=1.0).contains(&strength) {
+        return Err(format!("Strength must be between 0 and 1, got {}", strength));
+    }
+
+    let grid = 60.0 / bpm / division as f64;
+    let mut report = QuantizeReport {
+        grid_seconds: grid,
+        ..QuantizeReport::default()
+    };
+    let mut total_shift = 0.0;
+    for effect in &mut show.effects {
+        let target = (effect.start_time / grid).round() * grid;
+        let shift = (target - effect.start_time) * strength;
+        if shift.abs() < ON_GRID_EPSILON {
+            report.unchanged += 1;
+            continue;
+        }
+        effect.start_time = if strength >= 1.0 { target } else { effect.start_time + shift };
+        report.moved += 1;
+        total_shift += shift.abs();
+        report.max_shift = report.max_shift.max(shift.abs());
+    }
+    if report.moved > 0 {
+        report.mean_shift = total_shift / report.moved as f64;
+    }
+    Ok(report)
+}
+
+/// What each cue of a generated ripple fires.
+#[derive(Debug, Clone, Deserialize)]
+pub struct EffectTemplate {
+    #[serde(default)]
+    pub effect_type: String,
+    #[serde(default)]
+    pub duration: f64,
+    #[serde(default)]
+    pub params: HashMap<String, serde_json::Value>,
+    #[serde(default)]
+    pub ramp: Option<Ramp>,
+    #[serde(default)]
+    pub layer: Option<String>,
+}
+
+/// Adds one effect per (controller, channel) starting at `start_time`, each
+/// `interval_ms` after the previous. Returns the new effect ids in firing order.
+pub fn ripple(
+    show: &mut Show,
+    channels: &[(ControllerInfo, u32)],
+    start_time: f64,
+    interval_ms: u64,
+    template: &EffectTemplate,
+) -> Result<Vec<String>, String> {
+    if channels.is_empty() {
+        return Err("A ripple needs at least one channel".to_string());
+    }
+    if !start_time.is_finite() || start_time < 0.0 {
+        return Err(format!("Start time must be zero or positive, got {}", start_time));
+    }
+    let interval = Duration::from_millis(interval_ms);
+    let last_start = start_time + interval.as_secs_f64() * (channels.len() - 1) as f64;
+    let end = last_start + template.duration.max(0.0);
+    if show.total_duration > 0.0 && end > show.total_duration {
+        return Err(format!(
+            "Ripple ends at {:.3}s, after the show ends at {:.3}s",
+            end, show.total_duration
+        ));
+    }
+
+    // Consecutive cues on the same controller are `interval` times their distance in the row apart.
+    let mut last_index: HashMap<&str, usize> = HashMap::new();
+    for (index, (controller, _)) in channels.iter().enumerate() {
+        if let Some(previous) = last_index.insert(controller.address.as_str(), index) {
+            let gap = u32::try_from(index - previous)
+                .ok()
+                .and_then(|steps| interval.checked_mul(steps))
+                .ok_or_else(|| format!("Ripple interval of {} ms is too long", interval_ms))?;
+            if gap < controller.min_command_spacing() {
+                return Err(format!(
+                    "Cues on {} would be {} ms apart, the controller needs at least {} ms",
+                    controller.address,
+                    gap.as_millis(),
+                    controller.min_command_spacing().as_millis()
+                ));
+            }
+        }
+    }
+
+    let mut ids = Vec::with_capacity(channels.len());
+    for (index, (controller, channel)) in channels.iter().enumerate() {
+        let id = uuid::Uuid::new_v4().to_string();
+        show.effects.push(Effect {
+            id: id.clone(),
+            start_time: start_time + interval.as_secs_f64() * index as f64,
+            duration: template.duration,
+            controller: controller.address.clone(),
+            channel: *channel,
+            effect_type: template.effect_type.clone(),
+            params: template.params.clone(),
+            ramp: template.ramp.clone(),
+            enabled: true,
+            layer: template.layer.clone(),
+        });
+        ids.push(id);
+    }
+    Ok(ids)
+}
+
+/// How much `randomize` may vary each selected effect.
+#[derive(Debug, Clone, Deserialize)]
+pub struct RandomizeParams {
+    /// Start times move by up to this many milliseconds either way.
+    #[serde(default)]
+    pub timing_jitter_ms: u64,
+    /// Levels (the `level` param and level ramps) move by up to this many
+    /// percent points either way, within 0-100.
+    #[serde(default)]
+    pub intensity_jitter: f64,
+    /// Reusing a seed on the same effects reproduces a variation; a new one
+    /// is picked when None.
+    #[serde(default)]
+    pub seed: Option<u64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RandomizeReport {
+    pub seed: u64,
+    pub changed: usize,
+    pub max_shift_ms: f64,
+}
+
+// SplitMix64: small, and the same sequence on every platform.
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// Uniform in -spread..spread.
+    fn spread(&mut self, spread: f64) -> f64 {
+        let unit = (self.next() >> 11) as f64 / (1u64 << 53) as f64;
+        (unit * 2.0 - 1.0) * spread
+    }
+}
+
+/// Applies seeded random variation to the effects in `effect_ids`. Each
+/// effect draws from its own stream of the seed, so the result does not
+/// depend on the order or size of the selection. Fails if an effect would
+/// leave the show's bounds or a new cue spacing or relay conflict would
+/// appear.
+pub fn randomize(
+    show: &mut Show,
+    controllers: &[ControllerInfo],
+    effect_ids: &[String],
+    params: &RandomizeParams,
+) -> Result<RandomizeReport, String> {
+    if effect_ids.is_empty() {
+        return Err("Select at least one effect to randomize".to_string());
+    }
+    if !params.intensity_jitter.is_finite() || !(0.0..=100.0).contains(&params.intensity_jitter) {
+        return Err(format!("Intensity jitter must be between 0 and 100, got {}", params.intensity_jitter));
+    }
+    let selected: HashSet<&str> = effect_ids.iter().map(String::as_str).collect();
+    if let Some(missing) = selected.iter().find(|id| !show.effects.iter().any(|e| e.id == **id)) {
+        return Err(format!("Effect {} not found", missing));
+    }
+    let seed = params.seed.unwrap_or_else(|| {
+        SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_nanos() as u64).unwrap_or_default()
+    });
+
+    let before = conflicts(show, controllers);
+    let jitter = params.timing_jitter_ms as f64 / 1000.0;
+    let mut report = RandomizeReport {
+        seed,
+        changed: 0,
+        max_shift_ms: 0.0,
+    };
+    for effect in show.effects.iter_mut().filter(|e| selected.contains(e.id.as_str())) {
+        let mut rng = Rng(seed ^ (crc32fast::hash(effect.id.as_bytes()) as u64).wrapping_mul(0x9E37_79B9_7F4A_7C15));
+        let shift = rng.spread(jitter);
+        let varied_level = vary_levels(effect, &mut rng, params.intensity_jitter);
+        if shift == 0.0 && !varied_level {
+            continue;
+        }
+        effect.start_time += shift;
+        report.changed += 1;
+        report.max_shift_ms = report.max_shift_ms.max(shift.abs() * 1000.0);
+    }
+
+    for effect in show.effects.iter().filter(|e| selected.contains(e.id.as_str())) {
+        let end = effect.start_time + effect.duration.max(0.0);
+        if effect.start_time < 0.0 || (show.total_duration > 0.0 && end > show.total_duration) {
+            return Err(format!(
+                "Seed {} moves effect {} to {:.3}-{:.3}s, outside the show (0-{:.3}s)",
+                seed, effect.id, effect.start_time, end, show.total_duration
+            ));
+        }
+    }
+    if let Some((first, second)) = conflicts(show, controllers).difference(&before).next() {
+        return Err(format!("Seed {} would make effects {} and {} conflict", seed, first, second));
+    }
+    Ok(report)
+}
+
+// Returns whether anything changed.
+fn vary_levels(effect: &mut Effect, rng: &mut Rng, jitter: f64) -> bool {
+    if jitter <= 0.0 {
+        return false;
+    }
+    let mut vary = |level: f64| (level + rng.spread(jitter)).clamp(0.0, 100.0);
+    let mut changed = false;
+    if let Some(level) = effect.params.get("level").and_then(|v| v.as_f64()) {
+        effect.params.insert("level".to_string(), serde_json::json!(vary(level).round()));
+        changed = true;
+    }
+    if let Some(ramp) = &mut effect.ramp {
+        for value in [&mut ramp.from, &mut ramp.to] {
+            if let RampValue::Level(level) = value {
+                *level = vary(*level);
+                changed = true;
+            }
+        }
+    }
+    changed
+}
+
+// Cue spacing and relay conflicts between playing effects, as id pairs.
+fn conflicts(show: &Show, controllers: &[ControllerInfo]) -> BTreeSet<(String, String)> {
+    let known = registry::lookup_map(controllers);
+    let mut playing = show.clone();
+    playing.effects.retain(|e| show.plays(e));
+    let mut pairs: BTreeSet<(String, String)> = validation::check_cue_spacing(&playing, &known)
+        .into_iter()
+        .map(|v| (v.first_effect, v.second_effect))
+        .collect();
+    let is_relay = |reference: &str| known.get(reference).is_some_and(|c| c.is_relay());
+    pairs.extend(
+        relay::conflicts(&playing, is_relay)
+            .into_iter()
+            .map(|c| (c.first.id.clone(), c.second.id.clone())),
+    );
+    pairs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn controller(address: &str) -> ControllerInfo {
+        serde_json::from_value(json!({ "address": address, "controller_type": "firework", "min_command_spacing_ms": 100 }))
+            .unwrap()
+    }
+
+    fn show(total_duration: f64) -> Show {
+        serde_json::from_value(json!({ "id": "s1", "name": "Test", "total_duration": total_duration, "effects": [] })).unwrap()
+    }
+
+    fn template() -> EffectTemplate {
+        serde_json::from_value(json!({ "duration": 1.0 })).unwrap()
+    }
+
+    #[test]
+    fn ripple_spaces_cues_on_the_same_controller() {
+        let (a, b) = (controller("10.0.0.1"), controller("10.0.0.2"));
+        // a, b, a: the two cues on a are two intervals apart.
+        let channels = [(a.clone(), 1), (b, 1), (a, 2)];
+        let mut show = show(0.0);
+        assert!(ripple(&mut show, &channels, 1.0, 40, &template()).unwrap_err().contains("80 ms apart"));
+        assert!(show.effects.is_empty());
+
+        let ids = ripple(&mut show, &channels, 1.0, 50, &template()).unwrap();
+        let starts: Vec<f64> = ids.iter().map(|id| show.effect(id).unwrap().start_time).collect();
+        assert_eq!(starts, [1.0, 1.05, 1.1]);
+    }
+
+    #[test]
+    fn ripple_stays_inside_the_show() {
+        let channels: Vec<_> = (1..=3).map(|channel| (controller("10.0.0.1"), channel)).collect();
+        // The last cue starts at 9 s and runs a second past the 9.5 s show.
+        assert!(ripple(&mut show(9.5), &channels, 5.0, 2000, &template()).unwrap_err().contains("after the show ends"));
+        assert!(ripple(&mut show(10.0), &channels, 5.0, 2000, &template()).is_ok());
+    }
+
+    #[test]
+    fn huge_ripple_interval_is_refused() {
+        // Over a thousand intervals between the two cues on 10.0.0.1 overflow a Duration.
+        let mut channels = vec![(controller("10.0.0.1"), 1)];
+        channels.extend((1..=1001).map(|channel| (controller("10.0.0.2"), channel)));
+        channels.push((controller("10.0.0.1"), 2));
+        assert!(ripple(&mut show(0.0), &channels, 0.0, u64::MAX, &template()).unwrap_err().contains("too long"));
+    }
+}
@@ -0,0 +1,648 @@
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::path::PathBuf;
+use tauri::{AppHandle, Emitter};
+
+use crate::audit::now_millis;
+use crate::mqtt::TelemetryKind;
+use crate::network_watch::ChangeReason;
+use crate::preflight::{CheckOutcome, SelfTestCheck};
+use crate::protocols::osc::OscCommand;
+use crate::show_engine::PlaybackState;
+use crate::transport::Transport;
+use crate::trigger::TriggerSource;
+
+pub const SHOW_STATE_CHANGED: &str = "show-state-changed";
+pub const SHOW_TICK: &str = "show-tick";
+pub const SHOW_FINISHED: &str = "show-finished";
+pub const SHOW_ERROR: &str = "show-error";
+pub const EFFECT_FIRED: &str = "effect-fired";
+pub const CONTROLLER_STATUS: &str = "controller-status";
+pub const CONTROLLER_DISCOVERED: &str = "controller-discovered";
+pub const PERFORMANCE_WARNING: &str = "performance-warning";
+pub const SYNC_DRIFT: &str = "sync-drift";
+pub const CLOSE_BLOCKED: &str = "close-blocked";
+pub const SHOW_HELD: &str = "show-held";
+pub const TRIGGER_RECEIVED: &str = "trigger-received";
+pub const SHOW_OUTPUT_WARNING: &str = "show-output-warning";
+pub const SHOW_IMPORT_PROGRESS: &str = "show-import-progress";
+pub const SELF_TEST_PROGRESS: &str = "self-test-progress";
+pub const DEGRADED_MODE: &str = "degraded-mode";
+pub const CONTROLLER_TESTED: &str = "controller-tested";
+pub const SCHEDULE_FIRED: &str = "schedule-fired";
+pub const POWER_WARNING: &str = "power-warning";
+pub const NETWORK_CHANGED: &str = "network-changed";
+pub const CONTROLLERS_RECONNECTED: &str = "controllers-reconnected";
+pub const EFFECT_SKIPPED: &str = "effect-skipped";
+pub const SHOW_UPLOAD_PROGRESS: &str = "show-upload-progress";
+pub const WAVEFORM_PROGRESS: &str = "waveform-progress";
+pub const OSC_RECEIVED: &str = "osc-received";
+pub const MQTT_STATUS: &str = "mqtt-status";
+pub const CONTROLLER_TELEMETRY: &str = "controller-telemetry";
+pub const TIMECODE_STATUS: &str = "timecode-status";
+
+// Shared by every event so the UI can spot gaps and resync via get_show_status.
+static SEQUENCE: AtomicU64 = AtomicU64::new(0);
+
+/// Wrapper added to every payload.
+#[derive(Debug, Clone, Serialize)]
+struct Envelope<T> {
+    seq: u64,
+    timestamp: u64,
+    #[serde(flatten)]
+    payload: T,
+}
+
+/// Emits `payload` to every window, tagged with the next sequence number.
+pub fn emit<T: Serialize + Clone>(app: &AppHandle, event: &str, payload: T) {
+    let envelope = Envelope {
+        seq: SEQUENCE.fetch_add(1, Ordering::SeqCst) + 1,
+        timestamp: now_millis(),
+        payload,
+    };
+    if let Err(e) = app.emit(event, envelope) {
+        log::warn!("Failed to emit {}: {}", event, e);
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ShowStateChanged {
+    pub state: PlaybackState,
+    pub previous: PlaybackState,
+    pub show_id: Option<String>,
+    pub current_time: f64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ShowTick {
+    pub current_time: f64,
+    pub total_duration: f64,
+    pub active_effects: Vec<String>,
+    pub next_cue_time: Option<f64>,
+    pub next_cue_id: Option<String>,
+    /// Cues of this run so far, by controller address.
+    pub controllers: BTreeMap<String, ControllerDispatch>,
+}
+
+/// How the cues sent to one controller during a run went.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ControllerDispatch {
+    pub succeeded: u32,
+    pub failed: u32,
+    /// Not sent because the controller was disabled.
+    pub skipped: u32,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ShowFinished {
+    pub show_id: String,
+    pub total_duration: f64,
+    pub failed_effects: u32,
+    pub controllers: BTreeMap<String, ControllerDispatch>,
+}
+
+/// A show cue that failed, whether or not the show was held for it.
+#[derive(Debug, Clone, Serialize)]
+pub struct ShowError {
+    pub show_id: String,
+    pub current_time: f64,
+    pub effect_id: String,
+    pub controller: String,
+    pub channel: u32,
+    pub error: String,
+}
+
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FireSource {
+    Show,
+    Manual,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct EffectFired {
+    pub effect_id: Option<String>,
+    pub controller: String,
+    pub controller_name: Option<String>,
+    pub channel: u32,
+    pub source: FireSource,
+    pub success: bool,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ControllerStatus {
+    pub address: String,
+    pub name: Option<String>,
+    pub online: bool,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ControllerDiscovered {
+    pub address: String,
+    pub name: String,
+    pub controller_type: String,
+    pub transport: Transport,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PerformanceWarning {
+    pub metric: String,
+    pub value: f64,
+    pub threshold: f64,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DegradedMode {
+    pub active: bool,
+    pub late_ticks: u32,
+    pub window_ticks: u32,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ControllerTested {
+    pub address: String,
+    pub name: Option<String>,
+    pub index: usize,
+    pub total: usize,
+    pub responded: bool,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ScheduleFired {
+    pub schedule_id: String,
+    pub show_path: PathBuf,
+    pub due_at: u64,
+    pub started: bool,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PowerWarning {
+    /// None when the whole rig is over its total budget.
+    pub controller: Option<String>,
+    pub watts: f64,
+    pub budget_watts: f64,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct NetworkChanged {
+    pub reason: ChangeReason,
+    pub previous_address: Option<String>,
+    pub current_address: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ControllersReconnected {
+    pub reconnected: Vec<String>,
+    pub unreachable: Vec<String>,
+    /// Whether the running show lost any of its controllers.
+    pub show_affected: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct EffectSkipped {
+    pub effect_id: String,
+    pub controller: String,
+    pub channel: u32,
+    pub reason: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ShowUploadProgress {
+    pub address: String,
+    pub show_id: String,
+    pub bytes_sent: usize,
+    pub total_bytes: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct WaveformProgress {
+    pub path: String,
+    pub progress: f64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SyncDrift {
+    pub drift_ms: f64,
+    pub rate: f64,
+    pub engine_time: f64,
+    pub source_time: f64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CloseBlocked {
+    pub armed: bool,
+    pub show_running: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ShowHeld {
+    pub show_id: String,
+    pub current_time: f64,
+    pub effect_id: String,
+    pub controller: String,
+    pub channel: u32,
+    pub error: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TriggerReceived {
+    pub source: TriggerSource,
+    pub started: bool,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct OscReceived {
+    pub address: String,
+    pub from: String,
+    pub command: Option<OscCommand>,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ControllerTelemetryReceived {
+    pub address: String,
+    pub kind: TelemetryKind,
+    pub data: serde_json::Value,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ShowOutputWarning {
+    pub show_id: String,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SelfTestProgress {
+    pub controller: String,
+    pub check: SelfTestCheck,
+    pub outcome: CheckOutcome,
+    /// Issues this check found, of any severity.
+    pub issues: usize,
+    pub completed: usize,
+    pub total: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ShowImportProgress {
+    pub path: String,
+    pub completed: usize,
+    pub total: usize,
+    pub ok: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct EventField {
+    pub name: &'static str,
+    #[serde(rename = "type")]
+    pub field_type: &'static str,
+    pub description: &'static str,
+}
+
+#[derive(Debug, Serialize)]
+pub struct EventSchema {
+    pub name: &'static str,
+    pub description: &'static str,
+    pub fields: Vec<EventField>,
+}
+
+fn field(name: &'static str, field_type: &'static str, description: &'static str) -> EventField {
+    EventField {
+        name,
+        field_type,
+        description,
+    }
+}
+
+/// Describes every event payload. Keep in sync with the structs above.
+pub fn schema() -> Vec<EventSchema> {
+    let common = || {
+        vec![
+            field("seq", "number", "Increases by one per event across all event types"),
+            field("timestamp", "number", "Emit time in milliseconds since the Unix epoch"),
+        ]
+    };
+    let with_common = |fields: Vec<EventField>| common().into_iter().chain(fields).collect();
+
+    vec![
+        EventSchema {
+            name: SHOW_STATE_CHANGED,
+            description: "Playback moved between stopped, running, held and finished",
+            fields: with_common(vec![
+                field("state", "\"stopped\" | \"running\" | \"held\" | \"finished\"", "New playback state"),
+                field("previous", "\"stopped\" | \"running\" | \"held\" | \"finished\"", "State before the change"),
+                field("show_id", "string | null", "Show being played"),
+                field("current_time", "number", "Timeline position in seconds"),
+            ]),
+        },
+        EventSchema {
+            name: SHOW_TICK,
+            description: "Emitted on every engine tick while a show is running",
+            fields: with_common(vec![
+                field("current_time", "number", "Timeline position in seconds"),
+                field("total_duration", "number", "Show length in seconds"),
+                field("active_effects", "string[]", "Ids of effects currently running"),
+                field("next_cue_time", "number | null", "Start time of the next unfired effect"),
+                field("next_cue_id", "string | null", "Id of the next unfired effect"),
+                field(
+                    "controllers",
+                    "Record<string, { succeeded, failed, skipped }>",
+                    "Cues sent so far this run, by controller address",
+                ),
+            ]),
+        },
+        EventSchema {
+            name: SHOW_FINISHED,
+            description: "The show played to its end",
+            fields: with_common(vec![
+                field("show_id", "string", "Show that finished"),
+                field("total_duration", "number", "Show length in seconds"),
+                field("failed_effects", "number", "Cues that failed during the run"),
+                field(
+                    "controllers",
+                    "Record<string, { succeeded, failed, skipped }>",
+                    "Cues sent during the run, by controller address",
+                ),
+            ]),
+        },
+        EventSchema {
+            name: SHOW_ERROR,
+            description: "A show cue failed, whether or not the show was held for it",
+            fields: with_common(vec![
+                field("show_id", "string", "Show being played"),
+                field("current_time", "number", "Timeline position in seconds"),
+                field("effect_id", "string", "Effect that failed"),
+                field("controller", "string", "Controller address"),
+                field("channel", "number", "Output channel"),
+                field("error", "string", "Failure reason"),
+            ]),
+        },
+        EventSchema {
+            name: EFFECT_FIRED,
+            description: "An effect was sent to a controller, from the show or a manual override",
+            fields: with_common(vec![
+                field("effect_id", "string | null", "Show effect id, null for manual overrides"),
+                field("controller", "string", "Controller address"),
+                field("controller_name", "string | null", "Friendly name of the controller"),
+                field("channel", "number", "Output channel"),
+                field("source", "\"show\" | \"manual\"", "What triggered the effect"),
+                field("success", "boolean", "Whether the controller accepted the command"),
+                field("error", "string | null", "Failure reason"),
+            ]),
+        },
+        EventSchema {
+            name: CONTROLLER_STATUS,
+            description: "A controller's reachability changed",
+            fields: with_common(vec![
+                field("address", "string", "Controller address"),
+                field("name", "string | null", "Friendly name of the controller"),
+                field("online", "boolean", "Whether the controller is responding"),
+                field("error", "string | null", "Last error when offline"),
+            ]),
+        },
+        EventSchema {
+            name: CONTROLLER_DISCOVERED,
+            description: "Discovery found a controller and added it to the registry",
+            fields: with_common(vec![
+                field("address", "string", "Controller address; \"ble:<device id>\" for Bluetooth controllers"),
+                field("name", "string", "Name derived from the mDNS hostname or BLE advertisement"),
+                field("controller_type", "\"firework\" | \"lights\" | \"haze\"", "Controller type"),
+                field("transport", "\"http\" | \"ble\"", "How the controller is reached"),
+            ]),
+        },
+        EventSchema {
+            name: PERFORMANCE_WARNING,
+            description: "The engine is falling behind its tick schedule",
+            fields: with_common(vec![
+                field("metric", "string", "Name of the metric that crossed its threshold"),
+                field("value", "number", "Measured value"),
+                field("threshold", "number", "Threshold that was exceeded"),
+                field("message", "string", "Human readable summary"),
+            ]),
+        },
+        EventSchema {
+            name: DEGRADED_MODE,
+            description: "Adaptive performance cut back or restored preview updates and ramp frame rates",
+            fields: with_common(vec![
+                field("active", "boolean", "Whether output is now reduced"),
+                field("late_ticks", "number", "Late ticks in the window that triggered the change; 0 when leaving"),
+                field("window_ticks", "number", "Ticks in that window; 0 when leaving"),
+                field("message", "string", "Human readable summary"),
+            ]),
+        },
+        EventSchema {
+            name: CONTROLLER_TESTED,
+            description: "A rig test pulsed or queried one controller",
+            fields: with_common(vec![
+                field("address", "string", "Controller address"),
+                field("name", "string | null", "Friendly name of the controller"),
+                field("index", "number", "Position in the test order, from 0"),
+                field("total", "number", "Controllers in the test"),
+                field("responded", "boolean", "Whether the controller answered"),
+                field("error", "string | null", "Why it did not answer"),
+            ]),
+        },
+        EventSchema {
+            name: SCHEDULE_FIRED,
+            description: "A recurring schedule came due and started its show, or could not",
+            fields: with_common(vec![
+                field("schedule_id", "string", "Id from schedule_recurring"),
+                field("show_path", "string", "Show file the schedule plays"),
+                field("due_at", "number", "When the run was due, Unix milliseconds"),
+                field("started", "boolean", "Whether the show started"),
+                field("error", "string | null", "Why it did not start, e.g. another show was running"),
+            ]),
+        },
+        EventSchema {
+            name: POWER_WARNING,
+            description: "Live power draw reported by the controllers is over budget during playback",
+            fields: with_common(vec![
+                field("controller", "string | null", "Controller over its budget; null for the rig's total budget"),
+                field("watts", "number", "Measured draw"),
+                field("budget_watts", "number", "Budget that was exceeded"),
+                field("message", "string", "Human readable summary"),
+            ]),
+        },
+        EventSchema {
+            name: NETWORK_CHANGED,
+            description: "The desk changed networks or woke from sleep; controllers are being reconnected",
+            fields: with_common(vec![
+                field("reason", "\"address_changed\" | \"resumed\"", "What was detected"),
+                field("previous_address", "string | null", "The desk's address before"),
+                field("current_address", "string | null", "The desk's address now; null while offline"),
+            ]),
+        },
+        EventSchema {
+            name: CONTROLLERS_RECONNECTED,
+            description: "Known Wi-Fi controllers were probed again after a network change",
+            fields: with_common(vec![
+                field("reconnected", "string[]", "Controllers that answered"),
+                field("unreachable", "string[]", "Controllers that did not"),
+                field("show_affected", "boolean", "Whether the running show uses an unreachable controller; the error policy then applies"),
+            ]),
+        },
+        EventSchema {
+            name: EFFECT_SKIPPED,
+            description: "A show cue was not sent because its controller is disabled",
+            fields: with_common(vec![
+                field("effect_id", "string", "The skipped effect"),
+                field("controller", "string", "Controller address"),
+                field("channel", "number", "Channel the effect targets"),
+                field("reason", "string", "Why the effect was skipped"),
+            ]),
+        },
+        EventSchema {
+            name: SHOW_UPLOAD_PROGRESS,
+            description: "A chunk of a standalone sequence was accepted by its controller",
+            fields: with_common(vec![
+                field("address", "string", "Controller address"),
+                field("show_id", "string", "Show the sequence was compiled from"),
+                field("bytes_sent", "number", "Bytes uploaded so far"),
+                field("total_bytes", "number", "Size of the sequence"),
+            ]),
+        },
+        EventSchema {
+            name: WAVEFORM_PROGRESS,
+            description: "Part of an audio track was analyzed for its waveform",
+            fields: with_common(vec![
+                field("path", "string", "Track being analyzed"),
+                field("progress", "number", "Fraction of the track read, 0-1"),
+            ]),
+        },
+        EventSchema {
+            name: SYNC_DRIFT,
+            description: "The engine clock rate was adjusted to follow the audio or timecode source",
+            fields: with_common(vec![
+                field("drift_ms", "number", "Smoothed engine minus source time, positive when the engine is ahead"),
+                field("rate", "number", "New engine clock rate, 1.0 when no correction is needed"),
+                field("engine_time", "number", "Engine timeline position in seconds"),
+                field("source_time", "number", "Position reported by the sync source in seconds"),
+            ]),
+        },
+        EventSchema {
+            name: CLOSE_BLOCKED,
+            description: "The main window was kept open because the system is live; call confirm_close to exit anyway",
+            fields: with_common(vec![
+                field("armed", "boolean", "Whether the system is armed"),
+                field("show_running", "boolean", "Whether a show is playing"),
+            ]),
+        },
+        EventSchema {
+            name: SHOW_HELD,
+            description: "A cue failed under the hold error policy and the show is paused; call resume_show to continue",
+            fields: with_common(vec![
+                field("show_id", "string", "Show that was held"),
+                field("current_time", "number", "Hold point in seconds"),
+                field("effect_id", "string", "Effect that failed"),
+                field("controller", "string", "Controller the effect targets"),
+                field("channel", "number", "Output channel"),
+                field("error", "string", "Failure reason"),
+            ]),
+        },
+        EventSchema {
+            name: TRIGGER_RECEIVED,
+            description: "The external trigger fired, after debouncing",
+            fields: with_common(vec![
+                field(
+                    "source",
+                    "{ type: \"keyboard\", key } | { type: \"serial\", port } | { type: \"gpio\", pin, active_low }",
+                    "Configured trigger source",
+                ),
+                field("started", "boolean", "Whether the trigger started the show"),
+                field("error", "string | null", "Why the show was not started, e.g. the trigger is not armed"),
+            ]),
+        },
+        EventSchema {
+            name: OSC_RECEIVED,
+            description: "An OSC message arrived on the listen port, mapped to a command or not",
+            fields: with_common(vec![
+                field("address", "string", "OSC address of the message"),
+                field("from", "string", "Sender as ip:port"),
+                field(
+                    "command",
+                    "\"start_show\" | \"stop_show\" | \"pause_show\" | \"resume_show\" | \"seek\" | \"jump_to_marker\" | null",
+                    "Command the address is mapped to",
+                ),
+                field("error", "string | null", "Why the command did not run, e.g. the address is not mapped"),
+            ]),
+        },
+        EventSchema {
+            name: MQTT_STATUS,
+            description: "The MQTT broker connection came up, dropped or was closed",
+            fields: with_common(vec![
+                field("broker", "string | null", "Broker as host:port, null once disconnected"),
+                field("connected", "boolean", "Whether the connection is up"),
+                field("connected_since", "number | null", "Unix milliseconds the connection came up"),
+                field("messages_received", "number", "Messages received from the broker since connect_mqtt"),
+                field("last_error", "string | null", "Why the connection dropped; it is retried"),
+            ]),
+        },
+        EventSchema {
+            name: CONTROLLER_TELEMETRY,
+            description: "A registered controller sent a heartbeat or telemetry over MQTT",
+            fields: with_common(vec![
+                field("address", "string", "Controller address"),
+                field("kind", "\"heartbeat\" | \"telemetry\"", "Topic the message came in on"),
+                field("data", "unknown", "Payload as JSON, or a string if it is not JSON"),
+            ]),
+        },
+        EventSchema {
+            name: TIMECODE_STATUS,
+            description: "The sync mode changed, or timecode chase locked, freewheeled or lost timecode",
+            fields: with_common(vec![
+                field("mode", "\"internal\" | \"mtc\" | \"ltc\"", "Where the show clock comes from"),
+                field("input", "string | null", "MIDI port or audio device timecode is read from"),
+                field("offset", "number", "Timecode in seconds of show time zero"),
+                field(
+                    "state",
+                    "\"idle\" | \"locked\" | \"freewheel\" | \"lost\"",
+                    "Freewheel while timecode is briefly gone; lost pauses the show until it returns",
+                ),
+                field("timecode", "string | null", "Last timecode received, as hh:mm:ss:ff"),
+                field("frame_rate", "\"24\" | \"25\" | \"29.97df\" | \"30\" | null", "Frame rate of the last timecode"),
+            ]),
+        },
+        EventSchema {
+            name: SHOW_OUTPUT_WARNING,
+            description: "An opened show asks for output settings this build cannot apply",
+            fields: with_common(vec![
+                field("show_id", "string", "Show that was opened"),
+                field("message", "string", "What could not be configured"),
+            ]),
+        },
+        EventSchema {
+            name: SELF_TEST_PROGRESS,
+            description: "A pre-show self-test check finished on one controller",
+            fields: with_common(vec![
+                field("controller", "string", "Controller address"),
+                field(
+                    "check",
+                    "\"connectivity\" | \"readback\" | \"firmware\" | \"features\" | \"channels\" | \"power\"",
+                    "Check that finished",
+                ),
+                field("outcome", "\"passed\" | \"failed\" | \"skipped\"", "Skipped after a failed connectivity check or a cancel"),
+                field("issues", "number", "Issues the check found, of any severity"),
+                field("completed", "number", "Checks finished so far"),
+                field("total", "number", "Checks in the whole self-test"),
+            ]),
+        },
+        EventSchema {
+            name: SHOW_IMPORT_PROGRESS,
+            description: "A folder import finished reading one file",
+            fields: with_common(vec![
+                field("path", "string", "File just read"),
+                field("completed", "number", "Files read so far"),
+                field("total", "number", "Show files found in the folder"),
+                field("ok", "boolean", "Whether the file opened; failures are listed in the final manifest"),
+            ]),
+        },
+    ]
+}
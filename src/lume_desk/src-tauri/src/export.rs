@@ -0,0 +1,308 @@
+use crate::cue_sheet;
+use crate::models::{Effect, Show};
+use crate::show_store;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tauri::AppHandle;
+use tauri_plugin_dialog::DialogExt;
+use tokio::task::JoinSet;
+
+#[derive(Debug, Serialize)]
+#[serde(tag = "status", rename_all = "lowercase")]
+pub enum ExportOutcome {
+    Ok { path: String },
+    Failed { error: String },
+}
+
+/// A column of the CSV cue list.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CsvColumn {
+    Time,
+    Controller,
+    Channel,
+    /// The effect's "name" or "label" param, else its type.
+    Effect,
+    Duration,
+    /// The effect's "description" or "notes" param.
+    Notes,
+    Id,
+    EffectType,
+    Layer,
+}
+
+impl CsvColumn {
+    fn header(self) -> &'static str {
+        match self {
+            CsvColumn::Time => "time",
+            CsvColumn::Controller => "controller",
+            CsvColumn::Channel => "channel",
+            CsvColumn::Effect => "effect",
+            CsvColumn::Duration => "duration",
+            CsvColumn::Notes => "notes",
+            CsvColumn::Id => "id",
+            CsvColumn::EffectType => "effect_type",
+            CsvColumn::Layer => "layer",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CsvTimeFormat {
+    /// "272.500"
+    #[default]
+    Seconds,
+    /// "00:04:32.500"
+    Clock,
+}
+
+impl CsvTimeFormat {
+    fn format(self, seconds: f64) -> String {
+        let seconds = if seconds.is_finite() { seconds.max(0.0) } else { 0.0 };
+        match self {
+            CsvTimeFormat::Seconds => format!("{:.3}", seconds),
+            CsvTimeFormat::Clock => {
+                let millis = (seconds * 1000.0).round() as u64;
+                let secs = millis / 1000;
+                format!("{:02}:{:02}:{:02}.{:03}", secs / 3600, secs / 60 % 60, secs % 60, millis % 1000)
+            }
+        }
+    }
+}
+
+/// How the CSV cue list is written. Rows are the effects that play, in
+/// start order.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct CsvOptions {
+    pub delimiter: char,
+    pub time_format: CsvTimeFormat,
+    pub columns: Vec<CsvColumn>,
+}
+
+impl Default for CsvOptions {
+    fn default() -> Self {
+        Self {
+            delimiter: ',',
+            time_format: CsvTimeFormat::Seconds,
+            columns: vec![
+                CsvColumn::Time,
+                CsvColumn::Controller,
+                CsvColumn::Channel,
+                CsvColumn::Effect,
+                CsvColumn::Duration,
+                CsvColumn::Notes,
+            ],
+        }
+    }
+}
+
+/// File extension for a supported export format.
+fn extension(format: &str) -> Option<&'static str> {
+    match format {
+        "lume" => Some("lume"),
+        "csv" => Some("csv"),
+        "json" => Some("json"),
+        _ => None,
+    }
+}
+
+pub fn encode(show: &Show, format: &str, csv: &CsvOptions) -> Result<Vec<u8>, String> {
+    match format {
+        "lume" => show_store::encode_show_file(show),
+        "csv" => Ok(encode_csv(show, csv)?.into_bytes()),
+        "json" => serde_json::to_vec_pretty(show).map_err(|e| e.to_string()),
+        _ => Err(format!("Unsupported export format: {}", format)),
+    }
+}
+
+/// Writes `show` to `dir` in one format and returns the written path.
+pub fn export_to_dir(show: &Show, format: &str, dir: &Path, csv: &CsvOptions) -> Result<PathBuf, String> {
+    let ext = extension(format).ok_or_else(|| format!("Unsupported export format: {}", format))?;
+    let data = encode(show, format, csv)?;
+    let path = dir.join(format!("{}.{}", file_stem(&show.name), ext));
+    show_store::write_atomic(&path, &data).map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+    Ok(path)
+}
+
+/// Writes `show` as a native .lume file to `path`, or to where the operator
+/// picks in a save dialog without one. None if the dialog was cancelled.
+pub async fn export_lume(app: &AppHandle, show: &Show, path: Option<PathBuf>) -> Result<Option<PathBuf>, String> {
+    let path = match path {
+        Some(path) => path,
+        None => {
+            let (tx, rx) = tokio::sync::oneshot::channel();
+            app.dialog()
+                .file()
+                .set_title("Export show")
+                .add_filter("LUME show", &["lume"])
+                .set_file_name(format!("{}.lume", file_stem(&show.name)))
+                .save_file(move |picked| {
+                    let _ = tx.send(picked);
+                });
+            match rx.await.ok().flatten() {
+                Some(picked) => picked.into_path().map_err(|e| format!("Invalid export path: {}", e))?,
+                None => return Ok(None),
+            }
+        }
+    };
+    let data = show_store::encode_show_file(show)?;
+    show_store::write_atomic(&path, &data).map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+    log::info!("Exported show '{}' to {}", show.name, path.display());
+    Ok(Some(path))
+}
+
+/// Exports to every requested format concurrently. One failing format does
+/// not prevent the others from being written.
+pub async fn export_multi(show: Show, formats: Vec<String>, dir: PathBuf, csv: CsvOptions) -> HashMap<String, ExportOutcome> {
+    let show = Arc::new(show);
+    let dir = Arc::new(dir);
+    let csv = Arc::new(csv);
+    let mut jobs = JoinSet::new();
+    for format in formats.into_iter().collect::<BTreeSet<_>>() {
+        let (show, dir, csv) = (show.clone(), dir.clone(), csv.clone());
+        jobs.spawn_blocking(move || {
+            let result = export_to_dir(&show, &format, &dir, &csv);
+            (format, result)
+        });
+    }
+
+    let mut outcomes = HashMap::new();
+    while let Some(joined) = jobs.join_next().await {
+        match joined {
+            Ok((format, Ok(path))) => {
+                outcomes.insert(format, ExportOutcome::Ok { path: path.display().to_string() });
+            }
+            Ok((format, Err(error))) => {
+                log::warn!("Export to {} failed: {}", format, error);
+                outcomes.insert(format, ExportOutcome::Failed { error });
+            }
+            Err(e) => log::error!("Export task panicked: {}", e),
+        }
+    }
+    outcomes
+}
+
+fn encode_csv(show: &Show, options: &CsvOptions) -> Result<String, String> {
+    let delimiter = options.delimiter;
+    if matches!(delimiter, '"' | '\n' | '\r') {
+        return Err(format!("CSV delimiter cannot be {:?}", delimiter));
+    }
+    if options.columns.is_empty() {
+        return Err("Select at least one CSV column".to_string());
+    }
+    let separator = delimiter.to_string();
+    let header: Vec<String> = options.columns.iter().map(|c| csv_field(c.header(), delimiter)).collect();
+    let mut csv = header.join(&separator);
+    csv.push('\n');
+
+    let mut cues: Vec<&Effect> = show.effects.iter().filter(|e| show.plays(e)).collect();
+    cues.sort_by(|a, b| a.start_time.total_cmp(&b.start_time));
+    for effect in cues {
+        let row: Vec<String> = options
+            .columns
+            .iter()
+            .map(|column| {
+                let value = match column {
+                    CsvColumn::Time => options.time_format.format(effect.start_time),
+                    CsvColumn::Controller => effect.controller.clone(),
+                    CsvColumn::Channel => effect.channel.to_string(),
+                    CsvColumn::Effect => cue_sheet::param(effect, &["name", "label"])
+                        .unwrap_or(if effect.effect_type.is_empty() { &effect.id } else { &effect.effect_type })
+                        .to_string(),
+                    CsvColumn::Duration => options.time_format.format(effect.duration),
+                    CsvColumn::Notes => cue_sheet::param(effect, &["description", "notes"]).unwrap_or_default().to_string(),
+                    CsvColumn::Id => effect.id.clone(),
+                    CsvColumn::EffectType => effect.effect_type.clone(),
+                    CsvColumn::Layer => effect.layer.clone().unwrap_or_default(),
+                };
+                csv_field(&value, delimiter)
+            })
+            .collect();
+        csv.push_str(&row.join(&separator));
+        csv.push('\n');
+    }
+    Ok(csv)
+}
+
+fn csv_field(value: &str, delimiter: char) -> String {
+    if value.contains(['"', '\n', '\r', delimiter]) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+/// Turns a show name into a safe file name.
+fn file_stem(name: &str) -> String {
+    let stem: String = name
+        .trim()
+        .chars()
+        .map(|c| if c.is_alphanumeric() || c == '-' || c == '_' { c } else { '_' })
+        .collect();
+    if stem.is_empty() {
+        "show".to_string()
+    } else {
+        stem
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn show() -> Show {
+        serde_json::from_value(json!({
+            "id": "s1",
+            "name": "Finale",
+            "effects": [
+                { "id": "e2", "start_time": 3725.25, "duration": 1.5, "controller": "10.0.0.1", "channel": 2,
+                  "effect_type": "comet", "params": { "notes": "left, then right" } },
+                { "id": "e1", "start_time": 1.0, "controller": "10.0.0.2", "channel": 1,
+                  "effect_type": "mine", "params": { "name": "Opener \"A\"" } },
+                { "id": "e3", "start_time": 2.0, "controller": "10.0.0.2", "channel": 3, "enabled": false },
+            ],
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn lists_playing_cues_in_time_order() {
+        let csv = encode_csv(&show(), &CsvOptions::default()).unwrap();
+        assert_eq!(
+            csv,
+            "time,controller,channel,effect,duration,notes\n\
+             1.000,10.0.0.2,1,\"Opener \"\"A\"\"\",0.000,\n\
+             3725.250,10.0.0.1,2,comet,1.500,\"left, then right\"\n"
+        );
+    }
+
+    #[test]
+    fn applies_delimiter_time_format_and_columns() {
+        let options = CsvOptions {
+            delimiter: ';',
+            time_format: CsvTimeFormat::Clock,
+            columns: vec![CsvColumn::Id, CsvColumn::Time, CsvColumn::Notes],
+        };
+        let csv = encode_csv(&show(), &options).unwrap();
+        assert_eq!(csv, "id;time;notes\ne1;00:00:01.000;\ne2;01:02:05.250;left, then right\n");
+    }
+
+    #[test]
+    fn rejects_unusable_options() {
+        let quote = CsvOptions {
+            delimiter: '"',
+            ..CsvOptions::default()
+        };
+        assert!(encode_csv(&show(), &quote).is_err());
+        let empty = CsvOptions {
+            columns: Vec::new(),
+            ..CsvOptions::default()
+        };
+        assert!(encode_csv(&show(), &empty).is_err());
+    }
+}
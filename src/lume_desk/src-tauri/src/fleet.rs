@@ -0,0 +1,220 @@
+use serde::Serialize;
+use std::collections::BTreeSet;
+use std::sync::Arc;
+use tokio::sync::Semaphore;
+use tokio::task::JoinSet;
+
+use crate::batch::{BatchPolicy, BatchResult};
+use crate::controller_client;
+use crate::driver;
+use crate::registry::{ControllerInfo, ControllerRegistry};
+use crate::response_cache;
+use crate::transport;
+
+/// At most this many controllers are queried at once.
+pub const MAX_CONCURRENT_QUERIES: usize = 8;
+
+#[derive(Debug, Serialize)]
+pub struct FleetEntry {
+    pub address: String,
+    pub success: bool,
+    pub firmware_version: Option<String>,
+    pub system_name: Option<String>,
+    pub channel_count: Option<u32>,
+    pub max_areas: Option<u32>,
+    pub error: Option<String>,
+}
+
+/// Queries firmware and capabilities of every enabled controller and
+/// caches the results in the registry. Under `AbortOnFirstError` the
+/// queries still in flight are cancelled at the first failure. Recent
+/// replies are reused unless `force` is set.
+pub async fn query(registry: &ControllerRegistry, force: bool, policy: BatchPolicy) -> Result<BatchResult<FleetEntry>, String> {
+    let permits = Arc::new(Semaphore::new(MAX_CONCURRENT_QUERIES));
+    let mut queries = JoinSet::new();
+    let mut pending = BTreeSet::new();
+    for controller in registry.list()?.into_iter().filter(|c| c.enabled) {
+        let permits = permits.clone();
+        pending.insert(controller.address.clone());
+        queries.spawn(async move {
+            let _permit = permits.acquire_owned().await;
+            query_one(controller, force).await
+        });
+    }
+
+    let mut result = BatchResult::new(policy);
+    while let Some(joined) = queries.join_next().await {
+        let entry = match joined {
+            Ok(entry) => entry,
+            Err(e) if e.is_cancelled() => continue,
+            Err(e) => return Err(format!("Fleet query failed: {}", e)),
+        };
+        pending.remove(&entry.address);
+        if entry.success {
+            registry.update(&entry.address, |info| {
+                info.firmware_version = entry.firmware_version.clone();
+                info.channel_count = entry.channel_count;
+                if info.name.is_empty() {
+                    info.name = entry.system_name.clone().unwrap_or_default();
+                }
+            })?;
+        }
+        let address = entry.address.clone();
+        let outcome = entry.error.clone().map_or(Ok(()), Err);
+        if result.record(&address, outcome, entry) {
+            queries.abort_all();
+        }
+    }
+    result.details.sort_by(|a, b| a.address.cmp(&b.address));
+    result.skipped = pending.into_iter().collect();
+    Ok(result)
+}
+
+/// What a connection test found.
+#[derive(Debug, Serialize)]
+pub struct ConnectionHealth {
+    pub address: String,
+    pub reachable: bool,
+    /// Round trip of the `/status` request.
+    pub latency_ms: Option<u64>,
+    pub firmware_version: Option<String>,
+    pub channel_count: Option<u32>,
+    /// Why it is unreachable, or why its capabilities could not be read.
+    pub error: Option<String>,
+}
+
+/// Requests `/status`, then, for a registered controller that answered,
+/// reads its firmware and channel count fresh in its driver's protocol.
+pub async fn check_connection(address: &str, controller: Option<ControllerInfo>) -> ConnectionHealth {
+    let mut health = ConnectionHealth {
+        address: address.to_string(),
+        reachable: false,
+        latency_ms: None,
+        firmware_version: None,
+        channel_count: None,
+        error: None,
+    };
+    match controller_client::probe(address, transport::timeout_for(address)).await {
+        Ok(rtt) => {
+            health.reachable = true;
+            health.latency_ms = Some(rtt.as_millis() as u64);
+        }
+        Err(e) => {
+            health.error = Some(e);
+            return health;
+        }
+    }
+    if let Some(controller) = controller {
+        let entry = query_one(controller, true).await;
+        health.firmware_version = entry.firmware_version;
+        health.channel_count = entry.channel_count;
+        health.error = entry.error;
+    }
+    health
+}
+
+/// Firmware and capabilities of one controller, asked in its driver's protocol.
+pub async fn query_one(controller: ControllerInfo, force: bool) -> FleetEntry {
+    let address = controller.address.clone();
+    let timeout = transport::timeout_for(&controller.address);
+    let driver = driver::for_controller(&controller.address, &controller.controller_type);
+    // Only static parts of the replies are read here, so they are safe to cache.
+    let mut queries = JoinSet::new();
+    for (index, path) in driver.query_caps().iter().enumerate() {
+        let address = address.clone();
+        queries.spawn(async move { (index, response_cache::get_json(&address, path, timeout, force).await) });
+    }
+    let mut replies = vec![serde_json::Value::Null; driver.query_caps().len()];
+    let mut error = None;
+    while let Some(joined) = queries.join_next().await {
+        match joined {
+            Ok((index, Ok(reply))) => replies[index] = reply,
+            Ok((_, Err(e))) => error = error.or(Some(e)),
+            Err(e) => error = error.or(Some(format!("Capability query failed: {}", e))),
+        }
+    }
+    if let Some(error) = error {
+        return FleetEntry {
+            address,
+            success: false,
+            firmware_version: None,
+            system_name: None,
+            channel_count: None,
+            max_areas: None,
+            error: Some(error),
+        };
+    }
+
+    let caps = driver.parse_caps(&controller, &replies);
+    FleetEntry {
+        address,
+        success: true,
+        firmware_version: caps.firmware_version,
+        system_name: caps.system_name,
+        channel_count: caps.channel_count,
+        max_areas: caps.max_areas,
+        error: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock_controller::{MockController, Reply};
+    use serde_json::json;
+
+    fn info() -> Reply {
+        Reply::Json(json!({ "firmware": "2.1", "name": "Truss", "channels": 16 }))
+    }
+
+    #[tokio::test]
+    async fn capabilities_are_asked_in_the_driver_protocol() {
+        let mock = MockController::start().await;
+        mock.always("/info", info());
+        let entry = query_one(mock.controller("generic-http"), true).await;
+        assert!(entry.success, "{:?}", entry.error);
+        assert_eq!(entry.firmware_version.as_deref(), Some("2.1"));
+        assert_eq!(entry.channel_count, Some(16));
+        mock.assert_paths(&["/info"]);
+    }
+
+    #[tokio::test]
+    async fn failed_controller_does_not_stop_the_rest() {
+        let (good, bad) = (MockController::start().await, MockController::start().await);
+        good.always("/info", info());
+        bad.always("/info", Reply::Status(500));
+        let registry = ControllerRegistry::default();
+        registry.add(good.controller("generic-http")).unwrap();
+        registry.add(bad.controller("generic-http")).unwrap();
+
+        let result = query(&registry, true, BatchPolicy::Continue).await.unwrap();
+        assert_eq!(result.succeeded, [good.address.as_str()]);
+        assert_eq!(result.failed.len(), 1);
+        assert_eq!(result.failed[0].0, bad.address);
+        assert!(result.skipped.is_empty());
+        assert_eq!(registry.get(&good.address).unwrap().channel_count, Some(16));
+    }
+
+    #[tokio::test]
+    async fn connection_check_reports_latency_and_capabilities() {
+        let mock = MockController::start().await;
+        mock.always("/info", info());
+        let health = check_connection(&mock.address, Some(mock.controller("generic-http"))).await;
+        assert!(health.reachable, "{:?}", health.error);
+        assert!(health.latency_ms.is_some());
+        assert_eq!(health.firmware_version.as_deref(), Some("2.1"));
+        assert_eq!(health.channel_count, Some(16));
+        mock.assert_paths(&["/status", "/info"]);
+    }
+
+    #[tokio::test]
+    async fn unreachable_controller_is_not_queried_further() {
+        let mock = MockController::start().await;
+        mock.always("/status", Reply::Status(503));
+        let health = check_connection(&mock.address, Some(mock.controller("generic-http"))).await;
+        assert!(!health.reachable);
+        assert_eq!(health.latency_ms, None);
+        assert!(health.error.is_some());
+        mock.assert_paths(&["/status"]);
+    }
+}
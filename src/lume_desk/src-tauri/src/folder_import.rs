@@ -0,0 +1,148 @@
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use tauri::AppHandle;
+
+use crate::events::{self, ShowImportProgress};
+use crate::models::Show;
+use crate::show_store;
+
+/// Extensions recognized as show files: native snapshots and plain JSON exports.
+const SHOW_EXTENSIONS: [&str; 2] = ["lume", "json"];
+
+#[derive(Debug, Serialize)]
+pub struct ImportedShow {
+    pub path: String,
+    pub show_id: String,
+    pub title: String,
+    pub effect_count: usize,
+    pub total_duration: f64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ImportFailure {
+    pub path: String,
+    pub error: String,
+}
+
+/// Shows in the folder that share a title (ignoring case and surrounding spaces).
+#[derive(Debug, Serialize)]
+pub struct DuplicateTitle {
+    pub title: String,
+    pub paths: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ImportManifest {
+    pub dir: String,
+    pub imported: Vec<ImportedShow>,
+    pub failed: Vec<ImportFailure>,
+    pub duplicate_titles: Vec<DuplicateTitle>,
+}
+
+fn is_show_file(path: &Path) -> bool {
+    path.is_file()
+        && path
+            .extension()
+            .and_then(|e| e.to_str())
+            .is_some_and(|e| SHOW_EXTENSIONS.iter().any(|ext| e.eq_ignore_ascii_case(ext)))
+}
+
+/// Show files directly inside `dir`, sorted by path. Subfolders, journals and
+/// backups are left alone.
+fn show_files(dir: &Path) -> Result<Vec<PathBuf>, String> {
+    let entries = fs::read_dir(dir).map_err(|e| format!("Failed to read {}: {}", dir.display(), e))?;
+    let mut files: Vec<PathBuf> = entries
+        .filter_map(Result::ok)
+        .map(|entry| entry.path())
+        .filter(|path| is_show_file(path))
+        .collect();
+    files.sort();
+    Ok(files)
+}
+
+fn read_show(path: &Path) -> Result<Show, String> {
+    let is_native = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .is_some_and(|e| e.eq_ignore_ascii_case("lume"));
+    if is_native {
+        return show_store::read_show_file(path);
+    }
+    let data = fs::read(path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    serde_json::from_slice(&data).map_err(|e| format!("Failed to parse show file {}: {}", path.display(), e))
+}
+
+/// Reads every show file in `dir` and reports which ones opened and which
+/// did not. A file that fails is recorded and skipped; it never stops the
+/// rest. Nothing is loaded into the editor: each imported path can be opened
+/// with `import_show`. Blocking, so run it off the async runtime.
+pub fn import_folder(app: &AppHandle, dir: &Path) -> Result<ImportManifest, String> {
+    let files = show_files(dir)?;
+    let total = files.len();
+    log::info!("Importing {} show files from {}", total, dir.display());
+
+    let mut imported = Vec::new();
+    let mut failed = Vec::new();
+    for (index, path) in files.iter().enumerate() {
+        let display = path.display().to_string();
+        let result = read_show(path);
+        let ok = result.is_ok();
+        match result {
+            Ok(show) => imported.push(ImportedShow {
+                path: display.clone(),
+                show_id: show.id,
+                title: show.name,
+                effect_count: show.effects.len(),
+                total_duration: show.total_duration,
+            }),
+            Err(error) => {
+                log::warn!("Skipping {}: {}", display, error);
+                failed.push(ImportFailure {
+                    path: display.clone(),
+                    error,
+                });
+            }
+        }
+        events::emit(
+            app,
+            events::SHOW_IMPORT_PROGRESS,
+            ShowImportProgress {
+                path: display,
+                completed: index + 1,
+                total,
+                ok,
+            },
+        );
+    }
+
+    let duplicate_titles = duplicate_titles(&imported);
+    log::info!(
+        "Folder import finished: {} imported, {} failed, {} duplicate titles",
+        imported.len(),
+        failed.len(),
+        duplicate_titles.len()
+    );
+    Ok(ImportManifest {
+        dir: dir.display().to_string(),
+        imported,
+        failed,
+        duplicate_titles,
+    })
+}
+
+fn duplicate_titles(imported: &[ImportedShow]) -> Vec<DuplicateTitle> {
+    let mut by_title: BTreeMap<String, Vec<&ImportedShow>> = BTreeMap::new();
+    for show in imported {
+        by_title.entry(show.title.trim().to_lowercase()).or_default().push(show);
+    }
+    by_title
+        .into_values()
+        .filter(|shows| shows.len() > 1)
+        .map(|shows| DuplicateTitle {
+            title: shows[0].title.trim().to_string(),
+            paths: shows.iter().map(|s| s.path.clone()).collect(),
+        })
+        .collect()
+}
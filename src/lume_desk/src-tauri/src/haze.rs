@@ -0,0 +1,65 @@
+use std::time::Duration;
+
+use crate::dispatcher::{Dispatcher, OutputAction};
+use crate::manual_control::EffectParams;
+use crate::registry::{ControllerInfo, ControllerRegistry};
+
+/// Haze devices have a single output.
+pub const HAZE_CHANNEL: u32 = 1;
+
+/// Interlock: haze output is never requested for longer than this in one go,
+/// so a lost stop command cannot leave a machine running all night.
+pub const MAX_HAZE_RUN: Duration = Duration::from_secs(300);
+
+/// Turns haze output on at `level` percent for `timeout_secs`, after which
+/// it is switched off again. A level of 0 switches it off immediately.
+pub async fn set_output(dispatcher: &Dispatcher, controller: &ControllerInfo, level: u8, timeout_secs: f64) -> Result<(), String> {
+    if !controller.is_haze() {
+        return Err(format!("{} is not a haze device", controller.address));
+    }
+    if level > 100 {
+        return Err(format!("Haze level must be 0-100, got {}", level));
+    }
+    if level == 0 {
+        return dispatcher.send(&controller.address, HAZE_CHANNEL, &OutputAction::Haze(0)).await;
+    }
+    if !timeout_secs.is_finite() || timeout_secs <= 0.0 {
+        return Err("Haze output needs a positive timeout".to_string());
+    }
+    let timeout = Duration::from_secs_f64(timeout_secs);
+    if timeout > MAX_HAZE_RUN {
+        return Err(format!(
+            "Haze timeout of {:.0}s exceeds the {}s interlock",
+            timeout_secs,
+            MAX_HAZE_RUN.as_secs()
+        ));
+    }
+    dispatcher
+        .hold(&controller.address, HAZE_CHANNEL, OutputAction::Haze(level), OutputAction::Haze(0), timeout)
+        .await
+}
+
+/// Applies a show effect: `level` param (default 100) for `timeout_secs`,
+/// falling back to the effect duration.
+pub async fn apply_effect(
+    dispatcher: &Dispatcher,
+    controller: &ControllerInfo,
+    params: &EffectParams,
+    duration: f64,
+) -> Result<(), String> {
+    let level = params.get("level").and_then(|v| v.as_u64()).unwrap_or(100).min(u8::MAX as u64) as u8;
+    let timeout = params.get("timeout_secs").and_then(|v| v.as_f64()).unwrap_or(duration);
+    set_output(dispatcher, controller, level, timeout).await
+}
+
+/// Switches off every registered haze device, held or not.
+pub async fn all_off(dispatcher: &Dispatcher, registry: &ControllerRegistry) {
+    let Ok(controllers) = registry.list() else {
+        return;
+    };
+    for controller in controllers.iter().filter(|c| c.is_haze()) {
+        if let Err(e) = set_output(dispatcher, controller, 0, 0.0).await {
+            log::warn!("Failed to switch off haze on {}: {}", controller.address, e);
+        }
+    }
+}
@@ -0,0 +1,233 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::{Mutex, MutexGuard};
+use std::time::Duration;
+
+use crate::dispatcher::{Dispatcher, OutputAction};
+use crate::manual_control::EffectParams;
+use crate::registry::ControllerInfo;
+
+/// A point in the laser's scan field, normalized to -1..1 on both axes.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Point {
+    pub x: f64,
+    pub y: f64,
+}
+
+/// Safe-projection zone per laser address. A laser without a zone may not
+/// project anywhere.
+#[derive(Debug, Default)]
+pub struct LaserZones {
+    zones: Mutex<HashMap<String, Vec<Point>>>,
+}
+
+impl LaserZones {
+    fn lock(&self) -> Result<MutexGuard<'_, HashMap<String, Vec<Point>>>, String> {
+        self.zones.lock().map_err(|_| "Laser zones are unavailable".to_string())
+    }
+
+    pub fn set(&self, address: &str, polygon: Vec<Point>) -> Result<(), String> {
+        if polygon.len() < 3 {
+            return Err("A laser zone needs at least 3 points".to_string());
+        }
+        if let Some(p) = polygon.iter().find(|p| !in_field(p)) {
+            return Err(format!("Zone point ({}, {}) is outside the -1..1 scan field", p.x, p.y));
+        }
+        self.lock()?.insert(address.to_string(), polygon);
+        Ok(())
+    }
+
+    /// Checks that every point and segment of the beam path stays inside the
+    /// laser's zone. Paths are never clamped: anything outside is an error.
+    pub fn check(&self, address: &str, beam: &[Point]) -> Result<(), String> {
+        let zones = self.lock()?;
+        let zone = zones
+            .get(address)
+            .ok_or_else(|| format!("No safe-projection zone is configured for {}", address))?;
+        if let Some(p) = beam.iter().find(|p| !in_field(p) || !contains(zone, p)) {
+            return Err(format!("Beam point ({}, {}) is outside the safe zone of {}", p.x, p.y, address));
+        }
+        for pair in beam.windows(2) {
+            if crosses_boundary(zone, pair[0], pair[1]) {
+                return Err(format!(
+                    "Beam path from ({}, {}) to ({}, {}) leaves the safe zone of {}",
+                    pair[0].x, pair[0].y, pair[1].x, pair[1].y, address
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Refuses a laser command whose beam leaves the zone of `address`;
+    /// other actions pass.
+    pub fn admit(&self, address: &str, action: &OutputAction) -> Result<(), String> {
+        match action {
+            OutputAction::Laser(beam) => self.check(address, beam),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Projects a laser effect for `duration` seconds. The dispatcher refuses
+/// the beam if it leaves the safe zone.
+pub async fn project(
+    dispatcher: &Dispatcher,
+    controller: &ControllerInfo,
+    channel: u32,
+    params: &EffectParams,
+    duration: f64,
+) -> Result<(), String> {
+    let beam = beam_points(params)?;
+    if !duration.is_finite() || duration <= 0.0 {
+        return Err(format!("Laser effects need a positive duration, got {}", duration));
+    }
+    dispatcher
+        .hold(
+            &controller.address,
+            channel,
+            OutputAction::Laser(beam),
+            OutputAction::LaserOff,
+            Duration::from_secs_f64(duration),
+        )
+        .await
+}
+
+/// Reads the beam path of a laser effect: `points` as `[[x, y], ...]`, or a
+/// single `x`/`y` position. Effects without geometry cannot be verified and are rejected.
+pub fn beam_points(params: &EffectParams) -> Result<Vec<Point>, String> {
+    if let Some(points) = params.get("points") {
+        let points = points.as_array().ok_or("Laser points must be an array of [x, y] pairs")?;
+        let beam = points
+            .iter()
+            .map(|p| match p.as_array().map(Vec::as_slice) {
+                Some([x, y]) => match (x.as_f64(), y.as_f64()) {
+                    (Some(x), Some(y)) => Ok(Point { x, y }),
+                    _ => Err("Laser points must be numeric".to_string()),
+                },
+                _ => Err("Laser points must be [x, y] pairs".to_string()),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        if beam.is_empty() {
+            return Err("Laser effect has no beam points".to_string());
+        }
+        return Ok(beam);
+    }
+    match (params.get("x").and_then(|v| v.as_f64()), params.get("y").and_then(|v| v.as_f64())) {
+        (Some(x), Some(y)) => Ok(vec![Point { x, y }]),
+        _ => Err("Laser effect has no beam geometry (points or x/y)".to_string()),
+    }
+}
+
+fn in_field(p: &Point) -> bool {
+    p.x.is_finite() && p.y.is_finite() && p.x.abs() <= 1.0 && p.y.abs() <= 1.0
+}
+
+// Ray casting; points exactly on an edge count as inside.
+fn contains(zone: &[Point], p: &Point) -> bool {
+    let mut inside = false;
+    for (i, a) in zone.iter().enumerate() {
+        let b = &zone[(i + 1) % zone.len()];
+        if on_segment(*a, *b, *p) {
+            return true;
+        }
+        if (a.y > p.y) != (b.y > p.y) && p.x < (b.x - a.x) * (p.y - a.y) / (b.y - a.y) + a.x {
+            inside = !inside;
+        }
+    }
+    inside
+}
+
+// True if segment a-b properly crosses any zone edge, i.e. part of it lies
+// outside a non-convex zone even though both ends are inside.
+fn crosses_boundary(zone: &[Point], a: Point, b: Point) -> bool {
+    let midpoint = Point { x: (a.x + b.x) / 2.0, y: (a.y + b.y) / 2.0 };
+    if !contains(zone, &midpoint) {
+        return true;
+    }
+    zone.iter().enumerate().any(|(i, c)| {
+        let d = zone[(i + 1) % zone.len()];
+        let (d1, d2) = (cross(*c, d, a), cross(*c, d, b));
+        let (d3, d4) = (cross(a, b, *c), cross(a, b, d));
+        d1 * d2 < 0.0 && d3 * d4 < 0.0
+    })
+}
+
+fn cross(o: Point, a: Point, b: Point) -> f64 {
+    (a.x - o.x) * (b.y - o.y) - (a.y - o.y) * (b.x - o.x)
+}
+
+fn on_segment(a: Point, b: Point, p: Point) -> bool {
+    cross(a, b, p).abs() < 1e-9
+        && p.x >= a.x.min(b.x) - 1e-9
+        && p.x <= a.x.max(b.x) + 1e-9
+        && p.y >= a.y.min(b.y) - 1e-9
+        && p.y <= a.y.max(b.y) + 1e-9
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn points(list: &[(f64, f64)]) -> Vec<Point> {
+        list.iter().map(|&(x, y)| Point { x, y }).collect()
+    }
+
+    // A U open at the top: the notch spans x -0.2..0.2 down to y 0.
+    fn u_zone() -> Vec<Point> {
+        points(&[(-0.6, -0.6), (0.6, -0.6), (0.6, 0.6), (0.2, 0.6), (0.2, 0.0), (-0.2, 0.0), (-0.2, 0.6), (-0.6, 0.6)])
+    }
+
+    fn zones() -> LaserZones {
+        let zones = LaserZones::default();
+        zones.set("10.0.0.5", u_zone()).unwrap();
+        zones
+    }
+
+    #[test]
+    fn points_inside_outside_and_on_the_edge() {
+        let zone = u_zone();
+        assert!(contains(&zone, &Point { x: -0.4, y: 0.3 }));
+        assert!(!contains(&zone, &Point { x: 0.0, y: 0.3 }));
+        assert!(!contains(&zone, &Point { x: 0.9, y: 0.0 }));
+        assert!(contains(&zone, &Point { x: 0.6, y: 0.1 }));
+        assert!(contains(&zone, &Point { x: 0.0, y: 0.0 }));
+    }
+
+    #[test]
+    fn segment_across_the_notch_leaves_the_zone() {
+        let zone = u_zone();
+        // Both ends are in the arms of the U, the middle is in the notch.
+        let (a, b) = (Point { x: -0.4, y: 0.3 }, Point { x: 0.4, y: 0.3 });
+        assert!(contains(&zone, &a) && contains(&zone, &b));
+        assert!(crosses_boundary(&zone, a, b));
+        assert!(!crosses_boundary(&zone, Point { x: -0.4, y: -0.3 }, Point { x: 0.4, y: -0.3 }));
+    }
+
+    #[test]
+    fn segment_through_a_reflex_vertex() {
+        let zone = u_zone();
+        // Through the inner corner (0.2, 0.0), across the notch and into the left arm.
+        assert!(crosses_boundary(&zone, Point { x: 0.4, y: -0.2 }, Point { x: -0.3, y: 0.5 }));
+        // Through the same corner from the right arm to the base, staying inside.
+        assert!(!crosses_boundary(&zone, Point { x: 0.4, y: 0.2 }, Point { x: 0.0, y: -0.2 }));
+    }
+
+    #[test]
+    fn laser_without_a_zone_is_refused() {
+        let zones = zones();
+        let beam = points(&[(0.0, -0.3)]);
+        assert!(zones.check("10.0.0.5", &beam).is_ok());
+        assert!(zones.check("10.0.0.6", &beam).unwrap_err().contains("No safe-projection zone"));
+        assert!(zones.set("10.0.0.6", points(&[(0.0, 0.0), (0.5, 0.5)])).is_err());
+    }
+
+    #[test]
+    fn out_of_zone_laser_commands_are_refused() {
+        let zones = zones();
+        let across = OutputAction::Laser(points(&[(-0.4, 0.3), (0.4, 0.3)]));
+        assert!(zones.admit("10.0.0.5", &across).unwrap_err().contains("leaves the safe zone"));
+        assert!(zones.admit("10.0.0.5", &OutputAction::Laser(points(&[(-0.4, 0.3), (-0.4, -0.3)]))).is_ok());
+        assert!(zones.admit("10.0.0.5", &OutputAction::Laser(points(&[(1.5, 0.0)]))).is_err());
+        assert!(zones.admit("10.0.0.5", &OutputAction::LaserOff).is_ok());
+    }
+}
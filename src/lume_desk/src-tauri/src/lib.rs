@@ -1,4 +1,76 @@
+use tauri::Manager;
+
+mod audio;
+mod audio_analysis;
+mod audit;
+mod audit_report;
+mod backup;
+mod batch;
+#[cfg(feature = "ble")]
+mod ble;
+mod command_log;
 mod commands;
+mod control_lock;
+mod controller_client;
+mod controller_upload;
+mod crossfade;
+mod cue_sheet;
+mod diagnostics;
+mod discovery;
+mod dispatcher;
+mod dmx;
+mod driver;
+mod edit_ops;
+mod events;
+mod export;
+mod fleet;
+mod folder_import;
+mod haze;
+mod laser;
+mod live_edit;
+mod lume_format;
+mod manual_control;
+mod mdns;
+#[cfg(test)]
+mod mock_controller;
+mod models;
+mod monitor_window;
+mod mqtt;
+mod network;
+mod network_map;
+mod network_watch;
+mod output_refresh;
+mod palette;
+mod performance;
+mod power;
+mod preflight;
+mod protocols;
+mod ramp;
+mod readback;
+mod registry;
+mod registry_check;
+mod relay;
+mod response_cache;
+mod rig_test;
+mod safe_mode;
+mod safety;
+mod schedule;
+mod serial;
+mod show_engine;
+mod show_output;
+mod show_recovery;
+mod show_schema;
+mod show_stats;
+mod show_store;
+mod shutdown;
+mod thumbnail;
+mod timecode;
+mod time_format;
+mod transport;
+mod trigger;
+mod validation;
+mod waveform;
+mod zones;
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
@@ -12,19 +84,241 @@ pub fn run() {
     .plugin(tauri_plugin_os::init())
     .plugin(tauri_plugin_process::init())
     .plugin(tauri_plugin_window_state::Builder::default().build())
+    .plugin(tauri_plugin_global_shortcut::Builder::new().build())
+    .manage(show_store::ShowStore::default())
+    .manage(registry::ControllerRegistry::default())
+    .manage(safety::Safety::default())
+    .manage(show_engine::ShowEngine::default())
+    .manage(discovery::Discovery::default())
+    .manage(audit::AuditLog::default())
+    .manage(thumbnail::ThumbnailCache::default())
+    .manage(laser::LaserZones::default())
+    .manage(shutdown::CloseGuard::default())
+    .manage(output_refresh::OutputRefresh::default())
+    .manage(trigger::ExternalTrigger::default())
+    .manage(control_lock::ControlLock::default())
+    .manage(preflight::SelfTestControl::default())
+    .manage(schedule::ShowScheduler::default())
+    .manage(power::PowerMonitor::default())
+    .manage(performance::PerformanceRecorder::default())
+    .manage(controller_upload::ControllerUploads::default())
+    .manage(dmx::DmxOutput::default())
+    .manage(protocols::osc::Osc::default())
+    .manage(mqtt::Mqtt::default())
+    .manage(audio::AudioPlayer::default())
+    .manage(timecode::TimecodeChase::default())
     .invoke_handler(tauri::generate_handler![
       commands::start_show,
+      commands::crossfade_to_show,
+      commands::schedule_recurring,
+      commands::list_schedules,
+      commands::remove_schedule,
+      commands::try_parse_show,
       commands::stop_show,
+      commands::pause_show,
+      commands::resume_show,
+      commands::set_show_error_policy,
+      commands::rewind_show,
+      commands::seek_show,
+      commands::skip_to_end,
+      commands::apply_live_edit,
+      commands::acquire_control,
+      commands::release_control,
+      commands::get_control_owner,
       commands::get_show_status,
+      commands::set_time_display,
+      commands::get_time_display,
+      commands::report_sync_position,
+      commands::set_engine_tick_rate,
+      commands::load_comparison,
+      commands::switch_comparison,
+      commands::get_event_schema,
       commands::get_system_info,
       commands::scan_controllers,
+      commands::scan_ble_controllers,
+      commands::set_discovery_interval,
+      commands::set_broadcast_discovery,
       commands::test_controller_connection,
+      commands::set_transport_timeout,
+      commands::set_output_refresh_rate,
+      commands::get_output_refresh_rates,
+      commands::add_controller,
+      commands::remove_controller,
+      commands::update_controller_config,
+      commands::list_controllers,
+      commands::check_registry_consistency,
+      commands::favorite_controller,
+      commands::set_controller_enabled,
+      commands::resolve_controller,
+      commands::get_controller_network_info,
+      commands::get_controller_capabilities,
+      commands::invalidate_controller_cache,
+      commands::set_controller_cache_ttl,
+      commands::get_controller_command_log,
+      commands::set_command_log_size,
+      commands::set_soft_start,
+      commands::set_safe_default,
+      commands::set_controller_static_ip,
+      commands::upload_show_to_controller,
+      commands::verify_uploaded_show,
+      commands::trigger_controller_playback,
+      commands::identify_controller,
+      commands::query_fleet,
+      commands::build_network_map,
+      commands::read_controller_state,
+      commands::pulse_relay,
+      commands::test_controllers,
+      commands::emergency_stop,
+      commands::create_zone,
+      commands::delete_zone,
+      commands::list_zones,
+      commands::blackout_zone,
+      commands::clear_blackout,
+      commands::isolate_zone,
+      commands::clear_isolation,
+      commands::test_zone,
+      commands::set_zone_muted,
+      commands::get_dmx_config,
+      commands::set_artnet_universe,
+      commands::set_sacn_universe,
+      commands::set_sacn_sync,
+      commands::remove_dmx_universe,
+      commands::patch_dmx_channel,
+      commands::unpatch_dmx_channel,
+      commands::arm_system,
+      commands::disarm_system,
+      commands::get_arm_state,
+      commands::confirm_close,
+      commands::configure_trigger,
+      commands::arm_trigger,
+      commands::disarm_trigger,
+      commands::get_trigger_status,
+      commands::fire_trigger,
+      commands::configure_osc,
+      commands::map_osc_address,
+      commands::get_osc_config,
+      commands::connect_mqtt,
+      commands::disconnect_mqtt,
+      commands::get_mqtt_status,
+      commands::get_controller_telemetry,
+      commands::load_audio,
+      commands::set_audio_device,
+      commands::list_audio_devices,
+      commands::get_audio_status,
+      commands::set_sync_mode,
+      commands::get_sync_status,
+      commands::list_midi_ports,
+      commands::trigger_effect_now,
+      commands::flash_effect,
+      commands::release_flash,
+      commands::set_haze_output,
+      commands::set_channel_muted,
+      commands::get_audit_log,
+      commands::export_audit_log_pdf,
       commands::export_show,
+      commands::export_show_file,
+      commands::export_show_multi,
+      commands::export_show_schema,
+      commands::export_cue_sheet,
       commands::validate_show_data,
+      commands::set_laser_zone,
+      commands::load_show,
+      commands::upsert_effect,
+      commands::remove_effect,
+      commands::set_effect_enabled,
+      commands::create_layer,
+      commands::assign_effect_to_layer,
+      commands::set_layer_visible,
+      commands::set_layer_muted,
+      commands::save_show,
+      commands::save_show_delta,
+      commands::set_backup_retention,
+      commands::list_backups,
+      commands::restore_backup,
+      commands::import_show,
+      commands::import_show_folder,
+      commands::get_show_output_config,
+      commands::set_show_output_config,
+      commands::render_show_thumbnail,
+      commands::analyze_audio_waveform,
+      commands::analyze_audio,
+      commands::undo_show_edit,
+      commands::redo_show_edit,
+      commands::quantize_show,
+      commands::generate_ripple,
+      commands::randomize_effects,
+      commands::add_palette_color,
+      commands::resolve_palette,
+      commands::get_show_stats,
+      commands::preflight_show,
+      commands::cancel_self_test,
+      commands::list_displays,
+      commands::open_monitor_window,
+      commands::close_monitor_window,
       commands::send_system_notification,
-      commands::get_performance_stats
+      commands::is_safe_mode,
+      commands::enter_safe_mode,
+      commands::get_performance_stats,
+      commands::get_performance_history,
+      commands::set_performance_sampling,
+      commands::get_controller_stats,
+      commands::set_power_monitoring,
+      commands::set_adaptive_performance,
+      commands::generate_diagnostic_report
     ])
+    .on_window_event(shutdown::on_window_event)
     .setup(|app| {
+      if safe_mode::requested_at_launch() {
+        safe_mode::enter("requested at launch");
+      }
+      app.manage(dispatcher::Dispatcher::new(app.handle().clone()));
+      // Safe mode keeps the stop: it only makes things safe.
+      safety::register_emergency_shortcut(app.handle());
+      app.state::<performance::PerformanceRecorder>().start(app.handle());
+      app.state::<discovery::Discovery>().start(app.handle())?;
+      if !safe_mode::is_active() {
+        network_watch::start(app.handle());
+      }
+      let config_dir = app.path().app_config_dir()?;
+      let registry = app.state::<registry::ControllerRegistry>();
+      if safe_mode::is_active() {
+        // A broken config is the usual reason for safe mode; boot without it.
+        if let Err(e) = registry.load_controllers(config_dir.join("controllers.json")) {
+          log::warn!("Controllers not loaded in safe mode: {}", e);
+        }
+        if let Err(e) = registry.load_zones(config_dir.join("zones.json")) {
+          log::warn!("Zones not loaded in safe mode: {}", e);
+        }
+        if let Err(e) = app.state::<dmx::DmxOutput>().load(config_dir.join("dmx.json")) {
+          log::warn!("DMX patch not loaded in safe mode: {}", e);
+        }
+        if let Err(e) = app.state::<protocols::osc::Osc>().load(config_dir.join("osc.json")) {
+          log::warn!("OSC config not loaded in safe mode: {}", e);
+        }
+        if let Err(e) = app.state::<mqtt::Mqtt>().load(config_dir.join("mqtt.json")) {
+          log::warn!("MQTT broker not loaded in safe mode: {}", e);
+        }
+      } else {
+        registry.load_controllers(config_dir.join("controllers.json"))?;
+        registry.load_zones(config_dir.join("zones.json"))?;
+        app.state::<dmx::DmxOutput>().load(config_dir.join("dmx.json"))?;
+        let osc = app.state::<protocols::osc::Osc>();
+        osc.load(config_dir.join("osc.json"))?;
+        // Shows cannot be started in safe mode, so there is nothing to listen for.
+        osc.start(app.handle());
+        let mqtt = app.state::<mqtt::Mqtt>();
+        mqtt.load(config_dir.join("mqtt.json"))?;
+        mqtt.start(app.handle());
+      }
+      let scheduler = app.state::<schedule::ShowScheduler>();
+      match scheduler.load(config_dir.join("schedules.json")) {
+        // Scheduled shows could not start in safe mode anyway.
+        Ok(()) if !safe_mode::is_active() => scheduler.start(app.handle()),
+        Ok(()) => {}
+        Err(e) if safe_mode::is_active() => log::warn!("Schedules not loaded in safe mode: {}", e),
+        Err(e) => return Err(e.into()),
+      }
+
       if cfg!(debug_assertions) {
         app.handle().plugin(
           tauri_plugin_log::Builder::default()
@@ -0,0 +1,200 @@
+use serde::Deserialize;
+use std::collections::{HashMap, HashSet};
+
+use crate::models::{Effect, Show};
+
+/// A change to a show while it plays.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum LiveEdit {
+    /// Retimes a cue, which may move it past others.
+    Move { effect_id: String, start_time: f64 },
+    SetDuration { effect_id: String, duration: f64 },
+    /// Replaces the cue's params.
+    SetParams {
+        effect_id: String,
+        params: HashMap<String, serde_json::Value>,
+    },
+    Remove { effect_id: String },
+    Add { effect: Effect },
+}
+
+/// Where a running show stands when a live edit is judged.
+#[derive(Debug, Clone)]
+pub struct Playhead {
+    pub time: f64,
+    /// Effects fired since the playhead last moved, still running or not.
+    pub fired: HashSet<String>,
+}
+
+impl Playhead {
+    // An effect is upcoming only while it starts strictly after the
+    // playhead: one starting exactly there is due on this tick.
+    fn check_upcoming(&self, effect: &Effect) -> Result<(), String> {
+        if self.fired.contains(&effect.id) {
+            return Err(format!("Effect {} has already fired", effect.id));
+        }
+        if effect.start_time <= self.time {
+            return Err(format!(
+                "Effect {} starts at {:.3}s, not after the playhead at {:.3}s",
+                effect.id, effect.start_time, self.time
+            ));
+        }
+        Ok(())
+    }
+
+    fn check_start(&self, show: &Show, start_time: f64, duration: f64) -> Result<(), String> {
+        if !start_time.is_finite() || start_time <= self.time {
+            return Err(format!("Cues can only be placed after the playhead at {:.3}s", self.time));
+        }
+        if show.total_duration > 0.0 && start_time + duration.max(0.0) > show.total_duration {
+            return Err(format!("The cue would end after the show ends at {:.3}s", show.total_duration));
+        }
+        Ok(())
+    }
+}
+
+/// Applies `edit` to `show`, touching only effects that have not started
+/// by `playhead`. Fired and running effects are refused, as is placing a
+/// cue at or before the playhead, where it would never fire.
+pub fn apply(show: &mut Show, edit: &LiveEdit, playhead: &Playhead) -> Result<(), String> {
+    match edit {
+        LiveEdit::Add { effect } => {
+            if show.effects.iter().any(|e| e.id == effect.id) {
+                return Err(format!("Effect {} already exists", effect.id));
+            }
+            playhead.check_start(show, effect.start_time, effect.duration)?;
+            show.effects.push(effect.clone());
+        }
+        LiveEdit::Move { effect_id, start_time } => {
+            let index = upcoming(show, effect_id, playhead)?;
+            playhead.check_start(show, *start_time, show.effects[index].duration)?;
+            show.effects[index].start_time = *start_time;
+        }
+        LiveEdit::SetDuration { effect_id, duration } => {
+            let index = upcoming(show, effect_id, playhead)?;
+            if !duration.is_finite() || *duration < 0.0 {
+                return Err(format!("Duration must be zero or positive, got {}", duration));
+            }
+            playhead.check_start(show, show.effects[index].start_time, *duration)?;
+            show.effects[index].duration = *duration;
+        }
+        LiveEdit::SetParams { effect_id, params } => {
+            let index = upcoming(show, effect_id, playhead)?;
+            show.effects[index].params = params.clone();
+        }
+        LiveEdit::Remove { effect_id } => {
+            let index = upcoming(show, effect_id, playhead)?;
+            show.effects.remove(index);
+        }
+    }
+    Ok(())
+}
+
+fn upcoming(show: &Show, effect_id: &str, playhead: &Playhead) -> Result<usize, String> {
+    let index = show
+        .effects
+        .iter()
+        .position(|e| e.id == effect_id)
+        .ok_or_else(|| format!("Effect {} not found", effect_id))?;
+    playhead.check_upcoming(&show.effects[index])?;
+    Ok(index)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn show() -> Show {
+        serde_json::from_value(json!({
+            "id": "show",
+            "name": "Test",
+            "total_duration": 20.0,
+            "effects": [
+                { "id": "early", "start_time": 2.0, "duration": 1.0, "controller": "10.0.0.5", "channel": 1 },
+                { "id": "boundary", "start_time": 5.0, "duration": 1.0, "controller": "10.0.0.5", "channel": 2 },
+                { "id": "late", "start_time": 8.0, "duration": 1.0, "controller": "10.0.0.5", "channel": 3 }
+            ]
+        }))
+        .unwrap()
+    }
+
+    fn playhead(time: f64, fired: &[&str]) -> Playhead {
+        Playhead {
+            time,
+            fired: fired.iter().map(|id| id.to_string()).collect(),
+        }
+    }
+
+    fn move_to(effect_id: &str, start_time: f64) -> LiveEdit {
+        LiveEdit::Move {
+            effect_id: effect_id.to_string(),
+            start_time,
+        }
+    }
+
+    #[test]
+    fn effect_starting_exactly_at_playhead_is_refused() {
+        let mut show = show();
+        let error = apply(&mut show, &move_to("boundary", 9.0), &playhead(5.0, &["early"])).unwrap_err();
+        assert!(error.contains("not after the playhead"), "{}", error);
+        assert_eq!(show.effects[1].start_time, 5.0);
+    }
+
+    #[test]
+    fn effect_starting_just_after_playhead_can_be_edited() {
+        let mut show = show();
+        apply(&mut show, &move_to("boundary", 9.0), &playhead(4.999, &["early"])).unwrap();
+        assert_eq!(show.effects[1].start_time, 9.0);
+    }
+
+    #[test]
+    fn cue_cannot_be_moved_onto_the_playhead() {
+        let mut show = show();
+        assert!(apply(&mut show, &move_to("late", 5.0), &playhead(5.0, &[])).is_err());
+        apply(&mut show, &move_to("late", 5.001), &playhead(5.0, &[])).unwrap();
+        assert_eq!(show.effects[2].start_time, 5.001);
+    }
+
+    #[test]
+    fn fired_effect_is_refused_even_if_clock_stepped_back() {
+        let mut show = show();
+        let error = apply(&mut show, &move_to("boundary", 9.0), &playhead(4.99, &["boundary"])).unwrap_err();
+        assert!(error.contains("already fired"), "{}", error);
+    }
+
+    #[test]
+    fn running_effect_is_refused() {
+        let mut show = show();
+        let edit = LiveEdit::SetDuration {
+            effect_id: "early".to_string(),
+            duration: 0.5,
+        };
+        assert!(apply(&mut show, &edit, &playhead(2.5, &["early"])).is_err());
+        assert_eq!(show.effects[0].duration, 1.0);
+    }
+
+    #[test]
+    fn added_cue_must_start_after_playhead() {
+        let mut show = show();
+        let mut effect = show.effects[2].clone();
+        effect.id = "new".to_string();
+        effect.start_time = 5.0;
+        let edit = LiveEdit::Add { effect: effect.clone() };
+        assert!(apply(&mut show, &edit, &playhead(5.0, &[])).is_err());
+        effect.start_time = 5.5;
+        apply(&mut show, &LiveEdit::Add { effect }, &playhead(5.0, &[])).unwrap();
+        assert_eq!(show.effects.len(), 4);
+    }
+
+    #[test]
+    fn upcoming_cue_can_be_removed() {
+        let mut show = show();
+        let edit = LiveEdit::Remove {
+            effect_id: "late".to_string(),
+        };
+        apply(&mut show, &edit, &playhead(5.0, &["early", "boundary"])).unwrap();
+        assert!(show.effects.iter().all(|e| e.id != "late"));
+    }
+}
@@ -0,0 +1,189 @@
+use serde::Serialize;
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::collections::BTreeSet;
+
+use crate::audit::now_millis;
+use crate::models::Show;
+
+/// Revision of the native .lume container. Version 1 files were a bare
+/// snapshot (`{"snapshot_id", "show"}`) with no header.
+pub const LUME_FORMAT_VERSION: u32 = 2;
+
+const FORMAT_NAME: &str = "lume";
+
+/// Summary of the show stored in the header, readable without parsing the
+/// show itself.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LumeMetadata {
+    pub name: String,
+    pub description: String,
+    pub total_duration: f64,
+    pub effect_count: usize,
+    pub controllers: Vec<String>,
+    /// Unix milliseconds the file was written.
+    pub saved_at: u64,
+    /// Version of the app that wrote the file.
+    pub app_version: String,
+}
+
+// Field order is the order on disk; the show comes last so a truncated
+// file still has its header.
+#[derive(Serialize)]
+struct Header<'a> {
+    format: &'static str,
+    format_version: u32,
+    metadata: LumeMetadata,
+    /// "sha256:" and the hex digest of the show JSON.
+    checksum: String,
+    snapshot_id: &'a str,
+    show: &'a Value,
+}
+
+/// A decoded .lume file.
+#[derive(Debug)]
+pub struct LumeFile {
+    pub snapshot_id: String,
+    pub show: Show,
+    /// The format version the file was written in, when older than current.
+    pub upgraded_from: Option<u32>,
+}
+
+// Maps in serde_json values are sorted by key, so the digest does not
+// depend on the order the show's maps were written in.
+fn checksum(show: &Value) -> Result<String, String> {
+    let data = serde_json::to_vec(show).map_err(|e| e.to_string())?;
+    let digest = Sha256::digest(&data);
+    let hex: String = digest.iter().map(|b| format!("{:02x}", b)).collect();
+    Ok(format!("sha256:{}", hex))
+}
+
+fn metadata(show: &Show) -> LumeMetadata {
+    let controllers: BTreeSet<&str> = show.effects.iter().map(|e| e.controller.as_str()).collect();
+    LumeMetadata {
+        name: show.name.clone(),
+        description: show.description.clone(),
+        total_duration: show.total_duration,
+        effect_count: show.effects.len(),
+        controllers: controllers.into_iter().map(str::to_string).collect(),
+        saved_at: now_millis(),
+        app_version: env!("CARGO_PKG_VERSION").to_string(),
+    }
+}
+
+/// Encodes `show` as a current-version .lume file.
+pub fn encode(show: &Show, snapshot_id: &str) -> Result<Vec<u8>, String> {
+    let value = serde_json::to_value(show).map_err(|e| e.to_string())?;
+    let header = Header {
+        format: FORMAT_NAME,
+        format_version: LUME_FORMAT_VERSION,
+        metadata: metadata(show),
+        checksum: checksum(&value)?,
+        snapshot_id,
+        show: &value,
+    };
+    serde_json::to_vec(&header).map_err(|e| e.to_string())
+}
+
+/// Decodes a .lume file of any version this build knows, upgrading older
+/// ones. `verify` checks the show against the header's checksum; a file
+/// repaired after truncation cannot match it.
+pub fn decode(mut file: Value, verify: bool) -> Result<LumeFile, String> {
+    let version = match file.get("format_version") {
+        None => 1,
+        Some(version) => version
+            .as_u64()
+            .and_then(|v| u32::try_from(v).ok())
+            .ok_or_else(|| format!("Invalid format version {}", version))?,
+    };
+    if version > LUME_FORMAT_VERSION {
+        return Err(format!(
+            "File is format version {}, written by a newer LUME; this version reads up to {}",
+            version, LUME_FORMAT_VERSION
+        ));
+    }
+    if version >= 2 && file.get("format").and_then(Value::as_str) != Some(FORMAT_NAME) {
+        return Err("Not a LUME show file".to_string());
+    }
+    let snapshot_id = file
+        .get("snapshot_id")
+        .and_then(Value::as_str)
+        .ok_or_else(|| "Show file has no snapshot id".to_string())?
+        .to_string();
+    let show = file
+        .get_mut("show")
+        .map(Value::take)
+        .ok_or_else(|| "Show file has no show".to_string())?;
+
+    if verify && version >= 2 {
+        let expected = file.get("checksum").and_then(Value::as_str).unwrap_or_default();
+        if checksum(&show)? != expected {
+            return Err("Show file is damaged: its checksum does not match".to_string());
+        }
+    }
+
+    // Version 1 held the same show JSON, only without the header, so the
+    // show itself needs no changes yet. Later upgrades rewrite `show` here.
+    let show: Show = serde_json::from_value(show).map_err(|e| e.to_string())?;
+    Ok(LumeFile {
+        snapshot_id,
+        show,
+        upgraded_from: (version < LUME_FORMAT_VERSION).then_some(version),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn show() -> Show {
+        serde_json::from_value(json!({
+            "id": "s1",
+            "name": "Finale",
+            "total_duration": 12.5,
+            "effects": [
+                { "id": "e1", "start_time": 1.0, "controller": "10.0.0.2", "channel": 3, "params": { "b": 1, "a": 2 } },
+                { "id": "e2", "start_time": 2.0, "controller": "10.0.0.1", "channel": 1 },
+            ],
+        }))
+        .unwrap()
+    }
+
+    fn parse(data: &[u8]) -> Value {
+        serde_json::from_slice(data).unwrap()
+    }
+
+    #[test]
+    fn round_trips_with_header() {
+        let data = parse(&encode(&show(), "snap").unwrap());
+        assert_eq!(data["metadata"]["effect_count"], 2);
+        assert_eq!(data["metadata"]["controllers"], json!(["10.0.0.1", "10.0.0.2"]));
+        let file = decode(data, true).unwrap();
+        assert_eq!(file.show, show());
+        assert_eq!(file.snapshot_id, "snap");
+        assert_eq!(file.upgraded_from, None);
+    }
+
+    #[test]
+    fn detects_a_changed_show() {
+        let mut file = parse(&encode(&show(), "snap").unwrap());
+        file["show"]["name"] = json!("Edited");
+        assert!(decode(file.clone(), true).unwrap_err().contains("checksum"));
+        assert_eq!(decode(file, false).unwrap().show.name, "Edited");
+    }
+
+    #[test]
+    fn upgrades_a_headerless_snapshot() {
+        let file = decode(json!({ "snapshot_id": "old", "show": show() }), true).unwrap();
+        assert_eq!(file.upgraded_from, Some(1));
+        assert_eq!(file.show, show());
+    }
+
+    #[test]
+    fn refuses_newer_versions() {
+        let mut file = parse(&encode(&show(), "snap").unwrap());
+        file["format_version"] = json!(LUME_FORMAT_VERSION + 1);
+        assert!(decode(file, true).unwrap_err().contains("newer"));
+    }
+}
@@ -0,0 +1,77 @@
+use crate::audit::{AuditKind, AuditLog};
+use crate::dispatcher::{lighting_actions, Dispatcher, OutputAction};
+use crate::haze;
+use crate::laser;
+use crate::registry::ControllerInfo;
+use crate::safety::Safety;
+use std::collections::HashMap;
+use std::time::Duration;
+
+/// A flash is released automatically after this long in case the release
+/// never arrives (e.g. the webview froze while the button was held).
+const MAX_FLASH_HOLD: Duration = Duration::from_secs(30);
+
+pub type EffectParams = HashMap<String, serde_json::Value>;
+
+/// Fires an ad-hoc effect without touching the show. Pyro channels fire
+/// once and need the system armed; lighting channels are held for `duration`.
+pub async fn trigger_now(
+    dispatcher: &Dispatcher,
+    safety: &Safety,
+    audit: &AuditLog,
+    controller: &ControllerInfo,
+    channel: u32,
+    params: &EffectParams,
+    duration: f64,
+) -> Result<(), String> {
+    let result = if controller.is_pyro() {
+        match safety.require_armed() {
+            Ok(()) => dispatcher.send(&controller.address, channel, &OutputAction::Fire).await,
+            Err(e) => Err(e),
+        }
+    } else if controller.is_haze() {
+        haze::apply_effect(dispatcher, controller, params, duration).await
+    } else if controller.is_laser() {
+        laser::project(dispatcher, controller, channel, params, duration).await
+    } else if !duration.is_finite() || duration <= 0.0 {
+        Err(format!("Duration must be positive, got {}", duration))
+    } else {
+        let (on, off) = lighting_actions(params);
+        dispatcher
+            .hold(&controller.address, channel, on, off, Duration::from_secs_f64(duration))
+            .await
+    };
+
+    audit.record(
+        AuditKind::ManualOverride,
+        controller,
+        channel,
+        &result,
+        format!("Manual override for {:.2}s {}", duration, serde_json::to_string(params).unwrap_or_default()),
+    );
+    result
+}
+
+/// Starts a momentary flash on a lighting channel; call `release` to clear it.
+pub async fn flash(
+    dispatcher: &Dispatcher,
+    audit: &AuditLog,
+    controller: &ControllerInfo,
+    channel: u32,
+    params: &EffectParams,
+) -> Result<(), String> {
+    let result = if controller.is_pyro() || controller.is_haze() || controller.is_laser() {
+        Err("Flash is only available on lighting outputs".to_string())
+    } else {
+        let (on, off) = lighting_actions(params);
+        dispatcher.hold(&controller.address, channel, on, off, MAX_FLASH_HOLD).await
+    };
+    audit.record(AuditKind::Flash, controller, channel, &result, "Flash pressed".to_string());
+    result
+}
+
+pub async fn release(dispatcher: &Dispatcher, audit: &AuditLog, controller: &ControllerInfo, channel: u32) -> Result<(), String> {
+    let result = dispatcher.release(&controller.address, channel).await;
+    audit.record(AuditKind::FlashRelease, controller, channel, &result, "Flash released".to_string());
+    result
+}
@@ -0,0 +1,68 @@
+use mdns_sd::{ServiceDaemon, ServiceEvent, ServiceInfo};
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::time::Duration;
+
+/// The service LUME controller firmware advertises.
+pub const LUME_SERVICE: &str = "_lume._tcp.local.";
+
+/// One advertised instance of a service.
+#[derive(Debug, Clone, Default)]
+pub struct ServiceInstance {
+    /// "Stage Left" for "Stage Left._lume._tcp.local.".
+    pub name: String,
+    /// Host the SRV record points at, without the trailing dot.
+    pub host: Option<String>,
+    pub ip: Option<IpAddr>,
+    pub port: u16,
+    /// TXT record entries; keys are lowercased.
+    pub txt: HashMap<String, String>,
+}
+
+/// Browses for `service` and collects the instances resolved within
+/// `window`. The responder runs only for the length of the browse, so
+/// nothing holds port 5353 between discovery passes.
+pub async fn browse(service: &str, window: Duration) -> Result<Vec<ServiceInstance>, String> {
+    let daemon = ServiceDaemon::new().map_err(|e| format!("Failed to start mDNS: {}", e))?;
+    let events = daemon
+        .browse(service)
+        .map_err(|e| format!("Failed to send mDNS query: {}", e))?;
+
+    let mut instances: HashMap<String, ServiceInstance> = HashMap::new();
+    let deadline = tokio::time::Instant::now() + window;
+    while let Ok(Ok(event)) = tokio::time::timeout_at(deadline, events.recv_async()).await {
+        if let ServiceEvent::ServiceResolved(info) = event {
+            instances.insert(info.get_fullname().to_lowercase(), instance(service, &info));
+        }
+    }
+    // The daemon confirms on a channel nobody needs to wait on.
+    if let Err(e) = daemon.shutdown() {
+        log::debug!("mDNS did not shut down cleanly: {}", e);
+    }
+
+    let mut found: Vec<ServiceInstance> = instances.into_values().collect();
+    found.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(found)
+}
+
+// IPv4 addresses first, as the controllers' HTTP servers are IPv4 only.
+fn instance(service: &str, info: &ServiceInfo) -> ServiceInstance {
+    let fullname = info.get_fullname();
+    let name = fullname
+        .strip_suffix(service)
+        .and_then(|name| name.strip_suffix('.'))
+        .unwrap_or(fullname);
+    let addresses = info.get_addresses();
+    let host = info.get_hostname().trim_end_matches('.');
+    ServiceInstance {
+        name: name.to_string(),
+        host: (!host.is_empty()).then(|| host.to_string()),
+        ip: addresses.iter().find(|ip| ip.is_ipv4()).or_else(|| addresses.iter().next()).copied(),
+        port: info.get_port(),
+        txt: info
+            .get_properties()
+            .iter()
+            .map(|property| (property.key().to_lowercase(), property.val_str().to_string()))
+            .collect(),
+    }
+}
@@ -0,0 +1,221 @@
+use serde_json::{json, Value};
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::task::JoinHandle;
+
+use crate::registry::ControllerInfo;
+
+/// How a mock controller answers one request.
+#[derive(Debug, Clone)]
+pub enum Reply {
+    /// 200 with this JSON body.
+    Json(Value),
+    /// An error status with an empty body.
+    Status(u16),
+    /// The inner reply, sent only after the delay.
+    Delayed(Duration, Box<Reply>),
+    /// Closes the connection without answering, like a lost packet.
+    Drop,
+}
+
+impl Reply {
+    pub fn ok() -> Self {
+        Reply::Json(json!({}))
+    }
+
+    pub fn delayed(delay: Duration, reply: Reply) -> Self {
+        Reply::Delayed(delay, Box::new(reply))
+    }
+}
+
+/// One request as the mock controller received it.
+#[derive(Debug, Clone)]
+pub struct Received {
+    pub method: String,
+    /// Path and query, e.g. `/channel?id=3`.
+    pub path: String,
+    pub body: Vec<u8>,
+    pub at: Instant,
+}
+
+#[derive(Default)]
+struct Script {
+    received: Vec<Received>,
+    // Replies for paths starting with the prefix, used up in order.
+    queued: Vec<(String, VecDeque<Reply>)>,
+    // Overrides for paths starting with the prefix once nothing is queued.
+    fallbacks: Vec<(String, Reply)>,
+}
+
+impl Script {
+    fn reply(&mut self, path: &str) -> Reply {
+        let queued = self
+            .queued
+            .iter_mut()
+            .find(|(prefix, replies)| path.starts_with(prefix.as_str()) && !replies.is_empty());
+        if let Some((_, replies)) = queued {
+            return replies.pop_front().unwrap_or_else(Reply::ok);
+        }
+        self.fallbacks
+            .iter()
+            .rev()
+            .find(|(prefix, _)| path.starts_with(prefix.as_str()))
+            .map_or_else(Reply::ok, |(_, reply)| reply.clone())
+    }
+}
+
+/// A controller for tests: a local HTTP server that records every request
+/// and answers `{}` unless scripted to fail, stall or drop. Stops when
+/// dropped.
+pub struct MockController {
+    pub address: String,
+    script: Arc<Mutex<Script>>,
+    server: JoinHandle<()>,
+}
+
+impl MockController {
+    pub async fn start() -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").await.expect("bind mock controller");
+        let address = listener.local_addr().expect("mock controller address").to_string();
+        let script = Arc::new(Mutex::new(Script::default()));
+        let shared = script.clone();
+        let server = tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                tokio::spawn(serve(stream, shared.clone()));
+            }
+        });
+        Self { address, script, server }
+    }
+
+    /// The registry entry a test passes to the code under test.
+    pub fn controller(&self, controller_type: &str) -> ControllerInfo {
+        serde_json::from_value(json!({
+            "address": self.address,
+            "controller_type": controller_type,
+        }))
+        .expect("mock controller info")
+    }
+
+    /// Answers the next requests to paths starting with `prefix` with
+    /// `replies`, in order, after any already queued for it.
+    pub fn script(&self, prefix: &str, replies: impl IntoIterator<Item = Reply>) {
+        let mut script = self.script.lock().unwrap();
+        match script.queued.iter_mut().find(|(p, _)| p == prefix) {
+            Some((_, queued)) => queued.extend(replies),
+            None => script.queued.push((prefix.to_string(), replies.into_iter().collect())),
+        }
+    }
+
+    /// Answers every request to paths starting with `prefix` with `reply`
+    /// once nothing scripted is left; later calls take precedence.
+    pub fn always(&self, prefix: &str, reply: Reply) {
+        self.script.lock().unwrap().fallbacks.push((prefix.to_string(), reply));
+    }
+
+    pub fn received(&self) -> Vec<Received> {
+        self.script.lock().unwrap().received.clone()
+    }
+
+    pub fn paths(&self) -> Vec<String> {
+        self.received().into_iter().map(|r| r.path).collect()
+    }
+
+    /// Requests to paths starting with `prefix`.
+    pub fn count(&self, prefix: &str) -> usize {
+        self.paths().iter().filter(|p| p.starts_with(prefix)).count()
+    }
+
+    /// Asserts the exact stream of request paths received so far.
+    #[track_caller]
+    pub fn assert_paths(&self, expected: &[&str]) {
+        assert_eq!(self.paths(), expected, "requests received by {}", self.address);
+    }
+
+    /// Asserts each request path starts with the matching prefix, which is
+    /// enough where the tail carries ids, e.g. fire sequence numbers.
+    #[track_caller]
+    pub fn assert_path_prefixes(&self, expected: &[&str]) {
+        let paths = self.paths();
+        let matches = paths.len() == expected.len() && paths.iter().zip(expected).all(|(p, e)| p.starts_with(e));
+        assert!(matches, "{} received {:?}, expected {:?}", self.address, paths, expected);
+    }
+}
+
+impl Drop for MockController {
+    fn drop(&mut self) {
+        self.server.abort();
+    }
+}
+
+// Reads one request and answers it with the connection closed after, so no
+// request parsing has to cope with keep-alive.
+async fn serve(mut stream: TcpStream, script: Arc<Mutex<Script>>) {
+    let Some((method, path, body)) = read_request(&mut stream).await else {
+        return;
+    };
+    let mut reply = {
+        let mut script = script.lock().unwrap();
+        script.received.push(Received {
+            method,
+            path: path.clone(),
+            body,
+            at: Instant::now(),
+        });
+        script.reply(&path)
+    };
+    while let Reply::Delayed(delay, inner) = reply {
+        tokio::time::sleep(delay).await;
+        reply = *inner;
+    }
+    let (status, body) = match reply {
+        Reply::Json(value) => (200, value.to_string()),
+        Reply::Status(status) => (status, String::new()),
+        Reply::Drop | Reply::Delayed(..) => return,
+    };
+    let response = format!(
+        "HTTP/1.1 {} Mock\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        body.len(),
+        body
+    );
+    let _ = stream.write_all(response.as_bytes()).await;
+    let _ = stream.shutdown().await;
+}
+
+async fn read_request(stream: &mut TcpStream) -> Option<(String, String, Vec<u8>)> {
+    let mut data = Vec::new();
+    let mut buffer = [0_u8; 4096];
+    let header_end = loop {
+        let n = stream.read(&mut buffer).await.ok()?;
+        if n == 0 {
+            return None;
+        }
+        data.extend_from_slice(&buffer[..n]);
+        if let Some(end) = data.windows(4).position(|w| w == b"\r\n\r\n") {
+            break end + 4;
+        }
+    };
+    let head = String::from_utf8_lossy(&data[..header_end]).to_string();
+    let mut lines = head.lines();
+    let mut request_line = lines.next()?.split_whitespace();
+    let method = request_line.next()?.to_string();
+    let path = request_line.next()?.to_string();
+    let length = lines
+        .filter_map(|line| line.split_once(':'))
+        .find(|(name, _)| name.eq_ignore_ascii_case("content-length"))
+        .and_then(|(_, value)| value.trim().parse::<usize>().ok())
+        .unwrap_or(0);
+    let mut body = data[header_end..].to_vec();
+    while body.len() < length {
+        let n = stream.read(&mut buffer).await.ok()?;
+        if n == 0 {
+            return None;
+        }
+        body.extend_from_slice(&buffer[..n]);
+    }
+    body.truncate(length);
+    Some((method, path, body))
+}
@@ -0,0 +1,179 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+
+use crate::ramp::Ramp;
+use crate::show_output::ShowOutputConfig;
+
+/// Revision of the show JSON format. Bump it with any change that older
+/// readers would misread; exported schemas carry it.
+pub const SHOW_FORMAT_VERSION: u32 = 2;
+
+/// A show as held by the backend. Times are in seconds from show start.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct Show {
+    pub id: String,
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    #[serde(default)]
+    pub total_duration: f64,
+    #[serde(default)]
+    pub effects: Vec<Effect>,
+    /// Named colors that effects can reference instead of a literal color.
+    #[serde(default)]
+    pub palette: BTreeMap<String, Rgb>,
+    /// How the show should be driven; None uses the app's current settings.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub output: Option<ShowOutputConfig>,
+    /// Seconds into the show where playback was last stopped, offered as a
+    /// resume point when the show is opened again.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_position: Option<f64>,
+    /// Named points such as the start of each act, in any order.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub markers: Vec<Marker>,
+    /// Groups effects are organised in, e.g. "Lighting" or "Pyro", in
+    /// display order.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub layers: Vec<Layer>,
+}
+
+pub type Rgb = [u8; 3];
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct Marker {
+    pub time: f64,
+    pub name: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct Layer {
+    pub name: String,
+    /// Only affects the editor; hidden layers still play.
+    #[serde(default = "enabled_by_default")]
+    pub visible: bool,
+    /// The layer's effects are skipped at playback, without being edited.
+    #[serde(default)]
+    pub muted: bool,
+}
+
+/// A single scheduled effect on one controller channel.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct Effect {
+    pub id: String,
+    pub start_time: f64,
+    #[serde(default)]
+    pub duration: f64,
+    pub controller: String,
+    pub channel: u32,
+    #[serde(default)]
+    pub effect_type: String,
+    #[serde(default)]
+    pub params: HashMap<String, serde_json::Value>,
+    /// Interpolates the output level or color over the effect.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ramp: Option<Ramp>,
+    /// Disabled effects stay in the show but are skipped at playback.
+    #[serde(default = "enabled_by_default")]
+    pub enabled: bool,
+    /// Name of the layer the effect belongs to, if any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub layer: Option<String>,
+}
+
+fn enabled_by_default() -> bool {
+    true
+}
+
+/// Where and why show JSON failed to parse.
+#[derive(Debug, Clone, Serialize)]
+pub struct ShowParseError {
+    pub line: usize,
+    pub column: usize,
+    /// Path to the offending field, e.g. "effects[2].start_time"; "." for the whole document.
+    pub field: String,
+    pub message: String,
+}
+
+impl fmt::Display for ShowParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Invalid show data at line {}, column {} ({}): {}",
+            self.line, self.column, self.field, self.message
+        )
+    }
+}
+
+/// Parses show JSON, reporting the first error with its position and field.
+pub fn parse_show(data: &str) -> Result<Show, ShowParseError> {
+    let mut deserializer = serde_json::Deserializer::from_str(data);
+    let error = match serde_path_to_error::deserialize(&mut deserializer) {
+        Ok(show) => match deserializer.end() {
+            Ok(()) => return Ok(show),
+            Err(e) => ShowParseError {
+                line: e.line(),
+                column: e.column(),
+                field: ".".to_string(),
+                message: strip_location(&e),
+            },
+        },
+        Err(e) => ShowParseError {
+            line: e.inner().line(),
+            column: e.inner().column(),
+            field: e.path().to_string(),
+            message: strip_location(e.inner()),
+        },
+    };
+    Err(error)
+}
+
+// serde_json appends " at line L column C", which the error already carries.
+fn strip_location(error: &serde_json::Error) -> String {
+    let message = error.to_string();
+    let suffix = format!(" at line {} column {}", error.line(), error.column());
+    message.strip_suffix(&suffix).unwrap_or(&message).to_string()
+}
+
+impl Show {
+    pub fn effect(&self, id: &str) -> Option<&Effect> {
+        self.effects.iter().find(|e| e.id == id)
+    }
+
+    /// Replaces the effect with the same id, or appends it if it is new.
+    pub fn upsert_effect(&mut self, effect: Effect) {
+        match self.effects.iter_mut().find(|e| e.id == effect.id) {
+            Some(existing) => *existing = effect,
+            None => self.effects.push(effect),
+        }
+    }
+
+    pub fn remove_effect(&mut self, id: &str) -> bool {
+        let before = self.effects.len();
+        self.effects.retain(|e| e.id != id);
+        self.effects.len() != before
+    }
+
+    pub fn layer(&self, name: &str) -> Option<&Layer> {
+        self.layers.iter().find(|l| l.name == name)
+    }
+
+    pub fn layer_mut(&mut self, name: &str) -> Result<&mut Layer, String> {
+        self.layers
+            .iter_mut()
+            .find(|l| l.name == name)
+            .ok_or_else(|| format!("Layer {} not found", name))
+    }
+
+    /// Whether `effect` is on a muted layer.
+    pub fn is_layer_muted(&self, effect: &Effect) -> bool {
+        effect.layer.as_deref().and_then(|l| self.layer(l)).is_some_and(|l| l.muted)
+    }
+
+    /// Enabled and not on a muted layer.
+    pub fn plays(&self, effect: &Effect) -> bool {
+        effect.enabled && !self.is_layer_muted(effect)
+    }
+}
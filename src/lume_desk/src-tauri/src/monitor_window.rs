@@ -0,0 +1,89 @@
+use serde::Serialize;
+use tauri::{AppHandle, Manager, PhysicalPosition, PhysicalSize, WebviewUrl, WebviewWindowBuilder};
+
+/// Window label of the front-of-house monitor. Show events are emitted to
+/// every window, so the monitor receives the same stream as the main UI.
+pub const MONITOR_LABEL: &str = "monitor";
+
+/// The frontend renders its big-clock monitor view for this route.
+const MONITOR_URL: &str = "index.html?view=monitor";
+
+#[derive(Debug, Serialize)]
+pub struct DisplayInfo {
+    pub index: usize,
+    pub name: Option<String>,
+    pub width: u32,
+    pub height: u32,
+    pub x: i32,
+    pub y: i32,
+    pub scale_factor: f64,
+    pub is_primary: bool,
+}
+
+pub fn list_displays(app: &AppHandle) -> Result<Vec<DisplayInfo>, String> {
+    let primary = app.primary_monitor().map_err(|e| e.to_string())?;
+    let monitors = app.available_monitors().map_err(|e| e.to_string())?;
+    Ok(monitors
+        .iter()
+        .enumerate()
+        .map(|(index, monitor)| DisplayInfo {
+            index,
+            name: monitor.name().cloned(),
+            width: monitor.size().width,
+            height: monitor.size().height,
+            x: monitor.position().x,
+            y: monitor.position().y,
+            scale_factor: monitor.scale_factor(),
+            is_primary: primary
+                .as_ref()
+                .is_some_and(|p| p.name() == monitor.name() && p.position() == monitor.position()),
+        })
+        .collect())
+}
+
+/// Opens a borderless window covering the chosen display, or moves the
+/// existing monitor window there.
+pub fn open(app: &AppHandle, display_index: usize) -> Result<(), String> {
+    let monitors = app.available_monitors().map_err(|e| e.to_string())?;
+    let monitor = monitors.get(display_index).ok_or_else(|| {
+        format!(
+            "Display {} does not exist ({} connected)",
+            display_index,
+            monitors.len()
+        )
+    })?;
+    let position = *monitor.position();
+    let size = *monitor.size();
+
+    if let Some(window) = app.get_webview_window(MONITOR_LABEL) {
+        window
+            .set_position(PhysicalPosition::new(position.x, position.y))
+            .and_then(|_| window.set_size(PhysicalSize::new(size.width, size.height)))
+            .map_err(|e| e.to_string())?;
+        return Ok(());
+    }
+
+    let scale = monitor.scale_factor();
+    WebviewWindowBuilder::new(app, MONITOR_LABEL, WebviewUrl::App(MONITOR_URL.into()))
+        .title("LUME Monitor")
+        .decorations(false)
+        .resizable(false)
+        .focused(false)
+        .position(position.x as f64 / scale, position.y as f64 / scale)
+        .inner_size(size.width as f64 / scale, size.height as f64 / scale)
+        .build()
+        .map_err(|e| format!("Failed to open monitor window: {}", e))?;
+    log::info!("Opened monitor window on display {}", display_index);
+    Ok(())
+}
+
+pub fn close(app: &AppHandle) -> Result<bool, String> {
+    match app.get_webview_window(MONITOR_LABEL) {
+        Some(window) => {
+            window.close().map_err(|e| e.to_string())?;
+            log::info!("Closed monitor window");
+            Ok(true)
+        }
+        None => Ok(false),
+    }
+}
@@ -0,0 +1,515 @@
+use rumqttc::tokio_rustls::rustls::{ClientConfig, RootCertStore};
+use rumqttc::{AsyncClient, ConnectionError, Event, EventLoop, MqttOptions, Outgoing, Packet, QoS, SubscribeReasonCode, Transport};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fs;
+use std::io::ErrorKind;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Duration;
+use tauri::{AppHandle, Manager};
+
+use crate::audit::now_millis;
+use crate::events::{self, ControllerTelemetryReceived, ShowStateChanged};
+use crate::registry::{self, ControllerRegistry};
+
+pub const DEFAULT_PORT: u16 = 1883;
+
+/// Port of brokers reached over TLS ("mqtts://").
+pub const DEFAULT_TLS_PORT: u16 = 8883;
+
+/// Show state changes are published here, retained, as JSON.
+pub const SHOW_STATE_TOPIC: &str = "lume/show/state";
+
+/// Controllers publish to lume/controllers/<id>/heartbeat and
+/// lume/controllers/<id>/telemetry, where <id> is their address, name or MAC.
+const CONTROLLER_TOPICS: [&str; 2] = ["lume/controllers/+/heartbeat", "lume/controllers/+/telemetry"];
+
+/// Show state is what other systems act on, so the broker must take it at
+/// least once. Telemetry is sent again shortly anyway.
+const SHOW_STATE_QOS: QoS = QoS::AtLeastOnce;
+const TELEMETRY_QOS: QoS = QoS::AtMostOnce;
+
+const KEEP_ALIVE: Duration = Duration::from_secs(30);
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+const RECONNECT_MIN: Duration = Duration::from_secs(1);
+const RECONNECT_MAX: Duration = Duration::from_secs(30);
+
+/// Larger packets are refused; telemetry is a few hundred bytes.
+const MAX_PACKET: usize = 256 * 1024;
+
+/// Requests queued for the connection before publishing waits.
+const REQUEST_QUEUE: usize = 64;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MqttCredentials {
+    pub username: String,
+    #[serde(default)]
+    pub password: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TelemetryKind {
+    Heartbeat,
+    Telemetry,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct MqttStatus {
+    /// "host:port" of the broker, None when not configured.
+    pub broker: Option<String>,
+    pub connected: bool,
+    /// Unix milliseconds of the current connection.
+    pub connected_since: Option<u64>,
+    pub messages_received: u64,
+    pub last_error: Option<String>,
+}
+
+// The broker connected to at launch; `broker_url` is None after a disconnect.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct SavedBroker {
+    #[serde(default)]
+    broker_url: Option<String>,
+    #[serde(default)]
+    credentials: Option<MqttCredentials>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Broker {
+    host: String,
+    port: u16,
+    tls: bool,
+}
+
+impl Broker {
+    fn label(&self) -> String {
+        format!("{}:{}", self.host, self.port)
+    }
+
+    fn options(&self, credentials: Option<&MqttCredentials>) -> Result<MqttOptions, String> {
+        let id = uuid::Uuid::new_v4().simple().to_string();
+        let mut options = MqttOptions::new(format!("lume-{}", &id[..8]), self.host.clone(), self.port);
+        options
+            .set_keep_alive(KEEP_ALIVE)
+            .set_clean_session(true)
+            .set_max_packet_size(MAX_PACKET, MAX_PACKET);
+        if let Some(credentials) = credentials {
+            options.set_credentials(credentials.username.clone(), credentials.password.clone().unwrap_or_default());
+        }
+        if self.tls {
+            options.set_transport(Transport::tls_with_config(tls_config()?.into()));
+        }
+        Ok(options)
+    }
+}
+
+// Verifies brokers against the system's trusted roots. Certificates the
+// system has but rustls cannot parse are skipped.
+fn tls_config() -> Result<ClientConfig, String> {
+    let certificates = rustls_native_certs::load_native_certs()
+        .map_err(|e| format!("Failed to load the system's trusted certificates: {}", e))?;
+    let mut roots = RootCertStore::empty();
+    let (_, skipped) = roots.add_parsable_certificates(certificates);
+    if skipped > 0 {
+        log::debug!("{} system certificates skipped for MQTT TLS", skipped);
+    }
+    Ok(ClientConfig::builder().with_root_certificates(roots).with_no_client_auth())
+}
+
+#[derive(Default)]
+struct State {
+    status: MqttStatus,
+    // Bumped on every connect so the previous session exits.
+    generation: u64,
+    // The live session's client, for publishing.
+    client: Option<AsyncClient>,
+    // Published again on every reconnect, as brokers can lose retained messages.
+    last_show_state: Option<Vec<u8>>,
+    saved: SavedBroker,
+    path: Option<PathBuf>,
+}
+
+/// Optional link to an MQTT broker: publishes show state and takes in
+/// controller heartbeats and telemetry, which land in the controller
+/// registry. MQTT 3.1.1 over TCP or TLS. Cheap to clone.
+#[derive(Clone, Default)]
+pub struct Mqtt {
+    state: Arc<Mutex<State>>,
+}
+
+/// "mqtt://host:port", "tcp://host" or just "host"; the port defaults to
+/// 1883. "mqtts://" and "ssl://" brokers are reached over TLS, on 8883
+/// unless given.
+fn parse_broker_url(url: &str) -> Result<Broker, String> {
+    let url = url.trim().trim_end_matches('/');
+    let (rest, tls) = match ["mqtts://", "ssl://"].iter().find_map(|scheme| url.strip_prefix(scheme)) {
+        Some(rest) => (rest, true),
+        None => (
+            url.strip_prefix("mqtt://").or_else(|| url.strip_prefix("tcp://")).unwrap_or(url),
+            false,
+        ),
+    };
+    if rest.contains("://") {
+        return Err(format!("Unsupported broker URL {}", url));
+    }
+    let (host, port) = match rest.rsplit_once(':') {
+        Some((host, port)) if !host.contains(':') || host.ends_with(']') => {
+            let port = port.parse().map_err(|_| format!("Invalid broker port in {}", url))?;
+            (host, port)
+        }
+        _ => (rest, if tls { DEFAULT_TLS_PORT } else { DEFAULT_PORT }),
+    };
+    let host = host.trim_start_matches('[').trim_end_matches(']');
+    if host.is_empty() {
+        return Err("Broker URL needs a host".to_string());
+    }
+    Ok(Broker {
+        host: host.to_string(),
+        port,
+        tls,
+    })
+}
+
+// The controller id and kind of a lume/controllers/<id>/<kind> topic.
+fn parse_controller_topic(topic: &str) -> Option<(&str, TelemetryKind)> {
+    let rest = topic.strip_prefix("lume/controllers/")?;
+    let (id, kind) = rest.split_once('/')?;
+    let kind = match kind {
+        "heartbeat" => TelemetryKind::Heartbeat,
+        "telemetry" => TelemetryKind::Telemetry,
+        _ => return None,
+    };
+    (!id.is_empty()).then_some((id, kind))
+}
+
+// Waits for the broker to accept the first connection.
+async fn first_connection(broker: &Broker, events: &mut EventLoop) -> Result<(), String> {
+    let label = broker.label();
+    let accepted = async {
+        loop {
+            match events.poll().await {
+                Ok(Event::Incoming(Packet::ConnAck(_))) => return Ok(()),
+                Ok(_) => {}
+                Err(e) => return Err(connection_error(&label, e)),
+            }
+        }
+    };
+    tokio::time::timeout(CONNECT_TIMEOUT, accepted)
+        .await
+        .map_err(|_| format!("Broker {} did not answer within {} s", label, CONNECT_TIMEOUT.as_secs()))?
+}
+
+fn connection_error(label: &str, error: ConnectionError) -> String {
+    match error {
+        ConnectionError::ConnectionRefused(code) => format!("Broker {} refused the connection: {:?}", label, code),
+        e => format!("Broker {}: {}", label, e),
+    }
+}
+
+impl Mqtt {
+    fn lock(&self) -> Result<MutexGuard<'_, State>, String> {
+        self.state.lock().map_err(|_| "MQTT client is unavailable".to_string())
+    }
+
+    /// Loads the saved broker and remembers `path` for later saves. A
+    /// missing file means no broker.
+    pub fn load(&self, path: PathBuf) -> Result<(), String> {
+        let saved: SavedBroker = match fs::read(&path) {
+            Ok(data) => serde_json::from_slice(&data).map_err(|e| format!("Invalid MQTT config file: {}", e))?,
+            Err(e) if e.kind() == ErrorKind::NotFound => SavedBroker::default(),
+            Err(e) => return Err(format!("Failed to read {}: {}", path.display(), e)),
+        };
+        let mut state = self.lock()?;
+        state.saved = saved;
+        state.path = Some(path);
+        Ok(())
+    }
+
+    fn save(state: &State) -> Result<(), String> {
+        registry::save_json(state.path.clone(), &state.saved).map_err(|e| format!("Failed to save MQTT config: {}", e))
+    }
+
+    /// Connects to the loaded broker, if any, and keeps trying if it is
+    /// not up yet. A broken saved broker is logged, not fatal.
+    pub fn start(&self, app: &AppHandle) {
+        let Ok(saved) = self.lock().map(|state| state.saved.clone()) else {
+            return;
+        };
+        let Some(url) = saved.broker_url else {
+            return;
+        };
+        let started = parse_broker_url(&url).and_then(|broker| {
+            let (client, events) = AsyncClient::new(broker.options(saved.credentials.as_ref())?, REQUEST_QUEUE);
+            let generation = self.replace_session(&broker, client.clone())?;
+            log::info!("Connecting to saved MQTT broker {}", broker.label());
+            tauri::async_runtime::spawn(run(app.clone(), self.clone(), generation, broker, client, events, false));
+            Ok(())
+        });
+        if let Err(e) = started {
+            log::error!("Saved MQTT broker not connected: {}", e);
+        }
+    }
+
+    pub fn status(&self) -> Result<MqttStatus, String> {
+        Ok(self.lock()?.status.clone())
+    }
+
+    /// Connects to `broker_url`, replacing any earlier broker. The first
+    /// connection must succeed; after that the client reconnects on its own
+    /// until `disconnect`. The broker is saved and connected to again at
+    /// the next launch.
+    pub async fn connect(
+        &self,
+        app: &AppHandle,
+        broker_url: &str,
+        credentials: Option<MqttCredentials>,
+    ) -> Result<MqttStatus, String> {
+        let broker = parse_broker_url(broker_url)?;
+        if credentials.as_ref().is_some_and(|c| c.username.is_empty()) {
+            return Err("MQTT credentials need a username".to_string());
+        }
+        let (client, mut events) = AsyncClient::new(broker.options(credentials.as_ref())?, REQUEST_QUEUE);
+        first_connection(&broker, &mut events).await?;
+
+        let generation = self.replace_session(&broker, client.clone())?;
+        {
+            let mut state = self.lock()?;
+            state.saved = SavedBroker {
+                broker_url: Some(broker_url.trim().to_string()),
+                credentials,
+            };
+            Self::save(&state)?;
+        }
+        log::info!("Connected to MQTT broker {}", broker.label());
+        self.session_started(app, generation, &client);
+        tauri::async_runtime::spawn(run(app.clone(), self.clone(), generation, broker, client, events, true));
+        self.status()
+    }
+
+    // Makes `client` the live session, closing the one before it.
+    fn replace_session(&self, broker: &Broker, client: AsyncClient) -> Result<u64, String> {
+        let mut state = self.lock()?;
+        state.generation += 1;
+        if let Some(previous) = state.client.replace(client) {
+            let _ = previous.try_disconnect();
+        }
+        state.status = MqttStatus {
+            broker: Some(broker.label()),
+            ..MqttStatus::default()
+        };
+        Ok(state.generation)
+    }
+
+    /// Closes the broker connection, stops reconnecting and forgets the
+    /// broker, so the next launch does not connect.
+    pub fn disconnect(&self, app: &AppHandle) -> Result<MqttStatus, String> {
+        let status = {
+            let mut state = self.lock()?;
+            state.generation += 1;
+            if let Some(client) = state.client.take() {
+                let _ = client.try_disconnect();
+            }
+            state.status = MqttStatus::default();
+            state.saved = SavedBroker::default();
+            Self::save(&state)?;
+            state.status.clone()
+        };
+        log::info!("Disconnected from MQTT broker");
+        events::emit(app, events::MQTT_STATUS, status.clone());
+        Ok(status)
+    }
+
+    /// Publishes a show state change, if connected.
+    pub fn publish_show_state(&self, change: &ShowStateChanged) {
+        let Ok(payload) = serde_json::to_vec(change) else {
+            return;
+        };
+        let Ok(mut state) = self.lock() else {
+            return;
+        };
+        if let (Some(client), true) = (&state.client, state.status.connected) {
+            if let Err(e) = client.try_publish(SHOW_STATE_TOPIC, SHOW_STATE_QOS, true, payload.clone()) {
+                log::warn!("Failed to publish show state: {}", e);
+            }
+        }
+        state.last_show_state = Some(payload);
+    }
+
+    fn is_current(&self, generation: u64) -> bool {
+        self.lock().map(|s| s.generation == generation).unwrap_or(false)
+    }
+
+    // Subscribes and republishes the show state on every (re)connection,
+    // as sessions are clean. Ignored once superseded.
+    fn session_started(&self, app: &AppHandle, generation: u64, client: &AsyncClient) {
+        let status = {
+            let Ok(mut state) = self.lock() else {
+                return;
+            };
+            if state.generation != generation {
+                return;
+            }
+            let subscribed = client.try_subscribe_many(
+                CONTROLLER_TOPICS
+                    .iter()
+                    .map(|topic| rumqttc::SubscribeFilter::new(topic.to_string(), TELEMETRY_QOS)),
+            );
+            if let Err(e) = subscribed {
+                log::warn!("Failed to subscribe to controller topics: {}", e);
+            }
+            if let Some(payload) = &state.last_show_state {
+                let _ = client.try_publish(SHOW_STATE_TOPIC, SHOW_STATE_QOS, true, payload.clone());
+            }
+            state.status.connected = true;
+            state.status.connected_since = Some(now_millis());
+            state.status.last_error = None;
+            state.status.clone()
+        };
+        events::emit(app, events::MQTT_STATUS, status);
+    }
+
+    fn session_ended(&self, app: &AppHandle, generation: u64, error: String) {
+        let status = {
+            let Ok(mut state) = self.lock() else {
+                return;
+            };
+            if state.generation != generation {
+                return;
+            }
+            let was_connected = state.status.connected;
+            state.status.connected = false;
+            state.status.connected_since = None;
+            state.status.last_error = Some(error);
+            was_connected.then(|| state.status.clone())
+        };
+        if let Some(status) = status {
+            log::warn!("MQTT: {}", status.last_error.as_deref().unwrap_or_default());
+            events::emit(app, events::MQTT_STATUS, status);
+        }
+    }
+
+    fn count_message(&self) {
+        if let Ok(mut state) = self.lock() {
+            state.status.messages_received += 1;
+        }
+    }
+}
+
+// Drives the connection, reconnecting with backoff, until superseded.
+// `connected` says the first connection has already been accepted.
+async fn run(app: AppHandle, mqtt: Mqtt, generation: u64, broker: Broker, client: AsyncClient, mut events: EventLoop, connected: bool) {
+    let label = broker.label();
+    let mut backoff = RECONNECT_MIN;
+    let mut connected_before = connected;
+    while mqtt.is_current(generation) {
+        match events.poll().await {
+            Ok(Event::Incoming(Packet::ConnAck(_))) => {
+                log::info!("{} MQTT broker {}", if connected_before { "Reconnected to" } else { "Connected to" }, label);
+                connected_before = true;
+                backoff = RECONNECT_MIN;
+                mqtt.session_started(&app, generation, &client);
+            }
+            Ok(Event::Incoming(Packet::Publish(publish))) => {
+                mqtt.count_message();
+                receive(&app, &publish.topic, &publish.payload);
+            }
+            Ok(Event::Incoming(Packet::SubAck(ack))) if ack.return_codes.contains(&SubscribeReasonCode::Failure) => {
+                log::warn!("MQTT broker refused the controller topic subscription");
+            }
+            Ok(Event::Outgoing(Outgoing::Disconnect)) => break,
+            Ok(_) => {}
+            Err(e) => {
+                mqtt.session_ended(&app, generation, connection_error(&label, e));
+                tokio::time::sleep(backoff).await;
+                backoff = (backoff * 2).min(RECONNECT_MAX);
+            }
+        }
+    }
+}
+
+// Records a heartbeat or telemetry message against the controller it names.
+// Payloads that are not JSON are kept as a string.
+fn receive(app: &AppHandle, topic: &str, payload: &[u8]) {
+    if topic == SHOW_STATE_TOPIC {
+        return;
+    }
+    let Some((id, kind)) = parse_controller_topic(topic) else {
+        log::debug!("MQTT message on {} ignored", topic);
+        return;
+    };
+    let registry = app.state::<ControllerRegistry>();
+    let address = match registry.get(id) {
+        Ok(controller) => controller.address,
+        Err(_) => {
+            let mac = registry::normalize_mac(id);
+            let by_mac = registry.list().ok().and_then(|controllers| {
+                controllers
+                    .into_iter()
+                    .find(|c| c.mac.as_deref().is_some_and(|m| registry::normalize_mac(m) == mac))
+            });
+            match by_mac {
+                Some(controller) => controller.address,
+                None => {
+                    log::debug!("MQTT {} from unregistered controller {}", topic, id);
+                    return;
+                }
+            }
+        }
+    };
+    let data = serde_json::from_slice(payload).unwrap_or_else(|_| Value::String(String::from_utf8_lossy(payload).into_owned()));
+    let recorded = match kind {
+        TelemetryKind::Heartbeat => registry.record_heartbeat(&address).and_then(|_| registry.mark_seen(&address)),
+        TelemetryKind::Telemetry => registry.record_telemetry(&address, data.clone()),
+    };
+    if let Err(e) = recorded {
+        log::warn!("Failed to record MQTT {} from {}: {}", topic, address, e);
+    }
+    events::emit(
+        app,
+        events::CONTROLLER_TELEMETRY,
+        ControllerTelemetryReceived { address, kind, data },
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn controller_topics() {
+        assert_eq!(
+            parse_controller_topic("lume/controllers/10.0.0.5/heartbeat"),
+            Some(("10.0.0.5", TelemetryKind::Heartbeat))
+        );
+        assert_eq!(parse_controller_topic("lume/controllers//telemetry"), None);
+        assert_eq!(parse_controller_topic("lume/controllers/x/status"), None);
+    }
+
+    #[test]
+    fn broker_urls() {
+        let broker = |host: &str, port, tls| Ok(Broker { host: host.to_string(), port, tls });
+        assert_eq!(parse_broker_url("mqtt://broker.local"), broker("broker.local", DEFAULT_PORT, false));
+        assert_eq!(parse_broker_url("10.0.0.2:1884"), broker("10.0.0.2", 1884, false));
+        assert_eq!(parse_broker_url("tcp://[::1]:1883/"), broker("::1", 1883, false));
+        assert_eq!(parse_broker_url("mqtts://broker.local"), broker("broker.local", DEFAULT_TLS_PORT, true));
+        assert_eq!(parse_broker_url("ssl://broker.local:9000"), broker("broker.local", 9000, true));
+        assert!(parse_broker_url("ws://broker.local").is_err());
+    }
+
+    #[test]
+    fn saved_broker_survives_a_reload() {
+        let path = std::env::temp_dir().join(format!("lume-mqtt-{}.json", uuid::Uuid::new_v4()));
+        let mqtt = Mqtt::default();
+        mqtt.load(path.clone()).unwrap();
+        {
+            let mut state = mqtt.lock().unwrap();
+            state.saved.broker_url = Some("mqtts://broker.local".to_string());
+            Mqtt::save(&state).unwrap();
+        }
+        let reloaded = Mqtt::default();
+        reloaded.load(path.clone()).unwrap();
+        assert_eq!(reloaded.lock().unwrap().saved.broker_url.as_deref(), Some("mqtts://broker.local"));
+        let _ = fs::remove_file(path);
+    }
+}
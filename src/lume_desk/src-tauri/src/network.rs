@@ -0,0 +1,152 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::net::Ipv4Addr;
+use std::time::{Duration, Instant};
+
+use crate::controller_client::{self, RequestError};
+use crate::registry::{ControllerInfo, ControllerRegistry};
+use crate::response_cache;
+use crate::transport::{self, Transport};
+
+/// How long a controller gets to come back after a network change.
+const RECONNECT_TIMEOUT: Duration = Duration::from_secs(30);
+
+const RECONNECT_POLL: Duration = Duration::from_secs(1);
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AddressMode {
+    Dhcp,
+    Static,
+}
+
+/// As reported by the controller's `/wifi/info`. Firmware that predates a
+/// field leaves it None.
+#[derive(Debug, Serialize)]
+pub struct NetworkInfo {
+    pub address: String,
+    pub mode: Option<AddressMode>,
+    pub ip: Option<String>,
+    pub gateway: Option<String>,
+    pub subnet: Option<String>,
+    pub mac: Option<String>,
+    pub ssid: Option<String>,
+    pub rssi: Option<i64>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct StaticIpConfig {
+    pub ip: String,
+    pub gateway: String,
+    pub subnet: String,
+    #[serde(default)]
+    pub dns: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct StaticIpOutcome {
+    pub previous_address: String,
+    /// Where the controller is registered now.
+    pub address: String,
+    /// Whether it answered at its new address within the reconnect timeout.
+    pub reachable: bool,
+    pub warning: String,
+}
+
+/// Reuses a recent reply unless `force` is set.
+pub async fn info(controller: &ControllerInfo, force: bool) -> Result<NetworkInfo, String> {
+    let info = response_cache::get_json(&controller.address, "/wifi/info", transport::timeout(Transport::Http), force)
+        .await
+        .map_err(|e| format!("{} does not report its network configuration: {}", controller.address, e))?;
+    let text = |key: &str| info.get(key).and_then(Value::as_str).map(str::to_string);
+    Ok(NetworkInfo {
+        address: controller.address.clone(),
+        mode: info
+            .get("dhcp")
+            .and_then(Value::as_bool)
+            .map(|dhcp| if dhcp { AddressMode::Dhcp } else { AddressMode::Static }),
+        ip: text("ip"),
+        gateway: text("gateway"),
+        subnet: text("subnet"),
+        mac: text("mac"),
+        ssid: text("ssid"),
+        rssi: info.get("rssi").and_then(Value::as_i64),
+    })
+}
+
+/// Pushes a static IPv4 configuration. The controller expects
+/// `POST /wifi/static?ip=&gateway=&subnet=[&dns=]`, replies before applying
+/// it, then rejoins the network under the new address. Firmware without
+/// that endpoint answers 404 and nothing changes.
+///
+/// The controller is unreachable while it reconnects. If it was registered
+/// by IP, the registry entry (and its zones) move to the new IP; hostnames
+/// are kept as they resolve to the new address on their own.
+pub async fn set_static_ip(
+    registry: &ControllerRegistry,
+    controller: &ControllerInfo,
+    config: &StaticIpConfig,
+) -> Result<StaticIpOutcome, String> {
+    let ip = parse_ipv4("ip", &config.ip)?;
+    parse_ipv4("gateway", &config.gateway)?;
+    parse_ipv4("subnet", &config.subnet)?;
+    let mut path = format!("/wifi/static?ip={}&gateway={}&subnet={}", config.ip, config.gateway, config.subnet);
+    if let Some(dns) = &config.dns {
+        parse_ipv4("dns", dns)?;
+        path.push_str(&format!("&dns={}", dns));
+    }
+
+    let previous = controller.address.clone();
+    log::warn!("Changing network settings of {}; it will be unreachable while it reconnects", controller.label());
+    controller_client::post(&previous, &path, transport::timeout(Transport::Http))
+        .await
+        .map_err(|e| match e {
+            RequestError::Rejected(_) => format!("{} does not support static IP configuration: {}", previous, e),
+            RequestError::Unreachable(_) => e.to_string(),
+        })?;
+
+    response_cache::invalidate(&previous);
+    let registered_by_ip = previous.parse::<Ipv4Addr>().is_ok();
+    let address = if registered_by_ip { ip.to_string() } else { previous.clone() };
+    let reachable = wait_until_reachable(&address).await;
+    if registered_by_ip && address != previous {
+        response_cache::invalidate(&address);
+        registry.readdress(&previous, &address)?;
+    }
+    let warning = if reachable {
+        format!("{} came back at {}", previous, address)
+    } else {
+        format!(
+            "{} did not answer at {} within {}s; check the new settings on the device",
+            previous,
+            address,
+            RECONNECT_TIMEOUT.as_secs()
+        )
+    };
+    log::info!("{}", warning);
+    Ok(StaticIpOutcome {
+        previous_address: previous,
+        address,
+        reachable,
+        warning,
+    })
+}
+
+fn parse_ipv4(field: &str, value: &str) -> Result<Ipv4Addr, String> {
+    value
+        .parse()
+        .map_err(|_| format!("{} must be an IPv4 address, got '{}'", field, value))
+}
+
+async fn wait_until_reachable(address: &str) -> bool {
+    let started = Instant::now();
+    // Give the controller a moment to drop its old lease first.
+    tokio::time::sleep(RECONNECT_POLL).await;
+    while started.elapsed() < RECONNECT_TIMEOUT {
+        if controller_client::probe(address, transport::timeout_for(address)).await.is_ok() {
+            return true;
+        }
+        tokio::time::sleep(RECONNECT_POLL).await;
+    }
+    false
+}
@@ -0,0 +1,252 @@
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::net::Ipv4Addr;
+use std::sync::Arc;
+use tokio::sync::Semaphore;
+use tokio::task::JoinSet;
+
+use crate::controller_client;
+use crate::dispatcher::Dispatcher;
+use crate::fleet::MAX_CONCURRENT_QUERIES;
+use crate::network::{self, NetworkInfo};
+use crate::registry::{ControllerInfo, ControllerRegistry};
+use crate::transport::{self, Transport};
+
+/// Id of the node that stands for this computer.
+pub const DESK_NODE: &str = "desk";
+
+/// Round trips up to this count as a direct, healthy path.
+const NEAR_MS: f64 = 20.0;
+
+/// Round trips beyond this usually mean another access point or a poor link.
+const FAR_MS: f64 = 100.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LatencyGroup {
+    Near,
+    Mid,
+    Far,
+    Unreachable,
+}
+
+fn latency_group(latency_ms: Option<f64>) -> LatencyGroup {
+    match latency_ms {
+        None => LatencyGroup::Unreachable,
+        Some(ms) if ms <= NEAR_MS => LatencyGroup::Near,
+        Some(ms) if ms <= FAR_MS => LatencyGroup::Mid,
+        Some(_) => LatencyGroup::Far,
+    }
+}
+
+#[derive(Debug, Serialize)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+pub enum NodeKind {
+    Desk,
+    Gateway {
+        ip: String,
+    },
+    Controller {
+        address: String,
+        name: Option<String>,
+        transport: Transport,
+        ip: Option<String>,
+        subnet: Option<String>,
+        ssid: Option<String>,
+        rssi: Option<i64>,
+        latency_ms: Option<f64>,
+        latency_group: LatencyGroup,
+    },
+}
+
+#[derive(Debug, Serialize)]
+pub struct MapNode {
+    pub id: String,
+    #[serde(flatten)]
+    pub kind: NodeKind,
+}
+
+/// A link in the topology. Controller edges carry the measured round trip;
+/// desk-to-gateway edges carry the fastest round trip through that gateway.
+#[derive(Debug, Serialize)]
+pub struct MapEdge {
+    pub from: String,
+    pub to: String,
+    pub latency_ms: Option<f64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SubnetSummary {
+    /// e.g. "192.168.4.0/24".
+    pub cidr: String,
+    pub gateways: Vec<String>,
+    pub controllers: Vec<String>,
+    pub mean_latency_ms: Option<f64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct NetworkMap {
+    pub nodes: Vec<MapNode>,
+    pub edges: Vec<MapEdge>,
+    pub subnets: Vec<SubnetSummary>,
+    /// Controllers that did not report their network settings; they hang
+    /// straight off the desk node.
+    pub unmapped: Vec<String>,
+}
+
+#[derive(Default)]
+struct SubnetMembers {
+    gateways: Vec<String>,
+    controllers: Vec<String>,
+    latencies: Vec<f64>,
+}
+
+struct Sample {
+    controller: ControllerInfo,
+    latency_ms: Option<f64>,
+    info: Option<NetworkInfo>,
+}
+
+fn cidr(ip: &str, mask: &str) -> Option<String> {
+    let ip: Ipv4Addr = ip.parse().ok()?;
+    let mask: Ipv4Addr = mask.parse().ok()?;
+    let prefix = u32::from(mask).leading_ones();
+    let network = Ipv4Addr::from(u32::from(ip) & u32::from(mask));
+    Some(format!("{}/{}", network, prefix))
+}
+
+fn gateway_id(ip: &str) -> String {
+    format!("gateway:{}", ip)
+}
+
+/// Probes every registered controller and reads its network settings (from
+/// the cache when recent) to lay out subnets, shared gateways and latency
+/// groups. Controllers behind a slow gateway stand out as a group of far
+/// nodes on the same edge.
+pub async fn build(registry: &ControllerRegistry, dispatcher: &Dispatcher) -> Result<NetworkMap, String> {
+    let permits = Arc::new(Semaphore::new(MAX_CONCURRENT_QUERIES));
+    let mut samples = JoinSet::new();
+    for controller in registry.list()? {
+        let permits = permits.clone();
+        samples.spawn(async move {
+            let _permit = permits.acquire_owned().await;
+            let probe = controller_client::probe(&controller.address, transport::timeout(controller.transport)).await;
+            // Unreachable controllers cannot report their settings either.
+            let info = match &probe {
+                Ok(_) if controller.transport != Transport::Ble => network::info(&controller, false).await.ok(),
+                _ => None,
+            };
+            let sample = Sample {
+                latency_ms: probe.as_ref().ok().map(|rtt| rtt.as_secs_f64() * 1000.0),
+                controller,
+                info,
+            };
+            (sample, probe.err())
+        });
+    }
+
+    let mut collected = Vec::new();
+    while let Some(joined) = samples.join_next().await {
+        let (sample, error) = joined.map_err(|e| format!("Network map failed: {}", e))?;
+        dispatcher.note_reachability(&sample.controller.address, error.is_none(), error);
+        collected.push(sample);
+    }
+    collected.sort_by(|a, b| a.controller.address.cmp(&b.controller.address));
+    Ok(assemble(collected))
+}
+
+fn assemble(samples: Vec<Sample>) -> NetworkMap {
+    let mut nodes = vec![MapNode {
+        id: DESK_NODE.to_string(),
+        kind: NodeKind::Desk,
+    }];
+    let mut edges = Vec::new();
+    let mut unmapped = Vec::new();
+    // Fastest round trip seen through each gateway.
+    let mut gateways: BTreeMap<String, Option<f64>> = BTreeMap::new();
+    let mut subnets: BTreeMap<String, SubnetMembers> = BTreeMap::new();
+
+    for sample in samples {
+        let address = sample.controller.address.clone();
+        let info = sample.info.as_ref();
+        let gateway = info.and_then(|i| i.gateway.clone()).filter(|g| !g.is_empty());
+        let subnet = info.and_then(|i| cidr(i.ip.as_deref()?, i.subnet.as_deref()?));
+
+        match &gateway {
+            Some(ip) => {
+                let fastest = gateways.entry(ip.clone()).or_insert(None);
+                if let Some(ms) = sample.latency_ms {
+                    *fastest = Some(fastest.map_or(ms, |f: f64| f.min(ms)));
+                }
+                edges.push(MapEdge {
+                    from: gateway_id(ip),
+                    to: address.clone(),
+                    latency_ms: sample.latency_ms,
+                });
+            }
+            None => {
+                unmapped.push(address.clone());
+                edges.push(MapEdge {
+                    from: DESK_NODE.to_string(),
+                    to: address.clone(),
+                    latency_ms: sample.latency_ms,
+                });
+            }
+        }
+        if let Some(cidr) = &subnet {
+            let members = subnets.entry(cidr.clone()).or_default();
+            if let Some(ip) = &gateway {
+                if !members.gateways.contains(ip) {
+                    members.gateways.push(ip.clone());
+                }
+            }
+            members.controllers.push(address.clone());
+            members.latencies.extend(sample.latency_ms);
+        }
+
+        nodes.push(MapNode {
+            id: address.clone(),
+            kind: NodeKind::Controller {
+                name: sample.controller.display_name(),
+                transport: sample.controller.transport,
+                ip: info.and_then(|i| i.ip.clone()),
+                subnet,
+                ssid: info.and_then(|i| i.ssid.clone()),
+                rssi: info.and_then(|i| i.rssi),
+                latency_ms: sample.latency_ms,
+                latency_group: latency_group(sample.latency_ms),
+                address,
+            },
+        });
+    }
+
+    for (ip, fastest) in gateways {
+        nodes.push(MapNode {
+            id: gateway_id(&ip),
+            kind: NodeKind::Gateway { ip: ip.clone() },
+        });
+        edges.push(MapEdge {
+            from: DESK_NODE.to_string(),
+            to: gateway_id(&ip),
+            latency_ms: fastest,
+        });
+    }
+
+    let subnets = subnets
+        .into_iter()
+        .map(|(cidr, members)| SubnetSummary {
+            cidr,
+            mean_latency_ms: (!members.latencies.is_empty())
+                .then(|| members.latencies.iter().sum::<f64>() / members.latencies.len() as f64),
+            gateways: members.gateways,
+            controllers: members.controllers,
+        })
+        .collect();
+
+    NetworkMap {
+        nodes,
+        edges,
+        subnets,
+        unmapped,
+    }
+}
@@ -0,0 +1,150 @@
+use serde::Serialize;
+use std::net::{IpAddr, UdpSocket};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+use tauri::{AppHandle, Manager};
+use tokio::sync::Semaphore;
+use tokio::task::JoinSet;
+
+use crate::controller_client;
+use crate::discovery::Discovery;
+use crate::dispatcher::Dispatcher;
+use crate::events::{self, ControllersReconnected, NetworkChanged};
+use crate::fleet::MAX_CONCURRENT_QUERIES;
+use crate::registry::ControllerRegistry;
+use crate::response_cache;
+use crate::show_engine::ShowEngine;
+use crate::transport::{self, Transport};
+
+/// How often the desk's own address is checked.
+const POLL: Duration = Duration::from_secs(5);
+
+/// A wall-clock gap this much longer than the poll means the machine slept.
+const SLEEP_GAP: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChangeReason {
+    /// The desk's address on the network changed, or it joined or left one.
+    AddressChanged,
+    /// The machine woke from sleep.
+    Resumed,
+}
+
+/// The address the OS would use to reach other hosts. Connecting a UDP
+/// socket only picks a route; nothing is sent.
+fn local_address() -> Option<IpAddr> {
+    let socket = UdpSocket::bind("0.0.0.0:0").ok()?;
+    socket.connect("192.0.2.1:9").ok()?;
+    socket.local_addr().ok().map(|a| a.ip())
+}
+
+/// Watches for the desk changing networks or waking from sleep, and then
+/// drops stale connections, re-runs discovery and reconnects the known
+/// controllers. Started once at launch.
+pub fn start(app: &AppHandle) {
+    tauri::async_runtime::spawn(watch(app.clone()));
+}
+
+async fn watch(app: AppHandle) {
+    let mut address = local_address();
+    let mut last_tick = SystemTime::now();
+    let mut interval = tokio::time::interval(POLL);
+    interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+    loop {
+        interval.tick().await;
+        let now = SystemTime::now();
+        let slept = now.duration_since(last_tick).is_ok_and(|gap| gap > POLL + SLEEP_GAP);
+        last_tick = now;
+        let current = local_address();
+        let reason = if current != address {
+            ChangeReason::AddressChanged
+        } else if slept {
+            ChangeReason::Resumed
+        } else {
+            continue;
+        };
+        let previous = std::mem::replace(&mut address, current);
+        log::warn!("Network changed ({:?}): {:?} -> {:?}", reason, previous, current);
+        events::emit(
+            &app,
+            events::NETWORK_CHANGED,
+            NetworkChanged {
+                reason,
+                previous_address: previous.map(|a| a.to_string()),
+                current_address: current.map(|a| a.to_string()),
+            },
+        );
+        if current.is_some() {
+            reconnect(&app).await;
+        }
+    }
+}
+
+// Probes every enabled Wi-Fi controller on fresh connections. If the running
+// show lost any of its controllers, the show's error policy decides
+// whether it holds.
+async fn reconnect(app: &AppHandle) {
+    controller_client::reset_connections();
+    response_cache::clear();
+    if let Err(e) = app.state::<Discovery>().run_pass(app, true).await {
+        log::warn!("Discovery after network change failed: {}", e);
+    }
+
+    let Ok(controllers) = app.state::<ControllerRegistry>().list() else {
+        return;
+    };
+    let permits = Arc::new(Semaphore::new(MAX_CONCURRENT_QUERIES));
+    let mut probes = JoinSet::new();
+    for controller in controllers.into_iter().filter(|c| c.enabled && c.transport == Transport::Http) {
+        let permits = permits.clone();
+        probes.spawn(async move {
+            let _permit = permits.acquire_owned().await;
+            let result = controller_client::probe(&controller.address, transport::timeout(Transport::Http)).await;
+            (controller.address, result)
+        });
+    }
+    let dispatcher = app.state::<Dispatcher>();
+    let mut reconnected = Vec::new();
+    let mut unreachable = Vec::new();
+    while let Some(joined) = probes.join_next().await {
+        let Ok((address, result)) = joined else {
+            continue;
+        };
+        dispatcher.note_reachability(&address, result.is_ok(), result.as_ref().err().cloned());
+        match result {
+            Ok(_) => reconnected.push(address),
+            Err(_) => unreachable.push(address),
+        }
+    }
+    reconnected.sort();
+    unreachable.sort();
+    log::info!(
+        "After network change: {} controllers reconnected, {} unreachable",
+        reconnected.len(),
+        unreachable.len()
+    );
+
+    let engine = app.state::<ShowEngine>();
+    let registry = app.state::<ControllerRegistry>();
+    let lost_show_controller = engine.running_show().ok().flatten().is_some_and(|show| {
+        show.effects
+            .iter()
+            .filter_map(|e| registry.get(&e.controller).ok())
+            .any(|c| unreachable.contains(&c.address))
+    });
+    events::emit(
+        app,
+        events::CONTROLLERS_RECONNECTED,
+        ControllersReconnected {
+            reconnected,
+            unreachable,
+            show_affected: lost_show_controller,
+        },
+    );
+    if lost_show_controller {
+        if let Err(e) = engine.fail(app, "show controllers were lost after a network change") {
+            log::warn!("Could not apply the error policy: {}", e);
+        }
+    }
+}
@@ -0,0 +1,138 @@
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Manager};
+use tokio::time::MissedTickBehavior;
+
+use crate::dispatcher::Dispatcher;
+use crate::registry::ControllerInfo;
+
+pub const MIN_REFRESH_HZ: u32 = 1;
+pub const MAX_REFRESH_HZ: u32 = 200;
+
+/// Achieved rates are measured over windows of this length.
+const MEASURE_WINDOW: Duration = Duration::from_secs(1);
+
+struct Target {
+    hz: u32,
+    // Bumped whenever the rate changes so the old loop exits.
+    generation: u64,
+    achieved_hz: f64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RefreshRate {
+    pub target: String,
+    pub requested_hz: u32,
+    /// Refresh passes actually completed per second over the last window.
+    pub achieved_hz: f64,
+}
+
+/// Re-sends the steady state of a controller's outputs at its own rate,
+/// independent of the engine tick. Values are held between engine updates,
+/// not interpolated: LUME outputs are discrete states. Pyro controllers are
+/// never refreshed. Cheap to clone.
+#[derive(Clone, Default)]
+pub struct OutputRefresh {
+    targets: Arc<Mutex<BTreeMap<String, Target>>>,
+}
+
+impl OutputRefresh {
+    fn lock(&self) -> Result<MutexGuard<'_, BTreeMap<String, Target>>, String> {
+        self.targets.lock().map_err(|_| "Output refresh is unavailable".to_string())
+    }
+
+    /// Sets the refresh rate of `controller`; 0 stops refreshing it.
+    pub fn set_rate(&self, app: &AppHandle, controller: &ControllerInfo, hz: u32) -> Result<(), String> {
+        if controller.is_pyro() {
+            return Err(format!("{} drives pyro outputs, which are never refreshed", controller.address));
+        }
+        if hz != 0 && !(MIN_REFRESH_HZ..=MAX_REFRESH_HZ).contains(&hz) {
+            return Err(format!(
+                "Refresh rate must be 0 (off) or between {} and {} Hz, got {}",
+                MIN_REFRESH_HZ, MAX_REFRESH_HZ, hz
+            ));
+        }
+        let address = controller.address.clone();
+        let generation = {
+            let mut targets = self.lock()?;
+            let generation = targets.get(&address).map_or(0, |t| t.generation) + 1;
+            if hz == 0 {
+                targets.remove(&address);
+                log::info!("Output refresh for {} stopped", address);
+                return Ok(());
+            }
+            targets.insert(
+                address.clone(),
+                Target {
+                    hz,
+                    generation,
+                    achieved_hz: 0.0,
+                },
+            );
+            generation
+        };
+        log::info!("Output refresh for {} set to {} Hz", address, hz);
+        tauri::async_runtime::spawn(run_loop(app.clone(), self.clone(), address, hz, generation));
+        Ok(())
+    }
+
+    pub fn rates(&self) -> Result<Vec<RefreshRate>, String> {
+        Ok(self
+            .lock()?
+            .iter()
+            .map(|(target, t)| RefreshRate {
+                target: target.clone(),
+                requested_hz: t.hz,
+                achieved_hz: t.achieved_hz,
+            })
+            .collect())
+    }
+
+    // Stores the measured rate; false once the loop has been superseded.
+    fn report(&self, address: &str, generation: u64, achieved_hz: Option<f64>) -> bool {
+        let Ok(mut targets) = self.targets.lock() else {
+            return false;
+        };
+        match targets.get_mut(address) {
+            Some(target) if target.generation == generation => {
+                if let Some(achieved) = achieved_hz {
+                    target.achieved_hz = achieved;
+                }
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
+async fn run_loop(app: AppHandle, refresh: OutputRefresh, address: String, hz: u32, generation: u64) {
+    let mut interval = tokio::time::interval(Duration::from_secs_f64(1.0 / hz as f64));
+    // A slow controller lowers the achieved rate instead of causing bursts.
+    interval.set_missed_tick_behavior(MissedTickBehavior::Skip);
+    let dispatcher = app.state::<Dispatcher>().inner().clone();
+    let mut window_start = Instant::now();
+    let mut passes = 0u32;
+
+    loop {
+        interval.tick().await;
+        let elapsed = window_start.elapsed();
+        let achieved = (elapsed >= MEASURE_WINDOW).then(|| passes as f64 / elapsed.as_secs_f64());
+        if !refresh.report(&address, generation, achieved) {
+            break;
+        }
+        if achieved.is_some() {
+            window_start = Instant::now();
+            passes = 0;
+        }
+
+        for (channel, action) in dispatcher.refreshable_states(&address) {
+            // Mutes and blackouts refuse the send; the next pass tries again.
+            if let Err(e) = dispatcher.refresh(&address, channel, &action).await {
+                log::debug!("Refresh of {} channel {} skipped: {}", address, channel, e);
+            }
+        }
+        passes += 1;
+    }
+}
@@ -0,0 +1,43 @@
+use serde_json::Value;
+
+use crate::models::{Rgb, Show};
+
+/// Effect parameter holding the color: either a literal `[r, g, b]` or a
+/// palette reference `{"palette": "name"}`.
+pub const COLOR_PARAM: &str = "color";
+
+fn palette_ref(value: &Value) -> Option<&str> {
+    value.get("palette").and_then(Value::as_str)
+}
+
+pub fn add_color(show: &mut Show, name: &str, rgb: Rgb) -> Result<(), String> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err("Palette color name must not be empty".to_string());
+    }
+    show.palette.insert(name.to_string(), rgb);
+    Ok(())
+}
+
+/// Returns a copy of the show with every palette reference replaced by its
+/// literal color, ready for hardware output. Fails on references to colors
+/// that are not in the palette.
+pub fn resolve(show: &Show) -> Result<Show, String> {
+    let mut resolved = show.clone();
+    let mut missing = Vec::new();
+    for effect in &mut resolved.effects {
+        let Some(name) = effect.params.get(COLOR_PARAM).and_then(palette_ref) else {
+            continue;
+        };
+        match show.palette.get(name) {
+            Some(rgb) => {
+                effect.params.insert(COLOR_PARAM.to_string(), Value::from(rgb.to_vec()));
+            }
+            None => missing.push(format!("{} (effect {})", name, effect.id)),
+        }
+    }
+    if !missing.is_empty() {
+        return Err(format!("Unknown palette colors: {}", missing.join(", ")));
+    }
+    Ok(resolved)
+}
@@ -0,0 +1,372 @@
+use serde::Serialize;
+use std::collections::{HashMap, VecDeque};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+use sysinfo::{ProcessRefreshKind, ProcessesToUpdate, System};
+use tauri::{AppHandle, Manager};
+
+use crate::audit::now_millis;
+use crate::dispatcher::Dispatcher;
+use crate::events::{self, DegradedMode};
+use crate::power::PowerMonitor;
+use crate::show_engine::ShowEngine;
+
+/// Ticks more than this share late within the last second put the engine
+/// into degraded mode.
+const LATE_SHARE: f64 = 0.25;
+
+/// Degraded mode ends after this long without a late tick.
+const RECOVERY: Duration = Duration::from_secs(10);
+
+/// Playhead updates sent to the UI per second while degraded.
+pub const DEGRADED_PREVIEW_HZ: u32 = 5;
+
+/// Ceiling on ramp frame rates started while degraded.
+pub const DEGRADED_FRAME_HZ: u32 = 10;
+
+/// Performance history sampling interval bounds and default.
+pub const MIN_SAMPLE_INTERVAL_MS: u64 = 250;
+pub const MAX_SAMPLE_INTERVAL_MS: u64 = 60_000;
+const DEFAULT_SAMPLE_INTERVAL_MS: u64 = 1000;
+
+/// Once the history holds this many samples, neighbours are merged in pairs
+/// and each sample covers twice as long from then on.
+const MAX_SAMPLES: usize = 2048;
+
+static ADAPTIVE: AtomicBool = AtomicBool::new(false);
+static DEGRADED: AtomicBool = AtomicBool::new(false);
+// Lateness of the latest engine tick, and the worst since the last
+// history sample, in microseconds.
+static TICK_LATENESS_US: AtomicU64 = AtomicU64::new(0);
+static PEAK_LATENESS_US: AtomicU64 = AtomicU64::new(0);
+
+// Kept between readings: CPU usage is measured since the previous refresh.
+static PROCESS: Mutex<Option<System>> = Mutex::new(None);
+
+// Resident memory in bytes and CPU usage since the last reading, where
+// 100 is one fully used core. None if the process cannot be read.
+fn process_usage() -> Option<(u64, f64)> {
+    let pid = sysinfo::get_current_pid().ok()?;
+    let mut system = PROCESS.lock().ok()?;
+    let system = system.get_or_insert_with(System::new);
+    system.refresh_processes_specifics(
+        ProcessesToUpdate::Some(&[pid]),
+        false,
+        ProcessRefreshKind::nothing().with_memory().with_cpu(),
+    );
+    let process = system.process(pid)?;
+    Some((process.memory(), process.cpu_usage() as f64))
+}
+
+/// Resident memory of the app in bytes, or 0 if it cannot be read.
+pub fn memory_usage() -> u64 {
+    process_usage().map_or(0, |(memory, _)| memory)
+}
+
+/// Records how late an engine tick started.
+pub fn record_lateness(lateness: Duration) {
+    let us = lateness.as_micros() as u64;
+    TICK_LATENESS_US.store(us, Ordering::Relaxed);
+    PEAK_LATENESS_US.fetch_max(us, Ordering::Relaxed);
+}
+
+pub fn stats(dispatcher: &Dispatcher, power: &PowerMonitor) -> HashMap<String, f64> {
+    let mut stats = HashMap::new();
+    
+    let (memory, cpu) = process_usage().unwrap_or_default();
+    stats.insert("memory_mb".to_string(), memory as f64 / 1024.0 / 1024.0);
+    stats.insert("cpu_usage".to_string(), cpu);
+    stats.insert("tick_jitter_ms".to_string(), TICK_LATENESS_US.load(Ordering::Relaxed) as f64 / 1000.0);
+
+    let fire = dispatcher.fire_stats();
+    stats.insert("fire_sent".to_string(), fire.sent as f64);
+    stats.insert("fire_acked".to_string(), fire.acked as f64);
+    stats.insert("fire_retried".to_string(), fire.retried as f64);
+    stats.insert("fire_duplicates_suppressed".to_string(), fire.duplicates_suppressed as f64);
+    stats.insert("total_power_watts".to_string(), power.total_watts());
+    stats.insert("adaptive_performance".to_string(), if is_adaptive() { 1.0 } else { 0.0 });
+    stats.insert("degraded_mode".to_string(), if is_degraded() { 1.0 } else { 0.0 });
+    
+    stats
+}
+
+pub fn is_adaptive() -> bool {
+    ADAPTIVE.load(Ordering::Relaxed)
+}
+
+/// True while non-essential output is being cut back. Cue timing is never
+/// touched: the engine keeps ticking at its full rate.
+pub fn is_degraded() -> bool {
+    DEGRADED.load(Ordering::Relaxed)
+}
+
+/// Turning adaptive mode off restores full output straight away.
+pub fn set_adaptive(app: &AppHandle, enabled: bool) {
+    ADAPTIVE.store(enabled, Ordering::Relaxed);
+    log::info!("Adaptive performance {}", if enabled { "enabled" } else { "disabled" });
+    if !enabled {
+        leave(app, "adaptive performance was turned off");
+    }
+}
+
+fn leave(app: &AppHandle, reason: &str) {
+    if DEGRADED.swap(false, Ordering::Relaxed) {
+        log::info!("Leaving degraded mode: {}", reason);
+        events::emit(
+            app,
+            events::DEGRADED_MODE,
+            DegradedMode {
+                active: false,
+                late_ticks: 0,
+                window_ticks: 0,
+                message: format!("Full output restored: {}", reason),
+            },
+        );
+    }
+}
+
+/// Watches tick lateness for one run of the engine loop.
+pub struct LoadMonitor {
+    // Whether each of the last second's ticks was late, oldest first.
+    recent: VecDeque<bool>,
+    last_late: Option<Instant>,
+}
+
+impl LoadMonitor {
+    pub fn new() -> Self {
+        Self {
+            recent: VecDeque::new(),
+            last_late: None,
+        }
+    }
+
+    /// Records one tick at `hz` and enters or leaves degraded mode.
+    pub fn observe(&mut self, app: &AppHandle, late: bool, hz: u32) {
+        let window = hz.max(1) as usize;
+        self.recent.push_back(late);
+        while self.recent.len() > window {
+            self.recent.pop_front();
+        }
+        if late {
+            self.last_late = Some(Instant::now());
+        }
+        if !is_adaptive() {
+            return;
+        }
+
+        if is_degraded() {
+            if self.last_late.map_or(true, |t| t.elapsed() >= RECOVERY) {
+                leave(app, "the engine is keeping up again");
+            }
+            return;
+        }
+        let late_ticks = self.recent.iter().filter(|&&late| late).count();
+        if self.recent.len() < window || (late_ticks as f64) < window as f64 * LATE_SHARE {
+            return;
+        }
+        DEGRADED.store(true, Ordering::Relaxed);
+        log::warn!(
+            "Entering degraded mode: {} of the last {} ticks were late",
+            late_ticks,
+            window
+        );
+        events::emit(
+            app,
+            events::DEGRADED_MODE,
+            DegradedMode {
+                active: true,
+                late_ticks: late_ticks as u32,
+                window_ticks: window as u32,
+                message: format!(
+                    "{} of the last {} ticks were late; preview limited to {} Hz and ramps to {} fps",
+                    late_ticks, window, DEGRADED_PREVIEW_HZ, DEGRADED_FRAME_HZ
+                ),
+            },
+        );
+    }
+
+    /// The show ended; whatever load it caused is gone.
+    pub fn finish(self, app: &AppHandle) {
+        TICK_LATENESS_US.store(0, Ordering::Relaxed);
+        leave(app, "the show stopped");
+    }
+}
+
+/// One point of the performance history. Merged samples hold the mean of
+/// the gauges and the worst jitter.
+#[derive(Debug, Clone, Serialize)]
+pub struct PerformanceSample {
+    /// Milliseconds since the Unix epoch at the start of the sample.
+    pub timestamp: u64,
+    /// Resident memory of the app.
+    pub memory_mb: f64,
+    /// App CPU usage over the sample; 100 is one fully used core.
+    pub cpu_usage: f64,
+    /// Worst engine tick lateness during the sample, in milliseconds.
+    pub jitter_ms: f64,
+    /// Set if degraded mode was on at any point in the sample.
+    pub degraded: bool,
+    /// Set if a show was playing at any point in the sample.
+    pub show_running: bool,
+}
+
+impl PerformanceSample {
+    // Folds `other` in with `weight` (0..=1) of the mean.
+    fn merge(&mut self, other: &PerformanceSample, weight: f64) {
+        let mean = |a: f64, b: f64| a * (1.0 - weight) + b * weight;
+        self.memory_mb = mean(self.memory_mb, other.memory_mb);
+        self.cpu_usage = mean(self.cpu_usage, other.cpu_usage);
+        self.jitter_ms = self.jitter_ms.max(other.jitter_ms);
+        self.degraded |= other.degraded;
+        self.show_running |= other.show_running;
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PerformanceHistory {
+    pub interval_ms: u64,
+    /// Raw samples each point stands for; grows as a long session is halved.
+    pub samples_per_point: u32,
+    pub samples: Vec<PerformanceSample>,
+}
+
+struct RecorderState {
+    interval_ms: u64,
+    // Bumped when the interval changes so the old loop exits.
+    generation: u64,
+    samples: Vec<PerformanceSample>,
+    stride: u32,
+    // Raw samples not yet making up a full point, and how many.
+    pending: Option<(PerformanceSample, u32)>,
+}
+
+/// Samples the performance stats through the session. Cheap to clone.
+#[derive(Clone)]
+pub struct PerformanceRecorder {
+    state: Arc<Mutex<RecorderState>>,
+}
+
+impl Default for PerformanceRecorder {
+    fn default() -> Self {
+        Self {
+            state: Arc::new(Mutex::new(RecorderState {
+                interval_ms: DEFAULT_SAMPLE_INTERVAL_MS,
+                generation: 0,
+                samples: Vec::new(),
+                stride: 1,
+                pending: None,
+            })),
+        }
+    }
+}
+
+impl PerformanceRecorder {
+    fn lock(&self) -> Result<MutexGuard<'_, RecorderState>, String> {
+        self.state.lock().map_err(|_| "Performance history is unavailable".to_string())
+    }
+
+    /// Starts sampling at the current interval. Called once at launch.
+    pub fn start(&self, app: &AppHandle) {
+        let generation = match self.lock() {
+            Ok(mut state) => {
+                state.generation += 1;
+                state.generation
+            }
+            Err(_) => return,
+        };
+        tauri::async_runtime::spawn(sample_loop(app.clone(), self.clone(), generation));
+    }
+
+    /// Samples already taken are kept as they are.
+    pub fn set_interval(&self, app: &AppHandle, interval_ms: u64) -> Result<(), String> {
+        if !(MIN_SAMPLE_INTERVAL_MS..=MAX_SAMPLE_INTERVAL_MS).contains(&interval_ms) {
+            return Err(format!(
+                "Sampling interval must be between {} and {} ms, got {}",
+                MIN_SAMPLE_INTERVAL_MS, MAX_SAMPLE_INTERVAL_MS, interval_ms
+            ));
+        }
+        self.lock()?.interval_ms = interval_ms;
+        log::info!("Performance history sampled every {} ms", interval_ms);
+        self.start(app);
+        Ok(())
+    }
+
+    pub fn history(&self) -> Result<PerformanceHistory, String> {
+        let state = self.lock()?;
+        Ok(PerformanceHistory {
+            interval_ms: state.interval_ms,
+            samples_per_point: state.stride,
+            samples: state.samples.clone(),
+        })
+    }
+
+    fn push(&self, sample: PerformanceSample) {
+        let Ok(mut state) = self.lock() else {
+            return;
+        };
+        let (point, count) = match state.pending.take() {
+            Some((mut point, count)) => {
+                point.merge(&sample, 1.0 / (count + 1) as f64);
+                (point, count + 1)
+            }
+            None => (sample, 1),
+        };
+        if count < state.stride {
+            state.pending = Some((point, count));
+            return;
+        }
+        state.samples.push(point);
+        if state.samples.len() >= MAX_SAMPLES {
+            let halved = state
+                .samples
+                .chunks(2)
+                .map(|pair| {
+                    let mut point = pair[0].clone();
+                    if let Some(second) = pair.get(1) {
+                        point.merge(second, 0.5);
+                    }
+                    point
+                })
+                .collect();
+            state.samples = halved;
+            state.stride *= 2;
+        }
+    }
+
+    fn interval(&self, generation: u64) -> Option<Duration> {
+        let state = self.lock().ok()?;
+        (state.generation == generation).then(|| Duration::from_millis(state.interval_ms))
+    }
+}
+
+async fn sample_loop(app: AppHandle, recorder: PerformanceRecorder, generation: u64) {
+    while let Some(interval) = recorder.interval(generation) {
+        tokio::time::sleep(interval).await;
+        let timestamp = now_millis();
+        let stats = stats(&app.state::<Dispatcher>(), &app.state::<PowerMonitor>());
+        let stat = |key: &str| stats.get(key).copied().unwrap_or(0.0);
+        let show_running = app.state::<ShowEngine>().status().is_ok_and(|s| s.is_running);
+        recorder.push(PerformanceSample {
+            timestamp,
+            memory_mb: stat("memory_mb"),
+            cpu_usage: stat("cpu_usage"),
+            jitter_ms: PEAK_LATENESS_US.swap(0, Ordering::Relaxed) as f64 / 1000.0,
+            degraded: is_degraded(),
+            show_running,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_the_memory_of_this_process() {
+        let (memory, cpu) = process_usage().unwrap();
+        assert!(memory > 1024 * 1024);
+        assert!(cpu >= 0.0);
+        assert!(memory_usage() > 0);
+    }
+}
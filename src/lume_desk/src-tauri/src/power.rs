@@ -0,0 +1,268 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Manager};
+use tokio::sync::Semaphore;
+use tokio::task::JoinSet;
+
+use crate::audit::now_millis;
+use crate::controller_client;
+use crate::events::{self, PowerWarning};
+use crate::fleet::MAX_CONCURRENT_QUERIES;
+use crate::registry::{ControllerInfo, ControllerRegistry};
+use crate::show_engine::ShowEngine;
+use crate::transport;
+
+pub const DEFAULT_POLL_MS: u64 = 1000;
+pub const MIN_POLL_MS: u64 = 200;
+pub const MAX_POLL_MS: u64 = 60_000;
+
+/// Minimum gap between two power warnings about the same budget.
+const WARNING_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Key for warnings about the rig as a whole rather than one controller.
+const TOTAL: &str = "";
+
+/// Live draw of one controller, as it last reported it.
+#[derive(Debug, Clone, Serialize)]
+pub struct ControllerPower {
+    pub address: String,
+    pub name: Option<String>,
+    /// None if the controller does not report its draw.
+    pub watts: Option<f64>,
+    pub amps: Option<f64>,
+    pub budget_watts: Option<f64>,
+    pub over_budget: bool,
+    /// Unix timestamp in milliseconds of the reading.
+    pub measured_at: u64,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PowerSettings {
+    pub enabled: bool,
+    #[serde(default = "default_poll_ms")]
+    pub poll_ms: u64,
+    /// Budget for the whole rig; per-controller budgets come from the registry.
+    #[serde(default)]
+    pub total_budget_watts: Option<f64>,
+    /// Apply the show's error policy when live draw exceeds a budget.
+    #[serde(default)]
+    pub enforce: bool,
+}
+
+fn default_poll_ms() -> u64 {
+    DEFAULT_POLL_MS
+}
+
+struct PowerState {
+    settings: PowerSettings,
+    // Bumped when monitoring is reconfigured so the old loop exits.
+    generation: u64,
+    readings: BTreeMap<String, ControllerPower>,
+    last_warning: BTreeMap<String, Instant>,
+}
+
+/// Polls controllers that report real-time draw and checks it against
+/// their budgets. Cheap to clone.
+#[derive(Clone)]
+pub struct PowerMonitor {
+    state: Arc<Mutex<PowerState>>,
+}
+
+impl Default for PowerMonitor {
+    fn default() -> Self {
+        Self {
+            state: Arc::new(Mutex::new(PowerState {
+                settings: PowerSettings {
+                    enabled: false,
+                    poll_ms: DEFAULT_POLL_MS,
+                    total_budget_watts: None,
+                    enforce: false,
+                },
+                generation: 0,
+                readings: BTreeMap::new(),
+                last_warning: BTreeMap::new(),
+            })),
+        }
+    }
+}
+
+// `powerWatts`, or `currentAmps` times `voltage`.
+fn parse(status: &Value) -> (Option<f64>, Option<f64>) {
+    let number = |key: &str| status.get(key).and_then(Value::as_f64);
+    let amps = number("currentAmps");
+    let watts = number("powerWatts").or_else(|| Some(amps? * number("voltage")?));
+    (watts, amps)
+}
+
+async fn measure(controller: ControllerInfo) -> ControllerPower {
+    let status = controller_client::get_json(&controller.address, "/status", transport::timeout(controller.transport)).await;
+    let (watts, amps, error) = match status {
+        Ok(status) => {
+            let (watts, amps) = parse(&status);
+            (watts, amps, None)
+        }
+        Err(e) => (None, None, Some(e)),
+    };
+    ControllerPower {
+        over_budget: matches!((watts, controller.power_budget_watts), (Some(w), Some(b)) if w > b),
+        name: controller.display_name(),
+        address: controller.address,
+        watts,
+        amps,
+        budget_watts: controller.power_budget_watts,
+        measured_at: now_millis(),
+        error,
+    }
+}
+
+impl PowerMonitor {
+    fn lock(&self) -> Result<MutexGuard<'_, PowerState>, String> {
+        self.state.lock().map_err(|_| "Power monitor is unavailable".to_string())
+    }
+
+    pub fn configure(&self, app: &AppHandle, settings: PowerSettings) -> Result<(), String> {
+        if !(MIN_POLL_MS..=MAX_POLL_MS).contains(&settings.poll_ms) {
+            return Err(format!(
+                "Power poll interval must be between {} and {} ms, got {}",
+                MIN_POLL_MS, MAX_POLL_MS, settings.poll_ms
+            ));
+        }
+        if settings.total_budget_watts.is_some_and(|w| !w.is_finite() || w <= 0.0) {
+            return Err("Total power budget must be positive".to_string());
+        }
+        let generation = {
+            let mut state = self.lock()?;
+            state.generation += 1;
+            state.settings = settings.clone();
+            if !settings.enabled {
+                state.readings.clear();
+            }
+            state.generation
+        };
+        if settings.enabled {
+            log::info!("Power monitoring every {} ms", settings.poll_ms);
+            tauri::async_runtime::spawn(run_loop(app.clone(), self.clone(), generation));
+        } else {
+            log::info!("Power monitoring stopped");
+        }
+        Ok(())
+    }
+
+    pub fn settings(&self) -> Result<PowerSettings, String> {
+        Ok(self.lock()?.settings.clone())
+    }
+
+    /// Latest reading of every controller polled, by address.
+    pub fn readings(&self) -> Result<Vec<ControllerPower>, String> {
+        Ok(self.lock()?.readings.values().cloned().collect())
+    }
+
+    /// Sum of the latest reported draw.
+    pub fn total_watts(&self) -> f64 {
+        self.lock()
+            .map(|state| state.readings.values().filter_map(|r| r.watts).sum())
+            .unwrap_or(0.0)
+    }
+
+    fn is_current(&self, generation: u64) -> bool {
+        self.lock().map(|s| s.generation == generation).unwrap_or(false)
+    }
+
+    // Stores a poll's readings. While `playing`, also returns the warnings
+    // that are due, each at most every WARNING_INTERVAL per budget, and
+    // whether any budget is exceeded.
+    fn record(&self, readings: Vec<ControllerPower>, playing: bool) -> Option<(Vec<PowerWarning>, bool)> {
+        let mut state = self.lock().ok()?;
+        for reading in readings {
+            state.readings.insert(reading.address.clone(), reading);
+        }
+        if !playing {
+            return None;
+        }
+        let total: f64 = state.readings.values().filter_map(|r| r.watts).sum();
+        let mut over: Vec<PowerWarning> = state
+            .readings
+            .values()
+            .filter(|r| r.over_budget)
+            .map(|r| PowerWarning {
+                controller: Some(r.address.clone()),
+                watts: r.watts.unwrap_or(0.0),
+                budget_watts: r.budget_watts.unwrap_or(0.0),
+                message: format!(
+                    "{} draws {:.0} W of its {:.0} W budget",
+                    r.name.as_deref().unwrap_or(&r.address),
+                    r.watts.unwrap_or(0.0),
+                    r.budget_watts.unwrap_or(0.0)
+                ),
+            })
+            .collect();
+        if let Some(budget) = state.settings.total_budget_watts.filter(|&b| total > b) {
+            over.push(PowerWarning {
+                controller: None,
+                watts: total,
+                budget_watts: budget,
+                message: format!("The rig draws {:.0} W of its {:.0} W budget", total, budget),
+            });
+        }
+        let enforce = state.settings.enforce && !over.is_empty();
+        let now = Instant::now();
+        over.retain(|warning| {
+            let key = warning.controller.as_deref().unwrap_or(TOTAL).to_string();
+            let due = state.last_warning.get(&key).map_or(true, |t| now.duration_since(*t) >= WARNING_INTERVAL);
+            if due {
+                state.last_warning.insert(key, now);
+            }
+            due
+        });
+        Some((over, enforce))
+    }
+}
+
+async fn run_loop(app: AppHandle, monitor: PowerMonitor, generation: u64) {
+    let poll = monitor.settings().map_or(DEFAULT_POLL_MS, |s| s.poll_ms);
+    let mut interval = tokio::time::interval(Duration::from_millis(poll));
+    interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+    while monitor.is_current(generation) {
+        interval.tick().await;
+        let Ok(controllers) = app.state::<ControllerRegistry>().list() else {
+            continue;
+        };
+        let permits = Arc::new(Semaphore::new(MAX_CONCURRENT_QUERIES));
+        let mut polls = JoinSet::new();
+        for controller in controllers.into_iter().filter(|c| c.enabled) {
+            let permits = permits.clone();
+            polls.spawn(async move {
+                let _permit = permits.acquire_owned().await;
+                measure(controller).await
+            });
+        }
+        let mut readings = Vec::new();
+        while let Some(joined) = polls.join_next().await {
+            if let Ok(reading) = joined {
+                readings.push(reading);
+            }
+        }
+        if !monitor.is_current(generation) {
+            break;
+        }
+        // Overdraw only matters while a show is driving the outputs.
+        let engine = app.state::<ShowEngine>();
+        let playing = engine.status().is_ok_and(|s| s.is_running && !s.is_held);
+        let Some((warnings, enforce)) = monitor.record(readings, playing) else {
+            continue;
+        };
+        for warning in warnings {
+            log::warn!("{}", warning.message);
+            events::emit(&app, events::POWER_WARNING, warning);
+        }
+        if enforce {
+            if let Err(e) = engine.fail(&app, "live power draw is over budget") {
+                log::warn!("Could not apply the error policy: {}", e);
+            }
+        }
+    }
+}
@@ -0,0 +1,507 @@
+use crate::controller_client;
+use crate::dispatcher::Dispatcher;
+use crate::events::SelfTestProgress;
+use crate::fleet::MAX_CONCURRENT_QUERIES;
+use crate::models::{Effect, Show};
+use crate::readback::{self, ControllerState};
+use crate::registry::{self, ControllerInfo};
+use crate::transport;
+use crate::validation::{self, ChannelRangeViolation, DanglingReference};
+use serde::Serialize;
+use std::collections::BTreeSet;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use tokio::sync::Semaphore;
+use tokio::task::JoinSet;
+
+/// Oldest controller firmware the desktop app can drive.
+pub const MIN_FIRMWARE_VERSION: &str = "1.0.0";
+
+/// (feature, controller type, first firmware version that supports it).
+/// Raise a version here when a feature starts relying on newer firmware.
+const FEATURE_REQUIREMENTS: [(&str, &str, &str); 6] = [
+    ("relay_output", "lights", "1.0.0"),
+    ("selective_effects", "lights", "1.0.0"),
+    ("effect_interval", "lights", "1.0.0"),
+    ("pyro_fire", "firework", "1.0.0"),
+    ("haze_output", "haze", "1.0.0"),
+    ("laser_projection", "laser", "1.0.0"),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Error,
+    Warning,
+    Info,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum IssueCategory {
+    MissingController,
+    Firmware,
+    ChannelCapability,
+    PowerBudget,
+    Unreachable,
+    OutputMismatch,
+    /// The controller is disabled, so it was not checked and will not play.
+    Disabled,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PreflightIssue {
+    pub category: IssueCategory,
+    pub severity: Severity,
+    pub controller: Option<String>,
+    pub effect_id: Option<String>,
+    pub message: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PreflightReport {
+    /// False if any issue has error severity.
+    pub ready: bool,
+    pub controllers_checked: usize,
+    /// The self-test was cancelled; checks that never ran are counted in `skipped_checks`.
+    pub cancelled: bool,
+    /// Checks not run, because the controller was unreachable or the self-test was cancelled.
+    pub skipped_checks: usize,
+    pub issues: Vec<PreflightIssue>,
+    /// Controllers whose firmware is too old for a feature the show uses.
+    pub firmware_gaps: Vec<FirmwareGap>,
+    /// Effects whose controller or channel is not in the registry.
+    pub dangling_references: Vec<DanglingReference>,
+    /// Effects whose channel is beyond their controller's channel count.
+    pub channel_range: Vec<ChannelRangeViolation>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct FirmwareGap {
+    pub controller: String,
+    pub current_version: String,
+    pub required_version: String,
+    pub blocking_feature: String,
+}
+
+impl PreflightIssue {
+    fn new(category: IssueCategory, severity: Severity, controller: &str, message: String) -> Self {
+        Self {
+            category,
+            severity,
+            controller: Some(controller.to_string()),
+            effect_id: None,
+            message,
+        }
+    }
+}
+
+/// The checks run against each controller, in order.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SelfTestCheck {
+    Connectivity,
+    /// Outputs match what the desk last commanded, where the controller can report them.
+    Readback,
+    Firmware,
+    Features,
+    Power,
+}
+
+const CONTROLLER_CHECKS: [SelfTestCheck; 5] = [
+    SelfTestCheck::Connectivity,
+    SelfTestCheck::Readback,
+    SelfTestCheck::Firmware,
+    SelfTestCheck::Features,
+    SelfTestCheck::Power,
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CheckOutcome {
+    Passed,
+    Failed,
+    /// Not run: the controller was unreachable or the self-test was cancelled.
+    Skipped,
+}
+
+/// Called as each check completes.
+pub type Progress = Arc<dyn Fn(SelfTestProgress) + Send + Sync>;
+
+/// Lets the operator stop a running self-test. Only one runs at a time.
+#[derive(Default)]
+pub struct SelfTestControl {
+    cancel: Mutex<Option<Arc<AtomicBool>>>,
+}
+
+impl SelfTestControl {
+    /// Marks a self-test as running and returns its cancel flag.
+    pub fn begin(&self) -> Result<Arc<AtomicBool>, String> {
+        let mut current = self.cancel.lock().map_err(|_| "Self-test is unavailable".to_string())?;
+        if current.is_some() {
+            return Err("A self-test is already running".to_string());
+        }
+        let flag = Arc::new(AtomicBool::new(false));
+        *current = Some(flag.clone());
+        Ok(flag)
+    }
+
+    pub fn finish(&self) {
+        if let Ok(mut current) = self.cancel.lock() {
+            *current = None;
+        }
+    }
+
+    /// Returns false if no self-test was running.
+    pub fn cancel(&self) -> Result<bool, String> {
+        let current = self.cancel.lock().map_err(|_| "Self-test is unavailable".to_string())?;
+        match current.as_ref() {
+            Some(flag) => {
+                flag.store(true, Ordering::SeqCst);
+                log::info!("Self-test cancelled");
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+}
+
+/// Runs every pre-show check against the registry and returns one report.
+/// References to unregistered controllers and to channels outside a
+/// controller's range are found up front, without contacting anything. Registered controllers are
+/// then checked in parallel, a few at a time. Each controller is
+/// probed first; if it does not answer, its remaining checks are skipped.
+/// Once `cancel` is set no new check starts, checks already in flight
+/// finish, and the report covers what ran.
+pub async fn run(
+    show: &Show,
+    controllers: &[ControllerInfo],
+    dispatcher: &Dispatcher,
+    cancel: Arc<AtomicBool>,
+    progress: Progress,
+) -> PreflightReport {
+    let known = registry::lookup_map(controllers);
+    // Rewrite friendly-name references to addresses so every check below keys on address.
+    let mut show = show.clone();
+    for effect in &mut show.effects {
+        if let Some(info) = known.get(effect.controller.as_str()) {
+            effect.controller = info.address.clone();
+        }
+    }
+    let show = Arc::new(show);
+    let referenced: BTreeSet<&str> = show.effects.iter().map(|e| e.controller.as_str()).collect();
+    let dangling_references = validation::dangling_references(&show, &known);
+    let mut issues: Vec<PreflightIssue> = dangling_references
+        .iter()
+        .map(|d| PreflightIssue {
+            category: IssueCategory::MissingController,
+            severity: Severity::Error,
+            controller: Some(d.controller.clone()),
+            effect_id: Some(d.effect_id.clone()),
+            message: d.message.clone(),
+        })
+        .collect();
+    let channel_range = validation::channel_range_violations(&show, &known);
+    issues.extend(channel_range.iter().map(|v| PreflightIssue {
+        category: IssueCategory::ChannelCapability,
+        severity: Severity::Error,
+        controller: Some(v.controller.clone()),
+        effect_id: Some(v.effect_id.clone()),
+        message: v.to_string(),
+    }));
+
+    let mut to_check = Vec::new();
+    for info in referenced.iter().filter_map(|a| known.get(a)) {
+        if info.enabled {
+            to_check.push((*info).clone());
+        } else {
+            issues.push(PreflightIssue::new(
+                IssueCategory::Disabled,
+                Severity::Warning,
+                &info.address,
+                format!("{} is disabled; its effects will be skipped", info.label()),
+            ));
+        }
+    }
+
+    let total = to_check.len() * CONTROLLER_CHECKS.len();
+    let completed = Arc::new(AtomicUsize::new(0));
+    let permits = Arc::new(Semaphore::new(MAX_CONCURRENT_QUERIES));
+    let mut checks = JoinSet::new();
+    for info in to_check {
+        let (show, cancel, progress, completed, permits) =
+            (show.clone(), cancel.clone(), progress.clone(), completed.clone(), permits.clone());
+        let dispatcher = dispatcher.clone();
+        checks.spawn(async move {
+            let _permit = permits.acquire_owned().await;
+            let report = |check, outcome, issues: usize| {
+                progress(SelfTestProgress {
+                    controller: info.address.clone(),
+                    check,
+                    outcome,
+                    issues,
+                    completed: completed.fetch_add(1, Ordering::SeqCst) + 1,
+                    total,
+                });
+            };
+            check_controller(&show, &info, &dispatcher, &cancel, report).await
+        });
+    }
+
+    let mut firmware_gaps = Vec::new();
+    let mut skipped_checks = 0;
+    while let Some(joined) = checks.join_next().await {
+        match joined {
+            Ok(result) => {
+                issues.extend(result.issues);
+                firmware_gaps.extend(result.firmware_gaps);
+                skipped_checks += result.skipped;
+            }
+            Err(e) => log::error!("Self-test check panicked: {}", e),
+        }
+    }
+    issues.sort_by(|a, b| a.controller.cmp(&b.controller));
+    let cancelled = cancel.load(Ordering::SeqCst);
+
+    PreflightReport {
+        ready: is_ready(cancelled, &issues),
+        controllers_checked: referenced.len(),
+        cancelled,
+        skipped_checks,
+        issues,
+        firmware_gaps,
+        dangling_references,
+        channel_range,
+    }
+}
+
+// A partial report cannot vouch for the show.
+fn is_ready(cancelled: bool, issues: &[PreflightIssue]) -> bool {
+    !cancelled && !issues.iter().any(|i| i.severity == Severity::Error)
+}
+
+#[derive(Default)]
+struct ControllerResult {
+    issues: Vec<PreflightIssue>,
+    firmware_gaps: Vec<FirmwareGap>,
+    skipped: usize,
+}
+
+async fn check_controller(
+    show: &Show,
+    info: &ControllerInfo,
+    dispatcher: &Dispatcher,
+    cancel: &AtomicBool,
+    report: impl Fn(SelfTestCheck, CheckOutcome, usize),
+) -> ControllerResult {
+    let mut result = ControllerResult::default();
+    let mut reachable = true;
+    for check in CONTROLLER_CHECKS {
+        if !reachable || cancel.load(Ordering::SeqCst) {
+            result.skipped += 1;
+            report(check, CheckOutcome::Skipped, 0);
+            continue;
+        }
+        let found = match check {
+            SelfTestCheck::Connectivity => {
+                let found: Vec<PreflightIssue> = check_connectivity(info).await.into_iter().collect();
+                reachable = found.is_empty();
+                found
+            }
+            SelfTestCheck::Readback => check_readback(dispatcher, info).await.into_iter().collect(),
+            SelfTestCheck::Firmware => {
+                let mut found: Vec<PreflightIssue> = check_firmware(info).into_iter().collect();
+                if info.channel_count.is_none() {
+                    found.push(PreflightIssue::new(
+                        IssueCategory::ChannelCapability,
+                        Severity::Info,
+                        &info.address,
+                        format!("Channel count of {} is unknown; channel ranges not checked", info.address),
+                    ));
+                }
+                found
+            }
+            SelfTestCheck::Features => {
+                let gaps = check_features(show, info);
+                let found = gaps.iter().map(gap_issue).collect();
+                result.firmware_gaps.extend(gaps);
+                found
+            }
+            SelfTestCheck::Power => check_power(show, info).into_iter().collect(),
+        };
+        let failed = found.iter().any(|i| i.severity == Severity::Error);
+        let outcome = if failed { CheckOutcome::Failed } else { CheckOutcome::Passed };
+        report(check, outcome, found.len());
+        result.issues.extend(found);
+    }
+    result
+}
+
+// An unreachable controller is an error whatever it drives: a show cannot
+// be vouched for with outputs that will not answer.
+async fn check_connectivity(info: &ControllerInfo) -> Option<PreflightIssue> {
+    let error = controller_client::probe(&info.address, transport::timeout(info.transport)).await.err()?;
+    Some(PreflightIssue::new(IssueCategory::Unreachable, Severity::Error, &info.address, error))
+}
+
+// Controllers without read-back pass; there is nothing to compare.
+async fn check_readback(dispatcher: &Dispatcher, info: &ControllerInfo) -> Option<PreflightIssue> {
+    let message = match readback::read(dispatcher, info).await {
+        Ok(ControllerState::Supported { channels, mismatches, .. }) if mismatches > 0 => {
+            let which: Vec<String> = channels.iter().filter(|c| !c.matches).map(|c| c.channel.to_string()).collect();
+            format!("{} is not outputting what was commanded on channels {}", info.address, which.join(", "))
+        }
+        Ok(_) => return None,
+        Err(e) => format!("Could not read back outputs of {}: {}", info.address, e),
+    };
+    Some(PreflightIssue::new(IssueCategory::OutputMismatch, Severity::Warning, &info.address, message))
+}
+
+fn gap_issue(gap: &FirmwareGap) -> PreflightIssue {
+    PreflightIssue::new(
+        IssueCategory::Firmware,
+        Severity::Error,
+        &gap.controller,
+        format!(
+            "{} runs firmware {}, {} needs at least {}",
+            gap.controller, gap.current_version, gap.blocking_feature, gap.required_version
+        ),
+    )
+}
+
+fn check_firmware(info: &ControllerInfo) -> Option<PreflightIssue> {
+    let Some(version) = info.firmware_version.as_deref() else {
+        return Some(PreflightIssue::new(
+            IssueCategory::Firmware,
+            Severity::Warning,
+            &info.address,
+            format!("Firmware version of {} is unknown", info.address),
+        ));
+    };
+    if parse_version(version) < parse_version(MIN_FIRMWARE_VERSION) {
+        return Some(PreflightIssue::new(
+            IssueCategory::Firmware,
+            Severity::Error,
+            &info.address,
+            format!(
+                "{} runs firmware {}, at least {} is required",
+                info.address, version, MIN_FIRMWARE_VERSION
+            ),
+        ));
+    }
+    None
+}
+
+// Uses the firmware version cached by query_fleet; an unknown version is
+// already flagged by check_firmware.
+fn check_features(show: &Show, info: &ControllerInfo) -> Vec<FirmwareGap> {
+    let Some(version) = info.firmware_version.as_deref() else {
+        return Vec::new();
+    };
+    let used: BTreeSet<&str> = show
+        .effects
+        .iter()
+        .filter(|e| e.controller == info.address)
+        .flat_map(|e| features_used(e, info))
+        .collect();
+    FEATURE_REQUIREMENTS
+        .iter()
+        .filter(|(feature, controller_type, required)| {
+            used.contains(feature)
+                && *controller_type == info.controller_type
+                && parse_version(version) < parse_version(required)
+        })
+        .map(|(feature, _, required)| FirmwareGap {
+            controller: info.address.clone(),
+            current_version: version.to_string(),
+            required_version: required.to_string(),
+            blocking_feature: feature.to_string(),
+        })
+        .collect()
+}
+
+fn features_used(effect: &Effect, info: &ControllerInfo) -> Vec<&'static str> {
+    if info.is_pyro() {
+        return vec!["pyro_fire"];
+    }
+    if info.is_haze() {
+        return vec!["haze_output"];
+    }
+    if info.is_laser() {
+        return vec!["laser_projection"];
+    }
+    match (effect.params.contains_key("effect"), effect.params.contains_key("interval_ms")) {
+        (true, true) => vec!["selective_effects", "effect_interval"],
+        (true, false) => vec!["selective_effects"],
+        (false, _) => vec!["relay_output"],
+    }
+}
+
+// Sweeps effect start/end points to find the peak concurrent draw.
+fn check_power(show: &Show, info: &ControllerInfo) -> Option<PreflightIssue> {
+    let budget = info.power_budget_watts?;
+    let (peak, peak_time) = validation::peak_power(show, info)?;
+    (peak > budget).then(|| {
+        PreflightIssue::new(
+            IssueCategory::PowerBudget,
+            Severity::Warning,
+            &info.address,
+            format!(
+                "{} peaks at {:.0} W at {:.2}s, budget is {:.0} W",
+                info.address, peak, peak_time, budget
+            ),
+        )
+    })
+}
+
+/// Parses "1.2.0-beta" style versions into comparable numeric parts.
+fn parse_version(version: &str) -> Vec<u32> {
+    let mut parts: Vec<u32> = version
+        .trim_start_matches('v')
+        .split(['.', '-', '+'])
+        .map_while(|part| part.parse().ok())
+        .collect();
+    // "1.2" and "1.2.0" are the same version.
+    while parts.last() == Some(&0) {
+        parts.pop();
+    }
+    parts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock_controller::{MockController, Reply};
+    use serde_json::json;
+
+    #[tokio::test]
+    async fn unreachable_pyro_controller_blocks_the_show() {
+        let mock = MockController::start().await;
+        mock.always("/status", Reply::Drop);
+        let firework = mock.controller("firework");
+        let issue = check_connectivity(&firework).await.unwrap();
+        assert_eq!((issue.category, issue.severity), (IssueCategory::Unreachable, Severity::Error));
+        assert!(!is_ready(false, &[issue]));
+    }
+
+    #[tokio::test]
+    async fn answering_controller_passes_connectivity() {
+        let mock = MockController::start().await;
+        assert!(check_connectivity(&mock.controller("firework")).await.is_none());
+        assert_eq!(mock.count("/status"), 1);
+        assert!(is_ready(false, &[]));
+        assert!(!is_ready(true, &[]), "a cancelled self-test is never ready");
+    }
+
+    #[test]
+    fn old_firmware_is_an_error() {
+        let mut firework: ControllerInfo =
+            serde_json::from_value(json!({ "address": "10.0.0.5", "controller_type": "firework", "firmware_version": "0.9.4" }))
+                .unwrap();
+        let issue = check_firmware(&firework).unwrap();
+        assert_eq!(issue.severity, Severity::Error);
+        firework.firmware_version = Some("v1.0".to_string());
+        assert!(check_firmware(&firework).is_none());
+        assert_eq!(parse_version("1.2.0-beta"), [1, 2]);
+    }
+}
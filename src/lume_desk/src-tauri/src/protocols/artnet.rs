@@ -0,0 +1,66 @@
+use std::net::{IpAddr, SocketAddr};
+use tokio::net::UdpSocket;
+
+/// UDP port every Art-Net node listens on.
+pub const PORT: u16 = 6454;
+
+/// Highest 15-bit port-address (net, sub-net and universe).
+pub const MAX_UNIVERSE: u16 = 32767;
+
+const OP_DMX: u16 = 0x5000;
+const PROTOCOL_VERSION: u16 = 14;
+const HEADER_LEN: usize = 18;
+
+/// An ArtDmx packet carrying `data` for one universe. Art-Net wants an even
+/// length of 2 to 512 slots, so short frames are padded with zeros.
+/// `sequence` 0 tells nodes not to reorder packets.
+pub fn dmx_packet(universe: u16, sequence: u8, data: &[u8]) -> Vec<u8> {
+    let length = (data.len().clamp(2, 512) + 1) & !1;
+    let mut packet = Vec::with_capacity(HEADER_LEN + length);
+    packet.extend_from_slice(b"Art-Net\0");
+    packet.extend_from_slice(&OP_DMX.to_le_bytes());
+    packet.extend_from_slice(&PROTOCOL_VERSION.to_be_bytes());
+    packet.push(sequence);
+    // Physical input port; informational only.
+    packet.push(0);
+    packet.extend_from_slice(&(universe & MAX_UNIVERSE).to_le_bytes());
+    packet.extend_from_slice(&(length as u16).to_be_bytes());
+    packet.extend_from_slice(&data[..data.len().min(length)]);
+    packet.resize(HEADER_LEN + length, 0);
+    packet
+}
+
+/// Sends one universe to a node, or to every node when `target` is a
+/// broadcast address. The socket must allow broadcast for the latter.
+pub async fn send(socket: &UdpSocket, target: IpAddr, universe: u16, sequence: u8, data: &[u8]) -> Result<(), String> {
+    socket
+        .send_to(&dmx_packet(universe, sequence, data), SocketAddr::new(target, PORT))
+        .await
+        .map(|_| ())
+        .map_err(|e| format!("Failed to send Art-Net universe {} to {}: {}", universe, target, e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dmx_packet_layout() {
+        let packet = dmx_packet(0x1234, 7, &[10, 20, 30]);
+        assert_eq!(&packet[..8], b"Art-Net\0");
+        assert_eq!(&packet[8..10], &[0x00, 0x50]);
+        assert_eq!(&packet[10..12], &[0, 14]);
+        assert_eq!(packet[12], 7);
+        assert_eq!(&packet[14..16], &[0x34, 0x12]);
+        // Three slots are padded to an even four.
+        assert_eq!(&packet[16..18], &[0, 4]);
+        assert_eq!(&packet[18..], &[10, 20, 30, 0]);
+    }
+
+    #[test]
+    fn dmx_packet_caps_a_full_universe() {
+        let packet = dmx_packet(0, 1, &[255; 600]);
+        assert_eq!(packet.len(), HEADER_LEN + 512);
+        assert_eq!(&packet[16..18], &[0x02, 0x00]);
+    }
+}
@@ -0,0 +1,3 @@
+pub mod artnet;
+pub mod osc;
+pub mod sacn;
@@ -0,0 +1,555 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::ErrorKind;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Duration;
+use tauri::{AppHandle, Manager};
+use tokio::net::UdpSocket;
+
+use crate::control_lock::ControlLock;
+use crate::dispatcher::Dispatcher;
+use crate::events::{self, OscReceived};
+use crate::models::Marker;
+use crate::registry::{self, ControllerRegistry};
+use crate::show_engine::ShowEngine;
+use crate::show_store::ShowStore;
+use crate::trigger;
+use crate::zones;
+
+/// Sent to every target as the playhead passes a marker, with the marker
+/// name and its time in seconds.
+pub const MARKER_ADDRESS: &str = "/lume/marker";
+
+/// Largest packet read; OSC over UDP stays well below this.
+const MAX_PACKET: usize = 8192;
+
+/// How often a listener checks whether it has been replaced.
+const LISTEN_POLL: Duration = Duration::from_millis(250);
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum OscArg {
+    Int(i32),
+    Float(f32),
+    Str(String),
+    Bool(bool),
+}
+
+impl OscArg {
+    fn as_f64(&self) -> Option<f64> {
+        match self {
+            OscArg::Int(value) => Some(*value as f64),
+            OscArg::Float(value) => Some(*value as f64),
+            _ => None,
+        }
+    }
+
+    fn as_str(&self) -> Option<&str> {
+        match self {
+            OscArg::Str(value) => Some(value),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct OscMessage {
+    pub address: String,
+    pub args: Vec<OscArg>,
+}
+
+// OSC strings are NUL-terminated and padded to a multiple of four bytes.
+fn push_string(packet: &mut Vec<u8>, value: &str) {
+    packet.extend_from_slice(value.as_bytes());
+    packet.resize((packet.len() / 4 + 1) * 4, 0);
+}
+
+fn read_string(data: &[u8], pos: usize) -> Option<(String, usize)> {
+    let len = data.get(pos..)?.iter().position(|&b| b == 0)?;
+    let value = std::str::from_utf8(&data[pos..pos + len]).ok()?.to_string();
+    let next = pos + (len / 4 + 1) * 4;
+    (next <= data.len()).then_some((value, next))
+}
+
+fn read_word(data: &[u8], pos: usize) -> Option<[u8; 4]> {
+    data.get(pos..pos + 4)?.try_into().ok()
+}
+
+impl OscMessage {
+    pub fn encode(&self) -> Vec<u8> {
+        let mut packet = Vec::new();
+        push_string(&mut packet, &self.address);
+        let tags: String = std::iter::once(',')
+            .chain(self.args.iter().map(|arg| match arg {
+                OscArg::Int(_) => 'i',
+                OscArg::Float(_) => 'f',
+                OscArg::Str(_) => 's',
+                OscArg::Bool(true) => 'T',
+                OscArg::Bool(false) => 'F',
+            }))
+            .collect();
+        push_string(&mut packet, &tags);
+        for arg in &self.args {
+            match arg {
+                OscArg::Int(value) => packet.extend_from_slice(&value.to_be_bytes()),
+                OscArg::Float(value) => packet.extend_from_slice(&value.to_be_bytes()),
+                OscArg::Str(value) => push_string(&mut packet, value),
+                OscArg::Bool(_) => {}
+            }
+        }
+        packet
+    }
+
+    fn decode_one(data: &[u8]) -> Option<Self> {
+        let (address, pos) = read_string(data, 0)?;
+        if !address.starts_with('/') {
+            return None;
+        }
+        // Very old senders leave out the type tags; such a message has no arguments.
+        let Some((tags, mut pos)) = read_string(data, pos) else {
+            return Some(Self { address, args: Vec::new() });
+        };
+        let mut args = Vec::new();
+        for tag in tags.strip_prefix(',')?.chars() {
+            let arg = match tag {
+                'i' => OscArg::Int(i32::from_be_bytes(read_word(data, pos)?)),
+                'f' => OscArg::Float(f32::from_be_bytes(read_word(data, pos)?)),
+                's' => {
+                    let (value, next) = read_string(data, pos)?;
+                    pos = next;
+                    args.push(OscArg::Str(value));
+                    continue;
+                }
+                'T' => OscArg::Bool(true),
+                'F' => OscArg::Bool(false),
+                _ => return None,
+            };
+            if matches!(arg, OscArg::Int(_) | OscArg::Float(_)) {
+                pos += 4;
+            }
+            args.push(arg);
+        }
+        Some(Self { address, args })
+    }
+
+    /// The messages in a packet, with bundles flattened in order. Bundle time
+    /// tags are ignored: everything is handled on arrival. None for a packet
+    /// that is not valid OSC.
+    pub fn decode(data: &[u8]) -> Option<Vec<Self>> {
+        let Some(mut rest) = data.strip_prefix(b"#bundle\0") else {
+            return Self::decode_one(data).map(|message| vec![message]);
+        };
+        rest = rest.get(8..)?;
+        let mut messages = Vec::new();
+        while !rest.is_empty() {
+            let size = u32::from_be_bytes(read_word(rest, 0)?) as usize;
+            let element = rest.get(4..4 + size)?;
+            messages.extend(Self::decode(element)?);
+            rest = &rest[4 + size..];
+        }
+        Some(messages)
+    }
+}
+
+/// What a received OSC address does.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OscCommand {
+    /// Plays the loaded show.
+    StartShow,
+    StopShow,
+    PauseShow,
+    ResumeShow,
+    /// Moves the playhead to the first argument, in seconds.
+    Seek,
+    /// Moves the playhead to the marker named by the first argument.
+    JumpToMarker,
+}
+
+fn default_mappings() -> BTreeMap<String, OscCommand> {
+    [
+        ("/lume/start", OscCommand::StartShow),
+        ("/lume/stop", OscCommand::StopShow),
+        ("/lume/pause", OscCommand::PauseShow),
+        ("/lume/resume", OscCommand::ResumeShow),
+        ("/lume/seek", OscCommand::Seek),
+        ("/lume/jump", OscCommand::JumpToMarker),
+    ]
+    .into_iter()
+    .map(|(address, command)| (address.to_string(), command))
+    .collect()
+}
+
+fn default_listen_address() -> IpAddr {
+    IpAddr::V4(Ipv4Addr::LOCALHOST)
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OscConfig {
+    /// UDP port external controllers send to; None does not listen.
+    #[serde(default)]
+    pub listen_port: Option<u16>,
+    /// Interface the listener binds to. Loopback unless set, so only
+    /// software on this machine can start shows.
+    #[serde(default = "default_listen_address")]
+    pub listen_address: IpAddr,
+    /// Senders whose commands run; empty allows any that can reach
+    /// `listen_address`. Messages from others are reported, not run.
+    #[serde(default)]
+    pub allowed_senders: Vec<IpAddr>,
+    /// Media servers that get a message for every marker passed.
+    #[serde(default)]
+    pub targets: Vec<SocketAddr>,
+    #[serde(default = "default_mappings")]
+    pub mappings: BTreeMap<String, OscCommand>,
+}
+
+impl Default for OscConfig {
+    fn default() -> Self {
+        Self {
+            listen_port: None,
+            listen_address: default_listen_address(),
+            allowed_senders: Vec::new(),
+            targets: Vec::new(),
+            mappings: default_mappings(),
+        }
+    }
+}
+
+#[derive(Default)]
+struct State {
+    config: OscConfig,
+    path: Option<PathBuf>,
+    // Bumped when the listen port changes so the old listener exits.
+    generation: u64,
+}
+
+/// Takes show commands from external show controllers over OSC and tells
+/// media servers when the timeline passes a marker. Cheap to clone.
+#[derive(Clone, Default)]
+pub struct Osc {
+    state: Arc<Mutex<State>>,
+}
+
+impl OscConfig {
+    fn accepts(&self, sender: IpAddr) -> bool {
+        self.allowed_senders.is_empty() || self.allowed_senders.contains(&sender)
+    }
+}
+
+async fn bind(address: IpAddr, port: u16) -> Result<UdpSocket, String> {
+    UdpSocket::bind((address, port))
+        .await
+        .map_err(|e| format!("Failed to listen for OSC on {}: {}", SocketAddr::new(address, port), e))
+}
+
+impl Osc {
+    fn lock(&self) -> Result<MutexGuard<'_, State>, String> {
+        self.state.lock().map_err(|_| "OSC is unavailable".to_string())
+    }
+
+    /// Loads the saved port, targets and mappings and remembers `path` for
+    /// later saves. A missing file means the defaults.
+    pub fn load(&self, path: PathBuf) -> Result<(), String> {
+        let config: OscConfig = match fs::read(&path) {
+            Ok(data) => serde_json::from_slice(&data).map_err(|e| format!("Invalid OSC config file: {}", e))?,
+            Err(e) if e.kind() == ErrorKind::NotFound => OscConfig::default(),
+            Err(e) => return Err(format!("Failed to read {}: {}", path.display(), e)),
+        };
+        let mut state = self.lock()?;
+        state.config = config;
+        state.path = Some(path);
+        Ok(())
+    }
+
+    fn save(state: &State) -> Result<(), String> {
+        registry::save_json(state.path.clone(), &state.config).map_err(|e| format!("Failed to save OSC config: {}", e))
+    }
+
+    pub fn config(&self) -> Result<OscConfig, String> {
+        Ok(self.lock()?.config.clone())
+    }
+
+    /// Listens on the loaded port, if any. A port that cannot be bound is
+    /// logged, not fatal.
+    pub fn start(&self, app: &AppHandle) {
+        let Ok(state) = self.lock() else {
+            return;
+        };
+        let config = &state.config;
+        let (Some(port), address, generation) = (config.listen_port, config.listen_address, state.generation) else {
+            return;
+        };
+        drop(state);
+        let (app, osc) = (app.clone(), self.clone());
+        tauri::async_runtime::spawn(async move {
+            match bind(address, port).await {
+                Ok(socket) => listen(app, osc, generation, socket).await,
+                Err(e) => log::error!("{}", e),
+            }
+        });
+    }
+
+    /// Listens on `listen_port` of `listen_address` (loopback if None), or
+    /// stops listening without a port, runs commands only from
+    /// `allowed_senders` when any are given, and sends marker cues to
+    /// `targets` ("host:port"). Keeps the mappings.
+    pub async fn configure(
+        &self,
+        app: &AppHandle,
+        listen_port: Option<u16>,
+        listen_address: Option<IpAddr>,
+        allowed_senders: Vec<IpAddr>,
+        targets: Vec<String>,
+    ) -> Result<OscConfig, String> {
+        let listen_address = listen_address.unwrap_or_else(default_listen_address);
+        let mut resolved = Vec::with_capacity(targets.len());
+        for target in &targets {
+            let address = tokio::net::lookup_host(target.as_str())
+                .await
+                .map_err(|e| format!("Invalid OSC target {}: {}", target, e))?
+                .next()
+                .ok_or_else(|| format!("OSC target {} did not resolve", target))?;
+            resolved.push(address);
+        }
+        let rebind = {
+            let state = self.lock()?;
+            state.config.listen_port != listen_port || state.config.listen_address != listen_address
+        };
+        // Bound before anything changes so a port in use leaves the old setup.
+        let socket = match listen_port {
+            Some(port) if rebind => Some(bind(listen_address, port).await?),
+            _ => None,
+        };
+
+        let (config, generation) = {
+            let mut state = self.lock()?;
+            if rebind {
+                state.generation += 1;
+            }
+            state.config.listen_port = listen_port;
+            state.config.listen_address = listen_address;
+            state.config.allowed_senders = allowed_senders;
+            state.config.targets = resolved;
+            Self::save(&state)?;
+            (state.config.clone(), state.generation)
+        };
+        match listen_port {
+            Some(port) => log::info!(
+                "OSC listening on {}, {} target(s)",
+                SocketAddr::new(listen_address, port),
+                config.targets.len()
+            ),
+            None => log::info!("OSC not listening, {} target(s)", config.targets.len()),
+        }
+        if let Some(socket) = socket {
+            tauri::async_runtime::spawn(listen(app.clone(), self.clone(), generation, socket));
+        }
+        Ok(config)
+    }
+
+    /// Makes `address` run `command`; None removes the mapping.
+    pub fn map(&self, address: &str, command: Option<OscCommand>) -> Result<OscConfig, String> {
+        if !address.starts_with('/') {
+            return Err(format!("OSC addresses start with '/', got {}", address));
+        }
+        let mut state = self.lock()?;
+        match command {
+            Some(command) => state.config.mappings.insert(address.to_string(), command),
+            None => state.config.mappings.remove(address),
+        };
+        Self::save(&state)?;
+        Ok(state.config.clone())
+    }
+
+    fn is_current(&self, generation: u64) -> bool {
+        self.lock().map(|s| s.generation == generation).unwrap_or(false)
+    }
+
+    /// Sends a marker cue to every target for each marker the playhead passed.
+    pub fn markers_passed(&self, markers: Vec<Marker>) {
+        let targets = match self.lock() {
+            Ok(state) => state.config.targets.clone(),
+            Err(_) => return,
+        };
+        if targets.is_empty() || markers.is_empty() {
+            return;
+        }
+        tauri::async_runtime::spawn(async move {
+            let socket = match UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0)).await {
+                Ok(socket) => socket,
+                Err(e) => {
+                    log::error!("Failed to open OSC output socket: {}", e);
+                    return;
+                }
+            };
+            for marker in markers {
+                let packet = OscMessage {
+                    address: MARKER_ADDRESS.to_string(),
+                    args: vec![OscArg::Str(marker.name.clone()), OscArg::Float(marker.time as f32)],
+                }
+                .encode();
+                for target in &targets {
+                    if let Err(e) = socket.send_to(&packet, target).await {
+                        log::warn!("Failed to send OSC marker {} to {}: {}", marker.name, target, e);
+                    }
+                }
+                log::info!("OSC marker {} sent to {} target(s)", marker.name, targets.len());
+            }
+        });
+    }
+
+    // Runs the mapped command. Every message is reported, mapped or not, so
+    // the UI can show what a controller sends.
+    async fn receive(&self, app: &AppHandle, from: SocketAddr, message: OscMessage) {
+        let (accepted, command) = match self.lock() {
+            Ok(state) => (state.config.accepts(from.ip()), state.config.mappings.get(&message.address).copied()),
+            Err(_) => (false, None),
+        };
+        let result = match command {
+            _ if !accepted => Err(format!("{} is not an allowed OSC sender", from.ip())),
+            Some(command) => run(app, command, &message.args).await,
+            None => Err(format!("No command is mapped to {}", message.address)),
+        };
+        match (&command, &result) {
+            (Some(command), Ok(())) => log::info!("OSC {} from {} ran {:?}", message.address, from, command),
+            (_, Err(e)) => log::info!("OSC {} from {} ignored: {}", message.address, from, e),
+            (None, Ok(())) => {}
+        }
+        events::emit(
+            app,
+            events::OSC_RECEIVED,
+            OscReceived {
+                address: message.address,
+                from: from.to_string(),
+                command,
+                error: result.err(),
+            },
+        );
+    }
+}
+
+async fn run(app: &AppHandle, command: OscCommand, args: &[OscArg]) -> Result<(), String> {
+    app.state::<ControlLock>().check(None)?;
+    let engine = app.state::<ShowEngine>();
+    match command {
+        OscCommand::StartShow => trigger::start_loaded_show(app),
+        OscCommand::StopShow => {
+            if let Some((show_id, time)) = engine.stop(app).await? {
+                if let Err(e) = app.state::<ShowStore>().remember_position(&show_id, time) {
+                    log::warn!("Failed to remember playback position: {}", e);
+                }
+            }
+            zones::apply_safe_defaults(&app.state::<Dispatcher>(), &app.state::<ControllerRegistry>())
+                .await
+                .map(|_| ())
+        }
+        OscCommand::PauseShow => engine.pause(app).map(|_| ()),
+        OscCommand::ResumeShow => engine.resume(app).map(|_| ()),
+        OscCommand::Seek => {
+            let time = args
+                .first()
+                .and_then(OscArg::as_f64)
+                .ok_or("Seek needs a time in seconds")?;
+            engine.seek(app, time).await.map(|_| ())
+        }
+        OscCommand::JumpToMarker => {
+            let name = args.first().and_then(OscArg::as_str).ok_or("Jump needs a marker name")?;
+            let show = engine.running_show()?.ok_or("No show is playing")?;
+            let marker = show
+                .markers
+                .iter()
+                .find(|m| m.name == name)
+                .ok_or_else(|| format!("Show has no marker named {}", name))?;
+            engine.seek(app, marker.time).await.map(|_| ())
+        }
+    }
+}
+
+async fn listen(app: AppHandle, osc: Osc, generation: u64, socket: UdpSocket) {
+    let mut buffer = vec![0_u8; MAX_PACKET];
+    while osc.is_current(generation) {
+        let (len, from) = match tokio::time::timeout(LISTEN_POLL, socket.recv_from(&mut buffer)).await {
+            Ok(Ok(received)) => received,
+            Ok(Err(e)) => {
+                log::warn!("OSC receive failed: {}", e);
+                continue;
+            }
+            Err(_) => continue,
+        };
+        match OscMessage::decode(&buffer[..len]) {
+            Some(messages) => {
+                for message in messages {
+                    osc.receive(&app, from, message).await;
+                }
+            }
+            None => log::debug!("Ignored a packet from {} that is not OSC", from),
+        }
+    }
+    log::info!("OSC listener stopped");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn message_round_trip() {
+        let message = OscMessage {
+            address: "/lume/marker".to_string(),
+            args: vec![
+                OscArg::Str("drop".to_string()),
+                OscArg::Float(12.5),
+                OscArg::Int(-3),
+                OscArg::Bool(true),
+            ],
+        };
+        let packet = message.encode();
+        assert_eq!(packet.len() % 4, 0);
+        assert_eq!(&packet[..16], b"/lume/marker\0\0\0\0");
+        assert_eq!(&packet[16..24], b",sfiT\0\0\0");
+        assert_eq!(OscMessage::decode(&packet), Some(vec![message]));
+    }
+
+    #[test]
+    fn bundles_are_flattened() {
+        let start = OscMessage { address: "/lume/start".to_string(), args: Vec::new() }.encode();
+        let seek = OscMessage { address: "/lume/seek".to_string(), args: vec![OscArg::Float(4.0)] }.encode();
+        let mut packet = b"#bundle\0".to_vec();
+        packet.extend_from_slice(&[0, 0, 0, 0, 0, 0, 0, 1]);
+        for element in [&start, &seek] {
+            packet.extend_from_slice(&(element.len() as u32).to_be_bytes());
+            packet.extend_from_slice(element);
+        }
+        let messages = OscMessage::decode(&packet).unwrap();
+        assert_eq!(messages.len(), 2);
+        assert_eq!(messages[0].address, "/lume/start");
+        assert_eq!(messages[1].args, vec![OscArg::Float(4.0)]);
+    }
+
+    #[test]
+    fn listens_on_loopback_unless_configured() {
+        assert_eq!(OscConfig::default().listen_address, IpAddr::V4(Ipv4Addr::LOCALHOST));
+        let saved: OscConfig = serde_json::from_str(r#"{ "listen_port": 8000 }"#).unwrap();
+        assert!(saved.listen_address.is_loopback());
+    }
+
+    #[test]
+    fn only_allowed_senders_are_accepted() {
+        let mut config = OscConfig::default();
+        let (desk, stranger) = ("10.0.0.5".parse().unwrap(), "10.0.0.9".parse().unwrap());
+        assert!(config.accepts(stranger));
+        config.allowed_senders = vec![desk];
+        assert!(config.accepts(desk));
+        assert!(!config.accepts(stranger));
+    }
+
+    #[test]
+    fn malformed_packets_are_rejected() {
+        assert_eq!(OscMessage::decode(b"lume\0\0\0\0"), None);
+        assert_eq!(OscMessage::decode(b"/lume/seek\0\0,f\0\0\0\0"), None);
+        assert_eq!(OscMessage::decode(b"#bundle\0\0\0\0\0\0\0\x01\0\0\0\x40"), None);
+    }
+}
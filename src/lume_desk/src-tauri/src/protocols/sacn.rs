@@ -0,0 +1,161 @@
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::sync::OnceLock;
+use tokio::net::UdpSocket;
+
+/// UDP port every E1.31 receiver listens on.
+pub const PORT: u16 = 5568;
+
+pub const MIN_UNIVERSE: u16 = 1;
+pub const MAX_UNIVERSE: u16 = 63999;
+
+pub const DEFAULT_PRIORITY: u8 = 100;
+pub const MAX_PRIORITY: u8 = 200;
+
+const SOURCE_NAME: &str = "LUME";
+
+const ACN_IDENTIFIER: &[u8; 12] = b"ASC-E1.17\0\0\0";
+const VECTOR_ROOT_DATA: u32 = 0x0000_0004;
+const VECTOR_ROOT_EXTENDED: u32 = 0x0000_0008;
+const VECTOR_FRAMING_DATA: u32 = 0x0000_0002;
+const VECTOR_FRAMING_SYNC: u32 = 0x0000_0001;
+const VECTOR_DMP_SET_PROPERTY: u8 = 0x02;
+
+const DATA_HEADER_LEN: usize = 126;
+const SYNC_PACKET_LEN: usize = 49;
+
+/// Identifies this app as one source to receivers for as long as it runs.
+fn source_cid() -> &'static [u8; 16] {
+    static CID: OnceLock<[u8; 16]> = OnceLock::new();
+    CID.get_or_init(|| *uuid::Uuid::new_v4().as_bytes())
+}
+
+/// The multicast group receivers of `universe` join: 239.255.hi.lo.
+pub fn multicast_address(universe: u16) -> IpAddr {
+    let [hi, lo] = universe.to_be_bytes();
+    IpAddr::V4(Ipv4Addr::new(239, 255, hi, lo))
+}
+
+// PDU flags (0x7) over a 12-bit length counted from `start` to the end.
+fn flags_and_length(total: usize, start: usize) -> [u8; 2] {
+    (0x7000 | (total - start) as u16).to_be_bytes()
+}
+
+fn root_layer(packet: &mut Vec<u8>, total: usize, vector: u32) {
+    packet.extend_from_slice(&0x0010_u16.to_be_bytes());
+    packet.extend_from_slice(&0_u16.to_be_bytes());
+    packet.extend_from_slice(ACN_IDENTIFIER);
+    packet.extend_from_slice(&flags_and_length(total, 16));
+    packet.extend_from_slice(&vector.to_be_bytes());
+    packet.extend_from_slice(source_cid());
+}
+
+/// An E1.31 data packet carrying up to 512 slots of `universe`. Receivers
+/// take the highest-priority source of a universe. With a `sync_universe`
+/// receivers hold the frame until a sync packet for it arrives.
+pub fn data_packet(universe: u16, priority: u8, sync_universe: Option<u16>, sequence: u8, data: &[u8]) -> Vec<u8> {
+    let data = &data[..data.len().min(512)];
+    let total = DATA_HEADER_LEN + data.len();
+    let mut packet = Vec::with_capacity(total);
+    root_layer(&mut packet, total, VECTOR_ROOT_DATA);
+
+    packet.extend_from_slice(&flags_and_length(total, 38));
+    packet.extend_from_slice(&VECTOR_FRAMING_DATA.to_be_bytes());
+    let mut name = [0_u8; 64];
+    name[..SOURCE_NAME.len()].copy_from_slice(SOURCE_NAME.as_bytes());
+    packet.extend_from_slice(&name);
+    packet.push(priority.min(MAX_PRIORITY));
+    packet.extend_from_slice(&sync_universe.unwrap_or(0).to_be_bytes());
+    packet.push(sequence);
+    // Options: not a preview, not terminated, no forced sync.
+    packet.push(0);
+    packet.extend_from_slice(&universe.to_be_bytes());
+
+    packet.extend_from_slice(&flags_and_length(total, 115));
+    packet.push(VECTOR_DMP_SET_PROPERTY);
+    // Address and data type, first property address, address increment.
+    packet.extend_from_slice(&[0xa1, 0x00, 0x00, 0x00, 0x01]);
+    packet.extend_from_slice(&(data.len() as u16 + 1).to_be_bytes());
+    // DMX start code.
+    packet.push(0);
+    packet.extend_from_slice(data);
+    packet
+}
+
+/// An E1.31 synchronization packet: receivers waiting on `sync_universe`
+/// output the frames they hold.
+pub fn sync_packet(sync_universe: u16, sequence: u8) -> Vec<u8> {
+    let mut packet = Vec::with_capacity(SYNC_PACKET_LEN);
+    root_layer(&mut packet, SYNC_PACKET_LEN, VECTOR_ROOT_EXTENDED);
+    packet.extend_from_slice(&flags_and_length(SYNC_PACKET_LEN, 38));
+    packet.extend_from_slice(&VECTOR_FRAMING_SYNC.to_be_bytes());
+    packet.push(sequence);
+    packet.extend_from_slice(&sync_universe.to_be_bytes());
+    // Reserved.
+    packet.extend_from_slice(&[0, 0]);
+    packet
+}
+
+/// Sends one universe to `target`, or to its multicast group without one.
+pub async fn send(
+    socket: &UdpSocket,
+    target: Option<IpAddr>,
+    universe: u16,
+    priority: u8,
+    sync_universe: Option<u16>,
+    sequence: u8,
+    data: &[u8],
+) -> Result<(), String> {
+    let target = target.unwrap_or_else(|| multicast_address(universe));
+    socket
+        .send_to(&data_packet(universe, priority, sync_universe, sequence, data), SocketAddr::new(target, PORT))
+        .await
+        .map(|_| ())
+        .map_err(|e| format!("Failed to send sACN universe {} to {}: {}", universe, target, e))
+}
+
+pub async fn send_sync(socket: &UdpSocket, sync_universe: u16, sequence: u8) -> Result<(), String> {
+    let target = SocketAddr::new(multicast_address(sync_universe), PORT);
+    socket
+        .send_to(&sync_packet(sync_universe, sequence), target)
+        .await
+        .map(|_| ())
+        .map_err(|e| format!("Failed to send sACN sync on universe {}: {}", sync_universe, e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn data_packet_layout() {
+        let packet = data_packet(0x0102, 150, Some(7), 9, &[255; 512]);
+        assert_eq!(packet.len(), 638);
+        assert_eq!(&packet[4..16], ACN_IDENTIFIER);
+        assert_eq!(&packet[16..18], &[0x72, 0x6e]);
+        assert_eq!(&packet[38..40], &[0x72, 0x58]);
+        assert_eq!(&packet[44..48], SOURCE_NAME.as_bytes());
+        assert_eq!(packet[108], 150);
+        assert_eq!(&packet[109..111], &[0, 7]);
+        assert_eq!(packet[111], 9);
+        assert_eq!(&packet[113..115], &[0x01, 0x02]);
+        assert_eq!(&packet[115..117], &[0x72, 0x0b]);
+        assert_eq!(&packet[123..125], &[0x02, 0x01]);
+        assert_eq!(packet[125], 0);
+    }
+
+    #[test]
+    fn sync_packet_layout() {
+        let packet = sync_packet(7, 3);
+        assert_eq!(packet.len(), SYNC_PACKET_LEN);
+        assert_eq!(&packet[18..22], &VECTOR_ROOT_EXTENDED.to_be_bytes());
+        assert_eq!(&packet[40..44], &VECTOR_FRAMING_SYNC.to_be_bytes());
+        assert_eq!(packet[44], 3);
+        assert_eq!(&packet[45..47], &[0, 7]);
+    }
+
+    #[test]
+    fn multicast_group_follows_the_universe() {
+        assert_eq!(multicast_address(1), IpAddr::V4(Ipv4Addr::new(239, 255, 0, 1)));
+        assert_eq!(multicast_address(63999), IpAddr::V4(Ipv4Addr::new(239, 255, 249, 255)));
+    }
+}
@@ -0,0 +1,184 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+use crate::dispatcher::{Dispatcher, OutputAction};
+use crate::haze::{self, HAZE_CHANNEL};
+use crate::models::{Effect, Rgb};
+use crate::output_refresh::OutputRefresh;
+use crate::performance;
+use crate::registry::ControllerInfo;
+
+/// Frame rate for controllers without an output refresh rate of their own.
+pub const DEFAULT_FRAME_HZ: u32 = 25;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Curve {
+    #[default]
+    Linear,
+    EaseIn,
+    EaseOut,
+    EaseInOut,
+}
+
+impl Curve {
+    /// Maps progress `t` in 0..=1 onto the curve.
+    pub fn apply(self, t: f64) -> f64 {
+        let t = t.clamp(0.0, 1.0);
+        match self {
+            Curve::Linear => t,
+            Curve::EaseIn => t * t,
+            Curve::EaseOut => t * (2.0 - t),
+            Curve::EaseInOut if t < 0.5 => 2.0 * t * t,
+            Curve::EaseInOut => 1.0 - (2.0 - 2.0 * t).powi(2) / 2.0,
+        }
+    }
+}
+
+/// A dimmer or haze level in percent, or a color.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(untagged)]
+pub enum RampValue {
+    Level(f64),
+    Color(Rgb),
+}
+
+/// Moves an output from `from` to `to` over the start of an effect. The
+/// output then holds `to` until the effect ends and is cleared.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct Ramp {
+    pub from: RampValue,
+    pub to: RampValue,
+    #[serde(default)]
+    pub curve: Curve,
+    /// Seconds; defaults to the whole effect.
+    #[serde(default)]
+    pub duration: Option<f64>,
+}
+
+fn lerp(from: f64, to: f64, t: f64) -> f64 {
+    from + (to - from) * t
+}
+
+impl Ramp {
+    pub fn duration(&self, effect: &Effect) -> f64 {
+        self.duration.unwrap_or(effect.duration)
+    }
+
+    pub fn value_at(&self, progress: f64) -> RampValue {
+        let t = self.curve.apply(progress);
+        match (&self.from, &self.to) {
+            (RampValue::Color(from), RampValue::Color(to)) => {
+                RampValue::Color([0, 1, 2].map(|i| lerp(from[i] as f64, to[i] as f64, t).round() as u8))
+            }
+            (RampValue::Level(from), RampValue::Level(to)) => RampValue::Level(lerp(*from, *to, t)),
+            // Mixed kinds are rejected by `check`; hold the start value.
+            (from, _) => from.clone(),
+        }
+    }
+
+    /// Problems with `effect`'s ramp on its own terms; the controller type
+    /// is checked separately by `frame`.
+    pub fn check(&self, effect: &Effect, show_duration: f64) -> Result<(), String> {
+        match (&self.from, &self.to) {
+            (RampValue::Level(from), RampValue::Level(to)) => {
+                for level in [from, to] {
+                    if !(0.0..=100.0).contains(level) {
+                        return Err(format!("Ramp levels must be 0-100, got {}", level));
+                    }
+                }
+            }
+            (RampValue::Color(_), RampValue::Color(_)) => {}
+            _ => return Err("Ramp must go from a level to a level or from a color to a color".to_string()),
+        }
+        let duration = self.duration(effect);
+        if !duration.is_finite() || duration <= 0.0 {
+            return Err(format!("Ramp duration must be positive, got {}s", duration));
+        }
+        if duration > effect.duration {
+            return Err(format!(
+                "Ramp lasts {:.2}s but the effect only lasts {:.2}s",
+                duration, effect.duration
+            ));
+        }
+        if show_duration > 0.0 && effect.start_time + duration > show_duration {
+            return Err(format!(
+                "Ramp ends at {:.2}s, after the show ends at {:.2}s",
+                effect.start_time + duration,
+                show_duration
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// The output action for one frame of a ramp on `controller`.
+pub fn frame(controller: &ControllerInfo, value: &RampValue) -> Result<OutputAction, String> {
+    match value {
+        RampValue::Level(level) if controller.is_haze() => Ok(OutputAction::Haze(level.round() as u8)),
+        RampValue::Level(level) if controller.controller_type == "lights" => Ok(OutputAction::Dimmer(level.round() as u8)),
+        RampValue::Color(rgb) if controller.controller_type == "lights" => Ok(OutputAction::Color(*rgb)),
+        RampValue::Level(_) => Err(format!("{} has no dimmable output", controller.label())),
+        RampValue::Color(_) => Err(format!("{} has no color output", controller.label())),
+    }
+}
+
+/// Frames per second for ramps on `controller`: its output refresh rate if
+/// one is set, capped so frames never come faster than it can take commands,
+/// and lower still for ramps started in degraded mode.
+pub fn frame_rate(controller: &ControllerInfo, refresh: &OutputRefresh) -> u32 {
+    let requested = refresh
+        .rates()
+        .ok()
+        .and_then(|rates| rates.into_iter().find(|r| r.target == controller.address))
+        .map_or(DEFAULT_FRAME_HZ, |r| r.requested_hz);
+    let spacing_ms = controller.min_command_spacing().as_millis().max(1) as u32;
+    let rate = requested.min(1000 / spacing_ms);
+    if performance::is_degraded() {
+        rate.clamp(1, performance::DEGRADED_FRAME_HZ)
+    } else {
+        rate.max(1)
+    }
+}
+
+/// Plays a show effect that carries a ramp.
+pub async fn play(
+    dispatcher: &Dispatcher,
+    refresh: &OutputRefresh,
+    controller: &ControllerInfo,
+    effect: &Effect,
+    ramp: &Ramp,
+) -> Result<(), String> {
+    ramp.check(effect, 0.0)?;
+    let first = frame(controller, &ramp.from)?;
+    let off = first.clear_action().ok_or("Ramp output cannot be switched off")?;
+    let hold = Duration::from_secs_f64(effect.duration);
+    let channel = if controller.is_haze() {
+        if hold > haze::MAX_HAZE_RUN {
+            return Err(format!(
+                "Haze effect of {:.0}s exceeds the {}s interlock",
+                effect.duration,
+                haze::MAX_HAZE_RUN.as_secs()
+            ));
+        }
+        HAZE_CHANNEL
+    } else {
+        effect.channel
+    };
+
+    let ramp_frames = ramp.clone();
+    let frame_controller = controller.clone();
+    dispatcher
+        .stream(
+            &controller.address,
+            channel,
+            first,
+            move |progress| frame(&frame_controller, &ramp_frames.value_at(progress)),
+            Duration::from_secs_f64(ramp.duration(effect)),
+            hold,
+            off,
+            frame_rate(controller, refresh),
+        )
+        .await
+}
@@ -0,0 +1,148 @@
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::HashMap;
+
+use crate::controller_client;
+use crate::dispatcher::{Dispatcher, OutputAction};
+use crate::haze::HAZE_CHANNEL;
+use crate::models::Rgb;
+use crate::registry::ControllerInfo;
+use crate::transport;
+
+/// What one channel is outputting, next to what the desk last commanded.
+#[derive(Debug, Serialize)]
+pub struct ChannelReading {
+    pub channel: u32,
+    pub on: bool,
+    /// Dimmer or haze level in percent, if the controller reports one.
+    pub level: Option<u8>,
+    pub color: Option<Rgb>,
+    pub expected_on: bool,
+    pub expected_level: Option<u8>,
+    pub expected_color: Option<Rgb>,
+    /// False if the output differs from the command in any reported value.
+    pub matches: bool,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(tag = "status", rename_all = "lowercase")]
+pub enum ControllerState {
+    Supported {
+        address: String,
+        channels: Vec<ChannelReading>,
+        mismatches: usize,
+    },
+    /// The controller cannot report its outputs; nothing was compared.
+    Unsupported { address: String, reason: String },
+}
+
+struct Reported {
+    on: bool,
+    level: Option<u8>,
+    color: Option<Rgb>,
+}
+
+// Output values from `/status`. Lighting firmware reports `relayStates`
+// and, if it has dimmable or color outputs, `dimmerLevels` and `colors`,
+// all indexed from channel 1. Haze firmware reports `hazeLevel`.
+fn parse_status(controller: &ControllerInfo, status: &Value) -> Result<Vec<(u32, Reported)>, String> {
+    if controller.is_haze() {
+        let level = status
+            .get("hazeLevel")
+            .and_then(Value::as_u64)
+            .ok_or_else(|| "firmware does not report its haze level".to_string())?;
+        let level = level.min(100) as u8;
+        return Ok(vec![(
+            HAZE_CHANNEL,
+            Reported {
+                on: level > 0,
+                level: Some(level),
+                color: None,
+            },
+        )]);
+    }
+    let relays = status
+        .get("relayStates")
+        .and_then(Value::as_array)
+        .ok_or_else(|| "firmware does not report its output states".to_string())?;
+    let list = |key: &str| status.get(key).and_then(Value::as_array).cloned().unwrap_or_default();
+    let (levels, colors) = (list("dimmerLevels"), list("colors"));
+    Ok(relays
+        .iter()
+        .enumerate()
+        .map(|(index, on)| {
+            let level = levels.get(index).and_then(Value::as_u64).map(|l| l.min(100) as u8);
+            let color = colors
+                .get(index)
+                .and_then(|c| serde_json::from_value::<Rgb>(c.clone()).ok());
+            let reported = Reported {
+                on: on.as_bool().unwrap_or(false) || level.is_some_and(|l| l > 0),
+                level,
+                color,
+            };
+            (index as u32 + 1, reported)
+        })
+        .collect())
+}
+
+fn reading(channel: u32, reported: Reported, commanded: Option<&OutputAction>) -> ChannelReading {
+    let (expected_on, expected_level, expected_color) = match commanded {
+        Some(OutputAction::Relay(on)) => (*on, None, None),
+        Some(OutputAction::Dimmer(level)) | Some(OutputAction::Haze(level)) => (*level > 0, Some(*level), None),
+        Some(OutputAction::Color(rgb)) => (*rgb != [0, 0, 0], None, Some(*rgb)),
+        // Anything else the desk left running counts as on.
+        Some(_) => (true, None, None),
+        None => (false, None, None),
+    };
+    let matches = reported.on == expected_on
+        && expected_level.map_or(true, |l| reported.level.map_or(true, |r| r == l))
+        && expected_color.map_or(true, |c| reported.color.map_or(true, |r| r == c));
+    ChannelReading {
+        channel,
+        on: reported.on,
+        level: reported.level,
+        color: reported.color,
+        expected_on,
+        expected_level,
+        expected_color,
+        matches,
+    }
+}
+
+/// Asks `controller` what it is outputting right now and compares it with
+/// the steady state the desk last commanded on each channel. Firework
+/// cues and laser frames are momentary, so those controllers never support
+/// read-back.
+pub async fn read(dispatcher: &Dispatcher, controller: &ControllerInfo) -> Result<ControllerState, String> {
+    let address = controller.address.clone();
+    if controller.is_pyro() || controller.is_laser() {
+        return Ok(ControllerState::Unsupported {
+            reason: format!("{} outputs are momentary and cannot be read back", controller.label()),
+            address,
+        });
+    }
+    let status = controller_client::get_json(&address, "/status", transport::timeout(controller.transport)).await?;
+    let reported = match parse_status(controller, &status) {
+        Ok(reported) => reported,
+        Err(reason) => {
+            return Ok(ControllerState::Unsupported {
+                reason: format!("{} {}", controller.label(), reason),
+                address,
+            })
+        }
+    };
+    let commanded: HashMap<u32, OutputAction> = dispatcher.refreshable_states(&address).into_iter().collect();
+    let channels: Vec<ChannelReading> = reported
+        .into_iter()
+        .map(|(channel, reported)| reading(channel, reported, commanded.get(&channel)))
+        .collect();
+    let mismatches = channels.iter().filter(|c| !c.matches).count();
+    if mismatches > 0 {
+        log::warn!("{} differs from its commanded state on {} channels", controller.label(), mismatches);
+    }
+    Ok(ControllerState::Supported {
+        address,
+        channels,
+        mismatches,
+    })
+}
@@ -0,0 +1,675 @@
+use crate::audit;
+use crate::dispatcher::OutputAction;
+use crate::show_store;
+use crate::transport::{self, Transport};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::ErrorKind;
+use std::path::PathBuf;
+use std::sync::{Mutex, MutexGuard};
+use std::time::Duration;
+
+/// What we know about a controller the operator has paired with.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ControllerInfo {
+    pub address: String,
+    #[serde(default)]
+    pub name: String,
+    #[serde(default)]
+    pub controller_type: String,
+    #[serde(default)]
+    pub firmware_version: Option<String>,
+    #[serde(default)]
+    pub channel_count: Option<u32>,
+    #[serde(default)]
+    pub power_budget_watts: Option<f64>,
+    /// Overrides the per-type default in `min_command_spacing`.
+    #[serde(default)]
+    pub min_command_spacing_ms: Option<u64>,
+    /// Ramps lighting outputs up from off over this long instead of
+    /// switching them straight to their level, for high-inrush fixtures.
+    #[serde(default)]
+    pub soft_start_ms: Option<u64>,
+    /// Level (0-100) a channel is driven to on stop and emergency stop
+    /// instead of off, e.g. work lights. Relay contacts take 0 or 100.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub safe_defaults: BTreeMap<u32, u8>,
+    /// Part of the operator's main rig; listed first.
+    #[serde(default)]
+    pub favorite: bool,
+    /// Disabled controllers keep their config but are left out of playback,
+    /// polling and reconnects, e.g. a unit known to be faulty.
+    #[serde(default = "enabled_by_default")]
+    pub enabled: bool,
+    /// Hardware address, once the controller has reported it. Two entries
+    /// with the same MAC are the same device.
+    #[serde(default)]
+    pub mac: Option<String>,
+    #[serde(default)]
+    pub transport: Transport,
+    /// When the controller last answered, as a Unix timestamp in
+    /// milliseconds; kept to the nearest `LAST_SEEN_RESOLUTION`.
+    #[serde(default)]
+    pub last_seen: Option<u64>,
+}
+
+/// What a controller last reported over MQTT. Kept in memory only.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ControllerTelemetry {
+    /// Unix milliseconds of the last heartbeat.
+    pub last_heartbeat: Option<u64>,
+    /// The last telemetry payload, as sent.
+    pub telemetry: Option<Value>,
+    pub telemetry_at: Option<u64>,
+}
+
+/// Settings an operator edits on a registered controller. Fields left out
+/// keep their current value.
+#[derive(Debug, Default, Deserialize)]
+pub struct ControllerConfig {
+    /// An empty name clears it.
+    pub name: Option<String>,
+    /// Moves the entry to this address, e.g. an IP reserved for it at the router.
+    pub address: Option<String>,
+    pub channel_count: Option<u32>,
+    pub power_budget_watts: Option<f64>,
+    pub min_command_spacing_ms: Option<u64>,
+}
+
+fn enabled_by_default() -> bool {
+    true
+}
+
+/// The firework firmware holds each remote button for 500 ms and blocks
+/// while doing so, so it cannot take commands any faster.
+const FIREWORK_COMMAND_SPACING: Duration = Duration::from_millis(500);
+
+const DEFAULT_COMMAND_SPACING: Duration = Duration::from_millis(20);
+
+/// A write-with-response over BLE takes a couple of connection intervals.
+const BLE_COMMAND_SPACING: Duration = Duration::from_millis(100);
+
+/// Every reply marks a controller seen, so the saved timestamp is only
+/// moved on once it is this stale, sparing a file write per command.
+pub const LAST_SEEN_RESOLUTION: Duration = Duration::from_secs(60);
+
+impl ControllerInfo {
+    /// "Stage Left Tower (10.0.0.12)", or just the address when unnamed.
+    pub fn label(&self) -> String {
+        if self.name.is_empty() {
+            self.address.clone()
+        } else {
+            format!("{} ({})", self.name, self.address)
+        }
+    }
+
+    pub fn display_name(&self) -> Option<String> {
+        (!self.name.is_empty()).then(|| self.name.clone())
+    }
+
+    /// Shortest gap between two commands the hardware can execute.
+    pub fn min_command_spacing(&self) -> Duration {
+        match self.min_command_spacing_ms {
+            Some(ms) => Duration::from_millis(ms),
+            None if self.is_pyro() => FIREWORK_COMMAND_SPACING,
+            None if self.transport == Transport::Ble => BLE_COMMAND_SPACING,
+            None => DEFAULT_COMMAND_SPACING,
+        }
+    }
+
+    /// None when outputs switch straight to their level. Pyro channels
+    /// always fire instantly.
+    pub fn soft_start(&self) -> Option<Duration> {
+        match self.soft_start_ms {
+            Some(ms) if ms > 0 && !self.is_pyro() => Some(Duration::from_millis(ms)),
+            _ => None,
+        }
+    }
+
+    /// Refuses a safe default the controller cannot hold. Pyro, laser and
+    /// haze outputs always stop to off.
+    pub fn check_safe_default(&self, channel: u32, value: u8) -> Result<(), String> {
+        if self.is_pyro() || self.is_laser() || self.is_haze() {
+            return Err(format!("{} always stops to off; only lighting and relay channels take a safe default", self.label()));
+        }
+        if let Some(count) = self.channel_count {
+            if !(1..=count).contains(&channel) {
+                return Err(format!("{} has channels 1-{}, not {}", self.label(), count, channel));
+            }
+        }
+        if value > 100 {
+            return Err(format!("Safe default must be 0-100, got {}", value));
+        }
+        if self.is_relay() && value != 0 && value != 100 {
+            return Err("Relay contacts take a safe default of 0 (open) or 100 (closed)".to_string());
+        }
+        Ok(())
+    }
+
+    /// The action that puts `channel` at its safe default, if it has one.
+    pub fn safe_default_action(&self, channel: u32) -> Option<OutputAction> {
+        Some(match *self.safe_defaults.get(&channel)? {
+            0 => OutputAction::Relay(false),
+            100 => OutputAction::Relay(true),
+            level => OutputAction::Dimmer(level),
+        })
+    }
+
+    /// Firework controllers drive pyro channels.
+    pub fn is_pyro(&self) -> bool {
+        self.controller_type == "firework"
+    }
+
+    /// Fog and haze machines, driven by output level rather than channels.
+    pub fn is_haze(&self) -> bool {
+        self.controller_type == "haze"
+    }
+
+    /// Lasers project beams that must stay inside a configured safe zone.
+    pub fn is_laser(&self) -> bool {
+        self.controller_type == "laser"
+    }
+
+    /// Dry-contact relay boards for smoke curtains, motors and the like.
+    pub fn is_relay(&self) -> bool {
+        self.controller_type == "relay"
+    }
+}
+
+/// The form addresses are compared in: "HTTP://Lume-Lighting.local./" and
+/// "lume-lighting.local" are the same controller.
+pub fn normalize_address(address: &str) -> String {
+    let address = address.trim().to_lowercase();
+    let address = address
+        .strip_prefix("http://")
+        .or_else(|| address.strip_prefix("https://"))
+        .unwrap_or(&address);
+    let address = address.trim_end_matches('/');
+    let address = address.strip_suffix(":80").unwrap_or(address);
+    address.trim_end_matches('.').to_string()
+}
+
+/// "AA-BB-CC-DD-EE-FF" and "aa:bb:cc:dd:ee:ff" are the same MAC.
+pub fn normalize_mac(mac: &str) -> String {
+    mac.trim().to_lowercase().replace('-', ":")
+}
+
+/// Why `other` is taken to be the same device as `info`, if it is.
+pub fn same_device(info: &ControllerInfo, other: &ControllerInfo) -> Option<String> {
+    if normalize_address(&info.address) == normalize_address(&other.address) {
+        return Some(format!("{} and {} are the same address", info.address, other.address));
+    }
+    match (&info.mac, &other.mac) {
+        (Some(a), Some(b)) if normalize_mac(a) == normalize_mac(b) => Some(format!(
+            "{} and {} report the same MAC {}",
+            info.address,
+            other.address,
+            normalize_mac(a)
+        )),
+        _ => None,
+    }
+}
+
+// `added` folded into the entry already registered for the device. What was
+// just given wins, gaps are filled from the existing entry, and the
+// existing address is kept so shows and zones still resolve.
+fn merge(existing: ControllerInfo, added: ControllerInfo) -> ControllerInfo {
+    ControllerInfo {
+        name: if added.name.is_empty() { existing.name } else { added.name },
+        controller_type: if added.controller_type.is_empty() { existing.controller_type } else { added.controller_type },
+        firmware_version: added.firmware_version.or(existing.firmware_version),
+        channel_count: added.channel_count.or(existing.channel_count),
+        power_budget_watts: added.power_budget_watts.or(existing.power_budget_watts),
+        min_command_spacing_ms: added.min_command_spacing_ms.or(existing.min_command_spacing_ms),
+        soft_start_ms: added.soft_start_ms.or(existing.soft_start_ms),
+        safe_defaults: if added.safe_defaults.is_empty() { existing.safe_defaults } else { added.safe_defaults },
+        favorite: added.favorite || existing.favorite,
+        enabled: existing.enabled,
+        mac: added.mac.or(existing.mac),
+        transport: existing.transport,
+        last_seen: added.last_seen.max(existing.last_seen),
+        address: existing.address,
+    }
+}
+
+/// An added controller that turned out to be one already registered.
+#[derive(Debug, Clone, Serialize)]
+pub struct MergedDuplicate {
+    /// The existing entry it was merged into.
+    pub address: String,
+    pub reason: String,
+}
+
+/// Maps addresses and unambiguous friendly names to controllers, for
+/// resolving the controller references in show data.
+pub fn lookup_map(controllers: &[ControllerInfo]) -> BTreeMap<&str, &ControllerInfo> {
+    let mut names: BTreeMap<&str, Vec<&ControllerInfo>> = BTreeMap::new();
+    for controller in controllers.iter().filter(|c| !c.name.is_empty()) {
+        names.entry(controller.name.as_str()).or_default().push(controller);
+    }
+    let mut map: BTreeMap<&str, &ControllerInfo> = names
+        .into_iter()
+        .filter_map(|(name, matches)| match matches.as_slice() {
+            [only] => Some((name, *only)),
+            _ => None,
+        })
+        .collect();
+    // Addresses win over a name that happens to equal another controller's address.
+    map.extend(controllers.iter().map(|c| (c.address.as_str(), c)));
+    map
+}
+
+// Up to three names or addresses within a small edit distance of `wanted`.
+fn close_matches<'a>(wanted: &str, controllers: impl Iterator<Item = &'a ControllerInfo>) -> Vec<String> {
+    let wanted = wanted.to_lowercase();
+    let limit = (wanted.chars().count() / 3).max(2);
+    let mut scored: Vec<(usize, String)> = controllers
+        .flat_map(|c| [c.name.clone(), c.address.clone()])
+        .filter(|candidate| !candidate.is_empty())
+        .map(|candidate| (edit_distance(&wanted, &candidate.to_lowercase()), candidate))
+        .filter(|(distance, _)| *distance <= limit)
+        .collect();
+    scored.sort();
+    scored.dedup_by(|a, b| a.1 == b.1);
+    scored.into_iter().take(3).map(|(_, candidate)| candidate).collect()
+}
+
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let above = row[j + 1];
+            row[j + 1] = (above + 1).min(row[j] + 1).min(diagonal + usize::from(ca != *cb));
+            diagonal = above;
+        }
+    }
+    row[b.len()]
+}
+
+/// A named group of controllers, e.g. one area of the venue.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Zone {
+    pub name: String,
+    pub addresses: Vec<String>,
+}
+
+/// Known controllers keyed by address, plus the zones grouping them.
+#[derive(Debug, Default)]
+pub struct ControllerRegistry {
+    controllers: Mutex<BTreeMap<String, ControllerInfo>>,
+    zones: Mutex<BTreeMap<String, Zone>>,
+    // Where controllers and zones are saved; set once at startup.
+    controllers_path: Mutex<Option<PathBuf>>,
+    zones_path: Mutex<Option<PathBuf>>,
+    telemetry: Mutex<BTreeMap<String, ControllerTelemetry>>,
+}
+
+impl ControllerRegistry {
+    fn lock(&self) -> Result<MutexGuard<'_, BTreeMap<String, ControllerInfo>>, String> {
+        self.controllers
+            .lock()
+            .map_err(|_| "Controller registry is unavailable".to_string())
+    }
+
+    /// Adds a controller, replacing any existing entry for the same address.
+    /// A controller already registered under another spelling of its
+    /// address, or with the same MAC, is merged into that entry instead,
+    /// which is returned.
+    pub fn add(&self, info: ControllerInfo) -> Result<Option<MergedDuplicate>, String> {
+        if info.address.trim().is_empty() {
+            return Err("Controller address must not be empty".to_string());
+        }
+        let mut info = info;
+        if transport::ble_id(&info.address).is_some() {
+            info.transport = Transport::Ble;
+        }
+        if transport::serial_port(&info.address).is_some() {
+            info.transport = Transport::Serial;
+        }
+        let mut controllers = self.lock()?;
+        let duplicate = controllers
+            .values()
+            .filter(|c| c.address != info.address)
+            .find_map(|c| same_device(c, &info).map(|reason| (c.address.clone(), reason)));
+        let merged = match duplicate {
+            Some((existing, reason)) => {
+                log::warn!("Not adding {} twice: {}", info.address, reason);
+                if let Some(current) = controllers.remove(&existing) {
+                    info = merge(current, info);
+                }
+                Some(MergedDuplicate { address: existing, reason })
+            }
+            None => None,
+        };
+        controllers.insert(info.address.clone(), info);
+        self.save_controllers(&controllers)?;
+        Ok(merged)
+    }
+
+    pub fn remove(&self, address: &str) -> Result<bool, String> {
+        let mut controllers = self.lock()?;
+        let removed = controllers.remove(address).is_some();
+        if removed {
+            self.save_controllers(&controllers)?;
+            if let Ok(mut telemetry) = self.telemetry.lock() {
+                telemetry.remove(address);
+            }
+        }
+        Ok(removed)
+    }
+
+    /// Looks a controller up by address or by its friendly name.
+    pub fn get(&self, name_or_address: &str) -> Result<ControllerInfo, String> {
+        let controllers = self.lock()?;
+        if let Some(info) = controllers.get(name_or_address) {
+            return Ok(info.clone());
+        }
+        let wanted = name_or_address.trim();
+        for exact in [true, false] {
+            let matches: Vec<&ControllerInfo> = controllers
+                .values()
+                .filter(|c| {
+                    !c.name.is_empty() && if exact { c.name == wanted } else { c.name.eq_ignore_ascii_case(wanted) }
+                })
+                .collect();
+            match matches.as_slice() {
+                [] => continue,
+                [info] => return Ok((*info).clone()),
+                _ => {
+                    let addresses: Vec<&str> = matches.iter().map(|c| c.address.as_str()).collect();
+                    return Err(format!(
+                        "'{}' matches more than one controller ({}); use the address",
+                        wanted,
+                        addresses.join(", ")
+                    ));
+                }
+            }
+        }
+
+        let suggestions = close_matches(wanted, controllers.values());
+        if suggestions.is_empty() {
+            Err(format!("No controller matches '{}'", wanted))
+        } else {
+            Err(format!("No controller matches '{}'. Did you mean: {}?", wanted, suggestions.join(", ")))
+        }
+    }
+
+    /// Applies `update` to a registered controller. Returns false if it is not registered.
+    pub fn update(&self, address: &str, update: impl FnOnce(&mut ControllerInfo)) -> Result<bool, String> {
+        let mut controllers = self.lock()?;
+        let updated = controllers.get_mut(address).map(update).is_some();
+        if updated {
+            self.save_controllers(&controllers)?;
+        }
+        Ok(updated)
+    }
+
+    /// Records that a controller answered just now.
+    pub fn mark_seen(&self, address: &str) -> Result<(), String> {
+        let now = audit::now_millis();
+        let mut controllers = self.lock()?;
+        let Some(info) = controllers.get_mut(address) else {
+            return Ok(());
+        };
+        let resolution = LAST_SEEN_RESOLUTION.as_millis() as u64;
+        if info.last_seen.is_some_and(|seen| now.saturating_sub(seen) < resolution) {
+            return Ok(());
+        }
+        info.last_seen = Some(now);
+        self.save_controllers(&controllers)
+    }
+
+    fn update_telemetry(&self, address: &str, update: impl FnOnce(&mut ControllerTelemetry)) -> Result<(), String> {
+        let mut telemetry = self
+            .telemetry
+            .lock()
+            .map_err(|_| "Controller telemetry is unavailable".to_string())?;
+        update(telemetry.entry(address.to_string()).or_default());
+        Ok(())
+    }
+
+    pub fn record_heartbeat(&self, address: &str) -> Result<(), String> {
+        let now = audit::now_millis();
+        self.update_telemetry(address, |t| t.last_heartbeat = Some(now))
+    }
+
+    pub fn record_telemetry(&self, address: &str, data: Value) -> Result<(), String> {
+        let now = audit::now_millis();
+        self.update_telemetry(address, |t| {
+            t.telemetry = Some(data);
+            t.telemetry_at = Some(now);
+        })
+    }
+
+    /// Telemetry by controller address, for controllers that reported any.
+    pub fn telemetry(&self) -> Result<BTreeMap<String, ControllerTelemetry>, String> {
+        Ok(self
+            .telemetry
+            .lock()
+            .map_err(|_| "Controller telemetry is unavailable".to_string())?
+            .clone())
+    }
+
+    /// Applies an operator's edits to a registered controller, moving it
+    /// first if `config` pins a new address. Returns the updated entry.
+    pub fn configure(&self, address: &str, config: ControllerConfig) -> Result<ControllerInfo, String> {
+        let mut address = self.get(address)?.address;
+        if config.power_budget_watts.is_some_and(|w| !w.is_finite() || w <= 0.0) {
+            return Err("Power budget must be a positive number of watts".to_string());
+        }
+        if config.channel_count == Some(0) {
+            return Err("Channel count must be at least 1".to_string());
+        }
+        if let Some(to) = config.address.as_deref().map(str::trim).filter(|to| *to != address) {
+            if to.is_empty() {
+                return Err("Controller address must not be empty".to_string());
+            }
+            if self.lock()?.keys().any(|a| normalize_address(a) == normalize_address(to)) {
+                return Err(format!("A controller is already registered at {}", to));
+            }
+            log::info!("Moving controller {} to {}", address, to);
+            self.readdress(&address, to)?;
+            address = to.to_string();
+        }
+        self.update(&address, |info| {
+            if let Some(name) = config.name {
+                info.name = name.trim().to_string();
+            }
+            if let Some(count) = config.channel_count {
+                info.channel_count = Some(count);
+            }
+            if let Some(watts) = config.power_budget_watts {
+                info.power_budget_watts = Some(watts);
+            }
+            if let Some(ms) = config.min_command_spacing_ms {
+                info.min_command_spacing_ms = Some(ms);
+            }
+        })?;
+        self.get(&address)
+    }
+
+    /// Moves a controller to a new address, keeping its zone memberships.
+    pub fn readdress(&self, from: &str, to: &str) -> Result<(), String> {
+        {
+            let mut controllers = self.lock()?;
+            let mut info = controllers
+                .remove(from)
+                .ok_or_else(|| format!("No controller is registered at {}", from))?;
+            info.address = to.to_string();
+            controllers.insert(to.to_string(), info);
+            self.save_controllers(&controllers)?;
+        }
+        let mut zones = self.lock_zones()?;
+        let mut changed = false;
+        for zone in zones.values_mut() {
+            if let Some(address) = zone.addresses.iter_mut().find(|a| *a == from) {
+                *address = to.to_string();
+                zone.addresses.sort();
+                changed = true;
+            }
+        }
+        if changed {
+            self.save_zones(&zones)?;
+        }
+        Ok(())
+    }
+
+    pub fn list(&self) -> Result<Vec<ControllerInfo>, String> {
+        Ok(self.lock()?.values().cloned().collect())
+    }
+
+    /// Loads saved controllers and remembers `path` for later saves. A missing
+    /// file means none have been paired yet.
+    pub fn load_controllers(&self, path: PathBuf) -> Result<(), String> {
+        let controllers: Vec<ControllerInfo> = match fs::read(&path) {
+            Ok(data) => serde_json::from_slice(&data).map_err(|e| format!("Invalid controllers file: {}", e))?,
+            Err(e) if e.kind() == ErrorKind::NotFound => Vec::new(),
+            Err(e) => return Err(format!("Failed to read {}: {}", path.display(), e)),
+        };
+        *self.lock()? = controllers.into_iter().map(|c| (c.address.clone(), c)).collect();
+        *self
+            .controllers_path
+            .lock()
+            .map_err(|_| "Controller registry is unavailable".to_string())? = Some(path);
+        Ok(())
+    }
+
+    fn save_controllers(&self, controllers: &BTreeMap<String, ControllerInfo>) -> Result<(), String> {
+        let path = self
+            .controllers_path
+            .lock()
+            .map_err(|_| "Controller registry is unavailable".to_string())?
+            .clone();
+        let list: Vec<&ControllerInfo> = controllers.values().collect();
+        save_json(path, &list).map_err(|e| format!("Failed to save controllers: {}", e))
+    }
+
+    fn lock_zones(&self) -> Result<MutexGuard<'_, BTreeMap<String, Zone>>, String> {
+        self.zones.lock().map_err(|_| "Zones are unavailable".to_string())
+    }
+
+    /// Loads saved zones and remembers `path` for later saves. A missing file means no zones.
+    pub fn load_zones(&self, path: PathBuf) -> Result<(), String> {
+        let zones: Vec<Zone> = match fs::read(&path) {
+            Ok(data) => serde_json::from_slice(&data).map_err(|e| format!("Invalid zones file: {}", e))?,
+            Err(e) if e.kind() == ErrorKind::NotFound => Vec::new(),
+            Err(e) => return Err(format!("Failed to read {}: {}", path.display(), e)),
+        };
+        *self.lock_zones()? = zones.into_iter().map(|z| (z.name.clone(), z)).collect();
+        *self.zones_path.lock().map_err(|_| "Zones are unavailable".to_string())? = Some(path);
+        Ok(())
+    }
+
+    fn save_zones(&self, zones: &BTreeMap<String, Zone>) -> Result<(), String> {
+        let path = self.zones_path.lock().map_err(|_| "Zones are unavailable".to_string())?.clone();
+        let list: Vec<&Zone> = zones.values().collect();
+        save_json(path, &list).map_err(|e| format!("Failed to save zones: {}", e))
+    }
+
+    /// Creates a zone, or replaces the members of an existing one.
+    pub fn create_zone(&self, name: &str, addresses: Vec<String>) -> Result<Zone, String> {
+        let name = name.trim();
+        if name.is_empty() {
+            return Err("Zone name must not be empty".to_string());
+        }
+        let mut addresses = addresses
+            .iter()
+            .map(|a| self.get(a).map(|c| c.address))
+            .collect::<Result<Vec<_>, _>>()?;
+        addresses.sort();
+        addresses.dedup();
+        let zone = Zone {
+            name: name.to_string(),
+            addresses,
+        };
+        let mut zones = self.lock_zones()?;
+        zones.insert(zone.name.clone(), zone.clone());
+        self.save_zones(&zones)?;
+        Ok(zone)
+    }
+
+    pub fn delete_zone(&self, name: &str) -> Result<bool, String> {
+        let mut zones = self.lock_zones()?;
+        let removed = zones.remove(name).is_some();
+        if removed {
+            self.save_zones(&zones)?;
+        }
+        Ok(removed)
+    }
+
+    pub fn zone(&self, name: &str) -> Result<Zone, String> {
+        self.lock_zones()?
+            .get(name)
+            .cloned()
+            .ok_or_else(|| format!("Unknown zone: {}", name))
+    }
+
+    pub fn zones(&self) -> Result<Vec<Zone>, String> {
+        Ok(self.lock_zones()?.values().cloned().collect())
+    }
+}
+
+/// Saves `value` as pretty JSON at `path`. Nothing is saved until a path
+/// has been loaded.
+pub fn save_json<T: Serialize>(path: Option<PathBuf>, value: &T) -> Result<(), String> {
+    let Some(path) = path else {
+        return Ok(());
+    };
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+    }
+    let data = serde_json::to_vec_pretty(value).map_err(|e| e.to_string())?;
+    show_store::write_atomic(&path, &data).map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn controller(address: &str, name: &str, controller_type: &str) -> ControllerInfo {
+        serde_json::from_value(json!({ "address": address, "name": name, "controller_type": controller_type })).unwrap()
+    }
+
+    #[test]
+    fn ambiguous_names_do_not_resolve() {
+        let controllers = [
+            controller("10.0.0.1", "Rack", "firework"),
+            controller("10.0.0.2", "Tower", "lights"),
+            controller("10.0.0.3", "Tower", "lights"),
+        ];
+        let known = lookup_map(&controllers);
+        assert_eq!(known["Rack"].address, "10.0.0.1");
+        assert!(!known.contains_key("Tower"));
+        assert_eq!(known["10.0.0.3"].name, "Tower");
+    }
+
+    #[test]
+    fn same_device_is_merged_into_the_registered_entry() {
+        let registry = ControllerRegistry::default();
+        registry.add(controller("10.0.0.5", "Rack", "firework")).unwrap();
+        let mut again = controller("HTTP://10.0.0.5/", "", "");
+        again.firmware_version = Some("1.2.0".to_string());
+        let merged = registry.add(again).unwrap().unwrap();
+        assert_eq!(merged.address, "10.0.0.5");
+
+        let list = registry.list().unwrap();
+        assert_eq!(list.len(), 1);
+        assert_eq!((list[0].name.as_str(), list[0].firmware_version.as_deref()), ("Rack", Some("1.2.0")));
+        assert!(list[0].is_pyro());
+        assert_eq!(list[0].min_command_spacing(), FIREWORK_COMMAND_SPACING);
+    }
+
+    #[test]
+    fn unknown_names_suggest_close_matches() {
+        let registry = ControllerRegistry::default();
+        registry.add(controller("10.0.0.1", "Stage Left", "lights")).unwrap();
+        assert_eq!(registry.get("stage left").unwrap().address, "10.0.0.1");
+        assert!(registry.get("Stage Lft").unwrap_err().contains("Did you mean: Stage Left"));
+    }
+}
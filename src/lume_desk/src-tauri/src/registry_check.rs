@@ -0,0 +1,143 @@
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use tokio::sync::Semaphore;
+use tokio::task::JoinSet;
+
+use crate::controller_client;
+use crate::dispatcher::Dispatcher;
+use crate::fleet::MAX_CONCURRENT_QUERIES;
+use crate::registry::{self, ControllerInfo, ControllerRegistry};
+use crate::transport;
+
+/// Registry entries that are the same device.
+#[derive(Debug, Serialize)]
+pub struct DuplicateControllers {
+    pub addresses: Vec<String>,
+    pub reason: String,
+}
+
+/// A favorite that did not answer a probe.
+#[derive(Debug, Serialize)]
+pub struct UnreachableFavorite {
+    pub address: String,
+    pub name: Option<String>,
+    pub error: String,
+}
+
+/// A name that does not resolve to a single controller, so show data that
+/// uses it cannot be played.
+#[derive(Debug, Serialize)]
+pub struct AliasCollision {
+    pub alias: String,
+    pub addresses: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ConsistencyReport {
+    /// Nothing below needs attention.
+    pub consistent: bool,
+    pub controllers: usize,
+    pub duplicates: Vec<DuplicateControllers>,
+    pub unreachable_favorites: Vec<UnreachableFavorite>,
+    pub alias_collisions: Vec<AliasCollision>,
+}
+
+/// Looks the whole registry over for duplicate devices and colliding
+/// names, and probes every enabled favorite.
+pub async fn check(registry: &ControllerRegistry, dispatcher: &Dispatcher) -> Result<ConsistencyReport, String> {
+    let controllers = registry.list()?;
+    let unreachable_favorites = probe_favorites(&controllers, dispatcher).await;
+    let duplicates = duplicates(&controllers);
+    let alias_collisions = alias_collisions(&controllers);
+    Ok(ConsistencyReport {
+        consistent: duplicates.is_empty() && unreachable_favorites.is_empty() && alias_collisions.is_empty(),
+        controllers: controllers.len(),
+        duplicates,
+        unreachable_favorites,
+        alias_collisions,
+    })
+}
+
+// Entries are grouped with the first one they match, so a group lists
+// every spelling of the device that found its way in.
+fn duplicates(controllers: &[ControllerInfo]) -> Vec<DuplicateControllers> {
+    let mut groups: Vec<(&ControllerInfo, DuplicateControllers)> = Vec::new();
+    for controller in controllers {
+        let group = groups
+            .iter_mut()
+            .find_map(|(first, group)| registry::same_device(first, controller).map(|reason| (group, reason)));
+        match group {
+            Some((group, reason)) => {
+                group.addresses.push(controller.address.clone());
+                if group.reason.is_empty() {
+                    group.reason = reason;
+                }
+            }
+            None => groups.push((
+                controller,
+                DuplicateControllers {
+                    addresses: vec![controller.address.clone()],
+                    reason: String::new(),
+                },
+            )),
+        }
+    }
+    groups.into_iter().map(|(_, group)| group).filter(|g| g.addresses.len() > 1).collect()
+}
+
+// Names shared by several controllers, ignoring case as lookups do, and
+// names that are another controller's address.
+fn alias_collisions(controllers: &[ControllerInfo]) -> Vec<AliasCollision> {
+    let mut by_name: BTreeMap<String, Vec<&ControllerInfo>> = BTreeMap::new();
+    for controller in controllers.iter().filter(|c| !c.name.is_empty()) {
+        by_name.entry(controller.name.to_lowercase()).or_default().push(controller);
+    }
+    by_name
+        .into_values()
+        .filter_map(|named| {
+            let alias = &named[0].name;
+            let mut addresses: Vec<String> = named.iter().map(|c| c.address.clone()).collect();
+            addresses.extend(
+                controllers
+                    .iter()
+                    .filter(|c| registry::normalize_address(&c.address) == registry::normalize_address(alias))
+                    .filter(|c| !named.iter().any(|n| n.address == c.address))
+                    .map(|c| c.address.clone()),
+            );
+            (addresses.len() > 1).then(|| AliasCollision {
+                alias: alias.clone(),
+                addresses,
+            })
+        })
+        .collect()
+}
+
+async fn probe_favorites(controllers: &[ControllerInfo], dispatcher: &Dispatcher) -> Vec<UnreachableFavorite> {
+    let permits = Arc::new(Semaphore::new(MAX_CONCURRENT_QUERIES));
+    let mut probes = JoinSet::new();
+    for controller in controllers.iter().filter(|c| c.favorite && c.enabled).cloned() {
+        let permits = permits.clone();
+        probes.spawn(async move {
+            let _permit = permits.acquire_owned().await;
+            let result = controller_client::probe(&controller.address, transport::timeout(controller.transport)).await;
+            (controller, result)
+        });
+    }
+    let mut unreachable = Vec::new();
+    while let Some(joined) = probes.join_next().await {
+        let Ok((controller, result)) = joined else {
+            continue;
+        };
+        dispatcher.note_reachability(&controller.address, result.is_ok(), result.as_ref().err().cloned());
+        if let Err(error) = result {
+            unreachable.push(UnreachableFavorite {
+                name: controller.display_name(),
+                address: controller.address,
+                error,
+            });
+        }
+    }
+    unreachable.sort_by(|a, b| a.address.cmp(&b.address));
+    unreachable
+}
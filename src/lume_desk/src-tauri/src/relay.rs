@@ -0,0 +1,127 @@
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+use crate::dispatcher::{Dispatcher, OutputAction};
+use crate::models::{Effect, Show};
+use crate::registry::ControllerInfo;
+
+/// Longest pulse accepted, from an effect or a manual test.
+pub const MAX_PULSE: Duration = Duration::from_secs(60);
+
+/// Effects closer together than this count as simultaneous.
+const SIMULTANEOUS_SECS: f64 = 0.001;
+
+/// What a relay effect does with its channel.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RelayCommand {
+    /// Close the contact and leave it closed.
+    On,
+    /// Open the contact.
+    Off,
+    /// Close the contact, then open it again after the pulse.
+    Pulse(Duration),
+}
+
+fn pulse_length(ms: f64) -> Result<Duration, String> {
+    if !ms.is_finite() || ms <= 0.0 {
+        return Err(format!("Relay pulse must be positive, got {} ms", ms));
+    }
+    let pulse = Duration::from_secs_f64(ms / 1000.0);
+    if pulse > MAX_PULSE {
+        return Err(format!("Relay pulse of {:.0} ms exceeds {}s", ms, MAX_PULSE.as_secs()));
+    }
+    Ok(pulse)
+}
+
+/// Reads the `relay` param ("on", "off" or "pulse"). Without it, an effect
+/// with a duration pulses for that long and one without switches on.
+/// A pulse lasts `pulse_ms` if set, else the effect duration.
+pub fn command(effect: &Effect) -> Result<RelayCommand, String> {
+    let requested = effect.params.get("relay").and_then(|v| v.as_str());
+    match requested {
+        Some("on") => Ok(RelayCommand::On),
+        Some("off") => Ok(RelayCommand::Off),
+        Some("pulse") | None if effect.duration > 0.0 || effect.params.contains_key("pulse_ms") => {
+            let ms = effect
+                .params
+                .get("pulse_ms")
+                .and_then(|v| v.as_f64())
+                .unwrap_or(effect.duration * 1000.0);
+            pulse_length(ms).map(RelayCommand::Pulse)
+        }
+        Some("pulse") => Err("Relay pulse needs a duration or pulse_ms".to_string()),
+        None => Ok(RelayCommand::On),
+        Some(other) => Err(format!("Unknown relay command '{}', expected on, off or pulse", other)),
+    }
+}
+
+pub async fn apply(dispatcher: &Dispatcher, controller: &ControllerInfo, effect: &Effect) -> Result<(), String> {
+    match command(effect)? {
+        RelayCommand::On => dispatcher.send(&controller.address, effect.channel, &OutputAction::Relay(true)).await,
+        RelayCommand::Off => dispatcher.send(&controller.address, effect.channel, &OutputAction::Relay(false)).await,
+        RelayCommand::Pulse(pulse) => pulse_channel(dispatcher, controller, effect.channel, pulse).await,
+    }
+}
+
+/// Closes `channel` for `pulse`, then opens it again.
+pub async fn pulse_channel(dispatcher: &Dispatcher, controller: &ControllerInfo, channel: u32, pulse: Duration) -> Result<(), String> {
+    dispatcher
+        .hold(&controller.address, channel, OutputAction::Relay(true), OutputAction::Relay(false), pulse)
+        .await
+}
+
+/// Manual test pulse of `ms` on a relay device.
+pub async fn pulse(dispatcher: &Dispatcher, controller: &ControllerInfo, channel: u32, ms: u64) -> Result<(), String> {
+    if !controller.is_relay() {
+        return Err(format!("{} is not a relay device", controller.label()));
+    }
+    pulse_channel(dispatcher, controller, channel, pulse_length(ms as f64)?).await
+}
+
+/// A pair of relay effects that ask for opposite states at the same moment.
+pub struct RelayConflict<'a> {
+    pub first: &'a Effect,
+    pub second: &'a Effect,
+    pub reason: String,
+}
+
+/// Finds relay effects on one channel that contradict each other: an on
+/// and an off at the same instant, or any other command while a pulse is
+/// still closed (its release would undo the command, or the command would
+/// cut the pulse short). Overlapping pulses are fine: the later one
+/// extends the first. Effects with an invalid command are skipped here.
+pub fn conflicts<'a>(show: &'a Show, is_relay: impl Fn(&str) -> bool) -> Vec<RelayConflict<'a>> {
+    let mut by_channel: BTreeMap<(&str, u32), Vec<(&Effect, RelayCommand)>> = BTreeMap::new();
+    for effect in show.effects.iter().filter(|e| is_relay(&e.controller)) {
+        if let Ok(command) = command(effect) {
+            by_channel.entry((effect.controller.as_str(), effect.channel)).or_default().push((effect, command));
+        }
+    }
+
+    let mut found = Vec::new();
+    for mut effects in by_channel.into_values() {
+        effects.sort_by(|a, b| a.0.start_time.total_cmp(&b.0.start_time));
+        for (index, (first, first_command)) in effects.iter().enumerate() {
+            for (second, second_command) in &effects[index + 1..] {
+                let gap = second.start_time - first.start_time;
+                let reason = match (first_command, second_command) {
+                    (RelayCommand::Pulse(_), RelayCommand::Pulse(_)) => None,
+                    (RelayCommand::Pulse(pulse), _) if gap < pulse.as_secs_f64() => Some(format!(
+                        "{} switches the relay while the pulse of {} is still closed",
+                        second.id, first.id
+                    )),
+                    (_, RelayCommand::Pulse(_)) | (RelayCommand::On, RelayCommand::Off) | (RelayCommand::Off, RelayCommand::On)
+                        if gap < SIMULTANEOUS_SECS =>
+                    {
+                        Some(format!("{} and {} switch the relay at the same time", first.id, second.id))
+                    }
+                    _ => None,
+                };
+                if let Some(reason) = reason {
+                    found.push(RelayConflict { first, second, reason });
+                }
+            }
+        }
+    }
+    found
+}
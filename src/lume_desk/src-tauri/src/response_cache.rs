@@ -0,0 +1,80 @@
+use serde_json::Value;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+use crate::controller_client;
+
+pub const DEFAULT_TTL_SECS: u64 = 300;
+pub const MAX_TTL_SECS: u64 = 24 * 3600;
+
+static TTL_SECS: AtomicU64 = AtomicU64::new(DEFAULT_TTL_SECS);
+
+type Key = (String, String);
+
+// Replies by (address, path), with when they were fetched.
+fn cache() -> &'static Mutex<HashMap<Key, (Instant, Value)>> {
+    static CACHE: OnceLock<Mutex<HashMap<Key, (Instant, Value)>>> = OnceLock::new();
+    CACHE.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+pub fn ttl() -> Duration {
+    Duration::from_secs(TTL_SECS.load(Ordering::Relaxed))
+}
+
+/// How long cached replies are served; 0 turns caching off.
+pub fn set_ttl(secs: u64) -> Result<(), String> {
+    if secs > MAX_TTL_SECS {
+        return Err(format!("Cache TTL must be at most {} seconds, got {}", MAX_TTL_SECS, secs));
+    }
+    TTL_SECS.store(secs, Ordering::Relaxed);
+    log::info!("Caching controller info for {}s", secs);
+    Ok(())
+}
+
+/// `controller_client::get_json` for data that rarely changes, such as
+/// firmware version, capabilities and network settings. A reply younger
+/// than the TTL is returned without contacting the controller unless
+/// `force` is set. Failures are never cached.
+pub async fn get_json(address: &str, path: &str, timeout: Duration, force: bool) -> Result<Value, String> {
+    let key = (address.to_string(), path.to_string());
+    let ttl = ttl();
+    if !force {
+        let cached = cache()
+            .lock()
+            .ok()
+            .and_then(|c| c.get(&key).filter(|(at, _)| at.elapsed() < ttl).map(|(_, v)| v.clone()));
+        if let Some(value) = cached {
+            return Ok(value);
+        }
+    }
+    let value = controller_client::get_json(address, path, timeout).await?;
+    if !ttl.is_zero() {
+        if let Ok(mut cache) = cache().lock() {
+            cache.insert(key, (Instant::now(), value.clone()));
+        }
+    }
+    Ok(value)
+}
+
+/// Drops everything cached for `address`; returns how many replies were dropped.
+pub fn invalidate(address: &str) -> usize {
+    let Ok(mut cache) = cache().lock() else {
+        return 0;
+    };
+    let before = cache.len();
+    cache.retain(|(cached, _), _| cached != address);
+    let dropped = before - cache.len();
+    if dropped > 0 {
+        log::info!("Dropped {} cached replies from {}", dropped, address);
+    }
+    dropped
+}
+
+/// Drops everything cached, e.g. after the desk changed networks.
+pub fn clear() {
+    if let Ok(mut cache) = cache().lock() {
+        cache.clear();
+    }
+}
@@ -0,0 +1,244 @@
+use serde::Serialize;
+use std::future::Future;
+use std::time::Duration;
+use tauri::AppHandle;
+
+use crate::audit::{AuditKind, AuditLog};
+use crate::batch::{BatchPolicy, BatchResult};
+use crate::controller_client;
+use crate::dispatcher::{Dispatcher, OutputAction};
+use crate::events::{self, ControllerTested};
+use crate::haze::HAZE_CHANNEL;
+use crate::registry::ControllerInfo;
+use crate::relay;
+use crate::safety::{ArmState, Safety};
+use crate::transport;
+
+/// Every output is tested on the first channel.
+const TEST_CHANNEL: u32 = 1;
+
+/// How long a test pulse stays on.
+const TEST_PULSE: Duration = Duration::from_millis(500);
+
+/// Dimmer or haze level of a test pulse, in percent.
+const TEST_LEVEL: u8 = 20;
+
+pub const MAX_STAGGER_MS: u64 = 10_000;
+
+/// How a controller was tested.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TestMethod {
+    /// A short, dim output pulse.
+    Pulse,
+    /// Only asked for its status; pyro and laser outputs never pulse.
+    StatusOnly,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ControllerTestResult {
+    pub address: String,
+    pub name: Option<String>,
+    pub method: TestMethod,
+    pub responded: bool,
+    pub error: Option<String>,
+}
+
+fn method(controller: &ControllerInfo) -> TestMethod {
+    if controller.is_pyro() || controller.is_laser() {
+        TestMethod::StatusOnly
+    } else {
+        TestMethod::Pulse
+    }
+}
+
+async fn test_one(dispatcher: &Dispatcher, controller: &ControllerInfo) -> Result<(), String> {
+    let address = &controller.address;
+    match method(controller) {
+        TestMethod::StatusOnly => controller_client::probe(address, transport::timeout(controller.transport))
+            .await
+            .map(|_| ()),
+        TestMethod::Pulse if controller.is_haze() => {
+            dispatcher
+                .hold(address, HAZE_CHANNEL, OutputAction::Haze(TEST_LEVEL), OutputAction::Haze(0), TEST_PULSE)
+                .await
+        }
+        TestMethod::Pulse if controller.is_relay() => relay::pulse_channel(dispatcher, controller, TEST_CHANNEL, TEST_PULSE).await,
+        TestMethod::Pulse => {
+            dispatcher
+                .hold(address, TEST_CHANNEL, OutputAction::Dimmer(TEST_LEVEL), OutputAction::Dimmer(0), TEST_PULSE)
+                .await
+        }
+    }
+}
+
+/// Walks the rig one controller at a time, `stagger` apart, so a tech can
+/// see each unit answer in turn. Lights and haze get a dim half-second
+/// pulse and relays a short click; pyro and laser controllers are only
+/// asked for their status. Refuses to start while the system is armed if
+/// any pyro controller is included. Details are in test order.
+pub async fn run(
+    app: &AppHandle,
+    dispatcher: &Dispatcher,
+    safety: &Safety,
+    audit: &AuditLog,
+    controllers: Vec<ControllerInfo>,
+    stagger_ms: u64,
+    policy: BatchPolicy,
+) -> Result<BatchResult<ControllerTestResult>, String> {
+    check_start(safety, &controllers, stagger_ms)?;
+    let total = controllers.len();
+    log::info!("Testing {} controllers {} ms apart", total, stagger_ms);
+    let report = walk(
+        controllers,
+        Duration::from_millis(stagger_ms),
+        policy,
+        audit,
+        |controller| async move { test_one(dispatcher, &controller).await },
+        |tested| events::emit(app, events::CONTROLLER_TESTED, tested),
+    )
+    .await;
+    if report.aborted() {
+        log::warn!("Rig test stopped at the first failure; {} controllers not tested", report.skipped.len());
+    }
+    log::info!("Rig test: {} of {} controllers responded", report.succeeded.len(), total);
+    Ok(report)
+}
+
+fn check_start(safety: &Safety, controllers: &[ControllerInfo], stagger_ms: u64) -> Result<(), String> {
+    if stagger_ms > MAX_STAGGER_MS {
+        return Err(format!("Stagger must be at most {} ms, got {}", MAX_STAGGER_MS, stagger_ms));
+    }
+    if controllers.is_empty() {
+        return Err("No controllers to test".to_string());
+    }
+    if safety.state()? != ArmState::Disarmed {
+        if let Some(pyro) = controllers.iter().find(|c| c.is_pyro()) {
+            return Err(format!("{} is a pyro controller; disarm before testing it", pyro.label()));
+        }
+    }
+    Ok(())
+}
+
+// Tests each controller with `test` in turn, `stagger` apart, and hands
+// each outcome to `on_tested` as it comes in.
+async fn walk<F, Fut>(
+    controllers: Vec<ControllerInfo>,
+    stagger: Duration,
+    policy: BatchPolicy,
+    audit: &AuditLog,
+    test: F,
+    mut on_tested: impl FnMut(ControllerTested),
+) -> BatchResult<ControllerTestResult>
+where
+    F: Fn(ControllerInfo) -> Fut,
+    Fut: Future<Output = Result<(), String>>,
+{
+    let total = controllers.len();
+    let mut report = BatchResult::new(policy);
+    let mut remaining = controllers.into_iter().enumerate();
+    for (index, controller) in remaining.by_ref() {
+        if index > 0 {
+            tokio::time::sleep(stagger).await;
+        }
+        let method = method(&controller);
+        let result = test(controller.clone()).await;
+        if method == TestMethod::Pulse {
+            audit.record(AuditKind::ManualOverride, &controller, TEST_CHANNEL, &result, "Rig test pulse".to_string());
+        }
+        if let Err(e) = &result {
+            log::warn!("{} did not respond to the rig test: {}", controller.label(), e);
+        }
+        let tested = ControllerTestResult {
+            address: controller.address.clone(),
+            name: controller.display_name(),
+            method,
+            responded: result.is_ok(),
+            error: result.err(),
+        };
+        on_tested(ControllerTested {
+            address: tested.address.clone(),
+            name: tested.name.clone(),
+            index,
+            total,
+            responded: tested.responded,
+            error: tested.error.clone(),
+        });
+        let address = tested.address.clone();
+        let outcome = tested.error.clone().map_or(Ok(()), Err);
+        if report.record(&address, outcome, tested) {
+            break;
+        }
+    }
+    report.skipped = remaining.map(|(_, c)| c.address).collect();
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock_controller::{MockController, Reply};
+
+    const TIMEOUT: Duration = Duration::from_millis(500);
+    const STAGGER: Duration = Duration::from_millis(50);
+
+    async fn probe_all(mocks: &[&MockController], policy: BatchPolicy) -> (BatchResult<ControllerTestResult>, Vec<usize>) {
+        let controllers = mocks.iter().map(|m| m.controller("firework")).collect();
+        let mut order = Vec::new();
+        let report = walk(
+            controllers,
+            STAGGER,
+            policy,
+            &AuditLog::default(),
+            |controller| async move { controller_client::probe(&controller.address, TIMEOUT).await.map(|_| ()) },
+            |tested| order.push(tested.index),
+        )
+        .await;
+        (report, order)
+    }
+
+    #[test]
+    fn armed_system_refuses_to_test_pyro() {
+        let controller = |address: &str, controller_type: &str| -> ControllerInfo {
+            serde_json::from_value(serde_json::json!({ "address": address, "controller_type": controller_type })).unwrap()
+        };
+        let (pyro, lights) = (controller("10.0.0.9", "firework"), controller("10.0.0.1", "lights"));
+        let safety = Safety::default();
+        assert!(check_start(&safety, &[lights.clone(), pyro.clone()], 0).is_ok());
+
+        safety.arm().unwrap();
+        assert!(check_start(&safety, &[lights.clone(), pyro], 0).unwrap_err().contains("disarm"));
+        assert!(check_start(&safety, std::slice::from_ref(&lights), 0).is_ok());
+        assert!(check_start(&safety, &[lights], MAX_STAGGER_MS + 1).is_err());
+        assert!(check_start(&safety, &[], 0).is_err());
+    }
+
+    #[tokio::test]
+    async fn controllers_are_tested_in_order_stagger_apart() {
+        let mocks = [MockController::start().await, MockController::start().await, MockController::start().await];
+        let (report, order) = probe_all(&mocks.iter().collect::<Vec<_>>(), BatchPolicy::Continue).await;
+        assert_eq!(order, [0, 1, 2]);
+        assert_eq!(report.succeeded.len(), 3);
+        assert!(report.details.iter().all(|d| d.method == TestMethod::StatusOnly));
+
+        let times: Vec<_> = mocks.iter().map(|m| m.received()[0].at).collect();
+        for pair in times.windows(2) {
+            assert!(pair[1].duration_since(pair[0]) >= STAGGER);
+        }
+    }
+
+    #[tokio::test]
+    async fn failure_on_the_last_controller_still_aborts() {
+        let (first, last) = (MockController::start().await, MockController::start().await);
+        last.always("/status", Reply::Status(503));
+        let (report, _) = probe_all(&[&first, &last], BatchPolicy::AbortOnFirstError).await;
+        assert!(report.aborted());
+        assert!(report.skipped.is_empty());
+
+        first.always("/status", Reply::Status(503));
+        let (report, order) = probe_all(&[&first, &last], BatchPolicy::AbortOnFirstError).await;
+        assert!(report.aborted());
+        assert_eq!(order, [0]);
+        assert_eq!(report.skipped, std::slice::from_ref(&last.address));
+    }
+}
@@ -0,0 +1,53 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Launch flag that starts the app in safe mode.
+pub const FLAG: &str = "--safe-mode";
+
+/// Setting this to anything but "0" or "" starts the app in safe mode.
+pub const ENV_VAR: &str = "LUME_SAFE_MODE";
+
+static ACTIVE: AtomicBool = AtomicBool::new(false);
+
+// Tests run side by side in one process, so one entering safe mode must not
+// turn it on for the rest.
+#[cfg(test)]
+thread_local! {
+    static ACTIVE_ON_THREAD: std::cell::Cell<bool> = const { std::cell::Cell::new(false) };
+}
+
+/// Whether this launch asked for safe mode, by flag or environment.
+pub fn requested_at_launch() -> bool {
+    std::env::args().any(|arg| arg == FLAG)
+        || std::env::var(ENV_VAR).is_ok_and(|value| !value.is_empty() && value != "0")
+}
+
+pub fn is_active() -> bool {
+    #[cfg(test)]
+    if ACTIVE_ON_THREAD.with(std::cell::Cell::get) {
+        return true;
+    }
+    ACTIVE.load(Ordering::SeqCst)
+}
+
+/// Safe mode for the calling thread only.
+#[cfg(test)]
+pub fn enter_on_this_thread() {
+    ACTIVE_ON_THREAD.with(|active| active.set(true));
+}
+
+/// Turns safe mode on for the rest of this run. There is no way back short
+/// of restarting, so a half-recovered state never drives hardware.
+pub fn enter(reason: &str) {
+    if !ACTIVE.swap(true, Ordering::SeqCst) {
+        log::warn!("Safe mode ({}): hardware access and background tasks are disabled", reason);
+    }
+}
+
+/// Fails while in safe mode. Called before anything reaches a controller,
+/// except requests that only switch outputs off, such as an emergency stop.
+pub fn check() -> Result<(), String> {
+    if is_active() {
+        return Err("Safe mode is on: controllers cannot be contacted until the app is restarted normally".to_string());
+    }
+    Ok(())
+}
@@ -0,0 +1,229 @@
+use serde::Serialize;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::sync::{Mutex, MutexGuard};
+use tauri::{AppHandle, Manager};
+use tauri_plugin_global_shortcut::{GlobalShortcutExt, ShortcutState};
+use tokio::task::JoinSet;
+
+use crate::discovery::Discovery;
+use crate::dispatcher::Dispatcher;
+use crate::driver;
+use crate::dmx::DmxOutput;
+use crate::registry::ControllerRegistry;
+use crate::show_engine::ShowEngine;
+use crate::zones::{self, ZoneReport};
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ArmState {
+    Disarmed,
+    Armed,
+    /// Armed, with a show that fires pyro running.
+    Firing,
+}
+
+/// System-wide arming state: `Disarmed -> Armed -> Firing`, and back to
+/// `Armed` when the show ends. Disarming works from any state. Pyro outputs
+/// only fire while armed or firing.
+#[derive(Debug)]
+pub struct Safety {
+    state: Mutex<ArmState>,
+}
+
+impl Default for Safety {
+    fn default() -> Self {
+        Self {
+            state: Mutex::new(ArmState::Disarmed),
+        }
+    }
+}
+
+impl Safety {
+    fn lock(&self) -> Result<MutexGuard<'_, ArmState>, String> {
+        self.state.lock().map_err(|_| "Safety state is unavailable".to_string())
+    }
+
+    pub fn state(&self) -> Result<ArmState, String> {
+        Ok(*self.lock()?)
+    }
+
+    /// Arming a system that is already firing leaves it firing.
+    pub fn arm(&self) -> Result<ArmState, String> {
+        let mut state = self.lock()?;
+        if *state == ArmState::Disarmed {
+            *state = ArmState::Armed;
+            log::warn!("System ARMED");
+        }
+        Ok(*state)
+    }
+
+    pub fn disarm(&self) -> Result<ArmState, String> {
+        let mut state = self.lock()?;
+        *state = ArmState::Disarmed;
+        log::info!("System disarmed");
+        Ok(*state)
+    }
+
+    pub fn require_armed(&self) -> Result<(), String> {
+        match self.state()? {
+            ArmState::Armed | ArmState::Firing => Ok(()),
+            ArmState::Disarmed => Err("System is disarmed; arm it before firing pyro outputs".to_string()),
+        }
+    }
+
+    /// Enters `Firing` as a show with pyro cues starts; refused unless armed.
+    pub fn begin_firing(&self) -> Result<(), String> {
+        let mut state = self.lock()?;
+        match *state {
+            ArmState::Disarmed => Err("System is disarmed; arm it before starting a show with pyro cues".to_string()),
+            ArmState::Armed | ArmState::Firing => {
+                *state = ArmState::Firing;
+                log::warn!("System FIRING");
+                Ok(())
+            }
+        }
+    }
+
+    /// Back to `Armed` once the show stops or finishes. A system disarmed
+    /// meanwhile stays disarmed.
+    pub fn end_firing(&self) -> Result<(), String> {
+        let mut state = self.lock()?;
+        if *state == ArmState::Firing {
+            *state = ArmState::Armed;
+            log::info!("Show over; system still armed");
+        }
+        Ok(())
+    }
+}
+
+/// Arms or disarms the pyro controllers through their links once the desk
+/// has. Controllers that fail are logged; the desk's own state stands
+/// either way, and preflight reports unreachable pyro controllers.
+pub async fn arm_controllers(registry: &ControllerRegistry, armed: bool) -> Result<(), String> {
+    let mut links = JoinSet::new();
+    for controller in registry.list()?.into_iter().filter(|c| c.is_pyro()) {
+        links.spawn(async move {
+            let link = driver::link_for(&controller.address, &controller.controller_type);
+            link.arm(armed).await.err().map(|e| format!("{}: {}", controller.label(), e))
+        });
+    }
+    while let Some(joined) = links.join_next().await {
+        if let Ok(Some(failure)) = joined {
+            log::warn!("Controller did not {}: {}", if armed { "arm" } else { "disarm" }, failure);
+        }
+    }
+    Ok(())
+}
+
+/// Payload of the emergency stop broadcast. Controllers take it on their
+/// discovery port, so one packet reaches every unit on the subnet, whatever
+/// its registered address.
+pub const ABORT_MAGIC: &str = "LUME_ABORT";
+
+// UDP may drop a packet; the per-controller stops follow up anyway.
+const ABORT_REPEATS: usize = 3;
+
+/// Sends the abort packet to `target`, usually the broadcast address.
+/// Not gated by safe mode: it only makes things safe.
+pub async fn broadcast_abort(target: SocketAddr) -> Result<(), String> {
+    let socket = tokio::net::UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0))
+        .await
+        .map_err(|e| format!("Failed to open abort socket: {}", e))?;
+    socket
+        .set_broadcast(true)
+        .map_err(|e| format!("Failed to enable broadcast: {}", e))?;
+    for _ in 0..ABORT_REPEATS {
+        socket
+            .send_to(ABORT_MAGIC.as_bytes(), target)
+            .await
+            .map_err(|e| format!("Failed to broadcast abort: {}", e))?;
+    }
+    Ok(())
+}
+
+/// Raises an emergency stop from anywhere on the desktop, even while the
+/// window is unfocused or its webview hangs.
+pub const EMERGENCY_STOP_SHORTCUT: &str = "CommandOrControl+Shift+Space";
+
+/// Registers `EMERGENCY_STOP_SHORTCUT`. Another app holding the shortcut
+/// is logged, not fatal: the in-app stop still works.
+pub fn register_emergency_shortcut(app: &AppHandle) {
+    let registered = app.global_shortcut().on_shortcut(EMERGENCY_STOP_SHORTCUT, |app, _shortcut, event| {
+        if event.state != ShortcutState::Pressed {
+            return;
+        }
+        let app = app.clone();
+        tauri::async_runtime::spawn(async move {
+            if let Err(e) = emergency_stop(&app).await {
+                log::error!("Emergency stop from the global shortcut failed: {}", e);
+            }
+        });
+    });
+    match registered {
+        Ok(()) => log::info!("Emergency stop on {}", EMERGENCY_STOP_SHORTCUT),
+        Err(e) => log::error!("Could not register {} for emergency stop: {}", EMERGENCY_STOP_SHORTCUT, e),
+    }
+}
+
+/// Disarms, then broadcasts the abort packet, stops the show and puts every
+/// registered controller into its safe state at the same time. Returns within `zones::EMERGENCY_STOP_DEADLINE`
+/// of the disarm, reporting controllers that did not answer by then. Needs
+/// nothing from the webview, so every way of raising an emergency stop
+/// leads here.
+pub async fn emergency_stop(app: &AppHandle) -> Result<ZoneReport, String> {
+    log::warn!("EMERGENCY STOP");
+    if let Err(e) = app.state::<Safety>().disarm() {
+        log::error!("Emergency stop could not disarm: {}", e);
+    }
+    app.state::<DmxOutput>().release_all();
+    // The engine halts as soon as stop takes its lock; only releasing held
+    // outputs waits on the network, which the controller stop covers too.
+    let (engine, dispatcher, registry) = (app.state::<ShowEngine>(), app.state::<Dispatcher>(), app.state::<ControllerRegistry>());
+    let halt = tokio::time::timeout(zones::EMERGENCY_STOP_DEADLINE, engine.stop(app));
+    let controllers = zones::emergency_stop(&dispatcher, &registry);
+    let port = app.state::<Discovery>().broadcast().map(|probe| probe.port);
+    let abort = async {
+        let port = port?;
+        broadcast_abort(SocketAddr::new(IpAddr::V4(Ipv4Addr::BROADCAST), port)).await
+    };
+    let (halted, report, aborted) = tokio::join!(halt, controllers, abort);
+    if let Err(e) = aborted {
+        log::error!("Emergency stop broadcast failed: {}", e);
+    }
+    match halted {
+        Ok(Ok(_)) => {}
+        Ok(Err(e)) => log::error!("Emergency stop could not stop the show: {}", e),
+        Err(_) => log::error!("Show engine did not finish stopping within the emergency stop deadline"),
+    }
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::safe_mode;
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn abort_goes_out_in_safe_mode() {
+        let listener = tokio::net::UdpSocket::bind((Ipv4Addr::LOCALHOST, 0)).await.unwrap();
+        safe_mode::enter_on_this_thread();
+        broadcast_abort(listener.local_addr().unwrap()).await.unwrap();
+
+        let mut buffer = [0_u8; 64];
+        let received = tokio::time::timeout(Duration::from_secs(1), listener.recv(&mut buffer)).await.unwrap().unwrap();
+        assert_eq!(&buffer[..received], ABORT_MAGIC.as_bytes());
+    }
+
+    #[test]
+    fn disarming_leaves_firing() {
+        let safety = Safety::default();
+        assert!(safety.begin_firing().is_err());
+        safety.arm().unwrap();
+        safety.begin_firing().unwrap();
+        safety.disarm().unwrap();
+        assert_eq!(safety.state().unwrap(), ArmState::Disarmed);
+        safety.end_firing().unwrap();
+        assert_eq!(safety.state().unwrap(), ArmState::Disarmed);
+    }
+}
@@ -0,0 +1,469 @@
+use chrono::{DateTime, Datelike, Duration as ChronoDuration, FixedOffset, Local, NaiveDate, NaiveDateTime, NaiveTime, TimeZone, Utc, Weekday};
+use chrono_tz::Tz;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::ErrorKind;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Duration;
+use tauri::{AppHandle, Manager};
+
+use crate::audit::{now_millis, AuditLog};
+use crate::control_lock::ControlLock;
+use crate::events::{self, ScheduleFired};
+use crate::palette;
+use crate::show_engine::ShowEngine;
+use crate::show_store;
+
+/// How often the scheduler looks for due shows.
+const POLL: Duration = Duration::from_secs(1);
+
+/// A run missed by more than this, e.g. because the desk was off, is
+/// skipped rather than started late.
+const LATE_LIMIT_MS: i64 = 2 * 60 * 1000;
+
+/// Far enough to get past a polar night when looking for the next sunset.
+const MAX_SEARCH_DAYS: i64 = 400;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Day {
+    Mon,
+    Tue,
+    Wed,
+    Thu,
+    Fri,
+    Sat,
+    Sun,
+}
+
+impl Day {
+    fn weekday(self) -> Weekday {
+        match self {
+            Day::Mon => Weekday::Mon,
+            Day::Tue => Weekday::Tue,
+            Day::Wed => Weekday::Wed,
+            Day::Thu => Weekday::Thu,
+            Day::Fri => Weekday::Fri,
+            Day::Sat => Weekday::Sat,
+            Day::Sun => Weekday::Sun,
+        }
+    }
+}
+
+/// The time of day a recurring show starts.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum StartTime {
+    /// Wall-clock time in the rule's timezone, as "HH:MM".
+    At { time: String },
+    /// Sunset at the given place, shifted by `offset_minutes` (negative is before).
+    Sunset {
+        latitude: f64,
+        longitude: f64,
+        #[serde(default)]
+        offset_minutes: i32,
+    },
+}
+
+/// e.g. every Friday and Saturday at sunset:
+/// `{"days": ["fri", "sat"], "start": {"type": "sunset", "latitude": 52.5, "longitude": 13.4}}`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RecurrenceRule {
+    /// Days the show runs on; empty means every day.
+    #[serde(default)]
+    pub days: Vec<Day>,
+    pub start: StartTime,
+    /// "local" (the default) for the desk's own timezone, an IANA zone such
+    /// as "Europe/Berlin" for the venue's, both following daylight saving
+    /// changes, or a fixed UTC offset such as "+02:00".
+    #[serde(default)]
+    pub timezone: Option<String>,
+}
+
+enum Zone {
+    Local,
+    Named(Tz),
+    Fixed(FixedOffset),
+}
+
+fn parse_zone(timezone: Option<&str>) -> Result<Zone, String> {
+    let timezone = timezone.map(str::trim).unwrap_or("local");
+    match timezone {
+        "" | "local" => Ok(Zone::Local),
+        "UTC" | "utc" | "Z" => Ok(Zone::Fixed(FixedOffset::east_opt(0).expect("zero offset is valid"))),
+        name => name.parse::<Tz>().map(Zone::Named).or_else(|_| {
+            name.parse::<FixedOffset>().map(Zone::Fixed).map_err(|_| {
+                format!(
+                    "Timezone must be \"local\", a zone like \"Europe/Berlin\" or a UTC offset like \"+02:00\", got \"{}\"",
+                    name
+                )
+            })
+        }),
+    }
+}
+
+// Clocks going back make a wall time happen twice: take the first. Clocks
+// going forward skip it: take the same time an hour later.
+fn resolve<Tz: TimeZone>(tz: &Tz, naive: NaiveDateTime) -> Option<DateTime<Utc>> {
+    tz.from_local_datetime(&naive)
+        .earliest()
+        .or_else(|| tz.from_local_datetime(&(naive + ChronoDuration::hours(1))).earliest())
+        .map(|t| t.with_timezone(&Utc))
+}
+
+impl Zone {
+    fn date_of(&self, t: DateTime<Utc>) -> NaiveDate {
+        match self {
+            Zone::Local => t.with_timezone(&Local).date_naive(),
+            Zone::Named(tz) => t.with_timezone(tz).date_naive(),
+            Zone::Fixed(offset) => t.with_timezone(offset).date_naive(),
+        }
+    }
+
+    fn resolve(&self, naive: NaiveDateTime) -> Option<DateTime<Utc>> {
+        match self {
+            Zone::Local => resolve(&Local, naive),
+            Zone::Named(tz) => resolve(tz, naive),
+            Zone::Fixed(offset) => resolve(offset, naive),
+        }
+    }
+}
+
+fn parse_time(time: &str) -> Result<NaiveTime, String> {
+    NaiveTime::parse_from_str(time.trim(), "%H:%M").map_err(|_| format!("Start time must be HH:MM, got \"{}\"", time))
+}
+
+/// Sunset (the sun's upper edge at the horizon) at a place on `date`, from
+/// the NOAA sunrise equation; good to about a minute. None while the sun
+/// does not set, or does not rise, that day.
+pub fn sunset(date: NaiveDate, latitude: f64, longitude: f64) -> Option<DateTime<Utc>> {
+    let n = (date - NaiveDate::from_ymd_opt(2000, 1, 1)?).num_days() as f64;
+    let mean_noon = n - longitude / 360.0;
+    let anomaly = (357.5291 + 0.98560028 * mean_noon).rem_euclid(360.0).to_radians();
+    let center = 1.9148 * anomaly.sin() + 0.0200 * (2.0 * anomaly).sin() + 0.0003 * (3.0 * anomaly).sin();
+    let ecliptic = (anomaly.to_degrees() + center + 180.0 + 102.9372).rem_euclid(360.0).to_radians();
+    let transit = 2451545.0 + mean_noon + 0.0053 * anomaly.sin() - 0.0069 * (2.0 * ecliptic).sin();
+    let declination = (ecliptic.sin() * 23.4397f64.to_radians().sin()).asin();
+    let latitude = latitude.to_radians();
+    let cos_hour_angle =
+        ((-0.833f64).to_radians().sin() - latitude.sin() * declination.sin()) / (latitude.cos() * declination.cos());
+    if !(-1.0..=1.0).contains(&cos_hour_angle) {
+        return None;
+    }
+    let julian = transit + cos_hour_angle.acos().to_degrees() / 360.0;
+    DateTime::from_timestamp_millis(((julian - 2440587.5) * 86_400_000.0).round() as i64)
+}
+
+impl RecurrenceRule {
+    pub fn validate(&self) -> Result<(), String> {
+        parse_zone(self.timezone.as_deref())?;
+        match &self.start {
+            StartTime::At { time } => parse_time(time).map(|_| ()),
+            StartTime::Sunset {
+                latitude,
+                longitude,
+                offset_minutes,
+            } => {
+                if !(-90.0..=90.0).contains(latitude) || !(-180.0..=180.0).contains(longitude) {
+                    return Err(format!("Invalid location {}, {}", latitude, longitude));
+                }
+                if offset_minutes.abs() > 12 * 60 {
+                    return Err(format!("Sunset offset must be within 12 hours, got {} minutes", offset_minutes));
+                }
+                Ok(())
+            }
+        }
+    }
+
+    fn start_on(&self, date: NaiveDate, zone: &Zone) -> Result<Option<DateTime<Utc>>, String> {
+        Ok(match &self.start {
+            StartTime::At { time } => zone.resolve(date.and_time(parse_time(time)?)),
+            StartTime::Sunset {
+                latitude,
+                longitude,
+                offset_minutes,
+            } => sunset(date, *latitude, *longitude).map(|t| t + ChronoDuration::minutes(*offset_minutes as i64)),
+        })
+    }
+
+    /// The first start strictly after `after`, worked out day by day in the
+    /// rule's timezone so daylight saving changes are followed.
+    pub fn next_after(&self, after: DateTime<Utc>) -> Result<Option<DateTime<Utc>>, String> {
+        let zone = parse_zone(self.timezone.as_deref())?;
+        // From the day before: a sunset offset can move a start across midnight.
+        let first = zone.date_of(after) - ChronoDuration::days(1);
+        for offset in 0..=MAX_SEARCH_DAYS {
+            let date = first + ChronoDuration::days(offset);
+            if !self.days.is_empty() && !self.days.iter().any(|d| d.weekday() == date.weekday()) {
+                continue;
+            }
+            match self.start_on(date, &zone)? {
+                Some(start) if start > after => return Ok(Some(start)),
+                _ => {}
+            }
+        }
+        Ok(None)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Schedule {
+    pub id: String,
+    pub show_path: PathBuf,
+    pub rule: RecurrenceRule,
+    /// Unix timestamp in milliseconds.
+    pub created_at: u64,
+    /// When the schedule last came due, whether the show started or not.
+    #[serde(default)]
+    pub last_due: Option<u64>,
+}
+
+impl Schedule {
+    fn next_run(&self) -> Option<DateTime<Utc>> {
+        let since = DateTime::from_timestamp_millis(self.last_due.unwrap_or(self.created_at) as i64)?;
+        self.rule.next_after(since).ok().flatten()
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct ScheduleInfo {
+    #[serde(flatten)]
+    pub schedule: Schedule,
+    /// Unix timestamp in milliseconds; None if the rule never comes due.
+    pub next_run: Option<u64>,
+}
+
+#[derive(Default)]
+struct SchedulerState {
+    schedules: Vec<Schedule>,
+    path: Option<PathBuf>,
+}
+
+/// Recurring show starts, saved to disk. Arming stays manual: a scheduled
+/// show only fires pyro if the system is armed when it reaches those cues.
+#[derive(Clone, Default)]
+pub struct ShowScheduler {
+    state: Arc<Mutex<SchedulerState>>,
+}
+
+impl ShowScheduler {
+    fn lock(&self) -> Result<MutexGuard<'_, SchedulerState>, String> {
+        self.state.lock().map_err(|_| "Scheduler is unavailable".to_string())
+    }
+
+    /// Loads saved schedules and remembers `path` for later saves. A missing
+    /// file means no schedules.
+    pub fn load(&self, path: PathBuf) -> Result<(), String> {
+        let schedules: Vec<Schedule> = match fs::read(&path) {
+            Ok(data) => serde_json::from_slice(&data).map_err(|e| format!("Invalid schedules file: {}", e))?,
+            Err(e) if e.kind() == ErrorKind::NotFound => Vec::new(),
+            Err(e) => return Err(format!("Failed to read {}: {}", path.display(), e)),
+        };
+        let mut state = self.lock()?;
+        state.schedules = schedules;
+        state.path = Some(path);
+        Ok(())
+    }
+
+    fn save(state: &SchedulerState) -> Result<(), String> {
+        let Some(path) = &state.path else {
+            return Ok(());
+        };
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+        }
+        let data = serde_json::to_vec_pretty(&state.schedules).map_err(|e| e.to_string())?;
+        show_store::write_atomic(path, &data).map_err(|e| format!("Failed to save schedules: {}", e))
+    }
+
+    pub fn add(&self, show_path: PathBuf, rule: RecurrenceRule) -> Result<ScheduleInfo, String> {
+        rule.validate()?;
+        show_store::read_show_file(&show_path)?;
+        let schedule = Schedule {
+            id: uuid::Uuid::new_v4().to_string(),
+            show_path,
+            rule,
+            created_at: now_millis(),
+            last_due: None,
+        };
+        let info = info(schedule.clone());
+        if info.next_run.is_none() {
+            return Err("The schedule would never start a show".to_string());
+        }
+        let mut state = self.lock()?;
+        state.schedules.push(schedule);
+        Self::save(&state)?;
+        log::info!("Scheduled {} ({})", info.schedule.show_path.display(), info.schedule.id);
+        Ok(info)
+    }
+
+    pub fn remove(&self, id: &str) -> Result<bool, String> {
+        let mut state = self.lock()?;
+        let before = state.schedules.len();
+        state.schedules.retain(|s| s.id != id);
+        let removed = state.schedules.len() != before;
+        if removed {
+            Self::save(&state)?;
+            log::info!("Removed schedule {}", id);
+        }
+        Ok(removed)
+    }
+
+    /// Schedules by next run, soonest first.
+    pub fn list(&self) -> Result<Vec<ScheduleInfo>, String> {
+        let mut infos: Vec<ScheduleInfo> = self.lock()?.schedules.iter().cloned().map(info).collect();
+        infos.sort_by_key(|i| i.next_run.unwrap_or(u64::MAX));
+        Ok(infos)
+    }
+
+    // Marks every schedule whose next run has come as due, returning each
+    // with the time it was due.
+    fn take_due(&self, now: DateTime<Utc>) -> Vec<(Schedule, DateTime<Utc>)> {
+        let Ok(mut state) = self.lock() else {
+            return Vec::new();
+        };
+        let mut due = Vec::new();
+        for schedule in state.schedules.iter_mut() {
+            if let Some(at) = schedule.next_run().filter(|at| *at <= now) {
+                schedule.last_due = Some(now.timestamp_millis() as u64);
+                due.push((schedule.clone(), at));
+            }
+        }
+        if !due.is_empty() {
+            if let Err(e) = Self::save(&state) {
+                log::warn!("{}", e);
+            }
+        }
+        due
+    }
+
+    pub fn start(&self, app: &AppHandle) {
+        tauri::async_runtime::spawn(watch(app.clone(), self.clone()));
+    }
+}
+
+fn info(schedule: Schedule) -> ScheduleInfo {
+    let next_run = schedule.next_run().map(|t| t.timestamp_millis() as u64);
+    ScheduleInfo { schedule, next_run }
+}
+
+fn start_show(app: &AppHandle, schedule: &Schedule) -> Result<(), String> {
+    app.state::<ControlLock>().check(None)?;
+    // Played straight from disk so the show open in the editor is left alone.
+    let show = palette::resolve(&show_store::read_show_file(&schedule.show_path)?)?;
+    app.state::<ShowEngine>().start(app, show)
+}
+
+async fn watch(app: AppHandle, scheduler: ShowScheduler) {
+    let mut interval = tokio::time::interval(POLL);
+    loop {
+        interval.tick().await;
+        let now = Utc::now();
+        for (schedule, at) in scheduler.take_due(now) {
+            let late_ms = (now - at).num_milliseconds();
+            let result = if late_ms > LATE_LIMIT_MS {
+                Err(format!("missed by {} s", late_ms / 1000))
+            } else {
+                start_show(&app, &schedule)
+            };
+            let label = format!("schedule {}", schedule.id);
+            match &result {
+                Ok(()) => log::warn!("Schedule {} started {}", schedule.id, schedule.show_path.display()),
+                Err(e) => log::warn!("Schedule {} did not start {}: {}", schedule.id, schedule.show_path.display(), e),
+            }
+            app.state::<AuditLog>().record_trigger(&label, &result);
+            events::emit(
+                &app,
+                events::SCHEDULE_FIRED,
+                ScheduleFired {
+                    schedule_id: schedule.id,
+                    show_path: schedule.show_path,
+                    due_at: at.timestamp_millis() as u64,
+                    started: result.is_ok(),
+                    error: result.err(),
+                },
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn utc(text: &str) -> DateTime<Utc> {
+        text.parse().unwrap()
+    }
+
+    fn rule(value: serde_json::Value) -> RecurrenceRule {
+        let rule: RecurrenceRule = serde_json::from_value(value).unwrap();
+        rule.validate().unwrap();
+        rule
+    }
+
+    #[test]
+    fn weekly_rule_comes_due_on_its_days_in_its_timezone() {
+        let rule = rule(json!({ "days": ["fri", "sat"], "start": { "type": "at", "time": "21:30" }, "timezone": "+02:00" }));
+        // Wednesday 14 October 2026, so Friday is the 16th.
+        let friday = rule.next_after(utc("2026-10-14T12:00:00Z")).unwrap().unwrap();
+        assert_eq!(friday, utc("2026-10-16T19:30:00Z"));
+        let saturday = rule.next_after(friday).unwrap().unwrap();
+        assert_eq!(saturday, utc("2026-10-17T19:30:00Z"));
+        assert_eq!(rule.next_after(saturday).unwrap(), Some(utc("2026-10-23T19:30:00Z")));
+    }
+
+    #[test]
+    fn named_timezone_follows_daylight_saving() {
+        let friday = rule(json!({ "days": ["fri"], "start": { "type": "at", "time": "21:00" }, "timezone": "Europe/Berlin" }));
+        // Clocks go forward on Sunday 29 March 2026 and back on Sunday 25 October.
+        assert_eq!(friday.next_after(utc("2026-03-25T12:00:00Z")).unwrap(), Some(utc("2026-03-27T20:00:00Z")));
+        assert_eq!(friday.next_after(utc("2026-03-28T12:00:00Z")).unwrap(), Some(utc("2026-04-03T19:00:00Z")));
+        assert_eq!(friday.next_after(utc("2026-10-21T12:00:00Z")).unwrap(), Some(utc("2026-10-23T19:00:00Z")));
+        assert_eq!(friday.next_after(utc("2026-10-24T12:00:00Z")).unwrap(), Some(utc("2026-10-30T20:00:00Z")));
+
+        // 02:30 is skipped in spring and happens twice in autumn.
+        let night = rule(json!({ "start": { "type": "at", "time": "02:30" }, "timezone": "Europe/Berlin" }));
+        assert_eq!(night.next_after(utc("2026-03-28T12:00:00Z")).unwrap(), Some(utc("2026-03-29T01:30:00Z")));
+        assert_eq!(night.next_after(utc("2026-10-24T12:00:00Z")).unwrap(), Some(utc("2026-10-25T00:30:00Z")));
+    }
+
+    #[test]
+    fn sunset_rule_follows_the_sun() {
+        // Berlin's midsummer sunset is at about 21:33 CEST.
+        let sunset_utc = sunset(NaiveDate::from_ymd_opt(2026, 6, 21).unwrap(), 52.52, 13.405).unwrap();
+        assert!((sunset_utc - utc("2026-06-21T19:33:00Z")).num_minutes().abs() <= 3, "{}", sunset_utc);
+        let rule = rule(json!({ "start": { "type": "sunset", "latitude": 52.52, "longitude": 13.405, "offset_minutes": -30 } }));
+        let next = rule.next_after(utc("2026-06-21T12:00:00Z")).unwrap().unwrap();
+        assert_eq!(next, sunset_utc - ChronoDuration::minutes(30));
+        // No sunset in the midnight sun.
+        assert_eq!(sunset(NaiveDate::from_ymd_opt(2026, 6, 21).unwrap(), 80.0, 15.0), None);
+    }
+
+    #[test]
+    fn rejects_invalid_rules() {
+        let invalid = |value| serde_json::from_value::<RecurrenceRule>(value).unwrap().validate().is_err();
+        assert!(invalid(json!({ "start": { "type": "at", "time": "25:00" } })));
+        assert!(invalid(json!({ "start": { "type": "at", "time": "20:00" }, "timezone": "Mars/Olympus" })));
+        assert!(invalid(json!({ "start": { "type": "sunset", "latitude": 95.0, "longitude": 0.0 } })));
+    }
+
+    #[test]
+    fn due_schedule_is_taken_once() {
+        let scheduler = ShowScheduler::default();
+        scheduler.lock().unwrap().schedules.push(Schedule {
+            id: "s1".to_string(),
+            show_path: PathBuf::from("finale.lume"),
+            rule: rule(json!({ "start": { "type": "at", "time": "20:00" }, "timezone": "UTC" })),
+            created_at: utc("2026-10-14T12:00:00Z").timestamp_millis() as u64,
+            last_due: None,
+        });
+        assert!(scheduler.take_due(utc("2026-10-14T19:59:59Z")).is_empty());
+        let due = scheduler.take_due(utc("2026-10-14T20:00:01Z"));
+        assert_eq!(due.len(), 1);
+        assert_eq!(due[0].1, utc("2026-10-14T20:00:00Z"));
+        assert!(scheduler.take_due(utc("2026-10-14T20:00:02Z")).is_empty());
+        assert_eq!(scheduler.list().unwrap()[0].next_run, Some(utc("2026-10-15T20:00:00Z").timestamp_millis() as u64));
+    }
+}
@@ -0,0 +1,175 @@
+use serde_json::Value;
+use serialport::SerialPort;
+use std::collections::HashMap;
+use std::io::{ErrorKind, Read, Write};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+use crate::controller_client::RequestError;
+use crate::transport;
+
+/// Line speed of the firing firmware's serial console.
+pub const BAUD_RATE: u32 = 115_200;
+
+/// Asks the firing firmware for its state; only it answers with a
+/// "Software Area:" line.
+pub const STATUS_COMMAND: &str = "STATUS";
+
+/// The firmware answers a command with a burst of lines; the reply is taken
+/// to be complete once the port has been quiet this long.
+const QUIET: Duration = Duration::from_millis(150);
+
+/// Reads give up after this long without data, so a silent board never
+/// blocks one for long.
+const READ_TIMEOUT: Duration = Duration::from_millis(100);
+
+/// Device names of USB serial adapters and native USB boards on Linux and
+/// macOS. macOS lists each port twice; only the call-out `cu.` device is used.
+const PORT_PREFIXES: [&str; 4] = ["ttyUSB", "ttyACM", "cu.usbserial", "cu.usbmodem"];
+
+type Port = Arc<Mutex<Box<dyn SerialPort>>>;
+
+// Open ports by device path. A port stays open once used, as opening it
+// again can reset the board.
+fn open_ports() -> &'static Mutex<HashMap<String, Port>> {
+    static PORTS: OnceLock<Mutex<HashMap<String, Port>>> = OnceLock::new();
+    PORTS.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+/// USB serial ports a firing module may be on, as controller addresses.
+pub fn ports() -> Vec<String> {
+    let available = match serialport::available_ports() {
+        Ok(available) => available,
+        Err(e) => {
+            log::warn!("Failed to list serial ports: {}", e);
+            return Vec::new();
+        }
+    };
+    let mut found: Vec<String> = available
+        .into_iter()
+        .filter(|port| {
+            let name = port.port_name.rsplit('/').next().unwrap_or(&port.port_name);
+            PORT_PREFIXES.iter().any(|prefix| name.starts_with(prefix))
+        })
+        .map(|port| format!("{}{}", transport::SERIAL_PREFIX, port.port_name))
+        .collect();
+    found.sort();
+    found.dedup();
+    found
+}
+
+/// Whether a reply to `STATUS_COMMAND` came from the firing firmware.
+pub fn is_firing_module(reply: &Value) -> bool {
+    reply
+        .as_array()
+        .is_some_and(|lines| lines.iter().any(|line| line.as_str().is_some_and(|l| l.starts_with("Software Area:"))))
+}
+
+// Raw 8N1 at BAUD_RATE with DTR up, which the boards' USB chips need to
+// leave the firmware running.
+fn port(path: &str) -> Result<Port, String> {
+    let mut open = open_ports().lock().map_err(|_| "Serial ports are unavailable".to_string())?;
+    if let Some(port) = open.get(path) {
+        return Ok(port.clone());
+    }
+    let mut serial = serialport::new(path, BAUD_RATE)
+        .timeout(READ_TIMEOUT)
+        .open()
+        .map_err(|e| format!("Failed to open {}: {}", path, e))?;
+    serial
+        .write_data_terminal_ready(true)
+        .map_err(|e| format!("Failed to configure {}: {}", path, e))?;
+    log::info!("Opened serial port {} at {} baud", path, BAUD_RATE);
+    let port = Arc::new(Mutex::new(serial));
+    open.insert(path.to_string(), port.clone());
+    Ok(port)
+}
+
+// Drops a port that failed so the next request opens it afresh.
+fn forget(path: &str) {
+    if let Ok(mut open) = open_ports().lock() {
+        open.remove(path);
+    }
+}
+
+/// Opens the serial controller at `address` if it is not open already.
+pub async fn open(address: &str) -> Result<(), String> {
+    let path = transport::serial_port(address).unwrap_or(address).to_string();
+    tokio::task::spawn_blocking(move || port(&path).map(drop))
+        .await
+        .map_err(|e| format!("Serial open failed: {}", e))?
+}
+
+/// Closes the serial controller at `address`, e.g. a port a scan found
+/// something else on. The port closes once no request is using it.
+pub fn close(address: &str) {
+    forget(transport::serial_port(address).unwrap_or(address));
+}
+
+// Writes one command line and collects the reply lines until the port goes
+// quiet or `timeout` passes. Blocks the calling thread.
+fn exchange(path: &str, command: &str, timeout: Duration) -> Result<Vec<String>, RequestError> {
+    let port = port(path).map_err(RequestError::Unreachable)?;
+    let mut serial = port
+        .lock()
+        .map_err(|_| RequestError::Unreachable(format!("Serial port {} is unavailable", path)))?;
+    if let Err(e) = serial.write_all(format!("{}\n", command).as_bytes()).and_then(|_| serial.flush()) {
+        forget(path);
+        return Err(RequestError::Unreachable(format!("Failed to write to {}: {}", path, e)));
+    }
+
+    let deadline = Instant::now() + timeout;
+    let mut received = Vec::new();
+    let mut last_data: Option<Instant> = None;
+    let mut buffer = [0_u8; 256];
+    while Instant::now() < deadline {
+        match serial.read(&mut buffer) {
+            Ok(0) => {}
+            Ok(n) => {
+                received.extend_from_slice(&buffer[..n]);
+                last_data = Some(Instant::now());
+            }
+            // No data within the read timeout.
+            Err(e) if e.kind() == ErrorKind::TimedOut => {
+                if last_data.is_some_and(|t| t.elapsed() >= QUIET) && received.contains(&b'\n') {
+                    break;
+                }
+            }
+            Err(e) if e.kind() == ErrorKind::Interrupted => {}
+            Err(e) => {
+                forget(path);
+                return Err(RequestError::Unreachable(format!("Failed to read from {}: {}", path, e)));
+            }
+        }
+    }
+    // The firmware echoes every command as a DEBUG line first.
+    Ok(String::from_utf8_lossy(&received)
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with("DEBUG:"))
+        .map(str::to_string)
+        .collect())
+}
+
+/// Sends one command line to the serial controller at `address` and returns
+/// its reply lines as a JSON array of strings. A reply starting with
+/// "Invalid" or "Unknown command" is a rejection; none at all within
+/// `timeout` means the controller is unreachable.
+pub async fn request(address: &str, command: &str, timeout: Duration) -> Result<Value, RequestError> {
+    let path = transport::serial_port(address).unwrap_or(address).to_string();
+    let line = command.to_string();
+    let lines = tokio::task::spawn_blocking(move || exchange(&path, &line, timeout))
+        .await
+        .map_err(|e| RequestError::Unreachable(format!("Serial request failed: {}", e)))??;
+    if lines.is_empty() {
+        return Err(RequestError::Unreachable(format!(
+            "{} did not respond within {} ms",
+            address,
+            timeout.as_millis()
+        )));
+    }
+    if let Some(refusal) = lines.iter().find(|l| l.starts_with("Invalid") || l.starts_with("Unknown command")) {
+        return Err(RequestError::Rejected(format!("{} rejected {}: {}", address, command, refusal)));
+    }
+    Ok(Value::from(lines))
+}
@@ -0,0 +1,1292 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Manager};
+use tokio::time::MissedTickBehavior;
+
+use crate::audit::AuditLog;
+use crate::crossfade::{self, CrossfadeReport};
+use crate::dispatcher::{self, Dispatcher, OutputAction};
+use crate::dmx::DmxOutput;
+use crate::events::{
+    self, ControllerDispatch, EffectFired, EffectSkipped, FireSource, PerformanceWarning, ShowError, ShowFinished, ShowHeld,
+    ShowStateChanged, ShowTick, SyncDrift,
+};
+use crate::haze;
+use crate::laser;
+use crate::live_edit::{self, LiveEdit, Playhead};
+use crate::models::{Effect, Marker, Show};
+use crate::mqtt::Mqtt;
+use crate::output_refresh::OutputRefresh;
+use crate::performance::{self, LoadMonitor};
+use crate::protocols::osc::Osc;
+use crate::ramp;
+use crate::registry::{self, ControllerInfo, ControllerRegistry};
+use crate::relay;
+use crate::safe_mode;
+use crate::safety::Safety;
+
+/// Engine tick rate in Hz. A show-tick event is emitted every tick; cues do
+/// not wait for one, as the engine also wakes when the next cue is due.
+/// CPU use and event traffic grow linearly with the rate: 20 Hz suits most
+/// shows, higher rates give a smoother playhead, and 10 Hz is for
+/// low-power machines.
+pub const DEFAULT_TICK_HZ: u32 = 20;
+pub const MIN_TICK_HZ: u32 = 5;
+pub const MAX_TICK_HZ: u32 = 250;
+
+static TICK_HZ: AtomicU32 = AtomicU32::new(DEFAULT_TICK_HZ);
+
+pub fn tick_rate() -> u32 {
+    TICK_HZ.load(Ordering::Relaxed)
+}
+
+/// Takes effect on the next tick, including for a show that is already running.
+pub fn set_tick_rate(hz: u32) -> Result<(), String> {
+    if !(MIN_TICK_HZ..=MAX_TICK_HZ).contains(&hz) {
+        return Err(format!(
+            "Tick rate must be between {} and {} Hz, got {}",
+            MIN_TICK_HZ, MAX_TICK_HZ, hz
+        ));
+    }
+    TICK_HZ.store(hz, Ordering::Relaxed);
+    log::info!("Engine tick rate set to {} Hz", hz);
+    Ok(())
+}
+
+fn tick_period(hz: u32) -> Duration {
+    Duration::from_secs_f64(1.0 / hz as f64)
+}
+
+/// Minimum gap between two performance warnings.
+const WARNING_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Drift from the sync source beyond this starts a rate correction.
+const DRIFT_THRESHOLD_MS: f64 = 20.0;
+
+/// Measured drift is removed over roughly this long rather than snapped away.
+const DRIFT_CORRECTION_SECS: f64 = 2.0;
+
+/// The sync clock never runs more than this fraction fast or slow.
+const MAX_RATE_ADJUST: f64 = 0.05;
+
+/// The audio player's clock is steady, so its drift is held far tighter
+/// than the audio a UI or timecode reader reports.
+const AUDIO_DRIFT_THRESHOLD_MS: f64 = 2.0;
+
+/// Weight of each new drift sample in the smoothed estimate.
+const DRIFT_SMOOTHING: f64 = 0.3;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PlaybackState {
+    Stopped,
+    Running,
+    /// Paused by the engine after a cue failed; see `ErrorPolicy::Hold`.
+    Held,
+    Finished,
+}
+
+/// What the engine does when a controller does not accept a cue.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ErrorPolicy {
+    /// Report the failure and keep playing.
+    Continue,
+    /// Freeze the show at the failure until the operator resumes it.
+    Hold,
+}
+
+/// Which of the two versions is playing in A/B comparison mode.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ComparisonSide {
+    A,
+    B,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ShowStatus {
+    /// True while held as well: the show is still live.
+    pub is_running: bool,
+    #[serde(default)]
+    pub is_held: bool,
+    pub current_time: f64,
+    pub total_duration: f64,
+    pub active_effects: Vec<String>,
+    /// Set while comparing two show versions.
+    pub comparison: Option<ComparisonSide>,
+    /// Engine clock minus the sync source, positive when the engine is ahead.
+    /// None until a sync source reports a position.
+    pub drift_ms: Option<f64>,
+    /// Cues of the current show that a controller did not accept.
+    pub failed_effects: u32,
+    /// `current_time` in the display format, when asked for.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub current_time_display: Option<String>,
+}
+
+struct Comparison {
+    a: Show,
+    b: Show,
+    active: ComparisonSide,
+}
+
+impl Comparison {
+    fn active_show(&self) -> &Show {
+        self.version(self.active)
+    }
+
+    fn version(&self, side: ComparisonSide) -> &Show {
+        match side {
+            ComparisonSide::A => &self.a,
+            ComparisonSide::B => &self.b,
+        }
+    }
+}
+
+struct Playback {
+    show: Arc<Show>,
+    // Effect indices sorted by start time; everything before `next_cue` has fired.
+    order: Vec<usize>,
+    next_cue: usize,
+    total_duration: f64,
+    // The clock reads `anchor_time` at `anchor`, advancing at `rate`.
+    anchor: Instant,
+    anchor_time: f64,
+    rate: f64,
+    drift_ms: Option<f64>,
+    // (effect index, end time) of effects that fired and have not ended yet.
+    active: Vec<(usize, f64)>,
+    failures: u32,
+    // Per controller address, reported with every tick.
+    dispatched: BTreeMap<String, ControllerDispatch>,
+    // The clock stands still at `anchor_time` while held.
+    held: bool,
+    // Show time a crossfade into this show ends at; level effects starting
+    // before it fade up.
+    fade_in_until: Option<f64>,
+    // Layers whose cues are skipped; can change while the show plays.
+    muted_layers: HashSet<String>,
+    // Markers from this time on have not been passed yet.
+    marker_from: f64,
+}
+
+impl Playback {
+    fn new(show: Show) -> Self {
+        // Disabled effects are never scheduled.
+        let mut order: Vec<usize> = (0..show.effects.len()).filter(|&i| show.effects[i].enabled).collect();
+        order.sort_by(|&a, &b| show.effects[a].start_time.total_cmp(&show.effects[b].start_time));
+        let muted_layers = show.layers.iter().filter(|l| l.muted).map(|l| l.name.clone()).collect();
+        Self {
+            muted_layers,
+            total_duration: playing_duration(&show),
+            show: Arc::new(show),
+            order,
+            next_cue: 0,
+            anchor: Instant::now(),
+            anchor_time: 0.0,
+            rate: 1.0,
+            drift_ms: None,
+            active: Vec::new(),
+            failures: 0,
+            dispatched: BTreeMap::new(),
+            held: false,
+            fade_in_until: None,
+            marker_from: 0.0,
+        }
+    }
+
+    fn current_time(&self) -> f64 {
+        if self.held {
+            return self.anchor_time;
+        }
+        self.anchor_time + self.anchor.elapsed().as_secs_f64() * self.rate
+    }
+
+    fn hold(&mut self) {
+        self.anchor_time = self.current_time();
+        self.held = true;
+    }
+
+    fn release(&mut self) {
+        self.anchor = Instant::now();
+        self.held = false;
+    }
+
+    fn set_rate(&mut self, rate: f64) {
+        self.anchor_time = self.current_time();
+        self.anchor = Instant::now();
+        self.rate = rate;
+    }
+
+    // Moves the playhead; cues before `time` are treated as already fired.
+    fn seek(&mut self, time: f64) {
+        let time = time.clamp(0.0, self.total_duration.max(0.0));
+        self.anchor = Instant::now();
+        self.anchor_time = time;
+        self.marker_from = time;
+        let effects = &self.show.effects;
+        self.next_cue = if time >= self.total_duration {
+            // Cues at the very end are skipped too, so the show just finishes.
+            self.order.len()
+        } else {
+            self.order.partition_point(|&i| effects[i].start_time < time)
+        };
+        self.active.clear();
+    }
+
+    fn active_ids(&self) -> Vec<String> {
+        self.active.iter().map(|&(i, _)| self.show.effects[i].id.clone()).collect()
+    }
+
+    fn playhead(&self) -> Playhead {
+        Playhead {
+            time: self.current_time(),
+            fired: self.order[..self.next_cue]
+                .iter()
+                .map(|&i| self.show.effects[i].id.clone())
+                .collect(),
+        }
+    }
+
+    // Swaps in an edited version of the show without touching the clock.
+    // Cues already passed stay passed and running ones keep running; both
+    // are matched by id, as edits shift effect indices.
+    fn replace_show(&mut self, show: Show) {
+        let passed: HashSet<String> = self.playhead().fired;
+        let running: HashMap<String, f64> = self
+            .active
+            .iter()
+            .map(|&(i, end)| (self.show.effects[i].id.clone(), end))
+            .collect();
+        let mut order: Vec<usize> = (0..show.effects.len()).filter(|&i| show.effects[i].enabled).collect();
+        order.sort_by(|&a, &b| {
+            let (a, b) = (&show.effects[a], &show.effects[b]);
+            (!passed.contains(&a.id))
+                .cmp(&!passed.contains(&b.id))
+                .then(a.start_time.total_cmp(&b.start_time))
+        });
+        self.next_cue = order.iter().filter(|&&i| passed.contains(&show.effects[i].id)).count();
+        self.active = show
+            .effects
+            .iter()
+            .enumerate()
+            .filter_map(|(i, e)| running.get(&e.id).map(|&end| (i, end)))
+            .collect();
+        self.order = order;
+        self.total_duration = playing_duration(&show);
+        self.show = Arc::new(show);
+    }
+}
+
+// Whether playing `show` from `time` would fire any pyro cue.
+fn fires_pyro(app: &AppHandle, show: &Show, time: f64) -> Result<bool, String> {
+    Ok(plays_pyro(show, &app.state::<ControllerRegistry>().list()?, time))
+}
+
+fn plays_pyro(show: &Show, controllers: &[ControllerInfo], time: f64) -> bool {
+    let known = registry::lookup_map(controllers);
+    show.effects
+        .iter()
+        .filter(|e| show.plays(e) && e.start_time >= time)
+        .any(|e| known.get(e.controller.as_str()).is_some_and(|c| c.is_pyro()))
+}
+
+// The show's duration, or where its last effect ends if it has none.
+fn playing_duration(show: &Show) -> f64 {
+    if show.total_duration > 0.0 {
+        return show.total_duration;
+    }
+    show.effects
+        .iter()
+        .map(|e| e.start_time + e.duration.max(0.0))
+        .fold(0.0, f64::max)
+}
+
+struct EngineState {
+    state: PlaybackState,
+    playback: Option<Playback>,
+    // Bumped on every start/stop so a superseded tick loop exits.
+    run_id: u64,
+    comparison: Option<Comparison>,
+    error_policy: ErrorPolicy,
+}
+
+struct TickOutcome {
+    show_id: String,
+    tick: ShowTick,
+    due: Vec<Effect>,
+    fade_in_until: Option<f64>,
+    finished: bool,
+    held: bool,
+    // Markers the playhead passed since the last tick.
+    markers: Vec<Marker>,
+    // Wall-clock time until the next cue is due.
+    next_cue_in: Option<Duration>,
+}
+
+/// Plays the loaded show against the registered controllers. Cheap to clone.
+#[derive(Clone)]
+pub struct ShowEngine {
+    state: Arc<Mutex<EngineState>>,
+}
+
+impl Default for ShowEngine {
+    fn default() -> Self {
+        Self {
+            state: Arc::new(Mutex::new(EngineState {
+                state: PlaybackState::Stopped,
+                playback: None,
+                run_id: 0,
+                comparison: None,
+                error_policy: ErrorPolicy::Continue,
+            })),
+        }
+    }
+}
+
+impl ShowEngine {
+    fn lock(&self) -> Result<MutexGuard<'_, EngineState>, String> {
+        self.state.lock().map_err(|_| "Show engine is unavailable".to_string())
+    }
+
+    pub fn start(&self, app: &AppHandle, show: Show) -> Result<(), String> {
+        self.start_at(app, show, 0.0)
+    }
+
+    /// Starts playback `time` seconds into the show. Cues before that point
+    /// are not fired.
+    pub fn start_at(&self, app: &AppHandle, show: Show, time: f64) -> Result<(), String> {
+        safe_mode::check()?;
+        if !time.is_finite() || time < 0.0 {
+            return Err(format!("Start position must be zero or positive, got {}", time));
+        }
+        let fires_pyro = fires_pyro(app, &show, time)?;
+        let mut engine = self.lock()?;
+        if matches!(engine.state, PlaybackState::Running | PlaybackState::Held) {
+            return Err("A show is already running".to_string());
+        }
+        if fires_pyro {
+            app.state::<Safety>().begin_firing()?;
+        }
+        let previous = engine.state;
+        let show_id = show.id.clone();
+        let mut playback = Playback::new(show);
+        playback.seek(time);
+        let time = playback.anchor_time;
+        engine.playback = Some(playback);
+        engine.state = PlaybackState::Running;
+        engine.run_id += 1;
+        let run_id = engine.run_id;
+        drop(engine);
+
+        emit_state(app, PlaybackState::Running, previous, Some(show_id), time);
+        tauri::async_runtime::spawn(run_loop(app.clone(), self.clone(), run_id));
+        Ok(())
+    }
+
+    /// Stops playback and clears any held lighting outputs. Returns the
+    /// show id and the time it stopped at, or None if nothing was playing.
+    pub async fn stop(&self, app: &AppHandle) -> Result<Option<(String, f64)>, String> {
+        // Scoped so the guard is gone before the await below.
+        let (previous, playback) = {
+            let mut engine = self.lock()?;
+            if engine.state == PlaybackState::Stopped {
+                return Ok(None);
+            }
+            let previous = engine.state;
+            engine.state = PlaybackState::Stopped;
+            engine.run_id += 1;
+            (previous, engine.playback.take())
+        };
+
+        if let Err(e) = app.state::<Safety>().end_firing() {
+            log::error!("{}", e);
+        }
+        app.state::<DmxOutput>().release_all();
+        let dispatcher = app.state::<Dispatcher>();
+        dispatcher.release_all().await;
+        haze::all_off(&dispatcher, &app.state::<ControllerRegistry>()).await;
+        let stopped = playback.map(|p| {
+            let time = if previous == PlaybackState::Finished {
+                p.total_duration
+            } else {
+                p.current_time().min(p.total_duration)
+            };
+            (p.show.id.clone(), time)
+        });
+        let (show_id, time) = stopped.clone().map_or((None, 0.0), |(id, time)| (Some(id), time));
+        emit_state(app, PlaybackState::Stopped, previous, show_id, time);
+        Ok(stopped)
+    }
+
+    /// The show being played or held, if any.
+    pub fn running_show(&self) -> Result<Option<Arc<Show>>, String> {
+        let engine = self.lock()?;
+        Ok(match (&engine.playback, engine.state) {
+            (Some(playback), PlaybackState::Running | PlaybackState::Held) => Some(playback.show.clone()),
+            _ => None,
+        })
+    }
+
+    pub fn status(&self) -> Result<ShowStatus, String> {
+        let engine = self.lock()?;
+        let comparison = engine.comparison.as_ref().map(|c| c.active);
+        Ok(match &engine.playback {
+            Some(playback) => ShowStatus {
+                is_running: matches!(engine.state, PlaybackState::Running | PlaybackState::Held),
+                is_held: engine.state == PlaybackState::Held,
+                current_time: if engine.state == PlaybackState::Finished {
+                    playback.total_duration
+                } else {
+                    playback.current_time()
+                },
+                total_duration: playback.total_duration,
+                active_effects: playback.active_ids(),
+                comparison,
+                drift_ms: playback.drift_ms,
+                failed_effects: playback.failures,
+                current_time_display: None,
+            },
+            None => ShowStatus {
+                is_running: false,
+                is_held: false,
+                current_time: 0.0,
+                total_duration: 0.0,
+                active_effects: vec![],
+                comparison,
+                drift_ms: None,
+                failed_effects: 0,
+                current_time_display: None,
+            },
+        })
+    }
+
+    /// Mutes or unmutes a layer of the show being played, if it has one.
+    /// Returns the (controller, channel) pairs of effects on the layer that
+    /// are still running, for the caller to release when muting.
+    pub fn set_layer_muted(&self, layer: &str, muted: bool) -> Result<Vec<(String, u32)>, String> {
+        let mut engine = self.lock()?;
+        let Some(playback) = engine.playback.as_mut() else {
+            return Ok(Vec::new());
+        };
+        if playback.show.layer(layer).is_none() {
+            return Ok(Vec::new());
+        }
+        if !muted {
+            playback.muted_layers.remove(layer);
+            return Ok(Vec::new());
+        }
+        playback.muted_layers.insert(layer.to_string());
+        let show = playback.show.clone();
+        let on_layer = |i: usize| show.effects[i].layer.as_deref() == Some(layer);
+        let running = playback
+            .active
+            .iter()
+            .filter(|&&(i, _)| on_layer(i))
+            .map(|&(i, _)| (show.effects[i].controller.clone(), show.effects[i].channel))
+            .collect();
+        playback.active.retain(|&(i, _)| !on_layer(i));
+        Ok(running)
+    }
+
+    pub fn set_error_policy(&self, policy: ErrorPolicy) -> Result<(), String> {
+        self.lock()?.error_policy = policy;
+        log::info!("Show error policy set to {:?}", policy);
+        Ok(())
+    }
+
+    /// Holds the running show where it is, as a cue failure under the hold
+    /// policy does; outputs that are on stay on. Returns the position.
+    pub fn pause(&self, app: &AppHandle) -> Result<f64, String> {
+        let (show_id, time) = {
+            let mut engine = self.lock()?;
+            if engine.state != PlaybackState::Running {
+                return Err("No show is running".to_string());
+            }
+            let playback = engine.playback.as_mut().ok_or("No show is running")?;
+            playback.hold();
+            let held = (playback.show.id.clone(), playback.anchor_time);
+            engine.state = PlaybackState::Held;
+            held
+        };
+        emit_state(app, PlaybackState::Held, PlaybackState::Running, Some(show_id), time);
+        Ok(time)
+    }
+
+    /// Continues a held show from the exact point it was held at. Cues that
+    /// fired before the hold are not fired again. Returns the position.
+    pub fn resume(&self, app: &AppHandle) -> Result<f64, String> {
+        let (show_id, time) = {
+            let mut engine = self.lock()?;
+            if engine.state != PlaybackState::Held {
+                return Err("The show is not held".to_string());
+            }
+            let playback = engine.playback.as_mut().ok_or("The show is not held")?;
+            playback.release();
+            let held = (playback.show.id.clone(), playback.anchor_time);
+            engine.state = PlaybackState::Running;
+            held
+        };
+        emit_state(app, PlaybackState::Running, PlaybackState::Held, Some(show_id), time);
+        Ok(time)
+    }
+
+    /// Moves the playhead of the running or held show to `time`. Cues before
+    /// it count as passed and are never fired; held outputs are released.
+    /// Returns the new position.
+    pub async fn seek(&self, app: &AppHandle, time: f64) -> Result<f64, String> {
+        if !time.is_finite() {
+            return Err(format!("Seek position must be a number, got {}", time));
+        }
+        let time = {
+            let mut engine = self.lock()?;
+            if !matches!(engine.state, PlaybackState::Running | PlaybackState::Held) {
+                return Err("No show is playing".to_string());
+            }
+            let playback = engine.playback.as_mut().ok_or("No show is playing")?;
+            playback.seek(time);
+            playback.anchor_time
+        };
+        app.state::<DmxOutput>().release_all();
+        app.state::<Dispatcher>().release_all().await;
+        log::info!("Seeked to {:.2}s", time);
+        Ok(time)
+    }
+
+    /// Changes cues of the running or held show that have not started yet,
+    /// without stopping it; see `live_edit::apply`. The next tick plays from
+    /// the edited schedule. Returns the playhead the edit was judged at.
+    pub fn apply_live_edit(&self, edit: &LiveEdit) -> Result<Playhead, String> {
+        let mut engine = self.lock()?;
+        if !matches!(engine.state, PlaybackState::Running | PlaybackState::Held) {
+            return Err("No show is playing".to_string());
+        }
+        let playback = engine.playback.as_mut().ok_or("No show is playing")?;
+        let playhead = playback.playhead();
+        let mut show = (*playback.show).clone();
+        live_edit::apply(&mut show, edit, &playhead)?;
+        playback.replace_show(show);
+        log::info!("Live edit at {:.3}s: {:?}", playhead.time, edit);
+        Ok(playhead)
+    }
+
+    /// Jumps to the end; the next tick finishes the show without firing
+    /// anything that was skipped.
+    pub async fn skip_to_end(&self, app: &AppHandle) -> Result<f64, String> {
+        let end = self.status()?.total_duration;
+        self.seek(app, end).await
+    }
+
+    /// Compares the engine clock with the position reported by the sync
+    /// source (audio or timecode). Drift beyond the threshold is corrected by
+    /// running the clock slightly fast or slow, never by jumping. Returns the
+    /// smoothed drift in milliseconds.
+    pub fn report_sync(&self, app: &AppHandle, source_time: f64) -> Result<f64, String> {
+        self.sync_to(app, source_time, DRIFT_THRESHOLD_MS)
+    }
+
+    /// Like `report_sync`, for the position of the soundtrack `audio` plays.
+    pub fn follow_audio(&self, app: &AppHandle, audio_time: f64) -> Result<f64, String> {
+        self.sync_to(app, audio_time, AUDIO_DRIFT_THRESHOLD_MS)
+    }
+
+    fn sync_to(&self, app: &AppHandle, source_time: f64, threshold_ms: f64) -> Result<f64, String> {
+        if !source_time.is_finite() || source_time < 0.0 {
+            return Err(format!("Sync position must be zero or positive, got {}", source_time));
+        }
+        let (drift, rate, corrected, engine_time) = {
+            let mut engine = self.lock()?;
+            if engine.state != PlaybackState::Running {
+                return Err("No show is running".to_string());
+            }
+            let playback = engine.playback.as_mut().ok_or_else(|| "No show is running".to_string())?;
+            let engine_time = playback.current_time();
+            let sample = (engine_time - source_time) * 1000.0;
+            let drift = match playback.drift_ms {
+                Some(previous) => previous + (sample - previous) * DRIFT_SMOOTHING,
+                None => sample,
+            };
+            playback.drift_ms = Some(drift);
+
+            // Correct until the drift is back under half the threshold.
+            let target_rate = if drift.abs() > threshold_ms {
+                1.0 - (drift / 1000.0 / DRIFT_CORRECTION_SECS).clamp(-MAX_RATE_ADJUST, MAX_RATE_ADJUST)
+            } else if drift.abs() < threshold_ms / 2.0 {
+                1.0
+            } else {
+                playback.rate
+            };
+            let corrected = target_rate != playback.rate;
+            if corrected {
+                playback.set_rate(target_rate);
+            }
+            (drift, target_rate, corrected, engine_time)
+        };
+
+        if corrected {
+            log::info!("Sync drift {:.1} ms, clock rate now {:.4}", drift, rate);
+            events::emit(
+                app,
+                events::SYNC_DRIFT,
+                SyncDrift {
+                    drift_ms: drift,
+                    rate,
+                    engine_time,
+                    source_time,
+                },
+            );
+        }
+        Ok(drift)
+    }
+
+    /// Replaces the running show with `show`, starting it from the top
+    /// without stopping the clock loop. The outgoing show's dimmer, haze and
+    /// color levels fade down over `duration` while the incoming show's level
+    /// effects fade up; pyro, relays, lasers and lighting effects cannot
+    /// fade, so outgoing ones are cut at once and incoming ones fire as
+    /// scheduled. Ends comparison mode. An incoming show with pyro cues needs
+    /// the system armed, as for a start.
+    pub async fn crossfade(&self, app: &AppHandle, show: Show, duration: f64) -> Result<CrossfadeReport, String> {
+        let fade = crossfade::check_duration(duration)?;
+        let show_id = show.id.clone();
+        let fires_pyro = fires_pyro(app, &show, 0.0)?;
+        let run_id = self.swap_in(&app.state::<Safety>(), show, duration, fires_pyro)?;
+        emit_state(app, PlaybackState::Running, PlaybackState::Running, Some(show_id.clone()), 0.0);
+        tauri::async_runtime::spawn(run_loop(app.clone(), self.clone(), run_id));
+        crossfade::fade_out(
+            &app.state::<Dispatcher>(),
+            &app.state::<ControllerRegistry>(),
+            &app.state::<OutputRefresh>(),
+            show_id,
+            fade,
+        )
+        .await
+    }
+
+    // Replaces the running show with `show` as a new run, so the outgoing
+    // loop exits and its cues still in flight are not counted against the
+    // incoming show. Returns the new run id.
+    fn swap_in(&self, safety: &Safety, show: Show, fade_in: f64, fires_pyro: bool) -> Result<u64, String> {
+        let mut engine = self.lock()?;
+        match engine.state {
+            PlaybackState::Running => {}
+            PlaybackState::Held => return Err("Resume the show before crossfading".to_string()),
+            _ => return Err("No show is running to crossfade from".to_string()),
+        }
+        if fires_pyro {
+            safety.begin_firing()?;
+        }
+        let mut incoming = Playback::new(show);
+        incoming.fade_in_until = Some(fade_in);
+        engine.playback = Some(incoming);
+        engine.comparison = None;
+        engine.run_id += 1;
+        Ok(engine.run_id)
+    }
+
+    /// Enters comparison mode with version A active.
+    pub fn load_comparison(&self, a: Show, b: Show) -> Result<(), String> {
+        self.lock()?.comparison = Some(Comparison {
+            a,
+            b,
+            active: ComparisonSide::A,
+        });
+        Ok(())
+    }
+
+    pub fn clear_comparison(&self) -> Result<(), String> {
+        self.lock()?.comparison = None;
+        Ok(())
+    }
+
+    /// The active comparison version, if comparison mode is on.
+    pub fn comparison_show(&self) -> Result<Option<Show>, String> {
+        Ok(self.lock()?.comparison.as_ref().map(|c| c.active_show().clone()))
+    }
+
+    /// Switches to the other version. If a show is running, the new version
+    /// continues from the same timestamp as a new run; effects it would have
+    /// started earlier are not fired retroactively. A version with pyro cues
+    /// still to come needs the system armed, as for a start. Returns the
+    /// timestamp.
+    pub async fn switch_comparison(&self, app: &AppHandle, side: ComparisonSide) -> Result<f64, String> {
+        let controllers = app.state::<ControllerRegistry>().list()?;
+        let switched = self.switch_in(&app.state::<Safety>(), &controllers, side)?;
+        let Some((time, run_id)) = switched else {
+            log::info!("Comparison switched to {:?}", side);
+            return Ok(0.0);
+        };
+        // Outputs held by the previous version would otherwise linger.
+        app.state::<DmxOutput>().release_all();
+        app.state::<Dispatcher>().release_all().await;
+        tauri::async_runtime::spawn(run_loop(app.clone(), self.clone(), run_id));
+        log::info!("Comparison switched to {:?} at {:.2}s", side, time);
+        Ok(time)
+    }
+
+    // Makes `side` the active version and, if a show is playing, swaps it in
+    // at the current time as a new run. Returns the time and new run id, or
+    // None if nothing was playing.
+    fn switch_in(
+        &self,
+        safety: &Safety,
+        controllers: &[ControllerInfo],
+        side: ComparisonSide,
+    ) -> Result<Option<(f64, u64)>, String> {
+        let mut guard = self.lock()?;
+        let engine = &mut *guard;
+        let comparison = engine
+            .comparison
+            .as_mut()
+            .ok_or_else(|| "No comparison is loaded".to_string())?;
+        let playing = match engine.playback.as_ref() {
+            Some(playback) if matches!(engine.state, PlaybackState::Running | PlaybackState::Held) => playback,
+            _ => {
+                comparison.active = side;
+                return Ok(None);
+            }
+        };
+        let show = comparison.version(side);
+        let time = playing.current_time();
+        if plays_pyro(show, controllers, time) {
+            safety.begin_firing()?;
+        }
+        let mut next = Playback::new(show.clone());
+        next.seek(time);
+        if playing.held {
+            next.hold();
+        }
+        comparison.active = side;
+        engine.playback = Some(next);
+        engine.run_id += 1;
+        Ok(Some((time, engine.run_id)))
+    }
+
+    /// Applies the error policy to a failure that is not tied to one cue,
+    /// e.g. live power draw over budget. Returns true if the show was held.
+    pub fn fail(&self, app: &AppHandle, reason: &str) -> Result<bool, String> {
+        let (show_id, time) = {
+            let mut guard = self.lock()?;
+            let engine = &mut *guard;
+            if engine.error_policy != ErrorPolicy::Hold || engine.state != PlaybackState::Running {
+                return Ok(false);
+            }
+            let Some(playback) = engine.playback.as_mut() else {
+                return Ok(false);
+            };
+            playback.hold();
+            engine.state = PlaybackState::Held;
+            (playback.show.id.clone(), playback.anchor_time)
+        };
+        log::warn!("Show held at {:.2}s: {}", time, reason);
+        emit_state(app, PlaybackState::Held, PlaybackState::Running, Some(show_id), time);
+        Ok(true)
+    }
+
+    // Adds a cue's outcome to its controller's figures. Returns the show id
+    // and time while this run is current.
+    fn count_cue(&self, run_id: u64, controller: &str, count: impl FnOnce(&mut ControllerDispatch)) -> Option<(String, f64)> {
+        let mut engine = self.state.lock().ok()?;
+        if engine.run_id != run_id {
+            return None;
+        }
+        let playback = engine.playback.as_mut()?;
+        count(playback.dispatched.entry(controller.to_string()).or_default());
+        Some((playback.show.id.clone(), playback.current_time()))
+    }
+
+    // Counts a cue the controller did not accept, unless its run has since
+    // been replaced. Returns the show id and hold point if this failure put
+    // the show on hold.
+    fn record_failure(&self, run_id: u64) -> Option<(String, f64)> {
+        let mut guard = self.state.lock().ok()?;
+        let engine = &mut *guard;
+        if engine.run_id != run_id {
+            return None;
+        }
+        let playback = engine.playback.as_mut()?;
+        playback.failures += 1;
+        if engine.error_policy != ErrorPolicy::Hold || engine.state != PlaybackState::Running {
+            return None;
+        }
+        playback.hold();
+        engine.state = PlaybackState::Held;
+        Some((playback.show.id.clone(), playback.anchor_time))
+    }
+
+    // Moves the playhead to now, collecting effects that became due.
+    // Returns None once this loop has been superseded by a stop or restart.
+    // While held the playhead stands still and nothing becomes due.
+    fn advance(&self, run_id: u64) -> Option<TickOutcome> {
+        let mut guard = self.state.lock().ok()?;
+        let engine = &mut *guard;
+        if engine.run_id != run_id || !matches!(engine.state, PlaybackState::Running | PlaybackState::Held) {
+            return None;
+        }
+        let held = engine.state == PlaybackState::Held;
+        let playback = engine.playback.as_mut()?;
+        let now = playback.current_time();
+        if held {
+            return Some(TickOutcome {
+                show_id: playback.show.id.clone(),
+                tick: ShowTick {
+                    current_time: now,
+                    total_duration: playback.total_duration,
+                    active_effects: playback.active_ids(),
+                    next_cue_time: None,
+                    next_cue_id: None,
+                    controllers: BTreeMap::new(),
+                },
+                due: Vec::new(),
+                fade_in_until: None,
+                finished: false,
+                held: true,
+                markers: Vec::new(),
+                next_cue_in: None,
+            });
+        }
+
+        let mut due = Vec::new();
+        while let Some(&index) = playback.order.get(playback.next_cue) {
+            let effect = &playback.show.effects[index];
+            if effect.start_time > now {
+                break;
+            }
+            if effect.layer.as_ref().is_some_and(|l| playback.muted_layers.contains(l)) {
+                playback.next_cue += 1;
+                continue;
+            }
+            due.push(effect.clone());
+            playback.active.push((index, effect.start_time + effect.duration.max(0.0)));
+            playback.next_cue += 1;
+        }
+        playback.active.retain(|&(_, end)| end > now);
+        let markers = playback
+            .show
+            .markers
+            .iter()
+            .filter(|m| m.time >= playback.marker_from && m.time < now)
+            .cloned()
+            .collect();
+        playback.marker_from = now;
+
+        let finished = now >= playback.total_duration && playback.next_cue >= playback.order.len();
+        let tick = ShowTick {
+            current_time: now.min(playback.total_duration),
+            total_duration: playback.total_duration,
+            active_effects: playback.active_ids(),
+            next_cue_time: playback
+                .order
+                .get(playback.next_cue)
+                .map(|&i| playback.show.effects[i].start_time),
+            next_cue_id: playback
+                .order
+                .get(playback.next_cue)
+                .map(|&i| playback.show.effects[i].id.clone()),
+            controllers: playback.dispatched.clone(),
+        };
+        if finished {
+            engine.state = PlaybackState::Finished;
+        }
+        let next_cue_in = tick
+            .next_cue_time
+            .map(|time| Duration::from_secs_f64(((time - now) / playback.rate).max(0.0)));
+        Some(TickOutcome {
+            show_id: playback.show.id.clone(),
+            tick,
+            due,
+            fade_in_until: playback.fade_in_until,
+            finished,
+            held: false,
+            markers,
+            next_cue_in,
+        })
+    }
+}
+
+fn emit_state(app: &AppHandle, state: PlaybackState, previous: PlaybackState, show_id: Option<String>, time: f64) {
+    log::info!("Show {:?} -> {:?} at {:.2}s", previous, state, time);
+    let change = ShowStateChanged {
+        state,
+        previous,
+        show_id,
+        current_time: time,
+    };
+    app.state::<Mqtt>().publish_show_state(&change);
+    events::emit(app, events::SHOW_STATE_CHANGED, change);
+}
+
+async fn run_loop(app: AppHandle, engine: ShowEngine, run_id: u64) {
+    let mut hz = tick_rate();
+    let mut interval = tick_interval(hz);
+    let mut last_warning: Option<Instant> = None;
+    let mut monitor = LoadMonitor::new();
+    let mut last_preview: Option<Instant> = None;
+    let mut next_cue: Option<tokio::time::Instant> = None;
+
+    loop {
+        if tick_rate() != hz {
+            hz = tick_rate();
+            interval = tick_interval(hz);
+        }
+        // Waking for the next cue as well as on ticks keeps cue timing to
+        // about a millisecond whatever the tick rate.
+        let (scheduled, on_tick) = match next_cue {
+            Some(at) => tokio::select! {
+                scheduled = interval.tick() => (scheduled, true),
+                () = tokio::time::sleep_until(at) => (at, false),
+            },
+            None => (interval.tick().await, true),
+        };
+        // A tick starting over half a period late counts as falling behind.
+        let lateness_warning = tick_period(hz) / 2;
+        let lateness = scheduled.elapsed();
+        let Some(outcome) = engine.advance(run_id) else {
+            break;
+        };
+        next_cue = outcome.next_cue_in.map(|wait| tokio::time::Instant::now() + wait);
+        if outcome.held {
+            continue;
+        }
+
+        for effect in outcome.due {
+            let fade_in = outcome.fade_in_until.map(|end| end - effect.start_time).filter(|&left| left > 0.0);
+            tauri::async_runtime::spawn(fire_effect(app.clone(), engine.clone(), run_id, effect, fade_in));
+        }
+        app.state::<Osc>().markers_passed(outcome.markers);
+        // Cue wake-ups only fire what is due; ticks do the rest.
+        if !on_tick && !outcome.finished {
+            continue;
+        }
+        performance::record_lateness(lateness);
+        monitor.observe(&app, lateness > lateness_warning, hz);
+        if lateness > lateness_warning && last_warning.map_or(true, |t| t.elapsed() > WARNING_INTERVAL) {
+            last_warning = Some(Instant::now());
+            let late_ms = lateness.as_secs_f64() * 1000.0;
+            events::emit(
+                &app,
+                events::PERFORMANCE_WARNING,
+                PerformanceWarning {
+                    metric: "tick_lateness_ms".to_string(),
+                    value: late_ms,
+                    threshold: lateness_warning.as_secs_f64() * 1000.0,
+                    message: format!("Engine tick started {:.0} ms late", late_ms),
+                },
+            );
+        }
+
+        let time = outcome.tick.current_time;
+        let finished = outcome.finished.then(|| ShowFinished {
+            show_id: outcome.show_id.clone(),
+            total_duration: outcome.tick.total_duration,
+            failed_effects: outcome.tick.controllers.values().map(|c| c.failed).sum(),
+            controllers: outcome.tick.controllers.clone(),
+        });
+        // Degraded mode thins out the playhead preview; cues above are unaffected.
+        let preview_period = tick_period(performance::DEGRADED_PREVIEW_HZ);
+        if !performance::is_degraded() || outcome.finished || last_preview.map_or(true, |t| t.elapsed() >= preview_period) {
+            last_preview = Some(Instant::now());
+            events::emit(&app, events::SHOW_TICK, outcome.tick);
+        }
+        if let Some(finished) = finished {
+            if let Err(e) = app.state::<Safety>().end_firing() {
+                log::error!("{}", e);
+            }
+            emit_state(&app, PlaybackState::Finished, PlaybackState::Running, Some(outcome.show_id), time);
+            events::emit(&app, events::SHOW_FINISHED, finished);
+            break;
+        }
+    }
+    monitor.finish(&app);
+}
+
+fn tick_interval(hz: u32) -> tokio::time::Interval {
+    let mut interval = tokio::time::interval(tick_period(hz));
+    interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+    interval
+}
+
+// A failed cue is reported and counted. The show carries on, or is held
+// at that point under `ErrorPolicy::Hold`.
+async fn fire_effect(app: AppHandle, engine: ShowEngine, run_id: u64, effect: Effect, fade_in: Option<f64>) {
+    // Disabled controllers are a decision of the operator, not a failure, so
+    // the error policy does not apply.
+    if let Some(controller) = app.state::<ControllerRegistry>().get(&effect.controller).ok().filter(|c| !c.enabled) {
+        let reason = format!("{} is disabled", controller.label());
+        log::warn!("Effect {} skipped: {}", effect.id, reason);
+        engine.count_cue(run_id, &controller.address, |c| c.skipped += 1);
+        app.state::<AuditLog>()
+            .record_cue(&effect.id, &effect.controller, Some(&controller), effect.channel, &Err(reason.clone()));
+        events::emit(
+            &app,
+            events::EFFECT_SKIPPED,
+            EffectSkipped {
+                effect_id: effect.id,
+                controller: controller.address,
+                channel: effect.channel,
+                reason,
+            },
+        );
+        return;
+    }
+    let result = dispatch_effect(&app, &effect, fade_in).await;
+    let controller = app.state::<ControllerRegistry>().get(&effect.controller).ok();
+    let address = controller.as_ref().map_or(effect.controller.as_str(), |c| c.address.as_str());
+    let counted = engine.count_cue(run_id, address, |c| match result {
+        Ok(()) => c.succeeded += 1,
+        Err(_) => c.failed += 1,
+    });
+    if let Err(error) = &result {
+        log::warn!("Effect {} failed on {}: {}", effect.id, effect.controller, error);
+        if let Some((show_id, time)) = counted {
+            events::emit(
+                &app,
+                events::SHOW_ERROR,
+                ShowError {
+                    show_id,
+                    current_time: time,
+                    effect_id: effect.id.clone(),
+                    controller: address.to_string(),
+                    channel: effect.channel,
+                    error: error.clone(),
+                },
+            );
+        }
+        if let Some((show_id, time)) = engine.record_failure(run_id) {
+            log::warn!("Show held at {:.2}s after effect {} failed", time, effect.id);
+            emit_state(&app, PlaybackState::Held, PlaybackState::Running, Some(show_id.clone()), time);
+            events::emit(
+                &app,
+                events::SHOW_HELD,
+                ShowHeld {
+                    show_id,
+                    current_time: time,
+                    effect_id: effect.id.clone(),
+                    controller: effect.controller.clone(),
+                    channel: effect.channel,
+                    error: error.clone(),
+                },
+            );
+        }
+    }
+    app.state::<AuditLog>()
+        .record_cue(&effect.id, &effect.controller, controller.as_ref(), effect.channel, &result);
+    report_fire(&app, Some(effect.id), &effect.controller, effect.channel, FireSource::Show, &result);
+}
+
+// `fade_in` is how long an ongoing crossfade into this show has left.
+async fn dispatch_effect(app: &AppHandle, effect: &Effect, fade_in: Option<f64>) -> Result<(), String> {
+    // Patched channels are DMX fixtures, not registered controllers.
+    let dmx = app.state::<DmxOutput>();
+    if dmx.is_patched(&effect.controller, effect.channel) {
+        return dmx.play(effect);
+    }
+    let controller = app.state::<ControllerRegistry>().get(&effect.controller)?;
+    // Checked per cue too, so a disarm during the show stops its pyro.
+    if controller.is_pyro() {
+        app.state::<Safety>().require_armed()?;
+    }
+    let dispatcher = app.state::<Dispatcher>();
+    let faded;
+    let effect = match fade_in {
+        Some(remaining) => {
+            faded = crossfade::fade_in(effect, &controller, remaining);
+            &faded
+        }
+        None => effect,
+    };
+    if let Some(ramp) = &effect.ramp {
+        return ramp::play(&dispatcher, &app.state::<OutputRefresh>(), &controller, effect, ramp).await;
+    }
+    if controller.is_pyro() {
+        app.state::<Safety>().require_armed()?;
+        return dispatcher.send(&controller.address, effect.channel, &OutputAction::Fire).await;
+    }
+    if controller.is_haze() {
+        return haze::apply_effect(&dispatcher, &controller, &effect.params, effect.duration).await;
+    }
+    if controller.is_laser() {
+        return laser::project(&dispatcher, &controller, effect.channel, &effect.params, effect.duration).await;
+    }
+    if controller.is_relay() {
+        return relay::apply(&dispatcher, &controller, effect).await;
+    }
+
+    let (on, off) = dispatcher::lighting_actions(&effect.params);
+    if effect.duration > 0.0 {
+        dispatcher
+            .hold(&controller.address, effect.channel, on, off, Duration::from_secs_f64(effect.duration))
+            .await
+    } else {
+        dispatcher.send(&controller.address, effect.channel, &on).await
+    }
+}
+
+/// Emits effect-fired for a show cue or manual override. `controller` may
+/// be an address or a friendly name; the event carries both.
+pub fn report_fire(
+    app: &AppHandle,
+    effect_id: Option<String>,
+    controller: &str,
+    channel: u32,
+    source: FireSource,
+    result: &Result<(), String>,
+) {
+    let (address, name) = match app.state::<ControllerRegistry>().get(controller) {
+        Ok(info) => (info.address.clone(), info.display_name()),
+        Err(_) => (controller.to_string(), None),
+    };
+    events::emit(
+        app,
+        events::EFFECT_FIRED,
+        EffectFired {
+            effect_id,
+            controller: address,
+            controller_name: name,
+            channel,
+            source,
+            success: result.is_ok(),
+            error: result.as_ref().err().cloned(),
+        },
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::safety::ArmState;
+    use serde_json::json;
+
+    // Held at `time`, so the clock stands still for the assertions.
+    fn playback_at(time: f64) -> Playback {
+        let show: Show = serde_json::from_value(json!({
+            "id": "show",
+            "name": "Test",
+            "total_duration": 20.0,
+            "effects": [
+                { "id": "late", "start_time": 8.0, "duration": 1.0, "controller": "10.0.0.5", "channel": 3 },
+                { "id": "early", "start_time": 2.0, "duration": 4.0, "controller": "10.0.0.5", "channel": 1 },
+                { "id": "boundary", "start_time": 5.0, "duration": 1.0, "controller": "10.0.0.5", "channel": 2 }
+            ]
+        }))
+        .unwrap();
+        let mut playback = Playback::new(show);
+        playback.hold();
+        playback.seek(time);
+        playback
+    }
+
+    fn live_edit(playback: &mut Playback, edit: LiveEdit) -> Result<(), String> {
+        let mut show = (*playback.show).clone();
+        live_edit::apply(&mut show, &edit, &playback.playhead())?;
+        playback.replace_show(show);
+        Ok(())
+    }
+
+    fn upcoming(playback: &Playback) -> Vec<&str> {
+        playback.order[playback.next_cue..]
+            .iter()
+            .map(|&i| playback.show.effects[i].id.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn cue_at_the_playhead_stays_due_after_an_edit() {
+        let mut playback = playback_at(5.0);
+        assert_eq!(playback.playhead().time, 5.0);
+        assert!(live_edit(&mut playback, LiveEdit::Move { effect_id: "boundary".into(), start_time: 9.0 }).is_err());
+
+        live_edit(&mut playback, LiveEdit::Move { effect_id: "late".into(), start_time: 5.5 }).unwrap();
+        assert_eq!(upcoming(&playback), ["boundary", "late"]);
+        assert_eq!(playback.next_cue, 1);
+    }
+
+    #[test]
+    fn moved_cue_is_reordered_past_others() {
+        let mut playback = playback_at(4.0);
+        live_edit(&mut playback, LiveEdit::Move { effect_id: "boundary".into(), start_time: 10.0 }).unwrap();
+        assert_eq!(upcoming(&playback), ["late", "boundary"]);
+    }
+
+    #[test]
+    fn running_effect_is_kept_when_indices_shift() {
+        let mut playback = playback_at(3.0);
+        let early = playback.show.effects.iter().position(|e| e.id == "early").unwrap();
+        playback.active.push((early, 6.0));
+
+        live_edit(&mut playback, LiveEdit::Remove { effect_id: "late".into() }).unwrap();
+        assert_eq!(playback.active_ids(), ["early"]);
+        assert_eq!(playback.active[0].1, 6.0);
+        assert_eq!(upcoming(&playback), ["boundary"]);
+    }
+
+    fn running(show: Show) -> (ShowEngine, u64) {
+        let engine = ShowEngine::default();
+        let run_id = {
+            let mut state = engine.lock().unwrap();
+            state.state = PlaybackState::Running;
+            state.playback = Some(Playback::new(show));
+            state.run_id = 7;
+            state.run_id
+        };
+        (engine, run_id)
+    }
+
+    fn pyro_show() -> (Show, Vec<ControllerInfo>) {
+        let show: Show = serde_json::from_value(json!({
+            "id": "pyro",
+            "name": "Finale",
+            "total_duration": 10.0,
+            "effects": [{ "id": "shell", "start_time": 4.0, "controller": "10.0.0.9", "channel": 1 }]
+        }))
+        .unwrap();
+        let controllers = vec![serde_json::from_value(json!({ "address": "10.0.0.9", "controller_type": "firework" })).unwrap()];
+        (show, controllers)
+    }
+
+    #[test]
+    fn crossfading_into_pyro_needs_the_system_armed() {
+        let (engine, run_id) = running(playback_at(0.0).show.as_ref().clone());
+        let (pyro, controllers) = pyro_show();
+        assert!(plays_pyro(&pyro, &controllers, 0.0));
+        let safety = Safety::default();
+
+        assert!(engine.swap_in(&safety, pyro.clone(), 2.0, true).is_err());
+        let state = engine.lock().unwrap();
+        assert_eq!(state.playback.as_ref().unwrap().show.id, "show");
+        assert_eq!(state.run_id, run_id);
+        drop(state);
+
+        safety.arm().unwrap();
+        let next = engine.swap_in(&safety, pyro, 2.0, true).unwrap();
+        assert_eq!(safety.state().unwrap(), ArmState::Firing);
+        assert_eq!(engine.lock().unwrap().playback.as_ref().unwrap().show.id, "pyro");
+        // The outgoing run's loop and in-flight cues no longer count.
+        assert_ne!(next, run_id);
+        assert!(engine.record_failure(run_id).is_none());
+    }
+
+    #[test]
+    fn switching_to_a_pyro_version_needs_the_system_armed() {
+        let plain = playback_at(0.0).show.as_ref().clone();
+        let (engine, run_id) = running(plain.clone());
+        let (pyro, controllers) = pyro_show();
+        engine.load_comparison(plain, pyro).unwrap();
+        let safety = Safety::default();
+
+        assert!(engine.switch_in(&safety, &controllers, ComparisonSide::B).is_err());
+        assert_eq!(engine.comparison_show().unwrap().unwrap().id, "show");
+        assert_eq!(engine.lock().unwrap().run_id, run_id);
+
+        safety.arm().unwrap();
+        let (_, next) = engine.switch_in(&safety, &controllers, ComparisonSide::B).unwrap().unwrap();
+        assert_eq!(safety.state().unwrap(), ArmState::Firing);
+        assert_eq!(engine.running_show().unwrap().unwrap().id, "pyro");
+        assert_ne!(next, run_id);
+        assert!(engine.record_failure(run_id).is_none());
+    }
+}
@@ -0,0 +1,135 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::models::Show;
+use crate::transport::{self, Transport};
+
+/// Wire protocol a show is meant to be driven with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputProtocol {
+    /// The controllers' own HTTP/UDP/serial API.
+    Native,
+    #[serde(rename = "artnet")]
+    ArtNet,
+    Sacn,
+    Dmx,
+}
+
+impl OutputProtocol {
+    /// Only the native controller protocol has an output backend in this build.
+    pub fn is_supported(self) -> bool {
+        matches!(self, OutputProtocol::Native)
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            OutputProtocol::Native => "native",
+            OutputProtocol::ArtNet => "Art-Net",
+            OutputProtocol::Sacn => "sACN",
+            OutputProtocol::Dmx => "DMX",
+        }
+    }
+}
+
+/// Stored with the show so opening it sets up the outputs it was written for.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct ShowOutputConfig {
+    pub protocol: OutputProtocol,
+    /// Native only: how commands reach the controllers.
+    #[serde(default)]
+    pub transport: Option<Transport>,
+    /// Send timeout for `transport`, in milliseconds.
+    #[serde(default)]
+    pub timeout_ms: Option<u64>,
+    /// Art-Net/sACN node address or DMX interface port.
+    #[serde(default)]
+    pub target: Option<String>,
+    /// Art-Net/sACN universe.
+    #[serde(default)]
+    pub universe: Option<u16>,
+}
+
+impl ShowOutputConfig {
+    fn validate(&self) -> Result<(), String> {
+        if let Some(ms) = self.timeout_ms {
+            if !(transport::MIN_TIMEOUT_MS..=transport::MAX_TIMEOUT_MS).contains(&ms) {
+                return Err(format!(
+                    "Timeout must be between {} and {} ms, got {}",
+                    transport::MIN_TIMEOUT_MS,
+                    transport::MAX_TIMEOUT_MS,
+                    ms
+                ));
+            }
+        }
+        match self.protocol {
+            OutputProtocol::Native => {
+                if self.target.is_some() || self.universe.is_some() {
+                    return Err("Native output addresses each controller directly; target and universe do not apply".to_string());
+                }
+            }
+            OutputProtocol::ArtNet | OutputProtocol::Sacn | OutputProtocol::Dmx => {
+                if self.transport.is_some() {
+                    return Err(format!("{} output does not use a controller transport", self.protocol.label()));
+                }
+                if self.target.as_deref().map_or(true, |t| t.trim().is_empty()) {
+                    return Err(format!("{} output needs a target", self.protocol.label()));
+                }
+                if self.protocol == OutputProtocol::Dmx && self.universe.is_some() {
+                    return Err("DMX output drives a single universe; remove the universe".to_string());
+                }
+                // sACN universes start at 1; Art-Net allows 0..=32767.
+                match (self.protocol, self.universe) {
+                    (OutputProtocol::Sacn, Some(u)) if !(1..=63_999).contains(&u) => {
+                        return Err(format!("sACN universe must be between 1 and 63999, got {}", u));
+                    }
+                    (OutputProtocol::ArtNet, Some(u)) if u > 32_767 => {
+                        return Err(format!("Art-Net universe must be at most 32767, got {}", u));
+                    }
+                    _ => {}
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Why this build cannot drive the show as configured, if it cannot.
+    pub fn support_warning(&self) -> Option<String> {
+        (!self.protocol.is_supported()).then(|| {
+            format!(
+                "This show is configured for {} output, which this build does not support; it will be driven over the native controller protocol",
+                self.protocol.label()
+            )
+        })
+    }
+}
+
+/// Validates and stores `config` in the show; None clears it.
+pub fn set(show: &mut Show, config: Option<ShowOutputConfig>) -> Result<(), String> {
+    if let Some(config) = &config {
+        config.validate()?;
+    }
+    show.output = config;
+    Ok(())
+}
+
+/// Configures the outputs for a show that was just opened. Returns a
+/// warning if its protocol is not available here.
+pub fn apply(show: &Show) -> Option<String> {
+    let config = show.output.as_ref()?;
+    if let Some(warning) = config.support_warning() {
+        log::warn!("{}", warning);
+        return Some(warning);
+    }
+    if let Err(e) = config.validate() {
+        let warning = format!("Ignoring output settings of show '{}': {}", show.name, e);
+        log::warn!("{}", warning);
+        return Some(warning);
+    }
+    if let (Some(transport), Some(ms)) = (config.transport, config.timeout_ms) {
+        // Validated above, so this cannot fail.
+        let _ = transport::set_timeout(transport, ms);
+        log::info!("Show '{}' set the {:?} timeout to {} ms", show.name, transport, ms);
+    }
+    None
+}
@@ -0,0 +1,154 @@
+use serde::Serialize;
+
+use crate::models::Show;
+
+/// What a best-effort import of a truncated show file got back.
+#[derive(Debug, Clone, Serialize)]
+pub struct ShowRecovery {
+    pub recovered_effects: usize,
+    /// Extrapolated from the show length and the last recovered cue; None
+    /// when the file gives nothing to extrapolate from.
+    pub estimated_effects: Option<usize>,
+    pub message: String,
+}
+
+impl ShowRecovery {
+    pub fn new(show: &Show) -> Self {
+        let recovered_effects = show.effects.len();
+        let estimated_effects = estimate_total(show).filter(|total| *total > recovered_effects);
+        let message = match estimated_effects {
+            Some(total) => format!("Recovered {} of ~{} cues", recovered_effects, total),
+            None => format!("Recovered {} cues; the rest of the file was lost", recovered_effects),
+        };
+        Self {
+            recovered_effects,
+            estimated_effects,
+            message,
+        }
+    }
+}
+
+// Effects are written in start order, so the fraction of the show's length
+// the recovered cues cover is roughly the fraction of cues recovered.
+fn estimate_total(show: &Show) -> Option<usize> {
+    let last = show.effects.iter().map(|e| e.start_time).fold(0.0_f64, f64::max);
+    if show.effects.is_empty() || last <= 0.0 || show.total_duration <= last {
+        return None;
+    }
+    Some((show.effects.len() as f64 * show.total_duration / last).ceil() as usize)
+}
+
+struct Frame {
+    array: bool,
+    /// Key this container has in its parent object.
+    key: Option<String>,
+    /// Key of the value being read, when this is an object.
+    pending_key: Option<String>,
+    expect_key: bool,
+}
+
+/// Cuts a truncated show file (`{"snapshot_id": .., "show": {..}}`) back to
+/// the last complete show field or effect and closes what is still open.
+/// Nothing inside an effect or other nested value is kept half-written.
+/// Returns None when no such point exists.
+pub fn repair_truncated(data: &str) -> Option<String> {
+    let bytes = data.as_bytes();
+    let mut stack: Vec<Frame> = Vec::new();
+    let mut cut: Option<(usize, String)> = None;
+    let mut i = 0;
+
+    while i < bytes.len() {
+        match bytes[i] {
+            b' ' | b'\t' | b'\n' | b'\r' | b':' => i += 1,
+            b',' => {
+                if let Some(frame) = stack.last_mut() {
+                    frame.expect_key = !frame.array;
+                }
+                i += 1;
+            }
+            b'{' | b'[' => {
+                let key = stack.last_mut().and_then(|parent| parent.pending_key.take());
+                let array = bytes[i] == b'[';
+                stack.push(Frame {
+                    array,
+                    key,
+                    pending_key: None,
+                    expect_key: !array,
+                });
+                i += 1;
+                // An effects list with nothing complete in it yet.
+                if array && is_effects(&stack) {
+                    cut = Some((i, closers(&stack)));
+                }
+            }
+            b'}' | b']' => {
+                stack.pop()?;
+                i += 1;
+                value_done(&mut stack, i, &mut cut);
+            }
+            b'"' => {
+                let start = i + 1;
+                let mut end = start;
+                while end < bytes.len() && bytes[end] != b'"' {
+                    end += if bytes[end] == b'\\' { 2 } else { 1 };
+                }
+                if end >= bytes.len() {
+                    break;
+                }
+                i = end + 1;
+                match stack.last_mut() {
+                    Some(frame) if frame.expect_key => {
+                        frame.pending_key = Some(data[start..end].to_string());
+                        frame.expect_key = false;
+                    }
+                    _ => value_done(&mut stack, i, &mut cut),
+                }
+            }
+            _ => {
+                // A number, true, false or null; one running into the end of
+                // the data may have lost digits, so it is not complete.
+                let Some(end) = bytes[i..]
+                    .iter()
+                    .position(|b| matches!(b, b',' | b'}' | b']' | b' ' | b'\t' | b'\n' | b'\r'))
+                else {
+                    break;
+                };
+                i += end;
+                value_done(&mut stack, i, &mut cut);
+            }
+        }
+    }
+
+    let (end, closing) = cut?;
+    Some(format!("{}{}", &data[..end], closing))
+}
+
+fn value_done(stack: &mut [Frame], end: usize, cut: &mut Option<(usize, String)>) {
+    if let Some(frame) = stack.last_mut() {
+        frame.pending_key = None;
+    }
+    if keeps_whole_values(stack) {
+        *cut = Some((end, closers(stack)));
+    }
+}
+
+// Values finish cleanly at the top of the snapshot, in the show itself and
+// in its effects list; anywhere deeper a cut would leave a partial value.
+fn keeps_whole_values(stack: &[Frame]) -> bool {
+    match stack.len() {
+        0 | 1 => true,
+        2 => stack[1].key.as_deref() == Some("show"),
+        _ => is_effects(stack),
+    }
+}
+
+fn is_effects(stack: &[Frame]) -> bool {
+    stack.len() == 3
+        && stack[1].key.as_deref() == Some("show")
+        && stack[2].array
+        && stack[2].key.as_deref() == Some("effects")
+}
+
+fn closers(stack: &[Frame]) -> String {
+    stack.iter().rev().map(|f| if f.array { ']' } else { '}' }).collect()
+}
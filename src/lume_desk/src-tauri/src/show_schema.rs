@@ -0,0 +1,17 @@
+use schemars::schema_for;
+
+use crate::models::{Show, SHOW_FORMAT_VERSION};
+
+/// JSON Schema for show JSON as accepted by import and `parse_show`,
+/// generated from the serde structs so it cannot drift from them. The
+/// format revision is recorded under "x-lume-format-version" and in the
+/// title, so tools can check which revision they were built against.
+pub fn export() -> Result<serde_json::Value, String> {
+    let mut root = schema_for!(Show);
+    let metadata = root.schema.metadata();
+    metadata.title = Some(format!("LUME show, format version {}", SHOW_FORMAT_VERSION));
+    root.schema
+        .extensions
+        .insert("x-lume-format-version".to_string(), SHOW_FORMAT_VERSION.into());
+    serde_json::to_value(&root).map_err(|e| format!("Failed to encode show schema: {}", e))
+}
@@ -0,0 +1,78 @@
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+use crate::models::Show;
+use crate::registry::{self, ControllerInfo};
+
+#[derive(Debug, Serialize)]
+pub struct ShowStats {
+    pub total_effects: usize,
+    /// Disabled or on a muted layer; left out of every figure below.
+    pub silent_effects: usize,
+    /// By controller address, or by the show's reference if unregistered.
+    pub effects_per_controller: BTreeMap<String, usize>,
+    pub effects_per_layer: BTreeMap<String, usize>,
+    pub unlayered_effects: usize,
+    /// Effects on firework controllers, i.e. pyro cues fired.
+    pub fire_count: usize,
+    pub peak_concurrent_effects: usize,
+    /// When the peak is first reached, in seconds.
+    pub busiest_time: Option<f64>,
+    pub total_duration: f64,
+    /// Where the last effect ends, which can lie past `total_duration`.
+    pub last_effect_end: f64,
+}
+
+/// Aggregates over the effects that play, in a single sort of their start
+/// and end points.
+pub fn compute(show: &Show, controllers: &[ControllerInfo]) -> ShowStats {
+    let known = registry::lookup_map(controllers);
+    let mut stats = ShowStats {
+        total_effects: show.effects.len(),
+        silent_effects: 0,
+        effects_per_controller: BTreeMap::new(),
+        effects_per_layer: BTreeMap::new(),
+        unlayered_effects: 0,
+        fire_count: 0,
+        peak_concurrent_effects: 0,
+        busiest_time: None,
+        total_duration: show.total_duration,
+        last_effect_end: 0.0,
+    };
+    // (time, order, delta): at one instant, effects that ended are removed
+    // before new ones start, so back-to-back cues do not overlap, while an
+    // instantaneous cue still counts alongside everything running then.
+    let mut edges: Vec<(f64, u8, i64)> = Vec::with_capacity(show.effects.len() * 2);
+    for effect in &show.effects {
+        if !show.plays(effect) {
+            stats.silent_effects += 1;
+            continue;
+        }
+        let info = known.get(effect.controller.as_str());
+        let controller = info.map_or_else(|| effect.controller.clone(), |c| c.address.clone());
+        *stats.effects_per_controller.entry(controller).or_default() += 1;
+        match &effect.layer {
+            Some(layer) => *stats.effects_per_layer.entry(layer.clone()).or_default() += 1,
+            None => stats.unlayered_effects += 1,
+        }
+        if info.is_some_and(|c| c.is_pyro()) {
+            stats.fire_count += 1;
+        }
+        let duration = effect.duration.max(0.0);
+        let end = effect.start_time + duration;
+        stats.last_effect_end = stats.last_effect_end.max(end);
+        edges.push((effect.start_time, 1, 1));
+        edges.push((end, if duration > 0.0 { 0 } else { 2 }, -1));
+    }
+    edges.sort_by(|a, b| a.0.total_cmp(&b.0).then(a.1.cmp(&b.1)));
+
+    let mut running = 0_i64;
+    for (time, _, delta) in edges {
+        running += delta;
+        if running as usize > stats.peak_concurrent_effects && delta > 0 {
+            stats.peak_concurrent_effects = running as usize;
+            stats.busiest_time = Some(time);
+        }
+    }
+    stats
+}
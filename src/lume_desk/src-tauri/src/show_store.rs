@@ -0,0 +1,607 @@
+use crate::lume_format;
+use crate::models::{Effect, Show};
+use crate::show_recovery::{self, ShowRecovery};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap};
+use std::ffi::OsString;
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufRead, BufReader, ErrorKind, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, MutexGuard};
+
+/// Once the journal holds this many records, the next delta save is
+/// compacted into a fresh full snapshot instead.
+const COMPACT_AFTER_RECORDS: usize = 500;
+
+/// Number of edits kept for undo.
+const UNDO_LIMIT: usize = 100;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SaveMode {
+    Full,
+    Delta,
+}
+
+/// A show opened from disk; `recovery` is set when only part of a
+/// truncated file could be read.
+#[derive(Debug, Serialize)]
+pub struct ImportedShow {
+    #[serde(flatten)]
+    pub show: Show,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub recovery: Option<ShowRecovery>,
+    /// Format version of an older file; the next save writes the current one.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub upgraded_from: Option<u32>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SaveReport {
+    pub path: String,
+    pub mode: SaveMode,
+    pub records_written: usize,
+    pub journal_records: usize,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+enum JournalOp {
+    Upsert { effect: Effect },
+    Remove { effect_id: String },
+}
+
+// Each journal line is "<crc32 hex> <record json>" so a torn write at the
+// tail is detected and replay stops at the last intact record.
+#[derive(Debug, Serialize, Deserialize)]
+struct JournalRecord {
+    snapshot_id: String,
+    seq: u64,
+    op: JournalOp,
+}
+
+#[derive(Debug, Clone)]
+struct EffectChange {
+    id: String,
+    before: Option<Effect>,
+    after: Option<Effect>,
+}
+
+// One undoable edit. Only the effects that changed are kept; the show
+// header (everything but effects) is stored only when it changed too.
+#[derive(Debug, Clone)]
+struct EditEntry {
+    label: String,
+    changes: Vec<EffectChange>,
+    header: Option<(Show, Show)>,
+}
+
+#[derive(Debug, Default)]
+struct ShowDocument {
+    show: Option<Show>,
+    path: Option<PathBuf>,
+    snapshot_id: Option<String>,
+    next_seq: u64,
+    journal_records: usize,
+    dirty: BTreeSet<String>,
+    needs_full_save: bool,
+    undo: Vec<EditEntry>,
+    redo: Vec<EditEntry>,
+}
+
+impl ShowDocument {
+    fn apply(&mut self, entry: &EditEntry, forward: bool) -> Result<(), String> {
+        let show = self.show.as_mut().ok_or_else(|| "No show loaded".to_string())?;
+        for change in &entry.changes {
+            let target = if forward { &change.after } else { &change.before };
+            match target {
+                Some(effect) => show.upsert_effect(effect.clone()),
+                None => {
+                    show.remove_effect(&change.id);
+                }
+            }
+            self.dirty.insert(change.id.clone());
+        }
+        if let Some((before, after)) = &entry.header {
+            let effects = std::mem::take(&mut show.effects);
+            let last_position = show.last_position;
+            *show = if forward { after.clone() } else { before.clone() };
+            show.effects = effects;
+            // Where playback stopped is not part of the edit history.
+            show.last_position = last_position;
+            self.needs_full_save = true;
+        }
+        Ok(())
+    }
+}
+
+fn diff_effects(before: &[Effect], after: &[Effect]) -> Vec<EffectChange> {
+    let mut previous: HashMap<&str, &Effect> = before.iter().map(|e| (e.id.as_str(), e)).collect();
+    let mut changes = Vec::new();
+    for effect in after {
+        let old = previous.remove(effect.id.as_str());
+        if old != Some(effect) {
+            changes.push(EffectChange {
+                id: effect.id.clone(),
+                before: old.cloned(),
+                after: Some(effect.clone()),
+            });
+        }
+    }
+    for (id, effect) in previous {
+        changes.push(EffectChange {
+            id: id.to_string(),
+            before: Some(effect.clone()),
+            after: None,
+        });
+    }
+    changes
+}
+
+fn without_effects(show: &mut Show) -> Show {
+    let effects = std::mem::take(&mut show.effects);
+    let header = show.clone();
+    show.effects = effects;
+    header
+}
+
+/// The show currently loaded in the backend, with change tracking for delta saves.
+#[derive(Debug, Default)]
+pub struct ShowStore {
+    doc: Mutex<ShowDocument>,
+}
+
+impl ShowStore {
+    fn lock(&self) -> Result<MutexGuard<'_, ShowDocument>, String> {
+        self.doc.lock().map_err(|_| "Show store is unavailable".to_string())
+    }
+
+    /// Replaces the loaded show. The next save is always a full snapshot.
+    pub fn load(&self, show: Show) -> Result<(), String> {
+        let mut doc = self.lock()?;
+        *doc = ShowDocument {
+            show: Some(show),
+            needs_full_save: true,
+            ..ShowDocument::default()
+        };
+        Ok(())
+    }
+
+    pub fn current(&self) -> Result<Option<Show>, String> {
+        Ok(self.lock()?.show.clone())
+    }
+
+    /// Runs `edit` against a copy of the show and commits it as one undoable
+    /// step. The show is left untouched if `edit` fails.
+    pub fn edit<R>(&self, label: &str, edit: impl FnOnce(&mut Show) -> Result<R, String>) -> Result<R, String> {
+        let mut doc = self.lock()?;
+        let show = doc.show.as_mut().ok_or_else(|| "No show loaded".to_string())?;
+        let mut draft = show.clone();
+        let result = edit(&mut draft)?;
+
+        let changes = diff_effects(&show.effects, &draft.effects);
+        let header = Some((without_effects(show), without_effects(&mut draft))).filter(|(a, b)| a != b);
+        if changes.is_empty() && header.is_none() {
+            return Ok(result);
+        }
+
+        let entry = EditEntry {
+            label: label.to_string(),
+            changes,
+            header,
+        };
+        doc.apply(&entry, true)?;
+        doc.undo.push(entry);
+        if doc.undo.len() > UNDO_LIMIT {
+            doc.undo.remove(0);
+        }
+        doc.redo.clear();
+        Ok(result)
+    }
+
+    /// Reverts the most recent edit and returns its label.
+    pub fn undo(&self) -> Result<Option<String>, String> {
+        let mut doc = self.lock()?;
+        let Some(entry) = doc.undo.pop() else {
+            return Ok(None);
+        };
+        doc.apply(&entry, false)?;
+        let label = entry.label.clone();
+        doc.redo.push(entry);
+        Ok(Some(label))
+    }
+
+    /// Re-applies the most recently undone edit and returns its label.
+    pub fn redo(&self) -> Result<Option<String>, String> {
+        let mut doc = self.lock()?;
+        let Some(entry) = doc.redo.pop() else {
+            return Ok(None);
+        };
+        doc.apply(&entry, true)?;
+        let label = entry.label.clone();
+        doc.undo.push(entry);
+        Ok(Some(label))
+    }
+
+    pub fn upsert_effect(&self, effect: Effect) -> Result<(), String> {
+        self.edit("Edit effect", |show| {
+            show.upsert_effect(effect);
+            Ok(())
+        })
+    }
+
+    pub fn remove_effect(&self, effect_id: &str) -> Result<bool, String> {
+        self.edit("Remove effect", |show| Ok(show.remove_effect(effect_id)))
+    }
+
+    /// Writes a full snapshot and discards the journal.
+    pub fn save_full(&self, path: Option<PathBuf>) -> Result<SaveReport, String> {
+        let mut doc = self.lock()?;
+        if let Some(path) = path {
+            doc.path = Some(path);
+        }
+        write_full(&mut doc)
+    }
+
+    /// Appends the effects changed since the last save to the journal,
+    /// falling back to a full snapshot when one is required or due.
+    pub fn save_delta(&self) -> Result<SaveReport, String> {
+        let mut doc = self.lock()?;
+        let path = doc
+            .path
+            .clone()
+            .ok_or_else(|| "Show has not been saved yet; use save_show with a path first".to_string())?;
+        let snapshot_id = match doc.snapshot_id.clone() {
+            Some(id) if !doc.needs_full_save => id,
+            _ => return write_full(&mut doc),
+        };
+        if doc.journal_records + doc.dirty.len() > COMPACT_AFTER_RECORDS {
+            log::info!("Compacting show journal ({} records)", doc.journal_records);
+            return write_full(&mut doc);
+        }
+
+        let show = doc.show.as_ref().ok_or_else(|| "No show loaded".to_string())?;
+        let mut lines = String::new();
+        let mut seq = doc.next_seq;
+        for id in &doc.dirty {
+            let op = match show.effect(id) {
+                Some(effect) => JournalOp::Upsert { effect: effect.clone() },
+                None => JournalOp::Remove { effect_id: id.clone() },
+            };
+            let record = JournalRecord {
+                snapshot_id: snapshot_id.clone(),
+                seq,
+                op,
+            };
+            let json = serde_json::to_string(&record).map_err(|e| e.to_string())?;
+            lines.push_str(&format!("{:08x} {}\n", crc32fast::hash(json.as_bytes()), json));
+            seq += 1;
+        }
+
+        let written = doc.dirty.len();
+        if written > 0 {
+            let mut journal = OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(journal_path(&path))
+                .map_err(|e| format!("Failed to open show journal: {}", e))?;
+            journal
+                .write_all(lines.as_bytes())
+                .and_then(|_| journal.sync_data())
+                .map_err(|e| format!("Failed to write show journal: {}", e))?;
+        }
+
+        doc.next_seq = seq;
+        doc.journal_records += written;
+        doc.dirty.clear();
+        log::info!("Delta save wrote {} journal records", written);
+
+        Ok(SaveReport {
+            path: path.display().to_string(),
+            mode: SaveMode::Delta,
+            records_written: written,
+            journal_records: doc.journal_records,
+        })
+    }
+
+    /// Notes where playback of `show_id` stopped, if it is the loaded show.
+    /// A show stopped at its start or end has nothing to resume and the
+    /// position is cleared. Not an undoable edit. If the show has no unsaved
+    /// edits it is saved straight away so the position survives closing the
+    /// app; otherwise it goes out with the next save.
+    pub fn remember_position(&self, show_id: &str, time: f64) -> Result<Option<f64>, String> {
+        let mut doc = self.lock()?;
+        let clean = doc.dirty.is_empty() && !doc.needs_full_save && doc.path.is_some();
+        let Some(show) = doc.show.as_mut().filter(|s| s.id == show_id) else {
+            return Ok(None);
+        };
+        let end = if show.total_duration > 0.0 {
+            show.total_duration
+        } else {
+            show.effects.iter().map(|e| e.start_time + e.duration.max(0.0)).fold(0.0, f64::max)
+        };
+        let position = Some(time).filter(|&t| t > 0.0 && t < end);
+        if show.last_position == position {
+            return Ok(position);
+        }
+        show.last_position = position;
+        doc.needs_full_save = true;
+        if clean {
+            write_full(&mut doc)?;
+        }
+        match position {
+            Some(time) => log::info!("Remembered playback position {:.2}s", time),
+            None => log::info!("Cleared remembered playback position"),
+        }
+        Ok(position)
+    }
+
+    /// Loads a snapshot from disk and replays its journal on top of it.
+    pub fn import(&self, path: PathBuf) -> Result<Show, String> {
+        Ok(self.import_with(path, false)?.show)
+    }
+
+    /// Like `import`, but with `recover` a truncated snapshot is read up to
+    /// its last complete effect instead of failing.
+    pub fn import_with(&self, path: PathBuf, recover: bool) -> Result<ImportedShow, String> {
+        let loaded = read_file(&path, recover)?;
+        if let Some(recovery) = &loaded.recovery {
+            log::warn!("Show file {} was truncated: {}", path.display(), recovery.message);
+        }
+        if let Some(version) = loaded.upgraded_from {
+            log::info!("Show file {} is format version {}; upgrading on next save", path.display(), version);
+        }
+        log::info!(
+            "Imported show '{}' from {} ({} journal records replayed)",
+            loaded.show.name,
+            path.display(),
+            loaded.replayed
+        );
+
+        let mut doc = self.lock()?;
+        *doc = ShowDocument {
+            show: Some(loaded.show.clone()),
+            path: Some(path),
+            snapshot_id: Some(loaded.snapshot_id),
+            next_seq: loaded.next_seq,
+            journal_records: loaded.replayed,
+            // Appending after a damaged tail would hide the new records on
+            // the next import, so start a fresh snapshot instead.
+            // Older formats are rewritten whole in the current one.
+            needs_full_save: !loaded.intact || loaded.upgraded_from.is_some(),
+            ..ShowDocument::default()
+        };
+        Ok(ImportedShow {
+            show: loaded.show,
+            recovery: loaded.recovery,
+            upgraded_from: loaded.upgraded_from,
+        })
+    }
+}
+
+struct LoadedFile {
+    show: Show,
+    snapshot_id: String,
+    next_seq: u64,
+    replayed: usize,
+    intact: bool,
+    recovery: Option<ShowRecovery>,
+    upgraded_from: Option<u32>,
+}
+
+fn read_file(path: &Path, recover: bool) -> Result<LoadedFile, String> {
+    let data = fs::read(path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    let decoded = match serde_json::from_slice::<serde_json::Value>(&data) {
+        Ok(value) => lume_format::decode(value, true).map(|file| (file, None)),
+        Err(e) if recover && e.is_eof() => {
+            let value = recover_snapshot(&data)
+                .ok_or_else(|| format!("Show file {} is truncated and could not be recovered: {}", path.display(), e))?;
+            lume_format::decode(value, false).map(|file| {
+                let recovery = ShowRecovery::new(&file.show);
+                (file, Some(recovery))
+            })
+        }
+        Err(e) => Err(e.to_string()),
+    };
+    let (snapshot, recovery) = decoded.map_err(|e| format!("Failed to parse show file {}: {}", path.display(), e))?;
+
+    let mut show = snapshot.show;
+    let (records, journal_intact) = read_journal(&journal_path(path), &snapshot.snapshot_id)?;
+    let replayed = records.len();
+    let next_seq = records.last().map(|r| r.seq + 1).unwrap_or(0);
+    for record in records {
+        match record.op {
+            JournalOp::Upsert { effect } => show.upsert_effect(effect),
+            JournalOp::Remove { effect_id } => {
+                show.remove_effect(&effect_id);
+            }
+        }
+    }
+    Ok(LoadedFile {
+        show,
+        snapshot_id: snapshot.snapshot_id,
+        next_seq,
+        replayed,
+        intact: journal_intact && recovery.is_none(),
+        recovery,
+        upgraded_from: snapshot.upgraded_from,
+    })
+}
+
+fn recover_snapshot(data: &[u8]) -> Option<serde_json::Value> {
+    // The cut may have split a multi-byte character, which is dropped with the rest.
+    let text = String::from_utf8_lossy(data);
+    let repaired = show_recovery::repair_truncated(&text)?;
+    serde_json::from_str(&repaired).ok()
+}
+
+/// Reads a show file (snapshot plus journal) without loading it into the store.
+pub fn read_show_file(path: &Path) -> Result<Show, String> {
+    Ok(read_file(path, false)?.show)
+}
+
+fn write_full(doc: &mut ShowDocument) -> Result<SaveReport, String> {
+    let path = doc.path.clone().ok_or_else(|| "No save path specified".to_string())?;
+    let show = doc.show.clone().ok_or_else(|| "No show loaded".to_string())?;
+    // The journal next to the file only applies to the snapshot whose id it
+    // carries, so a stale journal is never replayed on a newer file.
+    let snapshot_id = uuid::Uuid::new_v4().to_string();
+    let data = lume_format::encode(&show, &snapshot_id)?;
+    write_atomic(&path, &data).map_err(|e| format!("Failed to save show to {}: {}", path.display(), e))?;
+
+    match fs::remove_file(journal_path(&path)) {
+        Ok(()) => {}
+        Err(e) if e.kind() == ErrorKind::NotFound => {}
+        Err(e) => log::warn!("Failed to remove stale show journal: {}", e),
+    }
+
+    doc.snapshot_id = Some(snapshot_id);
+    doc.next_seq = 0;
+    doc.journal_records = 0;
+    doc.dirty.clear();
+    doc.needs_full_save = false;
+    log::info!("Saved full show snapshot to {}", path.display());
+
+    Ok(SaveReport {
+        path: path.display().to_string(),
+        mode: SaveMode::Full,
+        records_written: show.effects.len(),
+        journal_records: 0,
+    })
+}
+
+/// Encodes a show as a standalone native file with an empty journal.
+pub fn encode_show_file(show: &Show) -> Result<Vec<u8>, String> {
+    lume_format::encode(show, &uuid::Uuid::new_v4().to_string())
+}
+
+/// Write-then-rename so a crash mid-write leaves the previous file intact.
+pub fn write_atomic(path: &Path, data: &[u8]) -> std::io::Result<()> {
+    let tmp = sibling_path(path, ".tmp");
+    let result = File::create(&tmp)
+        .and_then(|mut file| file.write_all(data).and_then(|_| file.sync_all()))
+        .and_then(|_| fs::rename(&tmp, path));
+    if result.is_err() {
+        let _ = fs::remove_file(&tmp);
+    }
+    result
+}
+
+/// Returns the intact records for `snapshot_id` and whether the whole file was intact.
+fn read_journal(path: &Path, snapshot_id: &str) -> Result<(Vec<JournalRecord>, bool), String> {
+    let file = match File::open(path) {
+        Ok(file) => file,
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok((Vec::new(), true)),
+        Err(e) => return Err(format!("Failed to open show journal: {}", e)),
+    };
+
+    let mut records: Vec<JournalRecord> = Vec::new();
+    for (index, line) in BufReader::new(file).lines().enumerate() {
+        let line = match line {
+            Ok(line) => line,
+            Err(e) => {
+                log::warn!("Show journal unreadable at line {}: {}", index + 1, e);
+                return Ok((records, false));
+            }
+        };
+        let record = line
+            .split_once(' ')
+            .filter(|(crc, json)| u32::from_str_radix(crc, 16).ok() == Some(crc32fast::hash(json.as_bytes())))
+            .and_then(|(_, json)| serde_json::from_str::<JournalRecord>(json).ok());
+        let expected_seq = records.last().map(|r| r.seq + 1).unwrap_or(0);
+        match record {
+            Some(record) if record.snapshot_id == snapshot_id && record.seq == expected_seq => records.push(record),
+            Some(record) if record.snapshot_id != snapshot_id => {
+                log::warn!("Ignoring show journal written for a different snapshot");
+                return Ok((Vec::new(), false));
+            }
+            _ => {
+                log::warn!("Show journal damaged at line {}; replay stopped", index + 1);
+                return Ok((records, false));
+            }
+        }
+    }
+    Ok((records, true))
+}
+
+pub fn journal_path(path: &Path) -> PathBuf {
+    sibling_path(path, ".journal")
+}
+
+fn sibling_path(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = OsString::from(path.as_os_str());
+    name.push(suffix);
+    PathBuf::from(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn effect(id: &str, start_time: f64) -> Effect {
+        serde_json::from_value(json!({ "id": id, "start_time": start_time, "duration": 1.0, "controller": "c1", "channel": 1 }))
+            .unwrap()
+    }
+
+    fn saved_store() -> (ShowStore, PathBuf) {
+        let path = std::env::temp_dir().join(format!("lume-show-{}.lume", uuid::Uuid::new_v4()));
+        let show = serde_json::from_value(json!({ "id": "s1", "name": "Test", "total_duration": 10.0, "effects": [] })).unwrap();
+        let store = ShowStore::default();
+        store.load(show).unwrap();
+        store.save_full(Some(path.clone())).unwrap();
+        (store, path)
+    }
+
+    fn remove(path: &Path) {
+        let _ = fs::remove_file(path);
+        let _ = fs::remove_file(journal_path(path));
+    }
+
+    #[test]
+    fn delta_saves_are_replayed_on_import() {
+        let (store, path) = saved_store();
+        store.upsert_effect(effect("e1", 1.0)).unwrap();
+        store.upsert_effect(effect("e2", 2.0)).unwrap();
+        let report = store.save_delta().unwrap();
+        assert_eq!((report.mode, report.records_written), (SaveMode::Delta, 2));
+        store.remove_effect("e1").unwrap();
+        store.save_delta().unwrap();
+
+        let imported = ShowStore::default();
+        let show = imported.import(path.clone()).unwrap();
+        let ids: Vec<&str> = show.effects.iter().map(|e| e.id.as_str()).collect();
+        assert_eq!(ids, ["e2"]);
+        // An intact journal is appended to, not rewritten.
+        imported.upsert_effect(effect("e3", 3.0)).unwrap();
+        assert_eq!(imported.save_delta().unwrap().journal_records, 4);
+        remove(&path);
+    }
+
+    #[test]
+    fn torn_journal_tail_is_dropped_and_forces_a_full_save() {
+        let (store, path) = saved_store();
+        store.upsert_effect(effect("e1", 1.0)).unwrap();
+        store.save_delta().unwrap();
+        let mut journal = OpenOptions::new().append(true).open(journal_path(&path)).unwrap();
+        journal.write_all(b"0badc0de {\"snapshot_id\":").unwrap();
+
+        let imported = ShowStore::default();
+        let show = imported.import(path.clone()).unwrap();
+        assert_eq!(show.effects.len(), 1);
+        assert_eq!(imported.save_delta().unwrap().mode, SaveMode::Full);
+        assert!(!journal_path(&path).exists());
+        remove(&path);
+    }
+
+    #[test]
+    fn full_save_discards_the_old_journal() {
+        let (store, path) = saved_store();
+        store.upsert_effect(effect("e1", 1.0)).unwrap();
+        store.save_delta().unwrap();
+        store.save_full(None).unwrap();
+        assert!(!journal_path(&path).exists());
+        assert_eq!(read_show_file(&path).unwrap().effects.len(), 1);
+        remove(&path);
+    }
+}
@@ -0,0 +1,58 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use tauri::{AppHandle, Manager, Window, WindowEvent};
+
+use crate::events::{self, CloseBlocked};
+use crate::safety::{ArmState, Safety};
+use crate::show_engine::ShowEngine;
+use crate::show_store::ShowStore;
+
+/// The main window; closing it ends the app.
+pub const MAIN_LABEL: &str = "main";
+
+/// Set once the operator has confirmed closing while live.
+#[derive(Debug, Default)]
+pub struct CloseGuard {
+    confirmed: AtomicBool,
+}
+
+// Errors count as live so a poisoned lock never lets the window close unasked.
+fn live_state(app: &AppHandle) -> CloseBlocked {
+    let armed = app.state::<Safety>().state() != Ok(ArmState::Disarmed);
+    let show_running = app.state::<ShowEngine>().status().map_or(true, |s| s.is_running);
+    CloseBlocked { armed, show_running }
+}
+
+/// Holds the main window open while the system is armed or a show is running,
+/// and asks the UI to confirm instead.
+pub fn on_window_event(window: &Window, event: &WindowEvent) {
+    let WindowEvent::CloseRequested { api, .. } = event else {
+        return;
+    };
+    if window.label() != MAIN_LABEL {
+        return;
+    }
+    let app = window.app_handle();
+    if app.state::<CloseGuard>().confirmed.load(Ordering::SeqCst) {
+        return;
+    }
+    let state = live_state(app);
+    if state.armed || state.show_running {
+        log::warn!("Close refused: armed={} show_running={}", state.armed, state.show_running);
+        api.prevent_close();
+        events::emit(app, events::CLOSE_BLOCKED, state);
+    }
+}
+
+/// Operator confirmed closing while live: stops the show, disarms and exits.
+pub async fn confirm_close(app: &AppHandle) -> Result<(), String> {
+    if let Some((show_id, time)) = app.state::<ShowEngine>().stop(app).await? {
+        if let Err(e) = app.state::<ShowStore>().remember_position(&show_id, time) {
+            log::warn!("Failed to remember playback position: {}", e);
+        }
+    }
+    app.state::<Safety>().disarm()?;
+    app.state::<CloseGuard>().confirmed.store(true, Ordering::SeqCst);
+    log::info!("Close confirmed by operator");
+    app.exit(0);
+    Ok(())
+}
@@ -0,0 +1,119 @@
+use crate::models::Show;
+use crate::show_store;
+use base64::Engine;
+use std::collections::{BTreeMap, HashMap};
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::SystemTime;
+
+const MAX_DIMENSION: u32 = 1024;
+
+type CacheKey = (PathBuf, u32, u32);
+
+/// Rendered thumbnails keyed by file and size, invalidated by file mtime.
+#[derive(Debug, Default)]
+pub struct ThumbnailCache {
+    entries: Mutex<HashMap<CacheKey, (SystemTime, String)>>,
+}
+
+impl ThumbnailCache {
+    /// Returns a PNG data URL for the show at `path`, rendering it on a
+    /// blocking thread unless a thumbnail for the current mtime is cached.
+    pub async fn get(&self, path: PathBuf, width: u32, height: u32) -> Result<String, String> {
+        if width == 0 || height == 0 || width > MAX_DIMENSION || height > MAX_DIMENSION {
+            return Err(format!("Thumbnail size must be between 1 and {} pixels", MAX_DIMENSION));
+        }
+        let modified = std::fs::metadata(&path)
+            .and_then(|m| m.modified())
+            .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+        let key = (path.clone(), width, height);
+        if let Some((mtime, url)) = self.entries.lock().ok().and_then(|e| e.get(&key).cloned()) {
+            if mtime == modified {
+                return Ok(url);
+            }
+        }
+
+        let url = tauri::async_runtime::spawn_blocking(move || {
+            let show = show_store::read_show_file(&path)?;
+            render(&show, width, height)
+        })
+        .await
+        .map_err(|e| format!("Thumbnail rendering failed: {}", e))??;
+
+        if let Ok(mut entries) = self.entries.lock() {
+            entries.insert(key, (modified, url.clone()));
+        }
+        Ok(url)
+    }
+}
+
+/// Renders effect density as a heat map: one horizontal band per
+/// controller channel, time running left to right.
+pub fn render(show: &Show, width: u32, height: u32) -> Result<String, String> {
+    let (w, h) = (width as usize, height as usize);
+    let end = show
+        .effects
+        .iter()
+        .map(|e| e.start_time + e.duration.max(0.0))
+        .fold(show.total_duration, f64::max);
+
+    let mut rows: BTreeMap<(&str, u32), usize> = BTreeMap::new();
+    for effect in &show.effects {
+        rows.insert((effect.controller.as_str(), effect.channel), 0);
+    }
+    let row_count = rows.len().max(1);
+    for (index, row) in rows.values_mut().enumerate() {
+        *row = index;
+    }
+
+    let mut density = vec![0.0_f32; w * h];
+    if end > 0.0 {
+        for effect in &show.effects {
+            let row = rows[&(effect.controller.as_str(), effect.channel)];
+            let y0 = row * h / row_count;
+            let y1 = ((row + 1) * h / row_count).max(y0 + 1).min(h);
+            let x0 = ((effect.start_time.max(0.0) / end) * w as f64) as usize;
+            // Every effect covers at least one column so instant cues still show.
+            let x1 = (((effect.start_time + effect.duration.max(0.0)) / end * w as f64).ceil() as usize)
+                .max(x0 + 1)
+                .min(w);
+            for y in y0..y1 {
+                for x in x0.min(w - 1)..x1 {
+                    density[y * w + x] += 1.0;
+                }
+            }
+        }
+    }
+
+    let peak = density.iter().copied().fold(0.0_f32, f32::max).max(1.0);
+    let mut pixels = Vec::with_capacity(w * h * 3);
+    for value in density {
+        pixels.extend_from_slice(&heat_color(value / peak));
+    }
+
+    let mut png_data = Vec::new();
+    {
+        let mut encoder = png::Encoder::new(&mut png_data, width, height);
+        encoder.set_color(png::ColorType::Rgb);
+        encoder.set_depth(png::BitDepth::Eight);
+        let mut writer = encoder.write_header().map_err(|e| e.to_string())?;
+        writer.write_image_data(&pixels).map_err(|e| e.to_string())?;
+    }
+    Ok(format!(
+        "data:image/png;base64,{}",
+        base64::engine::general_purpose::STANDARD.encode(png_data)
+    ))
+}
+
+// Dark background through orange to pale yellow.
+fn heat_color(t: f32) -> [u8; 3] {
+    if t <= 0.0 {
+        return [17, 24, 39];
+    }
+    let t = t.clamp(0.0, 1.0);
+    [
+        (120.0 + 135.0 * t) as u8,
+        (40.0 + 200.0 * t * t) as u8,
+        (20.0 + 140.0 * t * t * t) as u8,
+    ]
+}
@@ -0,0 +1,86 @@
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicU32, AtomicU8, Ordering};
+
+/// Frame rates accepted for timecode display (non-drop-frame).
+pub const SUPPORTED_FPS: [u32; 5] = [24, 25, 30, 50, 60];
+
+pub const DEFAULT_FPS: u32 = 30;
+
+// Absorbs float error so 0.04s at 25 fps is frame 1, not frame 0.
+const FRAME_EPSILON: f64 = 1e-6;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TimeFormat {
+    /// "272.50"
+    #[default]
+    Seconds,
+    /// "4:32"
+    MinSec,
+    /// SMPTE "00:04:32:12"
+    Timecode,
+}
+
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct TimeDisplay {
+    pub format: TimeFormat,
+    pub fps: u32,
+}
+
+static FORMAT: AtomicU8 = AtomicU8::new(TimeFormat::Seconds as u8);
+static FPS: AtomicU32 = AtomicU32::new(DEFAULT_FPS);
+
+pub fn current() -> TimeDisplay {
+    let format = match FORMAT.load(Ordering::Relaxed) {
+        1 => TimeFormat::MinSec,
+        2 => TimeFormat::Timecode,
+        _ => TimeFormat::Seconds,
+    };
+    TimeDisplay {
+        format,
+        fps: FPS.load(Ordering::Relaxed),
+    }
+}
+
+/// Sets how show times are formatted for display. `fps` only matters for
+/// timecode and keeps its current value when None.
+pub fn set(format: TimeFormat, fps: Option<u32>) -> Result<TimeDisplay, String> {
+    if let Some(fps) = fps {
+        if !SUPPORTED_FPS.contains(&fps) {
+            return Err(format!("Timecode frame rate must be one of {:?}, got {}", SUPPORTED_FPS, fps));
+        }
+        FPS.store(fps, Ordering::Relaxed);
+    }
+    FORMAT.store(format as u8, Ordering::Relaxed);
+    let display = current();
+    log::info!("Displaying show time as {:?} at {} fps", display.format, display.fps);
+    Ok(display)
+}
+
+/// Formats `seconds` with the current display setting.
+pub fn format_current(seconds: f64) -> String {
+    let display = current();
+    format(seconds, display.format, display.fps)
+}
+
+/// Formats a show time. Like a clock, minutes and timecode show the second
+/// or frame that has started, never one that is still to come, so the last
+/// frame of a second is `fps - 1` and never rolls over to `fps`.
+pub fn format(seconds: f64, format: TimeFormat, fps: u32) -> String {
+    let seconds = if seconds.is_finite() { seconds.max(0.0) } else { 0.0 };
+    match format {
+        TimeFormat::Seconds => format!("{:.2}", seconds),
+        TimeFormat::MinSec => {
+            let whole = (seconds + FRAME_EPSILON).floor() as u64;
+            format!("{}:{:02}", whole / 60, whole % 60)
+        }
+        TimeFormat::Timecode => {
+            let fps = u64::from(fps.max(1));
+            // Count whole frames first and split that, so rounding can never
+            // produce a frame number equal to the frame rate.
+            let frames = (seconds * fps as f64 + FRAME_EPSILON).floor() as u64;
+            let (secs, frame) = (frames / fps, frames % fps);
+            format!("{:02}:{:02}:{:02}:{:02}", secs / 3600, secs / 60 % 60, secs % 60, frame)
+        }
+    }
+}
@@ -0,0 +1,787 @@
+use midir::{Ignore, MidiInput, MidiInputConnection};
+use rodio::cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
+use rodio::cpal::{self, FromSample, Sample, SizedSample};
+use serde::{Deserialize, Serialize};
+use std::sync::{mpsc, Arc, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Manager};
+use tokio::sync::mpsc::{unbounded_channel, UnboundedReceiver, UnboundedSender};
+
+use crate::events;
+use crate::show_engine::ShowEngine;
+use crate::trigger;
+
+/// Name LUME's MIDI inputs go by on the system's MIDI service.
+const MIDI_CLIENT: &str = "LUME";
+
+/// No timecode for this long means the master stopped or the cable is out;
+/// the show freewheels on its own clock meanwhile.
+const DROPOUT: Duration = Duration::from_millis(150);
+
+/// How long the show freewheels before it is paused to wait for timecode.
+const FREEWHEEL: Duration = Duration::from_secs(2);
+
+/// Timecode this far from the show clock is a jump, followed by seeking
+/// rather than by slewing the clock.
+const RELOCK_JUMP_SECS: f64 = 0.5;
+
+/// How often dropouts are checked for.
+const WATCH_POLL: Duration = Duration::from_millis(40);
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SyncMode {
+    /// The engine runs on its own clock.
+    Internal,
+    /// MIDI timecode from a MIDI input.
+    Mtc,
+    /// Linear timecode from an audio input.
+    Ltc,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum FrameRate {
+    #[serde(rename = "24")]
+    Fps24,
+    #[serde(rename = "25")]
+    Fps25,
+    #[serde(rename = "29.97df")]
+    Fps2997Drop,
+    #[serde(rename = "30")]
+    Fps30,
+}
+
+impl FrameRate {
+    fn frames_per_second(self) -> f64 {
+        match self {
+            FrameRate::Fps24 => 24.0,
+            FrameRate::Fps25 => 25.0,
+            FrameRate::Fps2997Drop => 30_000.0 / 1001.0,
+            FrameRate::Fps30 => 30.0,
+        }
+    }
+
+    // The rate code MTC carries in its hours byte.
+    fn from_mtc(code: u8) -> Self {
+        match code & 0x03 {
+            0 => FrameRate::Fps24,
+            1 => FrameRate::Fps25,
+            2 => FrameRate::Fps2997Drop,
+            _ => FrameRate::Fps30,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Timecode {
+    pub hours: u8,
+    pub minutes: u8,
+    pub seconds: u8,
+    pub frames: u8,
+    pub rate: FrameRate,
+}
+
+impl Timecode {
+    /// Real time since 00:00:00:00. Drop-frame timecode skips frame numbers
+    /// 0 and 1 every minute except each tenth, so it is counted in frames.
+    pub fn to_seconds(self) -> f64 {
+        let (h, m, s, f) = (self.hours as f64, self.minutes as f64, self.seconds as f64, self.frames as f64);
+        match self.rate {
+            FrameRate::Fps2997Drop => {
+                let minutes = 60.0 * h + m;
+                let dropped = 2.0 * (minutes - (minutes / 10.0).floor());
+                ((3600.0 * h + 60.0 * m + s) * 30.0 + f - dropped) / self.rate.frames_per_second()
+            }
+            rate => 3600.0 * h + 60.0 * m + s + f / rate.frames_per_second(),
+        }
+    }
+
+    pub fn label(&self) -> String {
+        let separator = if self.rate == FrameRate::Fps2997Drop { ';' } else { ':' };
+        format!(
+            "{:02}:{:02}:{:02}{}{:02}",
+            self.hours, self.minutes, self.seconds, separator, self.frames
+        )
+    }
+}
+
+/// Decodes MIDI timecode from a raw MIDI byte stream: quarter-frame
+/// messages while the master runs, full-frame SysEx when it locates.
+#[derive(Default)]
+pub struct MtcParser {
+    pieces: [u8; 8],
+    // One bit per quarter-frame piece received since the last full time.
+    seen: u8,
+    awaiting_piece: bool,
+    sysex: Option<Vec<u8>>,
+}
+
+impl MtcParser {
+    /// The timecode a byte completes, and whether the master is running.
+    pub fn push(&mut self, byte: u8) -> Option<(Timecode, bool)> {
+        // Real-time bytes may arrive anywhere, even inside SysEx.
+        if byte >= 0xf8 {
+            return None;
+        }
+        if let Some(sysex) = &mut self.sysex {
+            if byte == 0xf7 {
+                let message = self.sysex.take()?;
+                return match message.as_slice() {
+                    [0x7f, _, 0x01, 0x01, hh, mm, ss, ff] => Some((
+                        Timecode {
+                            hours: hh & 0x1f,
+                            minutes: *mm,
+                            seconds: *ss,
+                            frames: *ff,
+                            rate: FrameRate::from_mtc(hh >> 5),
+                        },
+                        false,
+                    )),
+                    _ => None,
+                };
+            }
+            if byte & 0x80 == 0 && sysex.len() < 16 {
+                sysex.push(byte);
+                return None;
+            }
+            self.sysex = None;
+        }
+        match byte {
+            0xf0 => {
+                self.sysex = Some(Vec::new());
+                None
+            }
+            0xf1 => {
+                self.awaiting_piece = true;
+                None
+            }
+            _ if byte & 0x80 != 0 => {
+                self.awaiting_piece = false;
+                None
+            }
+            _ if self.awaiting_piece => {
+                self.awaiting_piece = false;
+                let piece = (byte >> 4) as usize & 0x07;
+                self.pieces[piece] = byte & 0x0f;
+                self.seen |= 1 << piece;
+                if piece != 7 || self.seen != 0xff {
+                    return None;
+                }
+                self.seen = 0;
+                let p = self.pieces;
+                Some((
+                    Timecode {
+                        frames: p[0] | (p[1] & 0x01) << 4,
+                        seconds: p[2] | (p[3] & 0x03) << 4,
+                        minutes: p[4] | (p[5] & 0x03) << 4,
+                        hours: p[6] | (p[7] & 0x01) << 4,
+                        rate: FrameRate::from_mtc(p[7] >> 1),
+                    },
+                    true,
+                ))
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Bits 64-79 of an LTC frame, oldest bit lowest.
+const LTC_SYNC_WORD: u16 = 0xbffc;
+
+/// Decodes linear timecode from mono audio samples. LTC is biphase mark
+/// coded: every bit starts with a transition and a 1 has another halfway.
+pub struct LtcDecoder {
+    sample_rate: f64,
+    high: bool,
+    since_edge: u32,
+    // Estimated samples per bit; adapts to the frame rate.
+    bit_period: f64,
+    half_bit: bool,
+    // The last 80 bits, oldest lowest.
+    bits: u128,
+}
+
+impl LtcDecoder {
+    /// Levels within this of zero do not count as a transition.
+    const HYSTERESIS: i16 = 1000;
+
+    pub fn new(sample_rate: u32) -> Self {
+        Self {
+            sample_rate: sample_rate as f64,
+            high: false,
+            since_edge: 0,
+            bit_period: sample_rate as f64 / (25.0 * 80.0),
+            half_bit: false,
+            bits: 0,
+        }
+    }
+
+    fn push_bit(&mut self, bit: bool) -> Option<Timecode> {
+        self.bits = (self.bits >> 1) | (u128::from(bit) << 79);
+        if (self.bits >> 64) as u16 != LTC_SYNC_WORD {
+            return None;
+        }
+        let field = |start: u32, len: u32| ((self.bits >> start) & ((1 << len) - 1)) as u8;
+        let drop_frame = field(10, 1) == 1;
+        let fps = self.sample_rate / (self.bit_period * 80.0);
+        let rate = if drop_frame {
+            FrameRate::Fps2997Drop
+        } else if fps < 24.5 {
+            FrameRate::Fps24
+        } else if fps < 27.5 {
+            FrameRate::Fps25
+        } else {
+            FrameRate::Fps30
+        };
+        Some(Timecode {
+            frames: field(0, 4) + 10 * field(8, 2),
+            seconds: field(16, 4) + 10 * field(24, 3),
+            minutes: field(32, 4) + 10 * field(40, 3),
+            hours: field(48, 4) + 10 * field(56, 2),
+            rate,
+        })
+    }
+
+    /// The timecode a sample completes, if any.
+    pub fn push(&mut self, sample: i16) -> Option<Timecode> {
+        self.since_edge += 1;
+        let high = if sample > Self::HYSTERESIS {
+            true
+        } else if sample < -Self::HYSTERESIS {
+            false
+        } else {
+            return None;
+        };
+        if high == self.high {
+            return None;
+        }
+        self.high = high;
+        let interval = self.since_edge as f64;
+        self.since_edge = 0;
+        if interval > self.bit_period * 0.75 {
+            self.half_bit = false;
+            self.bit_period += (interval - self.bit_period) * 0.1;
+            self.push_bit(false)
+        } else if self.half_bit {
+            self.half_bit = false;
+            self.bit_period += (2.0 * interval - self.bit_period) * 0.1;
+            self.push_bit(true)
+        } else {
+            self.half_bit = true;
+            None
+        }
+    }
+}
+
+/// Names of the MIDI input ports, as `set_mode` takes them.
+pub fn midi_ports() -> Result<Vec<String>, String> {
+    let input = MidiInput::new(MIDI_CLIENT).map_err(|e| format!("Failed to open MIDI: {}", e))?;
+    Ok(input.ports().iter().filter_map(|port| input.port_name(port).ok()).collect())
+}
+
+/// Where the engine takes its time from, and how well it is following.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ChaseState {
+    /// Internal clock, or waiting for the first timecode.
+    Idle,
+    Locked,
+    /// Timecode stopped; the show runs on for up to the freewheel time.
+    Freewheel,
+    /// Timecode has been gone longer than the freewheel time.
+    Lost,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SyncStatus {
+    pub mode: SyncMode,
+    /// MIDI port or audio device timecode is read from.
+    pub input: Option<String>,
+    /// Timecode the show's zero falls on, in seconds.
+    pub offset: f64,
+    pub state: ChaseState,
+    pub timecode: Option<String>,
+    pub frame_rate: Option<FrameRate>,
+}
+
+struct State {
+    mode: SyncMode,
+    input: Option<String>,
+    offset: f64,
+    // Bumped on every mode change so the old reader exits.
+    generation: u64,
+    // Keeps the MIDI or audio input open; dropped with the state it belongs to.
+    _open_input: Option<InputGuard>,
+    chase: ChaseState,
+    last_timecode: Option<(Timecode, Instant)>,
+    // Set once chasing has started the show, so timecode running on after
+    // the show finished or was stopped does not start it again.
+    started: bool,
+    // The show was paused after timecode was lost and is resumed on re-lock.
+    paused: bool,
+}
+
+impl Default for State {
+    fn default() -> Self {
+        Self {
+            mode: SyncMode::Internal,
+            input: None,
+            offset: 0.0,
+            generation: 0,
+            _open_input: None,
+            chase: ChaseState::Idle,
+            last_timecode: None,
+            started: false,
+            paused: false,
+        }
+    }
+}
+
+/// Slaves the show engine to external timecode. Locked, the engine follows
+/// the timecode, seeking on jumps. When timecode stops the show
+/// freewheels, then pauses until timecode returns. Cheap to clone.
+#[derive(Clone, Default)]
+pub struct TimecodeChase {
+    state: Arc<Mutex<State>>,
+}
+
+impl TimecodeChase {
+    fn lock(&self) -> Result<MutexGuard<'_, State>, String> {
+        self.state.lock().map_err(|_| "Timecode chase is unavailable".to_string())
+    }
+
+    pub fn mode(&self) -> SyncMode {
+        self.lock().map_or(SyncMode::Internal, |s| s.mode)
+    }
+
+    pub fn status(&self) -> Result<SyncStatus, String> {
+        let state = self.lock()?;
+        Ok(Self::status_of(&state))
+    }
+
+    fn status_of(state: &State) -> SyncStatus {
+        SyncStatus {
+            mode: state.mode,
+            input: state.input.clone(),
+            offset: state.offset,
+            state: state.chase,
+            timecode: state.last_timecode.map(|(tc, _)| tc.label()),
+            frame_rate: state.last_timecode.map(|(tc, _)| tc.rate),
+        }
+    }
+
+    /// Switches the clock source. MTC reads the MIDI input port named
+    /// `input`, LTC listens on the audio input device named `input` (the
+    /// default device without one). `offset` is the timecode of show time
+    /// zero, in seconds.
+    pub fn set_mode(&self, app: &AppHandle, mode: SyncMode, input: Option<String>, offset: f64) -> Result<SyncStatus, String> {
+        if !offset.is_finite() || offset < 0.0 {
+            return Err(format!("Timecode offset must be zero or positive, got {}", offset));
+        }
+        let input = if mode == SyncMode::Internal { None } else { input };
+        let (open_input, reader) = match (mode, &input) {
+            (SyncMode::Mtc, None) => {
+                return Err(format!("MTC needs a MIDI port; found: {}", midi_ports()?.join(", ")));
+            }
+            (SyncMode::Mtc, Some(port)) => {
+                let (messages, received) = unbounded_channel();
+                let port = port.clone();
+                let (guard, ()) = hold_input(move || open_mtc(&port, messages))?;
+                (Some(guard), Some(Reader::Mtc(received)))
+            }
+            (SyncMode::Ltc, device) => {
+                let (samples, received) = unbounded_channel();
+                let device = device.clone();
+                let (guard, sample_rate) = hold_input(move || open_ltc(device.as_deref(), samples))?;
+                (Some(guard), Some(Reader::Ltc(received, sample_rate)))
+            }
+            (SyncMode::Internal, _) => (None, None),
+        };
+        let (status, generation) = {
+            let mut state = self.lock()?;
+            *state = State {
+                mode,
+                input: input.clone(),
+                offset,
+                generation: state.generation + 1,
+                _open_input: open_input,
+                ..State::default()
+            };
+            (Self::status_of(&state), state.generation)
+        };
+        log::info!("Sync mode set to {:?}{}", mode, input.map(|i| format!(" on {}", i)).unwrap_or_default());
+        match reader {
+            Some(Reader::Mtc(messages)) => {
+                tauri::async_runtime::spawn(read_mtc(app.clone(), self.clone(), generation, messages));
+            }
+            Some(Reader::Ltc(samples, sample_rate)) => {
+                tauri::async_runtime::spawn(read_ltc(app.clone(), self.clone(), generation, samples, sample_rate));
+            }
+            None => {}
+        }
+        if mode != SyncMode::Internal {
+            tauri::async_runtime::spawn(watch_dropouts(app.clone(), self.clone(), generation));
+        }
+        events::emit(app, events::TIMECODE_STATUS, status.clone());
+        Ok(status)
+    }
+
+    fn is_current(&self, generation: u64) -> bool {
+        self.lock().map(|s| s.generation == generation).unwrap_or(false)
+    }
+
+    fn set_chase(&self, app: &AppHandle, generation: u64, chase: ChaseState) {
+        let status = {
+            let Ok(mut state) = self.lock() else {
+                return;
+            };
+            if state.generation != generation || state.chase == chase {
+                return;
+            }
+            state.chase = chase;
+            if chase == ChaseState::Lost {
+                state.started = false;
+            }
+            Self::status_of(&state)
+        };
+        log::info!("Timecode {:?}", chase);
+        events::emit(app, events::TIMECODE_STATUS, status);
+    }
+
+    // Moves the show to where the timecode says it is. `running` is false
+    // for a locate from a stopped master, which only moves the playhead.
+    async fn receive(&self, app: &AppHandle, generation: u64, timecode: Timecode, running: bool) {
+        let (offset, start, resume) = {
+            let Ok(mut state) = self.lock() else {
+                return;
+            };
+            if state.generation != generation {
+                return;
+            }
+            state.last_timecode = Some((timecode, Instant::now()));
+            let start = running && !state.started;
+            let resume = running && state.paused;
+            if start {
+                state.started = true;
+            }
+            if resume {
+                state.paused = false;
+            }
+            (state.offset, start, resume)
+        };
+        if running {
+            self.set_chase(app, generation, ChaseState::Locked);
+        }
+        let time = timecode.to_seconds() - offset;
+        if time < 0.0 {
+            return;
+        }
+        if let Err(e) = follow(app, time, running, start, resume).await {
+            log::debug!("Timecode {} not applied: {}", timecode.label(), e);
+        }
+    }
+
+    // Pauses the show once timecode has been gone for the freewheel time.
+    fn check_dropout(&self, app: &AppHandle, generation: u64) {
+        let (chase, since) = match self.lock() {
+            Ok(state) if state.generation == generation => (state.chase, state.last_timecode.map(|(_, at)| at.elapsed())),
+            _ => return,
+        };
+        let Some(since) = since else {
+            return;
+        };
+        match chase {
+            ChaseState::Locked if since > DROPOUT => self.set_chase(app, generation, ChaseState::Freewheel),
+            ChaseState::Freewheel if since > DROPOUT + FREEWHEEL => {
+                self.set_chase(app, generation, ChaseState::Lost);
+                let engine = app.state::<ShowEngine>();
+                if engine.status().is_ok_and(|s| s.is_running && !s.is_held) && engine.pause(app).is_ok() {
+                    log::warn!("Show paused: timecode lost");
+                    if let Ok(mut state) = self.lock() {
+                        state.paused = true;
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+// Starts, resumes, seeks or slews the engine to show time `time`.
+async fn follow(app: &AppHandle, time: f64, running: bool, start: bool, resume: bool) -> Result<(), String> {
+    let engine = app.state::<ShowEngine>();
+    let status = engine.status()?;
+    if !status.is_running {
+        if start {
+            log::info!("Timecode started the show at {:.2}s", time);
+            trigger::start_loaded_show_at(app, time)?;
+        }
+        return Ok(());
+    }
+    if !running || resume {
+        engine.seek(app, time).await?;
+        if resume {
+            engine.resume(app)?;
+        }
+        return Ok(());
+    }
+    // Held after a failed cue: the operator decides when to go on.
+    if status.is_held {
+        return Ok(());
+    }
+    if (status.current_time - time).abs() > RELOCK_JUMP_SECS {
+        engine.seek(app, time).await?;
+    } else {
+        engine.report_sync(app, time)?;
+    }
+    Ok(())
+}
+
+// Keeps an input opened by `hold_input` open until dropped.
+struct InputGuard {
+    _stop: mpsc::Sender<()>,
+}
+
+// Where a newly opened input's data arrives.
+enum Reader {
+    Mtc(UnboundedReceiver<Vec<u8>>),
+    /// Mono samples, and their rate.
+    Ltc(UnboundedReceiver<Vec<i16>>, u32),
+}
+
+// Opens an input with `open` on a thread of its own, where it stays until
+// the guard is dropped: audio streams cannot move between threads.
+fn hold_input<T, R, F>(open: F) -> Result<(InputGuard, R), String>
+where
+    F: FnOnce() -> Result<(T, R), String> + Send + 'static,
+    R: Send + 'static,
+{
+    let (ready, opened) = mpsc::channel();
+    let (stop, stopped) = mpsc::channel::<()>();
+    std::thread::spawn(move || match open() {
+        Ok((input, info)) => {
+            let _ = ready.send(Ok(info));
+            // Returns once the guard, the only sender, is dropped.
+            let _ = stopped.recv();
+            drop(input);
+        }
+        Err(e) => {
+            let _ = ready.send(Err(e));
+        }
+    });
+    let info = opened
+        .recv()
+        .map_err(|_| "Timecode input stopped while opening".to_string())??;
+    Ok((InputGuard { _stop: stop }, info))
+}
+
+fn open_mtc(port_name: &str, messages: UnboundedSender<Vec<u8>>) -> Result<(MidiInputConnection<()>, ()), String> {
+    let mut input = MidiInput::new(MIDI_CLIENT).map_err(|e| format!("Failed to open MIDI: {}", e))?;
+    // MTC is carried in time code and SysEx messages.
+    input.ignore(Ignore::ActiveSense);
+    let port = input
+        .ports()
+        .into_iter()
+        .find(|port| input.port_name(port).is_ok_and(|name| name == port_name))
+        .ok_or_else(|| format!("MIDI port {} does not exist", port_name))?;
+    let connection = input
+        .connect(
+            &port,
+            "lume-mtc",
+            move |_, message, _| {
+                let _ = messages.send(message.to_vec());
+            },
+            (),
+        )
+        .map_err(|e| format!("Failed to open MIDI port {}: {}", port_name, e))?;
+    Ok((connection, ()))
+}
+
+// Captures the first channel of audio input `device`, returning the stream
+// and its sample rate.
+fn open_ltc(device: Option<&str>, samples: UnboundedSender<Vec<i16>>) -> Result<(cpal::Stream, u32), String> {
+    let host = cpal::default_host();
+    let device = match device {
+        None => host.default_input_device().ok_or_else(|| "No audio input device".to_string())?,
+        Some(name) => host
+            .input_devices()
+            .map_err(|e| format!("Failed to list audio inputs: {}", e))?
+            .find(|device| device.name().is_ok_and(|n| n == name))
+            .ok_or_else(|| format!("Audio input {} not found", name))?,
+    };
+    let config = device
+        .default_input_config()
+        .map_err(|e| format!("Audio input has no capture format: {}", e))?;
+    let stream = match config.sample_format() {
+        cpal::SampleFormat::F32 => build_ltc_stream::<f32>(&device, &config.config(), samples),
+        cpal::SampleFormat::I32 => build_ltc_stream::<i32>(&device, &config.config(), samples),
+        cpal::SampleFormat::I16 => build_ltc_stream::<i16>(&device, &config.config(), samples),
+        cpal::SampleFormat::U16 => build_ltc_stream::<u16>(&device, &config.config(), samples),
+        other => Err(format!("Audio input uses unsupported sample format {}", other)),
+    }?;
+    stream
+        .play()
+        .map_err(|e| format!("Failed to start LTC input: {}", e))?;
+    Ok((stream, config.sample_rate().0))
+}
+
+fn build_ltc_stream<T>(device: &cpal::Device, config: &cpal::StreamConfig, samples: UnboundedSender<Vec<i16>>) -> Result<cpal::Stream, String>
+where
+    T: SizedSample,
+    i16: FromSample<T>,
+{
+    let channels = config.channels as usize;
+    device
+        .build_input_stream(
+            config,
+            move |data: &[T], _: &cpal::InputCallbackInfo| {
+                let _ = samples.send(data.iter().step_by(channels).map(|&s| i16::from_sample(s)).collect());
+            },
+            |e| log::error!("LTC input failed: {}", e),
+            None,
+        )
+        .map_err(|e| format!("Failed to open LTC input: {}", e))
+}
+
+// Each reader ends when its input is closed, which happens when the mode
+// changes and the input's guard is dropped.
+async fn read_mtc(app: AppHandle, chase: TimecodeChase, generation: u64, mut messages: UnboundedReceiver<Vec<u8>>) {
+    let mut parser = MtcParser::default();
+    while let Some(message) = messages.recv().await {
+        if !chase.is_current(generation) {
+            break;
+        }
+        for &byte in &message {
+            if let Some((timecode, running)) = parser.push(byte) {
+                chase.receive(&app, generation, timecode, running).await;
+            }
+        }
+    }
+}
+
+async fn read_ltc(app: AppHandle, chase: TimecodeChase, generation: u64, mut samples: UnboundedReceiver<Vec<i16>>, sample_rate: u32) {
+    let mut decoder = LtcDecoder::new(sample_rate);
+    while let Some(buffer) = samples.recv().await {
+        if !chase.is_current(generation) {
+            break;
+        }
+        for &sample in &buffer {
+            if let Some(timecode) = decoder.push(sample) {
+                chase.receive(&app, generation, timecode, true).await;
+            }
+        }
+    }
+}
+
+async fn watch_dropouts(app: AppHandle, chase: TimecodeChase, generation: u64) {
+    let mut interval = tokio::time::interval(WATCH_POLL);
+    while chase.is_current(generation) {
+        interval.tick().await;
+        chase.check_dropout(&app, generation);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn quarter_frames_assemble_a_timecode() {
+        let mut parser = MtcParser::default();
+        // 01:02:03:04 at 25 fps.
+        let pieces = [0x04, 0x10, 0x23, 0x30, 0x42, 0x50, 0x61, 0x72];
+        let mut decoded = None;
+        for piece in pieces {
+            decoded = parser.push(0xf1).or(parser.push(piece));
+        }
+        let (timecode, running) = decoded.unwrap();
+        assert!(running);
+        assert_eq!(timecode.label(), "01:02:03:04");
+        assert_eq!(timecode.rate, FrameRate::Fps25);
+    }
+
+    #[test]
+    fn full_frame_sysex_is_a_locate() {
+        let mut parser = MtcParser::default();
+        let message = [0xf0, 0x7f, 0x7f, 0x01, 0x01, 0x60 | 10, 20, 30, 15, 0xf7];
+        let decoded: Vec<_> = message.iter().filter_map(|&b| parser.push(b)).collect();
+        assert_eq!(decoded.len(), 1);
+        assert!(!decoded[0].1);
+        assert_eq!(decoded[0].0.label(), "10:20:30:15");
+        assert_eq!(decoded[0].0.rate, FrameRate::Fps30);
+    }
+
+    #[test]
+    fn dropping_the_guard_closes_the_input() {
+        let (messages, mut received) = unbounded_channel::<Vec<u8>>();
+        let (guard, ()) = hold_input(move || Ok((messages, ()))).unwrap();
+        assert!(received.try_recv().is_err());
+        drop(guard);
+        assert_eq!(received.blocking_recv(), None);
+    }
+
+    #[test]
+    fn drop_frame_counts_real_time() {
+        let timecode = Timecode {
+            hours: 1,
+            minutes: 0,
+            seconds: 0,
+            frames: 0,
+            rate: FrameRate::Fps2997Drop,
+        };
+        // An hour of drop-frame timecode is an hour of real time, to a frame.
+        assert!((timecode.to_seconds() - 3600.0).abs() < 0.04);
+    }
+
+    // Biphase mark audio for one LTC frame at 30 fps and 48 kHz.
+    fn ltc_frame(timecode: &Timecode, level: &mut i16) -> Vec<i16> {
+        let mut bits = [false; 80];
+        let mut set = |start: usize, len: usize, value: u8| {
+            for i in 0..len {
+                bits[start + i] = value >> i & 1 == 1;
+            }
+        };
+        set(0, 4, timecode.frames % 10);
+        set(8, 2, timecode.frames / 10);
+        set(16, 4, timecode.seconds % 10);
+        set(24, 3, timecode.seconds / 10);
+        set(32, 4, timecode.minutes % 10);
+        set(40, 3, timecode.minutes / 10);
+        set(48, 4, timecode.hours % 10);
+        set(56, 2, timecode.hours / 10);
+        for i in 0..16 {
+            bits[64 + i] = LTC_SYNC_WORD >> i & 1 == 1;
+        }
+        let mut samples = Vec::new();
+        for bit in bits {
+            *level = -*level;
+            samples.extend(std::iter::repeat(*level).take(10));
+            if bit {
+                *level = -*level;
+            }
+            samples.extend(std::iter::repeat(*level).take(10));
+        }
+        samples
+    }
+
+    #[test]
+    fn ltc_decodes_from_audio() {
+        let timecode = Timecode {
+            hours: 12,
+            minutes: 34,
+            seconds: 56,
+            frames: 12,
+            rate: FrameRate::Fps30,
+        };
+        let mut decoder = LtcDecoder::new(48_000);
+        let mut level = 10_000;
+        let mut decoded = Vec::new();
+        for _ in 0..3 {
+            for sample in ltc_frame(&timecode, &mut level) {
+                decoded.extend(decoder.push(sample));
+            }
+        }
+        assert!(!decoded.is_empty());
+        assert_eq!(decoded.last(), Some(&timecode));
+    }
+}
@@ -0,0 +1,77 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+/// How commands reach a controller. The ESP32 controllers speak HTTP over
+/// Wi-Fi; portable ones use a GATT service over Bluetooth LE instead, and
+/// wired firing modules their USB serial console.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum Transport {
+    #[default]
+    Http,
+    Serial,
+    Ble,
+}
+
+/// BLE controllers are registered as "ble:<device id>" so they can never be
+/// mistaken for a hostname.
+pub const BLE_PREFIX: &str = "ble:";
+
+/// The device id of a BLE controller address; None for network addresses.
+pub fn ble_id(address: &str) -> Option<&str> {
+    address.strip_prefix(BLE_PREFIX)
+}
+
+/// Serial controllers are registered as "serial:<device path>".
+pub const SERIAL_PREFIX: &str = "serial:";
+
+/// The device path of a serial controller address, e.g. "/dev/ttyUSB0".
+pub fn serial_port(address: &str) -> Option<&str> {
+    address.strip_prefix(SERIAL_PREFIX)
+}
+
+pub const MIN_TIMEOUT_MS: u64 = 50;
+pub const MAX_TIMEOUT_MS: u64 = 60_000;
+
+// Indexed by `Transport as usize`. HTTP matches the timeout the frontend uses
+// and serial is a direct cable. A BLE round trip spans several connection
+// intervals.
+static TIMEOUTS_MS: [AtomicU64; 3] = [
+    AtomicU64::new(3000),
+    AtomicU64::new(1000),
+    AtomicU64::new(5000),
+];
+
+/// Current send timeout for `transport`.
+pub fn timeout(transport: Transport) -> Duration {
+    Duration::from_millis(TIMEOUTS_MS[transport as usize].load(Ordering::Relaxed))
+}
+
+/// The transport a controller address is reached over.
+pub fn of_address(address: &str) -> Transport {
+    if ble_id(address).is_some() {
+        Transport::Ble
+    } else if serial_port(address).is_some() {
+        Transport::Serial
+    } else {
+        Transport::Http
+    }
+}
+
+/// Current send timeout for the controller at `address`.
+pub fn timeout_for(address: &str) -> Duration {
+    timeout(of_address(address))
+}
+
+pub fn set_timeout(transport: Transport, ms: u64) -> Result<(), String> {
+    if !(MIN_TIMEOUT_MS..=MAX_TIMEOUT_MS).contains(&ms) {
+        return Err(format!(
+            "Timeout must be between {} and {} ms, got {}",
+            MIN_TIMEOUT_MS, MAX_TIMEOUT_MS, ms
+        ));
+    }
+    TIMEOUTS_MS[transport as usize].store(ms, Ordering::Relaxed);
+    Ok(())
+}
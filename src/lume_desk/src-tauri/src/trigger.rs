@@ -0,0 +1,282 @@
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Manager};
+use std::io::{ErrorKind, Read};
+
+use crate::audit::AuditLog;
+use crate::control_lock::ControlLock;
+use crate::events::{self, TriggerReceived};
+use crate::palette;
+use crate::show_engine::ShowEngine;
+use crate::show_store::ShowStore;
+
+/// Signals closer together than this count as one press.
+const DEBOUNCE: Duration = Duration::from_millis(500);
+
+/// GPIO lines are sampled this often; a press is far longer than this.
+const GPIO_POLL: Duration = Duration::from_millis(10);
+
+/// Serial reads give up this often to check the trigger is still wanted.
+const SERIAL_POLL: Duration = Duration::from_millis(100);
+
+/// Line speed a serial trigger is opened at. USB buttons ignore it.
+const SERIAL_BAUD_RATE: u32 = 9600;
+
+/// Where an external start signal comes from.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum TriggerSource {
+    /// A USB button that types a key; the UI reports it through `fire_trigger`.
+    Keyboard { key: String },
+    /// Any line received on a serial device, e.g. "/dev/ttyUSB0", at
+    /// `SERIAL_BAUD_RATE`.
+    Serial { port: String },
+    /// A Linux sysfs GPIO input that has already been exported.
+    Gpio {
+        pin: u32,
+        #[serde(default)]
+        active_low: bool,
+    },
+}
+
+impl TriggerSource {
+    fn label(&self) -> String {
+        match self {
+            TriggerSource::Keyboard { key } => format!("keyboard key {}", key),
+            TriggerSource::Serial { port } => format!("serial {}", port),
+            TriggerSource::Gpio { pin, .. } => format!("GPIO {}", pin),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TriggerStatus {
+    pub source: Option<TriggerSource>,
+    pub armed: bool,
+}
+
+#[derive(Default)]
+struct TriggerState {
+    source: Option<TriggerSource>,
+    armed: bool,
+    // Bumped whenever the source changes so the old watcher exits.
+    generation: u64,
+    last_signal: Option<Instant>,
+}
+
+/// Starts the loaded show on an external signal. One-shot: the trigger
+/// disarms itself once it has started a show. Cheap to clone.
+#[derive(Clone, Default)]
+pub struct ExternalTrigger {
+    state: Arc<Mutex<TriggerState>>,
+}
+
+fn gpio_value_path(pin: u32) -> PathBuf {
+    PathBuf::from(format!("/sys/class/gpio/gpio{}/value", pin))
+}
+
+impl ExternalTrigger {
+    fn lock(&self) -> Result<MutexGuard<'_, TriggerState>, String> {
+        self.state.lock().map_err(|_| "External trigger is unavailable".to_string())
+    }
+
+    /// Replaces the trigger source and disarms. None removes the trigger.
+    pub fn configure(&self, app: &AppHandle, source: Option<TriggerSource>) -> Result<(), String> {
+        match &source {
+            Some(TriggerSource::Gpio { pin, .. }) if !gpio_value_path(*pin).exists() => {
+                return Err(format!(
+                    "GPIO {} is not available; export it first (needs a Linux board with sysfs GPIO)",
+                    pin
+                ));
+            }
+            Some(TriggerSource::Serial { port }) if !Path::new(port).exists() => {
+                return Err(format!("Serial port {} does not exist", port));
+            }
+            Some(TriggerSource::Keyboard { key }) if key.trim().is_empty() => {
+                return Err("Keyboard trigger needs a key".to_string());
+            }
+            _ => {}
+        }
+        let generation = {
+            let mut state = self.lock()?;
+            state.generation += 1;
+            state.source = source.clone();
+            state.armed = false;
+            state.generation
+        };
+        match &source {
+            Some(source) => log::info!("External trigger set to {}", source.label()),
+            None => log::info!("External trigger removed"),
+        }
+        match source {
+            Some(TriggerSource::Gpio { pin, active_low }) => {
+                tauri::async_runtime::spawn(watch_gpio(app.clone(), self.clone(), generation, pin, active_low));
+            }
+            Some(TriggerSource::Serial { port }) => {
+                let (app, trigger) = (app.clone(), self.clone());
+                tauri::async_runtime::spawn_blocking(move || watch_serial(app, trigger, generation, port));
+            }
+            Some(TriggerSource::Keyboard { .. }) | None => {}
+        }
+        Ok(())
+    }
+
+    pub fn set_armed(&self, armed: bool) -> Result<TriggerStatus, String> {
+        let mut state = self.lock()?;
+        if armed && state.source.is_none() {
+            return Err("No external trigger is configured".to_string());
+        }
+        state.armed = armed;
+        log::warn!("External trigger {}", if armed { "ARMED" } else { "disarmed" });
+        Ok(TriggerStatus {
+            source: state.source.clone(),
+            armed,
+        })
+    }
+
+    pub fn status(&self) -> Result<TriggerStatus, String> {
+        let state = self.lock()?;
+        Ok(TriggerStatus {
+            source: state.source.clone(),
+            armed: state.armed,
+        })
+    }
+
+    fn is_current(&self, generation: u64) -> bool {
+        self.lock().map(|s| s.generation == generation).unwrap_or(false)
+    }
+
+    /// A key press reported by the UI. Ignored unless the trigger is that key.
+    pub fn key_pressed(&self, app: &AppHandle, key: &str) -> Result<(), String> {
+        let generation = {
+            let state = self.lock()?;
+            match &state.source {
+                Some(TriggerSource::Keyboard { key: expected }) if expected == key => state.generation,
+                _ => return Err(format!("{} is not the configured trigger key", key)),
+            }
+        };
+        self.receive(app, generation);
+        Ok(())
+    }
+
+    // A debounced signal starts the loaded show if the trigger is armed.
+    // Every accepted signal is reported and audited, started or not.
+    fn receive(&self, app: &AppHandle, generation: u64) {
+        let (source, armed) = {
+            let Ok(mut state) = self.state.lock() else {
+                return;
+            };
+            if state.generation != generation {
+                return;
+            }
+            if state.last_signal.is_some_and(|t| t.elapsed() < DEBOUNCE) {
+                return;
+            }
+            state.last_signal = Some(Instant::now());
+            let Some(source) = state.source.clone() else {
+                return;
+            };
+            let armed = state.armed;
+            // Disarm before starting so a second signal cannot start it again.
+            state.armed = false;
+            (source, armed)
+        };
+
+        let result = if armed {
+            start_loaded_show(app)
+        } else {
+            Err("Trigger is not armed".to_string())
+        };
+        if armed && result.is_err() {
+            // Nothing started, so stay armed for the next signal.
+            if let Ok(mut state) = self.state.lock() {
+                if state.generation == generation {
+                    state.armed = true;
+                }
+            }
+        }
+
+        let label = source.label();
+        match &result {
+            Ok(()) => log::warn!("External trigger from {} started the show", label),
+            Err(e) => log::info!("External trigger from {} ignored: {}", label, e),
+        }
+        app.state::<AuditLog>().record_trigger(&label, &result);
+        events::emit(
+            app,
+            events::TRIGGER_RECEIVED,
+            TriggerReceived {
+                source,
+                started: result.is_ok(),
+                error: result.err(),
+            },
+        );
+    }
+}
+
+/// Starts the show loaded in the store, unless an operator holds the controls.
+pub fn start_loaded_show(app: &AppHandle) -> Result<(), String> {
+    start_loaded_show_at(app, 0.0)
+}
+
+/// Like `start_loaded_show`, `time` seconds into the show.
+pub fn start_loaded_show_at(app: &AppHandle, time: f64) -> Result<(), String> {
+    app.state::<ControlLock>().check(None)?;
+    let show = app
+        .state::<ShowStore>()
+        .current()?
+        .ok_or_else(|| "No show loaded".to_string())?;
+    let show = palette::resolve(&show)?;
+    app.state::<ShowEngine>().start_at(app, show, time)
+}
+
+async fn watch_gpio(app: AppHandle, trigger: ExternalTrigger, generation: u64, pin: u32, active_low: bool) {
+    let path = gpio_value_path(pin);
+    let mut was_active = true; // No edge until the line has been seen inactive.
+    let mut interval = tokio::time::interval(GPIO_POLL);
+    while trigger.is_current(generation) {
+        interval.tick().await;
+        let value = match tokio::fs::read_to_string(&path).await {
+            Ok(value) => value,
+            Err(e) => {
+                log::error!("GPIO {} trigger stopped: {}", pin, e);
+                break;
+            }
+        };
+        let active = (value.trim() == "1") != active_low;
+        if active && !was_active {
+            trigger.receive(&app, generation);
+        }
+        was_active = active;
+    }
+}
+
+// Runs on a blocking thread. Reads time out every SERIAL_POLL, so the port
+// is closed soon after the trigger is reconfigured, not at the next byte.
+fn watch_serial(app: AppHandle, trigger: ExternalTrigger, generation: u64, port: String) {
+    let mut serial = match serialport::new(&port, SERIAL_BAUD_RATE).timeout(SERIAL_POLL).open() {
+        Ok(serial) => serial,
+        Err(e) => {
+            log::error!("Serial trigger on {} failed to open: {}", port, e);
+            return;
+        }
+    };
+    let mut buf = [0u8; 64];
+    while trigger.is_current(generation) {
+        match serial.read(&mut buf) {
+            Ok(0) => {
+                log::error!("Serial trigger on {} closed", port);
+                break;
+            }
+            Ok(n) if buf[..n].contains(&b'\n') => trigger.receive(&app, generation),
+            Ok(_) => {}
+            Err(e) if matches!(e.kind(), ErrorKind::TimedOut | ErrorKind::Interrupted) => {}
+            Err(e) => {
+                log::error!("Serial trigger on {} failed: {}", port, e);
+                break;
+            }
+        }
+    }
+}
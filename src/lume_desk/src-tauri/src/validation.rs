@@ -0,0 +1,727 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use tokio::task::JoinSet;
+
+use crate::controller_client;
+use crate::dispatcher::Dispatcher;
+use crate::dmx::DmxConfig;
+use crate::laser::{self, LaserZones};
+use crate::models::{Effect, Show};
+use crate::preflight::Severity;
+use crate::ramp;
+use crate::relay;
+use crate::registry::{self, ControllerInfo};
+use crate::safe_mode;
+use crate::transport;
+
+/// Cues less than this many times their controller's minimum spacing apart
+/// are legal but leave no margin; reported as warnings.
+const TIGHT_SPACING_FACTOR: f64 = 1.25;
+
+/// How warnings count toward `ValidationReport::valid`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ValidationMode {
+    /// Only errors fail validation.
+    #[default]
+    Lenient,
+    /// Warnings fail validation too, e.g. for pyro shows before arming.
+    Strict,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ValidationCategory {
+    LaserSafety,
+    ControllerAvailability,
+    CueSpacing,
+    Ramp,
+    RelayConflict,
+    /// An effect targets a controller the patch does not have.
+    Patch,
+    /// An effect targets a channel beyond its controller's channel count.
+    ChannelRange,
+    /// Concurrent effects on a controller draw more than its power budget.
+    PowerBudget,
+    /// Negative or non-finite times, or effects past the end of the show.
+    Timing,
+    /// Effects that drive the same channel at the same time.
+    ChannelOverlap,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Availability {
+    Online,
+    Offline,
+    /// Registered, but nothing has been sent to it yet.
+    NotContacted,
+    /// Not in the registry at all.
+    Unregistered,
+    /// Registered but disabled; its effects are skipped at playback.
+    Disabled,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ControllerAvailability {
+    /// The controller as written in the show (address or friendly name).
+    pub reference: String,
+    pub address: Option<String>,
+    pub availability: Availability,
+}
+
+/// Two consecutive cues on one controller that the hardware cannot execute this close together.
+#[derive(Debug, Serialize)]
+pub struct SpacingViolation {
+    pub controller: String,
+    pub first_effect: String,
+    pub second_effect: String,
+    pub required_ms: u64,
+    pub actual_ms: f64,
+}
+
+/// An effect aimed at a controller the current patch does not have.
+#[derive(Debug, Clone, Serialize)]
+pub struct DanglingReference {
+    pub effect_id: String,
+    /// The controller as written in the show (address or friendly name).
+    pub controller: String,
+    pub channel: u32,
+    pub message: String,
+}
+
+/// An effect on a registered controller whose channel it does not have,
+/// typically after fixtures were re-patched.
+#[derive(Debug, Clone, Serialize)]
+pub struct ChannelRangeViolation {
+    pub effect_id: String,
+    /// Address of the controller.
+    pub controller: String,
+    pub channel: u32,
+    pub channel_count: u32,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ValidationIssue {
+    pub category: ValidationCategory,
+    pub severity: Severity,
+    pub effect_id: Option<String>,
+    pub controller: Option<String>,
+    pub message: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ValidationReport {
+    pub mode: ValidationMode,
+    /// False if any issue has error severity, or in strict mode warning
+    /// severity.
+    pub valid: bool,
+    pub timing_valid: bool,
+    pub effects_valid: bool,
+    /// True only if every referenced controller was online when last contacted.
+    pub controllers_available: bool,
+    pub controllers: Vec<ControllerAvailability>,
+    pub cue_spacing: Vec<SpacingViolation>,
+    /// Effects whose controller is not in the registry.
+    pub dangling_references: Vec<DanglingReference>,
+    pub channel_range: Vec<ChannelRangeViolation>,
+    pub issues: Vec<ValidationIssue>,
+    /// Effects left out of the checks above because they are disabled or on
+    /// a muted layer.
+    pub disabled_effects: Vec<String>,
+}
+
+/// Checks on show data that need no network access. Controller availability
+/// comes from the dispatcher's last known state rather than a fresh probe.
+/// Channels patched to DMX are only checked for timing and overlaps.
+pub fn validate(
+    show: &Show,
+    controllers: &[ControllerInfo],
+    zones: &LaserZones,
+    dispatcher: &Dispatcher,
+    dmx: &DmxConfig,
+    mode: ValidationMode,
+) -> ValidationReport {
+    let known = registry::lookup_map(controllers);
+    let disabled_effects = show.effects.iter().filter(|e| !show.plays(e)).map(|e| e.id.clone()).collect();
+    // Disabled effects never play, so they cannot conflict with anything.
+    let mut enabled = show.clone();
+    enabled.effects.retain(|e| show.plays(e));
+    let mut native = enabled.clone();
+    native.effects.retain(|e| !dmx.is_patched(&e.controller, e.channel));
+    let show = &native;
+    let dangling_references = dangling_references(show, &known);
+    let mut issues: Vec<ValidationIssue> = dangling_references
+        .iter()
+        .map(|d| ValidationIssue {
+            category: ValidationCategory::Patch,
+            severity: Severity::Error,
+            effect_id: Some(d.effect_id.clone()),
+            controller: Some(known.get(d.controller.as_str()).map_or_else(|| d.controller.clone(), |c| c.address.clone())),
+            message: d.message.clone(),
+        })
+        .collect();
+    let channel_range = channel_range_violations(show, &known);
+    issues.extend(channel_range.iter().map(|v| ValidationIssue {
+        category: ValidationCategory::ChannelRange,
+        severity: Severity::Error,
+        effect_id: Some(v.effect_id.clone()),
+        controller: Some(v.controller.clone()),
+        message: v.to_string(),
+    }));
+    issues.extend(check_timing(&enabled, &known));
+    issues.extend(check_channel_overlaps(&enabled, &known));
+    issues.extend(check_laser_zones(show, &known, zones));
+    issues.extend(check_ramps(show, &known));
+    issues.extend(check_relays(show, &known));
+    let effects_valid = !issues.iter().any(|i| i.severity == Severity::Error);
+    let cue_spacing = check_cue_spacing(show, &known);
+    issues.extend(cue_spacing.iter().map(|v| ValidationIssue {
+        category: ValidationCategory::CueSpacing,
+        severity: Severity::Error,
+        effect_id: Some(v.second_effect.clone()),
+        controller: Some(v.controller.clone()),
+        message: format!(
+            "Cues {} and {} on {} are {:.0} ms apart, the controller needs at least {} ms",
+            v.first_effect, v.second_effect, v.controller, v.actual_ms, v.required_ms
+        ),
+    }));
+    issues.extend(check_tight_spacing(show, &known));
+    issues.extend(check_power_budgets(show, &known));
+
+    let availability = check_availability(show, &known, dispatcher);
+    issues.extend(availability.iter().filter_map(availability_issue));
+    let controllers_available = availability.iter().all(|c| c.availability == Availability::Online);
+
+    let fails = |severity: Severity| match mode {
+        ValidationMode::Lenient => severity == Severity::Error,
+        ValidationMode::Strict => severity != Severity::Info,
+    };
+    ValidationReport {
+        mode,
+        valid: !issues.iter().any(|i| fails(i.severity)),
+        timing_valid: cue_spacing.is_empty()
+            && !issues.iter().any(|i| i.category == ValidationCategory::Timing && i.severity == Severity::Error),
+        effects_valid,
+        controllers_available,
+        controllers: availability,
+        cue_spacing,
+        dangling_references,
+        channel_range,
+        issues,
+        disabled_effects,
+    }
+}
+
+/// Probes every enabled controller the playing effects of `show` use and
+/// records the answers with the dispatcher, so `validate` reports them as
+/// they are now. Does nothing in safe mode, where nothing may be contacted.
+pub async fn refresh_availability(show: &Show, controllers: &[ControllerInfo], dispatcher: &Dispatcher) {
+    if safe_mode::is_active() {
+        return;
+    }
+    let known = registry::lookup_map(controllers);
+    let addresses: BTreeSet<String> = show
+        .effects
+        .iter()
+        .filter(|e| show.plays(e))
+        .filter_map(|e| known.get(e.controller.as_str()))
+        .filter(|c| c.enabled)
+        .map(|c| c.address.clone())
+        .collect();
+    let mut probes = JoinSet::new();
+    for address in addresses {
+        probes.spawn(async move {
+            let result = controller_client::probe(&address, transport::timeout_for(&address)).await;
+            (address, result)
+        });
+    }
+    while let Some(joined) = probes.join_next().await {
+        if let Ok((address, result)) = joined {
+            dispatcher.note_reachability(&address, result.is_ok(), result.err());
+        }
+    }
+}
+
+fn check_availability(
+    show: &Show,
+    known: &BTreeMap<&str, &ControllerInfo>,
+    dispatcher: &Dispatcher,
+) -> Vec<ControllerAvailability> {
+    let referenced: BTreeSet<&str> = show.effects.iter().map(|e| e.controller.as_str()).collect();
+    referenced
+        .into_iter()
+        .map(|reference| {
+            let info = known.get(reference);
+            let availability = match info.map(|c| (c.enabled, dispatcher.last_known_online(&c.address))) {
+                None => Availability::Unregistered,
+                Some((false, _)) => Availability::Disabled,
+                Some((true, None)) => Availability::NotContacted,
+                Some((true, Some(true))) => Availability::Online,
+                Some((true, Some(false))) => Availability::Offline,
+            };
+            ControllerAvailability {
+                reference: reference.to_string(),
+                address: info.map(|c| c.address.clone()),
+                availability,
+            }
+        })
+        .collect()
+}
+
+/// References in `show` to controllers the registry cannot resolve, e.g.
+/// one that was since removed or renamed.
+pub fn dangling_references(show: &Show, known: &BTreeMap<&str, &ControllerInfo>) -> Vec<DanglingReference> {
+    show.effects
+        .iter()
+        .filter(|effect| !known.contains_key(effect.controller.as_str()))
+        .map(|effect| DanglingReference {
+            effect_id: effect.id.clone(),
+            controller: effect.controller.clone(),
+            channel: effect.channel,
+            message: format!("Effect {} targets {}, which is not a registered controller", effect.id, effect.controller),
+        })
+        .collect()
+}
+
+impl std::fmt::Display for ChannelRangeViolation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Effect {} targets channel {}, outside 1-{} on {}",
+            self.effect_id, self.channel, self.channel_count, self.controller
+        )
+    }
+}
+
+/// Effects on registered controllers whose channel is outside the range the
+/// controller reported. Controllers with an unknown channel count pass.
+pub fn channel_range_violations(show: &Show, known: &BTreeMap<&str, &ControllerInfo>) -> Vec<ChannelRangeViolation> {
+    show.effects
+        .iter()
+        .filter_map(|effect| {
+            let info = known.get(effect.controller.as_str())?;
+            let count = info.channel_count?;
+            (!(1..=count).contains(&effect.channel)).then(|| ChannelRangeViolation {
+                effect_id: effect.id.clone(),
+                controller: info.address.clone(),
+                channel: effect.channel,
+                channel_count: count,
+            })
+        })
+        .collect()
+}
+
+fn availability_issue(controller: &ControllerAvailability) -> Option<ValidationIssue> {
+    let (severity, message) = match controller.availability {
+        // Reported per effect by the patch check.
+        Availability::Online | Availability::Unregistered => return None,
+        Availability::Disabled => (
+            Severity::Warning,
+            format!("{} is disabled; its effects will be skipped", controller.reference),
+        ),
+        Availability::Offline => (
+            Severity::Warning,
+            format!("{} did not respond when last contacted", controller.reference),
+        ),
+        Availability::NotContacted => (
+            Severity::Info,
+            format!("{} has not been contacted yet; test the connection", controller.reference),
+        ),
+    };
+    Some(ValidationIssue {
+        category: ValidationCategory::ControllerAvailability,
+        severity,
+        effect_id: None,
+        controller: Some(controller.address.clone().unwrap_or_else(|| controller.reference.clone())),
+        message,
+    })
+}
+
+// Cues are compared by start time with the next cue on the same controller,
+// whatever the channel, since the controller executes commands one at a time.
+pub fn check_cue_spacing(show: &Show, known: &BTreeMap<&str, &ControllerInfo>) -> Vec<SpacingViolation> {
+    consecutive_cues(show, known)
+        .into_iter()
+        .filter(|(info, first, second)| second.start_time - first.start_time < info.min_command_spacing().as_secs_f64())
+        .map(|(info, first, second)| SpacingViolation {
+            controller: info.address.clone(),
+            first_effect: first.id.clone(),
+            second_effect: second.id.clone(),
+            required_ms: info.min_command_spacing().as_millis() as u64,
+            actual_ms: (second.start_time - first.start_time) * 1000.0,
+        })
+        .collect()
+}
+
+// Each cue with the next one on the same controller, in start order.
+fn consecutive_cues<'a>(
+    show: &'a Show,
+    known: &BTreeMap<&str, &'a ControllerInfo>,
+) -> Vec<(&'a ControllerInfo, &'a Effect, &'a Effect)> {
+    let mut by_controller: BTreeMap<&str, (&ControllerInfo, Vec<&Effect>)> = BTreeMap::new();
+    for effect in &show.effects {
+        if let Some(info) = known.get(effect.controller.as_str()) {
+            by_controller.entry(info.address.as_str()).or_insert((info, Vec::new())).1.push(effect);
+        }
+    }
+
+    let mut pairs = Vec::new();
+    for (info, mut effects) in by_controller.into_values() {
+        effects.sort_by(|a, b| a.start_time.total_cmp(&b.start_time));
+        pairs.extend(effects.windows(2).map(|pair| (info, pair[0], pair[1])));
+    }
+    pairs
+}
+
+fn check_tight_spacing(show: &Show, known: &BTreeMap<&str, &ControllerInfo>) -> Vec<ValidationIssue> {
+    consecutive_cues(show, known)
+        .into_iter()
+        .filter_map(|(info, first, second)| {
+            let required = info.min_command_spacing().as_secs_f64();
+            let gap = second.start_time - first.start_time;
+            (required > 0.0 && gap >= required && gap < required * TIGHT_SPACING_FACTOR).then(|| ValidationIssue {
+                category: ValidationCategory::CueSpacing,
+                severity: Severity::Warning,
+                effect_id: Some(second.id.clone()),
+                controller: Some(info.address.clone()),
+                message: format!(
+                    "Cues {} and {} on {} are {:.0} ms apart, close to the {:.0} ms minimum",
+                    first.id,
+                    second.id,
+                    info.address,
+                    gap * 1000.0,
+                    required * 1000.0
+                ),
+            })
+        })
+        .collect()
+}
+
+// Times must be real and not negative. With a set duration the show stops
+// there, so an effect starting after it never plays and one running past
+// it is cut short.
+fn check_timing(show: &Show, known: &BTreeMap<&str, &ControllerInfo>) -> Vec<ValidationIssue> {
+    let issue = |severity: Severity, effect: &Effect, message: String| ValidationIssue {
+        category: ValidationCategory::Timing,
+        severity,
+        effect_id: Some(effect.id.clone()),
+        controller: Some(known.get(effect.controller.as_str()).map_or_else(|| effect.controller.clone(), |c| c.address.clone())),
+        message,
+    };
+    let end_of_show = show.total_duration;
+    let mut issues = Vec::new();
+    if !end_of_show.is_finite() || end_of_show < 0.0 {
+        issues.push(ValidationIssue {
+            category: ValidationCategory::Timing,
+            severity: Severity::Error,
+            effect_id: None,
+            controller: None,
+            message: format!("The show's total duration of {}s is not a valid length", end_of_show),
+        });
+    }
+    for effect in &show.effects {
+        if !effect.start_time.is_finite() || effect.start_time < 0.0 {
+            issues.push(issue(
+                Severity::Error,
+                effect,
+                format!("Effect {} starts at {}s; start times must be zero or later", effect.id, effect.start_time),
+            ));
+        } else if !effect.duration.is_finite() || effect.duration < 0.0 {
+            issues.push(issue(
+                Severity::Error,
+                effect,
+                format!("Effect {} lasts {}s; durations must be zero or positive", effect.id, effect.duration),
+            ));
+        } else if end_of_show > 0.0 && effect.start_time > end_of_show {
+            issues.push(issue(
+                Severity::Error,
+                effect,
+                format!(
+                    "Effect {} starts at {:.2}s, after the show ends at {:.2}s, and will never play",
+                    effect.id, effect.start_time, end_of_show
+                ),
+            ));
+        } else if end_of_show > 0.0 && effect.start_time + effect.duration > end_of_show {
+            issues.push(issue(
+                Severity::Warning,
+                effect,
+                format!(
+                    "Effect {} runs until {:.2}s and is cut short when the show ends at {:.2}s",
+                    effect.id,
+                    effect.start_time + effect.duration,
+                    end_of_show
+                ),
+            ));
+        }
+    }
+    issues
+}
+
+// Two effects driving one channel at once fight over its output, and the
+// later command wins. Relay channels are checked by `check_relays`, which
+// only objects to contradictory states.
+fn check_channel_overlaps(show: &Show, known: &BTreeMap<&str, &ControllerInfo>) -> Vec<ValidationIssue> {
+    let mut by_channel: BTreeMap<(String, u32), Vec<&Effect>> = BTreeMap::new();
+    for effect in show.effects.iter().filter(|e| e.start_time.is_finite() && e.duration.is_finite()) {
+        let info = known.get(effect.controller.as_str());
+        if info.is_some_and(|c| c.is_relay()) {
+            continue;
+        }
+        let controller = info.map_or_else(|| effect.controller.clone(), |c| c.address.clone());
+        by_channel.entry((controller, effect.channel)).or_default().push(effect);
+    }
+
+    let mut issues = Vec::new();
+    for ((controller, channel), mut effects) in by_channel {
+        effects.sort_by(|a, b| a.start_time.total_cmp(&b.start_time));
+        // The effect running longest so far, which any later start overlaps.
+        let mut running: Option<&Effect> = None;
+        for effect in effects {
+            if let Some(previous) = running {
+                let end = previous.start_time + previous.duration.max(0.0);
+                if effect.start_time < end {
+                    issues.push(ValidationIssue {
+                        category: ValidationCategory::ChannelOverlap,
+                        severity: Severity::Warning,
+                        effect_id: Some(effect.id.clone()),
+                        controller: Some(controller.clone()),
+                        message: format!(
+                            "Effect {} starts at {:.2}s on channel {} while {} runs until {:.2}s",
+                            effect.id, effect.start_time, channel, previous.id, end
+                        ),
+                    });
+                }
+                if effect.start_time + effect.duration.max(0.0) <= end {
+                    continue;
+                }
+            }
+            running = Some(effect);
+        }
+    }
+    issues
+}
+
+/// Highest concurrent draw of the effects on `info` that declare
+/// `power_watts`, with when it occurs; None without a budget to compare to.
+pub fn peak_power(show: &Show, info: &ControllerInfo) -> Option<(f64, f64)> {
+    info.power_budget_watts?;
+    let mut edges: Vec<(f64, f64)> = Vec::new();
+    for effect in show.effects.iter().filter(|e| e.controller == info.address) {
+        if let Some(watts) = effect.params.get("power_watts").and_then(|v| v.as_f64()) {
+            edges.push((effect.start_time, watts));
+            edges.push((effect.start_time + effect.duration.max(0.0), -watts));
+        }
+    }
+    // Process ends before starts at the same instant so back-to-back cues don't stack.
+    edges.sort_by(|a, b| a.0.total_cmp(&b.0).then(a.1.total_cmp(&b.1)));
+
+    let (mut load, mut peak, mut peak_time) = (0.0_f64, 0.0_f64, 0.0_f64);
+    for (time, delta) in edges {
+        load += delta;
+        if load > peak {
+            peak = load;
+            peak_time = time;
+        }
+    }
+    Some((peak, peak_time))
+}
+
+// A budget is a planning figure, not a hardware limit, so overruns warn.
+fn check_power_budgets(show: &Show, known: &BTreeMap<&str, &ControllerInfo>) -> Vec<ValidationIssue> {
+    let controllers: BTreeMap<&str, &ControllerInfo> = known.values().map(|c| (c.address.as_str(), *c)).collect();
+    controllers
+        .into_values()
+        .filter_map(|info| {
+            let budget = info.power_budget_watts?;
+            let (peak, at) = peak_power(show, info)?;
+            (peak > budget).then(|| ValidationIssue {
+                category: ValidationCategory::PowerBudget,
+                severity: Severity::Warning,
+                effect_id: None,
+                controller: Some(info.address.clone()),
+                message: format!("{} peaks at {:.0} W at {:.2}s, budget is {:.0} W", info.address, peak, at, budget),
+            })
+        })
+        .collect()
+}
+
+// Every laser effect must stay inside its laser's safe zone; nothing is clamped.
+fn check_laser_zones(
+    show: &Show,
+    known: &BTreeMap<&str, &ControllerInfo>,
+    zones: &LaserZones,
+) -> Vec<ValidationIssue> {
+    show.effects
+        .iter()
+        .filter_map(|effect| {
+            let laser = known.get(effect.controller.as_str()).filter(|c| c.is_laser())?;
+            let result = laser::beam_points(&effect.params).and_then(|beam| zones.check(&laser.address, &beam));
+            result.err().map(|message| ValidationIssue {
+                category: ValidationCategory::LaserSafety,
+                severity: Severity::Error,
+                effect_id: Some(effect.id.clone()),
+                controller: Some(laser.address.clone()),
+                message,
+            })
+        })
+        .collect()
+}
+
+// Ramps must be well formed, fit their effect and show, and suit the
+// controller's outputs. Unregistered controllers are reported elsewhere.
+fn check_ramps(show: &Show, known: &BTreeMap<&str, &ControllerInfo>) -> Vec<ValidationIssue> {
+    show.effects
+        .iter()
+        .filter_map(|effect| {
+            let ramp = effect.ramp.as_ref()?;
+            let controller = known.get(effect.controller.as_str());
+            let result = ramp.check(effect, show.total_duration).and_then(|()| match controller {
+                Some(controller) => ramp::frame(controller, &ramp.from).map(|_| ()),
+                None => Ok(()),
+            });
+            result.err().map(|message| ValidationIssue {
+                category: ValidationCategory::Ramp,
+                severity: Severity::Error,
+                effect_id: Some(effect.id.clone()),
+                controller: controller.map(|c| c.address.clone()),
+                message,
+            })
+        })
+        .collect()
+}
+
+// Relay channels must never be asked for opposite states at once.
+fn check_relays(show: &Show, known: &BTreeMap<&str, &ControllerInfo>) -> Vec<ValidationIssue> {
+    let is_relay = |reference: &str| known.get(reference).is_some_and(|c| c.is_relay());
+    let mut issues: Vec<ValidationIssue> = relay::conflicts(show, is_relay)
+        .into_iter()
+        .map(|conflict| ValidationIssue {
+            category: ValidationCategory::RelayConflict,
+            severity: Severity::Error,
+            effect_id: Some(conflict.second.id.clone()),
+            controller: known.get(conflict.first.controller.as_str()).map(|c| c.address.clone()),
+            message: format!("Channel {}: {}", conflict.first.channel, conflict.reason),
+        })
+        .collect();
+    // Commands that cannot be understood at all are errors too.
+    issues.extend(show.effects.iter().filter(|e| is_relay(&e.controller)).filter_map(|effect| {
+        relay::command(effect).err().map(|message| ValidationIssue {
+            category: ValidationCategory::RelayConflict,
+            severity: Severity::Error,
+            effect_id: Some(effect.id.clone()),
+            controller: known.get(effect.controller.as_str()).map(|c| c.address.clone()),
+            message,
+        })
+    }));
+    issues
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn controller(address: &str, channel_count: u32) -> ControllerInfo {
+        serde_json::from_value(json!({
+            "address": address,
+            "name": "Stage Left",
+            "controller_type": "lighting",
+            "channel_count": channel_count,
+            "min_command_spacing_ms": 100,
+        }))
+        .unwrap()
+    }
+
+    // Effects as (id, start, duration, controller, channel), in a 10 s show.
+    fn show(effects: &[(&str, f64, f64, &str, u32)]) -> Show {
+        let effects: Vec<_> = effects
+            .iter()
+            .map(|&(id, start_time, duration, controller, channel)| {
+                json!({ "id": id, "start_time": start_time, "duration": duration, "controller": controller, "channel": channel })
+            })
+            .collect();
+        serde_json::from_value(json!({ "id": "s1", "name": "Test", "total_duration": 10.0, "effects": effects })).unwrap()
+    }
+
+    fn ids(issues: &[ValidationIssue], severity: Severity) -> Vec<&str> {
+        issues
+            .iter()
+            .filter(|i| i.severity == severity)
+            .filter_map(|i| i.effect_id.as_deref())
+            .collect()
+    }
+
+    #[test]
+    fn rejects_unknown_controllers() {
+        let controllers = [controller("10.0.0.1", 8)];
+        let known = registry::lookup_map(&controllers);
+        let show = show(&[("e1", 1.0, 0.0, "Stage Left", 1), ("e2", 2.0, 0.0, "10.0.0.9", 1)]);
+        let dangling = dangling_references(&show, &known);
+        assert_eq!(dangling.len(), 1);
+        assert_eq!((dangling[0].effect_id.as_str(), dangling[0].controller.as_str()), ("e2", "10.0.0.9"));
+    }
+
+    #[test]
+    fn rejects_channels_outside_the_controller() {
+        let controllers = [controller("10.0.0.1", 8)];
+        let known = registry::lookup_map(&controllers);
+        let show = show(&[("e1", 1.0, 0.0, "10.0.0.1", 8), ("e2", 2.0, 0.0, "10.0.0.1", 9), ("e3", 3.0, 0.0, "10.0.0.1", 0)]);
+        let violations: Vec<_> = channel_range_violations(&show, &known).into_iter().map(|v| v.effect_id).collect();
+        assert_eq!(violations, ["e2", "e3"]);
+    }
+
+    #[test]
+    fn rejects_cues_closer_than_the_controller_allows() {
+        let controllers = [controller("10.0.0.1", 8)];
+        let known = registry::lookup_map(&controllers);
+        // 50 ms is too close for 100 ms spacing; 110 ms is legal but tight.
+        let show = show(&[("e1", 1.0, 0.0, "10.0.0.1", 1), ("e2", 1.05, 0.0, "10.0.0.1", 2), ("e3", 1.16, 0.0, "10.0.0.1", 3)]);
+        let violations = check_cue_spacing(&show, &known);
+        assert_eq!(violations.len(), 1);
+        assert_eq!((violations[0].second_effect.as_str(), violations[0].required_ms), ("e2", 100));
+        assert_eq!(ids(&check_tight_spacing(&show, &known), Severity::Warning), ["e3"]);
+    }
+
+    #[test]
+    fn rejects_times_outside_the_show() {
+        let controllers = [controller("10.0.0.1", 8)];
+        let known = registry::lookup_map(&controllers);
+        let show = show(&[
+            ("negative", -1.0, 0.0, "10.0.0.1", 1),
+            ("backwards", 1.0, -2.0, "10.0.0.1", 2),
+            ("late", 11.0, 0.0, "10.0.0.1", 3),
+            ("long", 9.0, 2.0, "10.0.0.1", 4),
+            ("fine", 2.0, 1.0, "10.0.0.1", 5),
+        ]);
+        let issues = check_timing(&show, &known);
+        assert_eq!(ids(&issues, Severity::Error), ["negative", "backwards", "late"]);
+        assert_eq!(ids(&issues, Severity::Warning), ["long"]);
+    }
+
+    #[test]
+    fn warns_about_effects_sharing_a_channel() {
+        let controllers = [controller("10.0.0.1", 8)];
+        let known = registry::lookup_map(&controllers);
+        let show = show(&[("e1", 1.0, 2.0, "10.0.0.1", 1), ("e2", 2.0, 1.0, "Stage Left", 1), ("e3", 3.0, 1.0, "10.0.0.1", 1)]);
+        assert_eq!(ids(&check_channel_overlaps(&show, &known), Severity::Warning), ["e2"]);
+    }
+
+    #[test]
+    fn reports_laser_beams_outside_the_safe_zone() {
+        let laser: ControllerInfo = serde_json::from_value(json!({ "address": "10.0.0.5", "controller_type": "laser" })).unwrap();
+        let controllers = [laser];
+        let known = registry::lookup_map(&controllers);
+        let zones = LaserZones::default();
+        let square = [(-0.5, -0.5), (0.5, -0.5), (0.5, 0.5), (-0.5, 0.5)];
+        zones.set("10.0.0.5", square.iter().map(|&(x, y)| laser::Point { x, y }).collect()).unwrap();
+        let mut show = show(&[("inside", 1.0, 1.0, "10.0.0.5", 1), ("outside", 2.0, 1.0, "10.0.0.5", 1), ("bare", 3.0, 1.0, "10.0.0.5", 1)]);
+        show.effects[0].params = serde_json::from_value(json!({ "x": 0.2, "y": 0.2 })).unwrap();
+        show.effects[1].params = serde_json::from_value(json!({ "points": [[0.0, 0.0], [0.9, 0.0]] })).unwrap();
+
+        let issues = check_laser_zones(&show, &known, &zones);
+        assert!(issues.iter().all(|i| i.category == ValidationCategory::LaserSafety));
+        // Effects without geometry cannot be verified, so they are refused too.
+        assert_eq!(ids(&issues, Severity::Error), ["outside", "bare"]);
+    }
+}
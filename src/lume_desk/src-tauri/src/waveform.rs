@@ -0,0 +1,272 @@
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::ErrorKind;
+use std::path::Path;
+use symphonia::core::audio::SampleBuffer;
+use symphonia::core::codecs::{Decoder, DecoderOptions, CODEC_TYPE_NULL};
+use symphonia::core::errors::Error as SymphoniaError;
+use symphonia::core::formats::{FormatOptions, FormatReader};
+use symphonia::core::io::MediaSourceStream;
+use symphonia::core::meta::MetadataOptions;
+use symphonia::core::probe::Hint;
+use tauri::AppHandle;
+
+use crate::events::{self, WaveformProgress};
+
+pub const MAX_BUCKETS: usize = 100_000;
+
+/// Progress events per analysis.
+const PROGRESS_STEPS: u64 = 20;
+
+/// Amplitude envelope of a track, one bucket per slice of time. Values are
+/// 0-1 of full scale, over all channels.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WaveformEnvelope {
+    pub sample_rate: u32,
+    pub channels: u16,
+    pub duration: f64,
+    pub bucket_seconds: f64,
+    pub peak: Vec<f32>,
+    pub rms: Vec<f32>,
+}
+
+// A track decoded packet by packet to interleaved samples, so it is never
+// held in memory whole.
+struct TrackReader {
+    format: Box<dyn FormatReader>,
+    decoder: Box<dyn Decoder>,
+    track_id: u32,
+    sample_rate: u32,
+    channels: u16,
+    /// Frames in the track, when the container says.
+    frames: Option<u64>,
+    buffer: Option<SampleBuffer<f32>>,
+    display: String,
+}
+
+impl TrackReader {
+    fn open(path: &Path) -> Result<Self, String> {
+        let display = path.display().to_string();
+        let file = File::open(path).map_err(|e| format!("Failed to open {}: {}", display, e))?;
+        let stream = MediaSourceStream::new(Box::new(file), Default::default());
+        let mut hint = Hint::new();
+        if let Some(extension) = path.extension().and_then(|e| e.to_str()) {
+            hint.with_extension(extension);
+        }
+        let probed = symphonia::default::get_probe()
+            .format(&hint, stream, &FormatOptions::default(), &MetadataOptions::default())
+            .map_err(|e| format!("{}: not a supported audio file ({})", display, e))?;
+        let track = probed
+            .format
+            .tracks()
+            .iter()
+            .find(|track| track.codec_params.codec != CODEC_TYPE_NULL)
+            .ok_or_else(|| format!("{} contains no audio", display))?;
+        let params = &track.codec_params;
+        let (Some(sample_rate), Some(channels)) = (params.sample_rate, params.channels) else {
+            return Err(format!("{}: unknown sample rate or channel layout", display));
+        };
+        let decoder = symphonia::default::get_codecs()
+            .make(params, &DecoderOptions::default())
+            .map_err(|e| format!("{}: unsupported audio codec ({})", display, e))?;
+        Ok(TrackReader {
+            track_id: track.id,
+            sample_rate,
+            channels: channels.count() as u16,
+            frames: params.n_frames,
+            format: probed.format,
+            decoder,
+            buffer: None,
+            display,
+        })
+    }
+
+    /// The next packet's samples, interleaved; None at the end of the track.
+    fn next_samples(&mut self) -> Result<Option<&[f32]>, String> {
+        loop {
+            let packet = match self.format.next_packet() {
+                Ok(packet) => packet,
+                Err(SymphoniaError::IoError(e)) if e.kind() == ErrorKind::UnexpectedEof => return Ok(None),
+                Err(e) => return Err(format!("Failed to read {}: {}", self.display, e)),
+            };
+            if packet.track_id() != self.track_id {
+                continue;
+            }
+            let decoded = match self.decoder.decode(&packet) {
+                Ok(decoded) => decoded,
+                // A damaged packet costs its few milliseconds, not the track.
+                Err(SymphoniaError::DecodeError(e)) => {
+                    log::debug!("Skipped an undecodable packet in {}: {}", self.display, e);
+                    continue;
+                }
+                Err(e) => return Err(format!("Failed to decode {}: {}", self.display, e)),
+            };
+            let spec = *decoded.spec();
+            if decoded.frames() == 0 || spec.channels.count() != self.channels as usize {
+                continue;
+            }
+            let needed = decoded.capacity() * spec.channels.count();
+            if self.buffer.as_ref().is_some_and(|buffer| buffer.capacity() < needed) {
+                self.buffer = None;
+            }
+            let buffer = self
+                .buffer
+                .get_or_insert_with(|| SampleBuffer::new(decoded.capacity() as u64, spec));
+            buffer.copy_interleaved_ref(decoded);
+            return Ok(Some(buffer.samples()));
+        }
+    }
+}
+
+// Frames in the track at `path`, by decoding it through.
+fn count_frames(path: &Path) -> Result<u64, String> {
+    let mut reader = TrackReader::open(path)?;
+    let channels = reader.channels as u64;
+    let mut frames = 0;
+    while let Some(samples) = reader.next_samples()? {
+        frames += samples.len() as u64 / channels;
+    }
+    Ok(frames)
+}
+
+/// Decodes the track at `path` (WAV, FLAC, MP3 or OGG Vorbis) front to
+/// back and reduces it to `buckets` peak/RMS pairs. Tracks whose container
+/// does not give their length are decoded through once more to find it.
+pub fn analyze(app: &AppHandle, path: &Path, buckets: usize) -> Result<WaveformEnvelope, String> {
+    scan(app, path, buckets, None).map(|(envelope, _)| envelope)
+}
+
+/// Like `analyze`, also returning the mean energy of the channels mixed
+/// down to mono for every `hop_seconds` slice of the track.
+pub fn analyze_with_energy(
+    app: &AppHandle,
+    path: &Path,
+    buckets: usize,
+    hop_seconds: f64,
+) -> Result<(WaveformEnvelope, Vec<f32>), String> {
+    scan(app, path, buckets, Some(hop_seconds))
+}
+
+fn scan(app: &AppHandle, path: &Path, buckets: usize, hop_seconds: Option<f64>) -> Result<(WaveformEnvelope, Vec<f32>), String> {
+    if buckets == 0 || buckets > MAX_BUCKETS {
+        return Err(format!("Buckets must be between 1 and {}, got {}", MAX_BUCKETS, buckets));
+    }
+    let mut reader = TrackReader::open(path)?;
+    let frames = match reader.frames {
+        Some(frames) => frames,
+        None => count_frames(path)?,
+    };
+    if frames == 0 {
+        return Err(format!("{} contains no audio", path.display()));
+    }
+    let (sample_rate, channels) = (reader.sample_rate, reader.channels);
+    let buckets = buckets.min(frames as usize);
+    let frames_per_bucket = frames.div_ceil(buckets as u64);
+    let mut peak = vec![0.0_f32; buckets];
+    let mut sum_squares = vec![0.0_f64; buckets];
+    let mut counts = vec![0_u64; buckets];
+    let hop_frames = hop_seconds.map(|hop| ((hop * sample_rate as f64).round() as u64).max(1));
+    let mut energy = vec![0.0_f64; hop_frames.map_or(0, |hop| frames.div_ceil(hop) as usize)];
+
+    let display = path.display().to_string();
+    let mut frame = 0_u64;
+    let mut next_progress = 1;
+    // A container's frame count can be an estimate; decoding stops at it.
+    while frame < frames {
+        let Some(samples) = reader.next_samples()? else {
+            break;
+        };
+        for block in samples.chunks_exact(channels as usize) {
+            if frame >= frames {
+                break;
+            }
+            let bucket = (frame / frames_per_bucket) as usize;
+            let mut mono = 0.0_f64;
+            for &signed in block {
+                let value = signed.abs().min(1.0);
+                peak[bucket] = peak[bucket].max(value);
+                sum_squares[bucket] += (value as f64) * (value as f64);
+                counts[bucket] += 1;
+                mono += signed as f64;
+            }
+            if let Some(hop) = hop_frames {
+                let mono = mono / channels as f64;
+                energy[(frame / hop) as usize] += mono * mono / hop as f64;
+            }
+            frame += 1;
+        }
+        while frame * PROGRESS_STEPS >= frames * next_progress && next_progress <= PROGRESS_STEPS {
+            events::emit(
+                app,
+                events::WAVEFORM_PROGRESS,
+                WaveformProgress {
+                    path: display.clone(),
+                    progress: next_progress as f64 / PROGRESS_STEPS as f64,
+                },
+            );
+            next_progress += 1;
+        }
+    }
+    if frame == 0 {
+        return Err(format!("{} contains no audio", path.display()));
+    }
+
+    let rms = sum_squares
+        .iter()
+        .zip(&counts)
+        .map(|(sum, count)| if *count == 0 { 0.0 } else { (sum / *count as f64).sqrt() as f32 })
+        .collect();
+    log::info!("Analyzed waveform of {} ({} frames, {} buckets)", display, frame, buckets);
+    let envelope = WaveformEnvelope {
+        sample_rate,
+        channels,
+        duration: frame as f64 / sample_rate as f64,
+        bucket_seconds: frames_per_bucket as f64 / sample_rate as f64,
+        peak,
+        rms,
+    };
+    Ok((envelope, energy.into_iter().map(|e| e as f32).collect()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A 16-bit stereo WAV of `frames` frames at 8 kHz, left rising and
+    // right at half scale.
+    fn wav(frames: u32) -> Vec<u8> {
+        let data_len = frames * 4;
+        let mut data = Vec::new();
+        data.extend_from_slice(b"RIFF");
+        data.extend_from_slice(&(36 + data_len).to_le_bytes());
+        data.extend_from_slice(b"WAVEfmt ");
+        data.extend_from_slice(&16_u32.to_le_bytes());
+        for field in [1_u16, 2] {
+            data.extend_from_slice(&field.to_le_bytes());
+        }
+        data.extend_from_slice(&8000_u32.to_le_bytes());
+        data.extend_from_slice(&32000_u32.to_le_bytes());
+        for field in [4_u16, 16] {
+            data.extend_from_slice(&field.to_le_bytes());
+        }
+        data.extend_from_slice(b"data");
+        data.extend_from_slice(&data_len.to_le_bytes());
+        for i in 0..frames {
+            data.extend_from_slice(&(i as i16).to_le_bytes());
+            data.extend_from_slice(&16_384_i16.to_le_bytes());
+        }
+        data
+    }
+
+    #[test]
+    fn decodes_interleaved_samples() {
+        let path = std::env::temp_dir().join(format!("lume-waveform-{}.wav", uuid::Uuid::new_v4()));
+        std::fs::write(&path, wav(1000)).unwrap();
+        let mut reader = TrackReader::open(&path).unwrap();
+        assert_eq!((reader.sample_rate, reader.channels, reader.frames), (8000, 2, Some(1000)));
+        let samples = reader.next_samples().unwrap().unwrap().to_vec();
+        assert_eq!(samples[..4], [0.0, 0.5, 1.0 / 32_768.0, 0.5]);
+        assert_eq!(count_frames(&path).unwrap(), 1000);
+        let _ = std::fs::remove_file(&path);
+    }
+}
@@ -0,0 +1,305 @@
+use serde::Serialize;
+use std::collections::HashSet;
+use std::time::Duration;
+use tokio::task::JoinSet;
+
+use crate::controller_client;
+use crate::dispatcher::{Dispatcher, OutputAction};
+use crate::haze;
+use crate::registry::{ControllerInfo, ControllerRegistry};
+use crate::safe_mode;
+use crate::transport;
+
+/// An emergency stop gives up on a controller that has not taken it by
+/// then and reports it as failed, so one dead unit cannot hold up the rest.
+pub const EMERGENCY_STOP_DEADLINE: Duration = Duration::from_secs(2);
+
+#[derive(Debug, Serialize)]
+pub struct ZoneFailure {
+    pub address: String,
+    pub error: String,
+}
+
+/// Which controllers a zone operation actually reached.
+#[derive(Debug, Serialize)]
+pub struct ZoneReport {
+    pub zone: String,
+    pub reached: Vec<String>,
+    pub failed: Vec<ZoneFailure>,
+}
+
+impl ZoneReport {
+    fn new(zone: &str) -> Self {
+        Self {
+            zone: zone.to_string(),
+            reached: Vec::new(),
+            failed: Vec::new(),
+        }
+    }
+
+    fn record(&mut self, address: String, result: Result<(), String>) {
+        match result {
+            Ok(()) => self.reached.push(address),
+            Err(error) => self.failed.push(ZoneFailure { address, error }),
+        }
+    }
+
+    fn sort(mut self) -> Self {
+        self.reached.sort();
+        self.failed.sort_by(|a, b| a.address.cmp(&b.address));
+        self
+    }
+}
+
+/// Switches off every output of the zone's controllers at once and keeps
+/// them dark, refusing new output, until `clear_blackout`.
+pub async fn blackout(dispatcher: &Dispatcher, registry: &ControllerRegistry, name: &str) -> Result<ZoneReport, String> {
+    let zone = registry.zone(name)?;
+    // Latch first so nothing can slip in while the off commands are in flight.
+    for address in &zone.addresses {
+        dispatcher.set_blacked_out(address, true)?;
+    }
+    let mut tasks = JoinSet::new();
+    for address in zone.addresses {
+        let dispatcher = dispatcher.clone();
+        let controller = registry.get(&address);
+        tasks.spawn(async move {
+            let result = match controller {
+                Ok(controller) => blackout_controller(&dispatcher, &controller).await,
+                Err(e) => Err(e),
+            };
+            (address, result)
+        });
+    }
+
+    let mut report = ZoneReport::new(name);
+    while let Some(joined) = tasks.join_next().await {
+        let (address, result) = joined.map_err(|e| format!("Blackout failed: {}", e))?;
+        report.record(address, result);
+    }
+    log::info!("Blackout of zone '{}' reached {} controllers", name, report.reached.len());
+    Ok(report.sort())
+}
+
+/// Puts one controller into its dark/safe state, dropping anything held on it.
+pub async fn blackout_controller(dispatcher: &Dispatcher, controller: &ControllerInfo) -> Result<(), String> {
+    dispatcher.release_controller(&controller.address).await;
+    if controller.is_haze() {
+        return haze::set_output(dispatcher, controller, 0, 0.0).await;
+    }
+    for action in blackout_actions(controller) {
+        dispatcher.send(&controller.address, 0, &action).await?;
+    }
+    Ok(())
+}
+
+// What darkens a controller other than haze, which is driven by level.
+fn blackout_actions(controller: &ControllerInfo) -> Vec<OutputAction> {
+    if controller.is_pyro() || controller.is_laser() {
+        vec![OutputAction::EmergencyStop]
+    } else if controller.is_relay() {
+        // Open is the safe state of every contact.
+        vec![OutputAction::AllRelays(false)]
+    } else {
+        vec![OutputAction::StopEffect, OutputAction::AllRelays(false)]
+    }
+}
+
+/// Drives each channel with a safe default to it, e.g. work lights back on.
+/// Only sent once the controller's outputs have been cleared.
+async fn hold_safe_defaults(dispatcher: &Dispatcher, controller: &ControllerInfo) -> Result<(), String> {
+    let mut failed = Vec::new();
+    for &channel in controller.safe_defaults.keys() {
+        let Some(action) = controller.safe_default_action(channel) else {
+            continue;
+        };
+        if let Err(e) = dispatcher.send(&controller.address, channel, &action).await {
+            failed.push(format!("channel {}: {}", channel, e));
+        }
+    }
+    if failed.is_empty() {
+        Ok(())
+    } else {
+        Err(format!("Safe defaults not applied on {}", failed.join(", ")))
+    }
+}
+
+/// Applies the safe defaults of every controller that has any, after a
+/// show stops. Controllers without them are not in the report.
+pub async fn apply_safe_defaults(dispatcher: &Dispatcher, registry: &ControllerRegistry) -> Result<ZoneReport, String> {
+    let mut tasks = JoinSet::new();
+    for controller in registry.list()?.into_iter().filter(|c| !c.safe_defaults.is_empty()) {
+        let dispatcher = dispatcher.clone();
+        tasks.spawn(async move {
+            let result = hold_safe_defaults(&dispatcher, &controller).await;
+            (controller.address, result)
+        });
+    }
+    let mut report = ZoneReport::new("safe-defaults");
+    while let Some(joined) = tasks.join_next().await {
+        let (address, result) = joined.map_err(|e| format!("Applying safe defaults failed: {}", e))?;
+        if let Err(e) = &result {
+            log::warn!("{}: {}", address, e);
+        }
+        report.record(address, result);
+    }
+    Ok(report.sort())
+}
+
+/// Puts every registered controller into its dark/safe state at once:
+/// pyro and lasers stop, haze goes off, lights go dark and relay contacts
+/// open, then channels with a safe default go to it, each controller within
+/// `EMERGENCY_STOP_DEADLINE`. Unlike a blackout nothing stays latched afterwards.
+/// In safe mode only the switching off goes out; safe defaults are skipped.
+pub async fn emergency_stop(dispatcher: &Dispatcher, registry: &ControllerRegistry) -> Result<ZoneReport, String> {
+    let mut tasks = JoinSet::new();
+    for controller in registry.list()? {
+        let dispatcher = dispatcher.clone();
+        tasks.spawn(async move {
+            let stop = async {
+                blackout_controller(&dispatcher, &controller).await?;
+                if safe_mode::is_active() {
+                    return Ok(());
+                }
+                hold_safe_defaults(&dispatcher, &controller).await
+            };
+            let result = tokio::time::timeout(EMERGENCY_STOP_DEADLINE, stop).await.unwrap_or_else(|_| {
+                Err(format!("No answer within {} ms", EMERGENCY_STOP_DEADLINE.as_millis()))
+            });
+            (controller.address, result)
+        });
+    }
+    let mut report = ZoneReport::new("all");
+    while let Some(joined) = tasks.join_next().await {
+        let (address, result) = joined.map_err(|e| format!("Emergency stop failed: {}", e))?;
+        if let Err(e) = &result {
+            log::error!("Emergency stop did not reach {}: {}", address, e);
+        }
+        report.record(address, result);
+    }
+    log::warn!("Emergency stop reached {} controllers, {} failed", report.reached.len(), report.failed.len());
+    Ok(report.sort())
+}
+
+/// Lets the zone's controllers take output again. Nothing is switched back on.
+pub fn clear_blackout(dispatcher: &Dispatcher, registry: &ControllerRegistry, name: &str) -> Result<ZoneReport, String> {
+    let zone = registry.zone(name)?;
+    let mut report = ZoneReport::new(name);
+    for address in zone.addresses {
+        let result = dispatcher.set_blacked_out(&address, false);
+        report.record(address, result);
+    }
+    Ok(report)
+}
+
+/// Mutes the controllers of every other zone so only this one responds,
+/// replacing any previous isolation. Returns the controllers that were muted.
+pub fn isolate(dispatcher: &Dispatcher, registry: &ControllerRegistry, name: &str) -> Result<ZoneReport, String> {
+    let others = outside(registry, name)?;
+    dispatcher.set_isolated_out(others.clone())?;
+    log::info!("Isolated zone '{}', {} other controllers muted", name, others.len());
+    let mut report = ZoneReport::new(name);
+    report.reached = others.into_iter().collect();
+    Ok(report.sort())
+}
+
+// Controllers in other zones and not also in zone `name`.
+fn outside(registry: &ControllerRegistry, name: &str) -> Result<HashSet<String>, String> {
+    let zone = registry.zone(name)?;
+    let keep: HashSet<&String> = zone.addresses.iter().collect();
+    Ok(registry
+        .zones()?
+        .into_iter()
+        .filter(|z| z.name != zone.name)
+        .flat_map(|z| z.addresses)
+        .filter(|a| !keep.contains(a))
+        .collect())
+}
+
+pub fn clear_isolation(dispatcher: &Dispatcher) -> Result<(), String> {
+    dispatcher.set_isolated_out(HashSet::new())
+}
+
+/// Checks that every controller in the zone answers, without firing anything.
+pub async fn test(registry: &ControllerRegistry, name: &str) -> Result<ZoneReport, String> {
+    let zone = registry.zone(name)?;
+    let mut probes = JoinSet::new();
+    for address in zone.addresses {
+        probes.spawn(async move {
+            let result = controller_client::probe(&address, transport::timeout_for(&address)).await;
+            (address, result.map(|_| ()))
+        });
+    }
+
+    let mut report = ZoneReport::new(name);
+    while let Some(joined) = probes.join_next().await {
+        let (address, result) = joined.map_err(|e| format!("Zone test failed: {}", e))?;
+        report.record(address, result);
+    }
+    Ok(report.sort())
+}
+
+/// Mutes or unmutes every controller in the zone.
+pub fn set_muted(dispatcher: &Dispatcher, registry: &ControllerRegistry, name: &str, muted: bool) -> Result<ZoneReport, String> {
+    let zone = registry.zone(name)?;
+    let mut report = ZoneReport::new(name);
+    for address in zone.addresses {
+        let result = dispatcher.set_controller_muted(&address, muted);
+        report.record(address, result);
+    }
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock_controller::{MockController, Reply};
+    use serde_json::json;
+
+    fn registry(controllers: &[(&str, &str)]) -> ControllerRegistry {
+        let registry = ControllerRegistry::default();
+        for (address, controller_type) in controllers {
+            let info = serde_json::from_value(json!({ "address": address, "controller_type": controller_type })).unwrap();
+            registry.add(info).unwrap();
+        }
+        registry
+    }
+
+    fn addresses(list: &[&str]) -> Vec<String> {
+        list.iter().map(|a| a.to_string()).collect()
+    }
+
+    #[test]
+    fn isolation_mutes_only_controllers_outside_the_zone() {
+        let registry = registry(&[("10.0.0.1", "lights"), ("10.0.0.2", "lights"), ("10.0.0.3", "lights"), ("10.0.0.4", "firework")]);
+        registry.create_zone("Stage", addresses(&["10.0.0.1", "10.0.0.2"])).unwrap();
+        registry.create_zone("Wings", addresses(&["10.0.0.2", "10.0.0.3"])).unwrap();
+        registry.create_zone("Roof", addresses(&["10.0.0.4"])).unwrap();
+        // 10.0.0.2 is on stage too, so it keeps playing.
+        let mut muted: Vec<String> = outside(&registry, "Stage").unwrap().into_iter().collect();
+        muted.sort();
+        assert_eq!(muted, ["10.0.0.3", "10.0.0.4"]);
+        assert!(outside(&registry, "Backstage").is_err());
+    }
+
+    #[test]
+    fn blackout_puts_each_kind_of_controller_in_its_safe_state() {
+        let registry = registry(&[("10.0.0.1", "firework"), ("10.0.0.2", "relay"), ("10.0.0.3", "lights")]);
+        let actions = |address: &str| blackout_actions(&registry.get(address).unwrap());
+        assert_eq!(actions("10.0.0.1"), [OutputAction::EmergencyStop]);
+        assert_eq!(actions("10.0.0.2"), [OutputAction::AllRelays(false)]);
+        assert_eq!(actions("10.0.0.3"), [OutputAction::StopEffect, OutputAction::AllRelays(false)]);
+    }
+
+    #[tokio::test]
+    async fn zone_test_reports_which_controllers_answered() {
+        let (up, down) = (MockController::start().await, MockController::start().await);
+        down.always("/status", Reply::Status(503));
+        let registry = registry(&[(&up.address, "lights"), (&down.address, "lights")]);
+        registry.create_zone("Stage", vec![up.address.clone(), down.address.clone()]).unwrap();
+        let report = test(&registry, "Stage").await.unwrap();
+        assert_eq!(report.reached, std::slice::from_ref(&up.address));
+        assert_eq!(report.failed.len(), 1);
+        assert_eq!(report.failed[0].address, down.address);
+    }
+}
@@ -1,13 +1,63 @@
 import { invoke } from '@tauri-apps/api/core';
+import { listen, type UnlistenFn } from '@tauri-apps/api/event';
 import { sendNotification } from '@tauri-apps/plugin-notification';
 import { useState, useEffect } from 'react';
 
 // Type definitions matching Rust structs
 export interface ShowStatus {
   is_running: boolean;
+  is_held: boolean;
   current_time: number;
   total_duration: number;
   active_effects: string[];
+  comparison: 'a' | 'b' | null;
+  drift_ms: number | null;
+  failed_effects: number;
+  current_time_display?: string;
+}
+
+export interface ValidationIssue {
+  category: string;
+  severity: 'error' | 'warning' | 'info';
+  effect_id: string | null;
+  controller: string | null;
+  message: string;
+}
+
+export type ValidationMode = 'strict' | 'lenient';
+
+export interface ValidationReport {
+  mode: ValidationMode;
+  valid: boolean;
+  timing_valid: boolean;
+  effects_valid: boolean;
+  controllers_available: boolean;
+  controllers: {
+    reference: string;
+    address: string | null;
+    availability: 'online' | 'offline' | 'not_contacted' | 'unregistered' | 'disabled';
+  }[];
+  cue_spacing: {
+    controller: string;
+    first_effect: string;
+    second_effect: string;
+    required_ms: number;
+    actual_ms: number;
+  }[];
+  dangling_references: {
+    effect_id: string;
+    controller: string;
+    channel: number;
+    message: string;
+  }[];
+  channel_range: {
+    effect_id: string;
+    controller: string;
+    channel: number;
+    channel_count: number;
+  }[];
+  issues: ValidationIssue[];
+  disabled_effects: string[];
 }
 
 export interface SystemInfo {
@@ -15,42 +65,431 @@ export interface SystemInfo {
   platform: string;
   architecture: string;
   memory_usage: number;
+  hostname: string;
+  cpu_core_count: number;
+  total_system_memory: number;
+  uptime_secs: number;
+}
+
+export interface PerformanceSample {
+  timestamp: number;
+  memory_mb: number;
+  cpu_usage: number;
+  jitter_ms: number;
+  degraded: boolean;
+  show_running: boolean;
+}
+
+export interface PerformanceHistory {
+  interval_ms: number;
+  samples_per_point: number;
+  samples: PerformanceSample[];
+}
+
+/** Cues a run has sent to one controller so far. */
+export interface ControllerDispatch {
+  succeeded: number;
+  failed: number;
+  skipped: number;
+}
+
+/** Fields every backend event carries. */
+interface EventEnvelope {
+  seq: number;
+  timestamp: number;
+}
+
+export interface ShowTickEvent extends EventEnvelope {
+  current_time: number;
+  total_duration: number;
+  active_effects: string[];
+  next_cue_time: number | null;
+  next_cue_id: string | null;
+  controllers: Record<string, ControllerDispatch>;
+}
+
+export interface EffectFiredEvent extends EventEnvelope {
+  effect_id: string | null;
+  controller: string;
+  controller_name: string | null;
+  channel: number;
+  source: 'show' | 'manual';
+  success: boolean;
+  error: string | null;
+}
+
+export interface ShowFinishedEvent extends EventEnvelope {
+  show_id: string;
+  total_duration: number;
+  failed_effects: number;
+  controllers: Record<string, ControllerDispatch>;
+}
+
+export interface ShowErrorEvent extends EventEnvelope {
+  show_id: string;
+  current_time: number;
+  effect_id: string;
+  controller: string;
+  channel: number;
+  error: string;
+}
+
+/** One scheduled effect of a backend show. Times are in seconds. */
+export interface BackendEffect {
+  id: string;
+  start_time: number;
+  duration?: number;
+  controller: string;
+  channel: number;
+  effect_type?: string;
+  params?: Record<string, unknown>;
+  ramp?: unknown;
+  enabled?: boolean;
+  layer?: string | null;
+}
+
+/** A show as the backend plays, validates and exports it. */
+export interface BackendShow {
+  id: string;
+  name: string;
+  description?: string;
+  total_duration?: number;
+  effects: BackendEffect[];
+  palette?: Record<string, [number, number, number]>;
+  output?: unknown;
+  last_position?: number | null;
+  markers?: { time: number; name: string }[];
+  layers?: { name: string; visible?: boolean; muted?: boolean }[];
+}
+
+/** A controller found by a discovery scan. */
+export interface DiscoveredController {
+  address: string;
+  name: string;
+  ip: string | null;
+  port: number;
+  firmware_version: string | null;
+  controller_type: string;
+  mac: string | null;
+  device_id: string | null;
+}
+
+/** Result of a controller connection test. */
+export interface ConnectionHealth {
+  address: string;
+  reachable: boolean;
+  latency_ms: number | null;
+  firmware_version: string | null;
+  channel_count: number | null;
+  error: string | null;
+}
+
+/** Port and payload of the UDP broadcast discovery probe. */
+export interface BroadcastProbe {
+  port: number;
+  magic: string;
+}
+
+/**
+ * A universe and how it is sent. Art-Net needs a node or broadcast target;
+ * sACN without a target goes to the universe's multicast group.
+ */
+export interface DmxUniverse {
+  universe: number;
+  protocol: 'artnet' | 'sacn';
+  target: string | null;
+  priority: number;
+}
+
+/** Plays the cues of a fixture channel on DMX slots; address is 1-512. */
+export interface DmxPatch {
+  fixture: string;
+  channel: number;
+  universe: number;
+  address: number;
+  footprint?: 'dimmer' | 'rgb';
+}
+
+export interface DmxConfig {
+  universes: DmxUniverse[];
+  patch: DmxPatch[];
+  sync_universe: number | null;
+}
+
+export type OscCommand = 'start_show' | 'stop_show' | 'pause_show' | 'resume_show' | 'seek' | 'jump_to_marker';
+
+/**
+ * OSC listen port and interface (loopback by default), the senders allowed
+ * to run commands (empty allows any), marker cue targets ("ip:port") and
+ * address mappings.
+ */
+export interface OscConfig {
+  listen_port: number | null;
+  listen_address: string;
+  allowed_senders: string[];
+  targets: string[];
+  mappings: Record<string, OscCommand>;
+}
+
+/** The soundtrack the show plays to. */
+export interface AudioTrack {
+  path: string;
+  sample_rate: number;
+  channels: number;
+  duration: number;
+}
+
+export interface AudioStatus {
+  track: AudioTrack | null;
+  device: string | null;
+  playing: boolean;
+  position: number | null;
+  latency_ms: number | null;
+  error: string | null;
+}
+
+/** Waveform peaks plus onsets and a beat grid, all times in seconds. */
+export interface AudioAnalysis {
+  waveform: {
+    sample_rate: number;
+    channels: number;
+    duration: number;
+    bucket_seconds: number;
+    peak: number[];
+    rms: number[];
+  };
+  onsets: number[];
+  beats: number[];
+  bpm: number | null;
+  cached: boolean;
+}
+
+export interface MqttStatus {
+  broker: string | null;
+  connected: boolean;
+  connected_since: number | null;
+  messages_received: number;
+  last_error: string | null;
+}
+
+/** What a controller last reported over MQTT. */
+export interface ControllerTelemetry {
+  last_heartbeat: number | null;
+  telemetry: unknown;
+  telemetry_at: number | null;
+}
+
+/** CSV cue list layout; omitted fields keep their defaults. */
+export interface CsvOptions {
+  delimiter?: string;
+  time_format?: 'seconds' | 'clock';
+  columns?: ('time' | 'controller' | 'channel' | 'effect' | 'duration' | 'notes' | 'id' | 'effect_type' | 'layer')[];
+}
+
+export type SyncMode = 'internal' | 'mtc' | 'ltc';
+
+/** Where the show clock comes from, and how well timecode is being followed. */
+export interface SyncStatus {
+  mode: SyncMode;
+  input: string | null;
+  offset: number;
+  state: 'idle' | 'locked' | 'freewheel' | 'lost';
+  timecode: string | null;
+  frame_rate: '24' | '25' | '29.97df' | '30' | null;
+}
+
+/** Controllers a stop or zone operation reached, and those that failed. */
+export interface ZoneReport {
+  zone: string;
+  reached: string[];
+  failed: { address: string; error: string }[];
 }
 
 // Show control API
 export class TauriShowAPI {
-  static async startShow(showData: string): Promise<string> {
-    return await invoke('start_show', { showData });
+  /** Without a show, plays the one already loaded in the backend. */
+  static async startShow(show?: BackendShow, resume = false): Promise<string> {
+    return await invoke('start_show', { show, resume });
   }
 
-  static async stopShow(): Promise<void> {
+  static async stopShow(): Promise<ZoneReport> {
     return await invoke('stop_show');
   }
 
+  static async pauseShow(): Promise<number> {
+    return await invoke('pause_show');
+  }
+
+  static async resumeShow(): Promise<number> {
+    return await invoke('resume_show');
+  }
+
+  static async seekShow(positionMs: number): Promise<number> {
+    return await invoke('seek_show', { positionMs: Math.max(0, Math.round(positionMs)) });
+  }
+
   static async getShowStatus(): Promise<ShowStatus> {
     return await invoke('get_show_status');
   }
 
-  static async validateShowData(showData: string): Promise<Record<string, boolean>> {
-    return await invoke('validate_show_data', { showData });
+  static async validateShowData(show: BackendShow, mode?: ValidationMode): Promise<ValidationReport> {
+    return await invoke('validate_show_data', { show, mode });
   }
 
-  static async exportShow(showData: string, format: string): Promise<string> {
-    return await invoke('export_show', { showData, format });
+  static async exportShow(show: BackendShow, format: string, csv?: CsvOptions): Promise<string> {
+    return await invoke('export_show', { show, format, csv });
+  }
+
+  /** Writes a versioned .lume file; without a path a save dialog asks for one. Null if cancelled. */
+  static async exportShowFile(show: BackendShow, path?: string): Promise<string | null> {
+    return await invoke('export_show_file', { show, path });
+  }
+}
+
+// Show progress pushed by the engine, at the rate set with set_tick_rate.
+export class TauriShowEvents {
+  static onTick(handler: (tick: ShowTickEvent) => void): Promise<UnlistenFn> {
+    return listen<ShowTickEvent>('show-tick', (event) => handler(event.payload));
+  }
+
+  static onCueFired(handler: (fired: EffectFiredEvent) => void): Promise<UnlistenFn> {
+    return listen<EffectFiredEvent>('effect-fired', (event) => handler(event.payload));
+  }
+
+  static onFinished(handler: (finished: ShowFinishedEvent) => void): Promise<UnlistenFn> {
+    return listen<ShowFinishedEvent>('show-finished', (event) => handler(event.payload));
+  }
+
+  static onError(handler: (error: ShowErrorEvent) => void): Promise<UnlistenFn> {
+    return listen<ShowErrorEvent>('show-error', (event) => handler(event.payload));
   }
 }
 
 // Hardware control API
 export class TauriHardwareAPI {
-  static async scanControllers(): Promise<string[]> {
+  static async scanControllers(): Promise<DiscoveredController[]> {
     return await invoke('scan_controllers');
   }
 
-  static async testControllerConnection(address: string): Promise<boolean> {
+  static async setBroadcastDiscovery(port?: number, magic?: string): Promise<BroadcastProbe> {
+    return await invoke('set_broadcast_discovery', { port, magic });
+  }
+
+  static async testControllerConnection(address: string): Promise<ConnectionHealth> {
     return await invoke('test_controller_connection', { address });
   }
 }
 
+// DMX output API
+export class TauriDmxAPI {
+  static async getConfig(): Promise<DmxConfig> {
+    return await invoke('get_dmx_config');
+  }
+
+  static async setArtNetUniverse(universe: number, target: string): Promise<DmxUniverse> {
+    return await invoke('set_artnet_universe', { universe, target });
+  }
+
+  static async setSacnUniverse(universe: number, target?: string, priority?: number): Promise<DmxUniverse> {
+    return await invoke('set_sacn_universe', { universe, target, priority });
+  }
+
+  static async setSacnSync(universe: number | null): Promise<void> {
+    return await invoke('set_sacn_sync', { universe });
+  }
+
+  static async removeUniverse(universe: number): Promise<boolean> {
+    return await invoke('remove_dmx_universe', { universe });
+  }
+
+  static async patchChannel(patch: DmxPatch): Promise<DmxPatch> {
+    return await invoke('patch_dmx_channel', { patch });
+  }
+
+  static async unpatchChannel(fixture: string, channel: number): Promise<boolean> {
+    return await invoke('unpatch_dmx_channel', { fixture, channel });
+  }
+}
+
+// OSC API
+export class TauriOscAPI {
+  static async getConfig(): Promise<OscConfig> {
+    return await invoke('get_osc_config');
+  }
+
+  static async configure(
+    listenPort: number | null,
+    targets: string[],
+    listenAddress: string | null = null,
+    allowedSenders: string[] = []
+  ): Promise<OscConfig> {
+    return await invoke('configure_osc', { listenPort, listenAddress, allowedSenders, targets });
+  }
+
+  static async mapAddress(address: string, command: OscCommand | null): Promise<OscConfig> {
+    return await invoke('map_osc_address', { address, command });
+  }
+}
+
+// Soundtrack playback API
+export class TauriAudioAPI {
+  /** WAV, FLAC, MP3, OGG Vorbis or AAC; playback then follows the show. */
+  static async load(path: string): Promise<AudioTrack> {
+    return await invoke('load_audio', { path });
+  }
+
+  static async setDevice(id: string | null): Promise<AudioStatus> {
+    return await invoke('set_audio_device', { id });
+  }
+
+  static async listDevices(): Promise<string[]> {
+    return await invoke('list_audio_devices');
+  }
+
+  static async getStatus(): Promise<AudioStatus> {
+    return await invoke('get_audio_status');
+  }
+
+  static async analyze(path: string): Promise<AudioAnalysis> {
+    return await invoke('analyze_audio', { path });
+  }
+}
+
+// Timecode chase API
+export class TauriTimecodeAPI {
+  /** `input` is a MIDI port for MTC or an audio device for LTC; `offset` is the timecode of show time zero. */
+  static async setSyncMode(mode: SyncMode, input?: string, offset?: number): Promise<SyncStatus> {
+    return await invoke('set_sync_mode', { mode, input, offset });
+  }
+
+  static async getStatus(): Promise<SyncStatus> {
+    return await invoke('get_sync_status');
+  }
+
+  static async listMidiPorts(): Promise<string[]> {
+    return await invoke('list_midi_ports');
+  }
+}
+
+// MQTT API
+export class TauriMqttAPI {
+  static async connect(brokerUrl: string, credentials?: { username: string; password?: string }): Promise<MqttStatus> {
+    return await invoke('connect_mqtt', { brokerUrl, credentials });
+  }
+
+  static async disconnect(): Promise<MqttStatus> {
+    return await invoke('disconnect_mqtt');
+  }
+
+  static async getStatus(): Promise<MqttStatus> {
+    return await invoke('get_mqtt_status');
+  }
+
+  static async getControllerTelemetry(): Promise<Record<string, ControllerTelemetry>> {
+    return await invoke('get_controller_telemetry');
+  }
+}
+
 // System information API
 export class TauriSystemAPI {
   static async getSystemInfo(): Promise<SystemInfo> {
@@ -61,6 +500,10 @@ export class TauriSystemAPI {
     return await invoke('get_performance_stats');
   }
 
+  static async getPerformanceHistory(): Promise<PerformanceHistory> {
+    return await invoke('get_performance_history');
+  }
+
   static async sendNotification(title: string, message: string): Promise<void> {
     // Use both Tauri command and native notification
     try {
@@ -75,7 +518,13 @@ export class TauriSystemAPI {
 // Unified API class
 export class TauriAPI {
   static show = TauriShowAPI;
+  static showEvents = TauriShowEvents;
   static hardware = TauriHardwareAPI;
+  static dmx = TauriDmxAPI;
+  static osc = TauriOscAPI;
+  static mqtt = TauriMqttAPI;
+  static audio = TauriAudioAPI;
+  static timecode = TauriTimecodeAPI;
   static system = TauriSystemAPI;
 
   // Utility methods